@@ -1,3 +1,58 @@
+use std::process::Command;
+
 fn main() {
+    emit_build_info();
     tauri_build::build()
 }
+
+/// 将 git 提交、构建时间、rustc 版本与依赖版本写入编译期环境变量，供 `get_app_info` 读取
+fn emit_build_info() {
+    let git_commit = run("git", &["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RURAY_GIT_COMMIT={}", git_commit);
+
+    let build_timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    println!("cargo:rustc-env=RURAY_BUILD_TIMESTAMP={}", build_timestamp);
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = run(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RURAY_RUSTC_VERSION={}", rustc_version);
+
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (package, key) in [("tauri", "RURAY_TAURI_VERSION"), ("tun", "RURAY_TUN_VERSION")] {
+        let version = locked_version(&lock, package).unwrap_or_else(|| "unknown".to_string());
+        println!("cargo:rustc-env={}={}", key, version);
+    }
+
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
+
+/// 执行命令并返回去掉首尾空白的标准输出，失败时返回 None
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// 从 Cargo.lock 中读取依赖的锁定版本
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line {
+            return lines.next()?
+                .trim()
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')
+                .map(str::to_string);
+        }
+    }
+    None
+}
@@ -0,0 +1,306 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_debug, log_info, log_warn};
+
+use crate::commands;
+use crate::config::{AppConfig, ControlApiConfig};
+use crate::config_store::ConfigStore;
+use crate::error::{AppError, ErrorCode};
+use crate::state::AppState;
+
+/// 请求头的最大长度
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+/// 请求体的最大长度
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// 读取请求的超时时间
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// 接受连接失败（如文件描述符耗尽）后的首次等待时间，连续失败时加倍
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// 接受连接失败后的最长等待时间
+const ACCEPT_RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// 解析后的 HTTP 请求
+struct ApiRequest {
+    method: String,
+    path: String,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+/// 本地控制 API 服务
+/// 在 127.0.0.1 上提供基于令牌认证的 HTTP 接口，供脚本、Stream Deck 等外部程序控制代理：
+/// * `GET /status` - 代理状态
+/// * `GET /servers` - 服务器列表
+/// * `POST /start` - 启动代理，请求体 `{"server_id": "..."}` 可选，缺省时使用上次的服务器
+/// * `POST /stop` - 停止代理
+/// * `POST /switch` - 切换服务器，请求体 `{"server_id": "..."}`
+pub struct ApiServer {
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+// 全局单例实例
+static API_SERVER: OnceLock<ApiServer> = OnceLock::new();
+
+impl ApiServer {
+    /// 获取全局控制 API 服务实例（单例模式）
+    pub fn instance() -> &'static ApiServer {
+        API_SERVER.get_or_init(|| {
+            Self {
+                app_handle: Arc::new(Mutex::new(None)),
+                task: Arc::new(Mutex::new(None)),
+            }
+        })
+    }
+
+    /// 按配置启动服务
+    /// 未启用时只记录应用句柄
+    ///
+    /// # 参数
+    /// * `app_handle` - Tauri 应用句柄，用于访问应用状态
+    pub async fn start(&self, app_handle: AppHandle) -> Result<()> {
+        *self.app_handle.lock().unwrap() = Some(app_handle);
+        self.restart().await
+    }
+
+    /// 按当前配置重新启动服务
+    /// 配置修改后调用，未启用时只停止服务
+    pub async fn restart(&self) -> Result<()> {
+        self.stop();
+
+        let config = AppConfig::load()?;
+        if !config.control_api.enabled {
+            return Ok(());
+        }
+        let api_config = Self::ensure_token(config.control_api)?;
+
+        let listener = TcpListener::bind(("127.0.0.1", api_config.port))
+            .await
+            .with_context(|| format!("控制 API 无法监听端口 {}", api_config.port))?;
+        log_info!("控制 API 已启动: http://127.0.0.1:{}", api_config.port);
+
+        let app_handle = self.app_handle.lock().unwrap().clone().context("应用尚未初始化")?;
+        let token = api_config.token;
+        let task = tauri::async_runtime::spawn(async move {
+            let mut retry_delay = ACCEPT_RETRY_DELAY;
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(connection) => {
+                        retry_delay = ACCEPT_RETRY_DELAY;
+                        connection
+                    }
+                    Err(e) => {
+                        // 文件描述符耗尽（EMFILE/ENFILE）时 accept 会立即再次失败，等待后再重试，避免空转刷屏
+                        log_warn!("控制 API 接受连接失败，{}ms 后重试: {}", retry_delay.as_millis(), e);
+                        tokio::time::sleep(retry_delay).await;
+                        retry_delay = (retry_delay * 2).min(ACCEPT_RETRY_MAX_DELAY);
+                        continue;
+                    }
+                };
+                let app_handle = app_handle.clone();
+                let token = token.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = Self::handle_connection(stream, &app_handle, &token).await {
+                        log_debug!("控制 API 处理请求失败: {}", e);
+                    }
+                });
+            }
+        });
+
+        *self.task.lock().unwrap() = Some(task);
+        Ok(())
+    }
+
+    /// 停止服务
+    pub fn stop(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+            log_info!("控制 API 已停止");
+        }
+    }
+
+    /// 确保访问令牌存在，为空时生成并保存
+    fn ensure_token(mut api_config: ControlApiConfig) -> Result<ControlApiConfig> {
+        if api_config.token.is_empty() {
+            api_config.token = Self::generate_token();
+            let token = api_config.token.clone();
+            ConfigStore::instance().update(|config| config.control_api.token = token)?;
+        }
+        Ok(api_config)
+    }
+
+    /// 生成随机访问令牌
+    pub fn generate_token() -> String {
+        uuid::Uuid::new_v4().simple().to_string()
+    }
+
+    /// 处理一个连接（每个连接只处理一个请求）
+    async fn handle_connection(mut stream: TcpStream, app_handle: &AppHandle, token: &str) -> Result<()> {
+        let request = match tokio::time::timeout(READ_TIMEOUT, Self::read_request(&mut stream)).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(e)) => {
+                Self::write_response(&mut stream, 400, &json!({ "error": e.to_string() })).await?;
+                return Ok(());
+            }
+            Err(_) => return Err(anyhow::anyhow!("读取请求超时")),
+        };
+
+        let authorized = request.token.as_deref().map(|t| constant_time_eq(t, token)).unwrap_or(false);
+        let (status, body) = if authorized {
+            Self::route(&request, app_handle).await
+        } else {
+            (401, json!({ "error": "访问令牌无效" }))
+        };
+
+        log_debug!("控制 API {} {} -> {}", request.method, request.path, status);
+        Self::write_response(&mut stream, status, &body).await
+    }
+
+    /// 分发请求
+    async fn route(request: &ApiRequest, app_handle: &AppHandle) -> (u16, serde_json::Value) {
+        let state = app_handle.state::<AppState>();
+        let server_id = serde_json::from_slice::<serde_json::Value>(&request.body)
+            .ok()
+            .and_then(|body| body.get("server_id").and_then(|v| v.as_str()).map(str::to_string));
+
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/status") => commands::get_proxy_status(state)
+                .await
+                .and_then(|status| serde_json::to_value(status).map_err(|e| AppError::from(e.to_string()))),
+            // 只返回不含凭据的摘要，不经过需要解锁的 get_servers，也不刷新应用锁的自动锁定计时
+            ("GET", "/servers") => state.config.get().map_err(AppError::from).map(|config| {
+                json!(config.servers.iter()
+                    .map(|s| json!({ "id": s.id, "name": s.name, "protocol": s.protocol, "favorite": s.favorite }))
+                    .collect::<Vec<_>>())
+            }),
+            ("POST", "/start") => {
+                let server_id = server_id.or_else(|| AppConfig::load().ok().and_then(|c| c.current_server));
+                match server_id {
+                    Some(server_id) => commands::start_proxy(state, server_id).await.map(|_| json!({ "ok": true })),
+                    None => Err(AppError::invalid_input("未指定服务器且没有上次使用的服务器")),
+                }
+            }
+            ("POST", "/switch") => match server_id {
+                Some(server_id) => commands::start_proxy(state, server_id).await.map(|_| json!({ "ok": true })),
+                None => Err(AppError::invalid_input("缺少 server_id")),
+            },
+            ("POST", "/stop") => commands::stop_proxy(state).await.map(|_| json!({ "ok": true })),
+            _ => return (404, json!({ "error": "未知的接口" })),
+        };
+
+        match result {
+            Ok(body) => (200, body),
+            Err(e) => {
+                let status = match e.code {
+                    ErrorCode::InvalidInput | ErrorCode::ValidationFailed => 400,
+                    ErrorCode::AppLocked => 403,
+                    ErrorCode::NotFound => 404,
+                    _ => 500,
+                };
+                (status, json!({ "error": e.message, "code": e.code }))
+            }
+        }
+    }
+
+    /// 读取并解析 HTTP 请求
+    async fn read_request(stream: &mut TcpStream) -> Result<ApiRequest> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 1024];
+        let header_end = loop {
+            let read = stream.read(&mut chunk).await.context("读取请求失败")?;
+            if read == 0 {
+                return Err(anyhow::anyhow!("连接已关闭"));
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+            if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if buffer.len() > MAX_HEADER_BYTES {
+                return Err(anyhow::anyhow!("请求头过长"));
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().context("请求格式无效")?.to_uppercase();
+        let path = request_line.next().context("请求格式无效")?;
+        let path = path.split('?').next().unwrap_or(path).trim_end_matches('/').to_string();
+
+        let mut content_length = 0usize;
+        let mut token = None;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.parse().context("Content-Length 无效")?,
+                "authorization" => {
+                    token = value.strip_prefix("Bearer ").map(|t| t.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+        if content_length > MAX_BODY_BYTES {
+            return Err(anyhow::anyhow!("请求体过大"));
+        }
+
+        let mut body = buffer[header_end..].to_vec();
+        while body.len() < content_length {
+            let read = stream.read(&mut chunk).await.context("读取请求体失败")?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..read]);
+        }
+        body.truncate(content_length);
+
+        Ok(ApiRequest {
+            method,
+            path: if path.is_empty() { "/".to_string() } else { path },
+            token,
+            body,
+        })
+    }
+
+    /// 写入 JSON 响应
+    async fn write_response(stream: &mut TcpStream, status: u16, body: &serde_json::Value) -> Result<()> {
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            _ => "Internal Server Error",
+        };
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.context("写入响应失败")?;
+        stream.shutdown().await.ok();
+        Ok(())
+    }
+}
+
+/// 按固定时间比较令牌，避免通过响应时间逐字节猜测令牌
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
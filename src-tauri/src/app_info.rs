@@ -0,0 +1,79 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 版本与构建信息
+// 应用版本、git 提交、构建时间与 rustc/tauri 版本在编译期由 build.rs 写入，
+// 操作系统与架构在运行时检测。用于关于页面，并附加在启动日志与导出的日志中便于排查问题
+
+use serde::Serialize;
+
+/// 版本与构建信息
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    /// 应用版本
+    pub version: String,
+    /// 构建时的 git 提交（短哈希），不在 git 仓库中构建时为 `unknown`
+    pub git_commit: String,
+    /// 构建时间（RFC3339）
+    pub build_date: String,
+    /// 构建类型：debug 或 release
+    pub build_profile: String,
+    /// tauri 版本
+    pub tauri_version: String,
+    /// 编译使用的 rustc 版本
+    pub rust_version: String,
+    /// TUN 模式使用的 tun 库版本（TUN 转发为内置实现，不捆绑 tun2proxy）
+    pub tun_version: String,
+    /// 操作系统名称与版本，如 `Windows 11 (26100)`
+    pub os: String,
+    /// 操作系统类型：windows、macos、linux
+    pub os_family: String,
+    /// CPU 架构，如 `x86_64`
+    pub arch: String,
+}
+
+impl AppInfo {
+    /// 一行摘要，用于日志
+    ///
+    /// # 返回值
+    /// * `String` - 形如 `RuRay 0.1.0 (a1b2c3d, release, 2026-10-16T08:00:00+00:00) on Windows 11 (26100) x86_64`
+    pub fn summary(&self) -> String {
+        format!(
+            "RuRay {} ({}, {}, {}) on {} {}",
+            self.version, self.git_commit, self.build_profile, self.build_date, self.os, self.arch
+        )
+    }
+}
+
+/// 获取版本与构建信息
+///
+/// # 返回值
+/// * `AppInfo` - 版本与构建信息
+pub fn app_info() -> AppInfo {
+    let build_date = env!("RURAY_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let os = sysinfo::System::long_os_version()
+        .or_else(sysinfo::System::name)
+        .unwrap_or_else(|| std::env::consts::OS.to_string());
+
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("RURAY_GIT_COMMIT").to_string(),
+        build_date,
+        build_profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        tauri_version: env!("RURAY_TAURI_VERSION").to_string(),
+        rust_version: env!("RURAY_RUSTC_VERSION").to_string(),
+        tun_version: env!("RURAY_TUN_VERSION").to_string(),
+        os,
+        os_family: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+    }
+}
@@ -0,0 +1,256 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 应用锁
+// 启用后打开设置、编辑服务器、导出配置与分享链接等包含凭据的操作需先用 PIN 解锁。
+// 解锁状态只保存在内存中：应用启动时处于锁定状态，超过自动锁定时间没有受保护的操作时重新锁定。
+// 配置中只保存 PIN 的 PBKDF2 哈希与盐
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::config_store::ConfigStore;
+use crate::error::{AppError, ErrorCode};
+
+/// PIN 哈希的 PBKDF2 迭代次数
+const PIN_HASH_ROUNDS: u32 = 100_000;
+/// PIN 最短长度
+const MIN_PIN_LEN: usize = 4;
+/// 连续输错多少次后暂停尝试
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+/// 连续输错后暂停尝试的时长
+const LOCKOUT_DURATION: Duration = Duration::from_secs(30);
+
+/// 全局应用锁实例
+static APP_LOCK: OnceLock<AppLock> = OnceLock::new();
+
+/// 应用锁设置
+/// 只能通过 `enable_app_lock` / `disable_app_lock` 修改，保存应用配置时保留原值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// PIN 哈希（Base64）
+    #[serde(default)]
+    pub pin_hash: String,
+    /// 哈希盐（Base64）
+    #[serde(default)]
+    pub salt: String,
+    /// 无操作多少分钟后自动锁定，0 表示不自动锁定
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u64,
+}
+
+impl Default for AppLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pin_hash: String::new(),
+            salt: String::new(),
+            auto_lock_minutes: default_auto_lock_minutes(),
+        }
+    }
+}
+
+/// 默认 5 分钟无操作后自动锁定
+fn default_auto_lock_minutes() -> u64 {
+    5
+}
+
+/// 应用锁状态
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLockStatus {
+    /// 是否启用
+    pub enabled: bool,
+    /// 当前是否锁定
+    pub locked: bool,
+    /// 自动锁定时间（分钟）
+    pub auto_lock_minutes: u64,
+    /// 输错次数过多时剩余的等待秒数
+    pub retry_after_secs: u64,
+}
+
+/// 解锁状态
+struct LockState {
+    /// 最近一次解锁或受保护操作的时间，锁定时为空
+    unlocked_at: Option<Instant>,
+    /// 连续输错次数
+    failed_attempts: u32,
+    /// 暂停尝试的截止时间
+    blocked_until: Option<Instant>,
+}
+
+/// 应用锁
+pub struct AppLock {
+    state: Mutex<LockState>,
+}
+
+impl AppLock {
+    /// 获取全局应用锁实例（单例模式）
+    pub fn instance() -> &'static AppLock {
+        APP_LOCK.get_or_init(|| Self {
+            state: Mutex::new(LockState { unlocked_at: None, failed_attempts: 0, blocked_until: None }),
+        })
+    }
+
+    /// 当前配置中的应用锁设置
+    fn config() -> AppLockConfig {
+        AppConfig::load().map(|config| config.app_lock).unwrap_or_default()
+    }
+
+    /// 根据自动锁定时间判断当前是否锁定
+    fn is_locked(config: &AppLockConfig, state: &LockState) -> bool {
+        if !config.enabled {
+            return false;
+        }
+        match state.unlocked_at {
+            None => true,
+            Some(_) if config.auto_lock_minutes == 0 => false,
+            Some(at) => at.elapsed() > Duration::from_secs(config.auto_lock_minutes * 60),
+        }
+    }
+
+    /// 查询应用锁状态
+    pub fn status(&self) -> AppLockStatus {
+        let config = Self::config();
+        let state = self.state.lock().unwrap();
+        AppLockStatus {
+            enabled: config.enabled,
+            locked: Self::is_locked(&config, &state),
+            auto_lock_minutes: config.auto_lock_minutes,
+            retry_after_secs: state.blocked_until
+                .map(|until| until.saturating_duration_since(Instant::now()).as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    /// 受保护操作前调用：锁定时返回 `APP_LOCKED` 错误，未锁定时刷新自动锁定计时
+    pub fn ensure_unlocked(&self) -> Result<(), AppError> {
+        let config = Self::config();
+        let mut state = self.state.lock().unwrap();
+        if Self::is_locked(&config, &state) {
+            state.unlocked_at = None;
+            return Err(AppError::new(ErrorCode::AppLocked, "应用已锁定，请输入 PIN 解锁"));
+        }
+        if config.enabled {
+            state.unlocked_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    /// 使用 PIN 解锁
+    ///
+    /// # 参数
+    /// * `pin` - PIN
+    ///
+    /// # 异常
+    /// * PIN 错误或输错次数过多暂停尝试时返回错误
+    pub fn unlock(&self, pin: &str) -> Result<(), AppError> {
+        let config = Self::config();
+        if !config.enabled {
+            return Ok(());
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(until) = state.blocked_until {
+            let remaining = until.saturating_duration_since(Instant::now());
+            if !remaining.is_zero() {
+                return Err(AppError::new(ErrorCode::AppLocked, format!("输错次数过多，请 {} 秒后重试", remaining.as_secs().max(1))));
+            }
+            state.blocked_until = None;
+        }
+
+        if verify_pin(&config, pin) {
+            state.unlocked_at = Some(Instant::now());
+            state.failed_attempts = 0;
+            return Ok(());
+        }
+
+        state.failed_attempts += 1;
+        log_warn!("应用锁 PIN 错误（连续 {} 次）", state.failed_attempts);
+        if state.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            state.failed_attempts = 0;
+            state.blocked_until = Some(Instant::now() + LOCKOUT_DURATION);
+        }
+        Err(AppError::new(ErrorCode::AppLocked, "PIN 错误"))
+    }
+
+    /// 立即锁定
+    pub fn lock(&self) {
+        self.state.lock().unwrap().unlocked_at = None;
+    }
+
+    /// 启用应用锁或修改 PIN、自动锁定时间
+    /// 已启用时需处于解锁状态；设置后保持解锁
+    ///
+    /// # 参数
+    /// * `pin` - 新 PIN，为空时保留原 PIN（首次启用时必填）
+    /// * `auto_lock_minutes` - 自动锁定时间（分钟），为空时保持不变
+    pub fn enable(&self, pin: Option<&str>, auto_lock_minutes: Option<u64>) -> Result<(), AppError> {
+        self.ensure_unlocked()?;
+        let mut lock_config = Self::config();
+        match pin {
+            Some(pin) => {
+                if pin.chars().count() < MIN_PIN_LEN {
+                    return Err(AppError::invalid_input(format!("PIN 至少需要 {} 位", MIN_PIN_LEN)));
+                }
+                let mut salt = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut salt);
+                lock_config.salt = BASE64.encode(salt);
+                lock_config.pin_hash = BASE64.encode(hash_pin(pin, &salt));
+            }
+            None if lock_config.pin_hash.is_empty() => {
+                return Err(AppError::invalid_input("启用应用锁需要设置 PIN"));
+            }
+            None => {}
+        }
+        lock_config.enabled = true;
+        if let Some(minutes) = auto_lock_minutes {
+            lock_config.auto_lock_minutes = minutes;
+        }
+
+        ConfigStore::instance().update(|config| config.app_lock = lock_config)?;
+        self.state.lock().unwrap().unlocked_at = Some(Instant::now());
+        log_info!("已启用应用锁");
+        Ok(())
+    }
+
+    /// 关闭应用锁，需提供当前 PIN
+    ///
+    /// # 参数
+    /// * `pin` - 当前 PIN
+    pub fn disable(&self, pin: &str) -> Result<(), AppError> {
+        self.unlock(pin)?;
+        ConfigStore::instance().update(|config| config.app_lock = AppLockConfig::default())?;
+        log_info!("已关闭应用锁");
+        Ok(())
+    }
+}
+
+/// 计算 PIN 哈希
+fn hash_pin(pin: &str, salt: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(pin.as_bytes(), salt, PIN_HASH_ROUNDS, &mut hash);
+    hash
+}
+
+/// 校验 PIN
+fn verify_pin(config: &AppLockConfig, pin: &str) -> bool {
+    let (Ok(salt), Ok(expected)) = (BASE64.decode(&config.salt), BASE64.decode(&config.pin_hash)) else {
+        return false;
+    };
+    let actual = hash_pin(pin, &salt);
+    // 逐字节比较全部内容，避免按时间差推测
+    actual.len() == expected.len() && actual.iter().zip(&expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -0,0 +1,251 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 数据目录备份与恢复
+// 备份为 zip 文件，包含 config.json（服务器、订阅、路由/PAC 规则与配置方案都保存在其中）和
+// server/ 目录下的服务器核心配置，另附 backup.json 记录格式版本与应用版本。
+// 核心可执行文件、地理位置数据、统计历史、日志等可重新下载或生成的内容不会备份
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+// 导入日志宏
+use crate::log_info;
+
+use crate::config::AppConfig;
+use crate::config_store::ConfigStore;
+use crate::xray::compare_versions;
+
+/// 备份格式版本，备份内容的结构变化时递增
+const BACKUP_FORMAT_VERSION: u32 = 1;
+/// 备份说明文件名
+const MANIFEST_NAME: &str = "backup.json";
+/// 配置文件名
+const CONFIG_NAME: &str = "config.json";
+/// 服务器配置目录名（相对数据目录）
+const SERVER_DIR_NAME: &str = "server";
+/// 服务器配置目录中的临时文件前缀（测速、API 调用时生成），不备份
+const TEMP_FILE_PREFIXES: [&str; 2] = ["xray_speedtest_", "xray_api_"];
+
+/// 备份说明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// 备份格式版本
+    pub format_version: u32,
+    /// 创建备份的应用版本
+    pub app_version: String,
+    /// 创建时间
+    pub created_at: String,
+    /// 备份中配置的最后修改时间，用于同步时判断哪一方更新
+    #[serde(default)]
+    pub config_updated_at: String,
+    /// 备份中的文件（相对数据目录）
+    pub files: Vec<String>,
+}
+
+/// 获取数据目录
+fn data_dir() -> Result<PathBuf> {
+    let config_path = AppConfig::config_path()?;
+    Ok(config_path.parent().context("无法获取配置目录")?.to_path_buf())
+}
+
+/// 收集需要备份的文件
+///
+/// # 返回值
+/// * `Result<Vec<(String, PathBuf)>>` - 备份中的名称（使用 `/` 分隔）与磁盘路径
+fn collect_files(data_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = vec![(CONFIG_NAME.to_string(), data_dir.join(CONFIG_NAME))];
+
+    let mut pending = vec![data_dir.join(SERVER_DIR_NAME)];
+    while let Some(dir) = pending.pop() {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("无法读取目录: {}", dir.display()))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if TEMP_FILE_PREFIXES.iter().any(|prefix| file_name.starts_with(prefix)) {
+                continue;
+            }
+            let relative = path.strip_prefix(data_dir)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, path));
+        }
+    }
+
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// 创建备份
+/// 写入前先将缓存的配置落盘，保证备份的是最新配置
+///
+/// # 参数
+/// * `path` - 备份文件路径
+///
+/// # 返回值
+/// * `Result<BackupManifest>` - 备份说明
+pub fn create_backup(path: &Path) -> Result<BackupManifest> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("无法创建备份文件: {}", path.display()))?;
+    let manifest = write_backup(file)?;
+    log_info!("已创建备份: {}，共 {} 个文件", path.display(), manifest.files.len());
+    Ok(manifest)
+}
+
+/// 将备份写入任意输出（文件或内存）
+///
+/// # 参数
+/// * `output` - 备份输出
+///
+/// # 返回值
+/// * `Result<BackupManifest>` - 备份说明
+pub fn write_backup<W: Write + Seek>(output: W) -> Result<BackupManifest> {
+    let store = ConfigStore::instance();
+    store.flush().context("保存当前配置失败")?;
+    let config = store.get()?;
+
+    let data_dir = data_dir()?;
+    let files = collect_files(&data_dir)?;
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        config_updated_at: config.updated_at,
+        files: files.iter().map(|(name, _)| name.clone()).collect(),
+    };
+
+    let mut writer = zip::ZipWriter::new(output);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    writer.start_file(MANIFEST_NAME, options)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    for (name, source) in &files {
+        let content = std::fs::read(source)
+            .with_context(|| format!("无法读取文件: {}", source.display()))?;
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(&content)?;
+    }
+    writer.finish().context("无法写入备份文件")?;
+    Ok(manifest)
+}
+
+/// 恢复备份
+/// 备份格式或应用版本比当前新时拒绝恢复；config.json 解析通过后才写入任何文件，
+/// 写入后重新加载配置并通知界面。本机相关的设置（核心路径、日志路径、窗口状态、WebDAV、应用锁、钩子与核心启动参数）保留本机当前的值
+///
+/// # 参数
+/// * `path` - 备份文件路径
+///
+/// # 返回值
+/// * `Result<BackupManifest>` - 备份说明
+///
+/// # 异常
+/// * 文件不是有效的 RuRay 备份时返回错误
+/// * 备份来自更新的版本时返回错误
+pub fn restore_backup(path: &Path) -> Result<BackupManifest> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("无法打开备份文件: {}", path.display()))?;
+    let local = AppConfig::load()?;
+    let (manifest, restored) = read_backup(file, |mut config| {
+        config.keep_local_settings(&local);
+        config
+    })?;
+
+    log_info!(
+        "已从 {} 恢复备份（RuRay {}，{}），共 {} 个服务器配置文件",
+        path.display(),
+        manifest.app_version,
+        manifest.created_at,
+        restored
+    );
+    Ok(manifest)
+}
+
+/// 从备份中读取说明
+fn manifest_of<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<BackupManifest> {
+    let mut entry = archive.by_name(MANIFEST_NAME).context("不是有效的 RuRay 备份：缺少 backup.json")?;
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    serde_json::from_str(&content).context("无法解析备份说明")
+}
+
+/// 从任意输入（文件或内存）恢复备份
+///
+/// # 参数
+/// * `input` - 备份输入
+/// * `prepare` - 写入前对备份中的配置进行调整，如与本地配置合并
+///
+/// # 返回值
+/// * `Result<(BackupManifest, usize)>` - 备份说明与恢复的服务器配置文件数
+pub fn read_backup<R: Read + Seek>(input: R, prepare: impl FnOnce(AppConfig) -> AppConfig) -> Result<(BackupManifest, usize)> {
+    let mut archive = zip::ZipArchive::new(input).context("无法读取备份文件")?;
+    let manifest = manifest_of(&mut archive)?;
+
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(anyhow::anyhow!(
+            "备份格式版本 {} 高于当前支持的版本 {}，请升级 RuRay 后再恢复",
+            manifest.format_version,
+            BACKUP_FORMAT_VERSION
+        ));
+    }
+    let app_version = env!("CARGO_PKG_VERSION");
+    if compare_versions(&manifest.app_version, app_version) == std::cmp::Ordering::Greater {
+        return Err(anyhow::anyhow!(
+            "备份来自更新的 RuRay {}（当前 {}），请升级后再恢复",
+            manifest.app_version,
+            app_version
+        ));
+    }
+
+    let config: AppConfig = {
+        let mut entry = archive.by_name(CONFIG_NAME).context("备份中缺少 config.json")?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        serde_json::from_str(&content).context("备份中的 config.json 无效")?
+    };
+
+    let data_dir = data_dir()?;
+    let mut restored = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).context("无法读取备份内容")?;
+        if entry.is_dir() || entry.name() == MANIFEST_NAME || entry.name() == CONFIG_NAME {
+            continue;
+        }
+        // 只恢复服务器配置目录，忽略备份中的其他文件和越出数据目录的路径
+        let Some(relative) = entry.enclosed_name().map(Path::to_path_buf) else { continue };
+        if !relative.starts_with(SERVER_DIR_NAME) {
+            continue;
+        }
+        let target = data_dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建目录: {}", parent.display()))?;
+        }
+        let mut output = std::fs::File::create(&target)
+            .with_context(|| format!("无法写入文件: {}", target.display()))?;
+        std::io::copy(&mut entry, &mut output)?;
+        restored += 1;
+    }
+
+    let config = prepare(config);
+    let server_ids: Vec<String> = config.servers.iter().map(|s| s.id.clone()).collect();
+    let store = ConfigStore::instance();
+    store.set(config);
+    store.flush().context("无法写入配置文件")?;
+    store.emit_servers_changed("updated", &server_ids);
+
+    Ok((manifest, restored))
+}
@@ -0,0 +1,262 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 本地代理限速
+// Xray 的 policy/levels 不支持限速，这里在核心前面加一层本地整形：启用限速时核心的 HTTP/SOCKS
+// 入站改为监听内部空闲端口，RuRay 在用户配置的端口上接受连接并转发到核心，按令牌桶限制上传/下载速率。
+// 速率可在运行中直接调整；启用或关闭限速需要切换入站端口，由调用方重启核心。
+// 仅转发 TCP，SOCKS 的 UDP 中继直接到达核心内部端口，不受限速影响。仅 Xray 核心支持
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{Duration, Instant};
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::core_backend::CoreKind;
+
+/// 转发时每次读取的最大字节数
+const RELAY_CHUNK_SIZE: usize = 16 * 1024;
+
+/// 全局限速实例
+static BANDWIDTH_LIMITER: OnceLock<BandwidthLimiter> = OnceLock::new();
+
+/// 限速设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BandwidthLimitConfig {
+    /// 上传速率上限（kbps），0 表示不限制
+    #[serde(default)]
+    pub up_kbps: u64,
+    /// 下载速率上限（kbps），0 表示不限制
+    #[serde(default)]
+    pub down_kbps: u64,
+}
+
+impl BandwidthLimitConfig {
+    /// 是否启用了限速（任一方向有上限）
+    pub fn is_active(&self) -> bool {
+        self.up_kbps > 0 || self.down_kbps > 0
+    }
+}
+
+/// kbps 转换为每秒字节数
+fn kbps_to_bytes(kbps: u64) -> u64 {
+    kbps * 1000 / 8
+}
+
+/// 令牌桶，速率为 0 时不限制
+/// 允许令牌欠账：发送后按欠下的字节数等待，桶容量为一秒的流量
+struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    /// 每秒字节数
+    rate: u64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(BucketState { rate: 0, tokens: 0.0, last: Instant::now() }),
+        }
+    }
+
+    fn set_rate(&self, rate: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.rate = rate;
+        state.tokens = state.tokens.min(rate as f64);
+        state.last = Instant::now();
+    }
+
+    /// 消耗令牌，不足时等待到补足为止
+    async fn consume(&self, bytes: usize) {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            if state.rate == 0 {
+                return;
+            }
+            let now = Instant::now();
+            let rate = state.rate as f64;
+            state.tokens = (state.tokens + now.duration_since(state.last).as_secs_f64() * rate).min(rate);
+            state.last = now;
+            state.tokens -= bytes as f64;
+            if state.tokens < 0.0 {
+                Duration::from_secs_f64(-state.tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// 本地代理限速器
+pub struct BandwidthLimiter {
+    up: TokenBucket,
+    down: TokenBucket,
+    /// 核心 HTTP/SOCKS 入站使用的内部端口，未启用限速时为空
+    backend_ports: Mutex<Option<(u16, u16)>>,
+    relays: Mutex<Vec<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+impl BandwidthLimiter {
+    /// 获取全局限速实例（单例模式）
+    pub fn instance() -> &'static BandwidthLimiter {
+        BANDWIDTH_LIMITER.get_or_init(|| Self {
+            up: TokenBucket::new(),
+            down: TokenBucket::new(),
+            backend_ports: Mutex::new(None),
+            relays: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 核心 HTTP/SOCKS 入站应使用的内部端口，未启用限速时为 None
+    pub fn backend_ports(&self) -> Option<(u16, u16)> {
+        *self.backend_ports.lock().unwrap()
+    }
+
+    /// 限速转发是否正在运行
+    pub fn is_active(&self) -> bool {
+        self.backend_ports().is_some()
+    }
+
+    /// 更新速率，对已建立的连接立即生效
+    ///
+    /// # 参数
+    /// * `limit` - 限速设置
+    pub fn set_rates(&self, limit: &BandwidthLimitConfig) {
+        self.up.set_rate(kbps_to_bytes(limit.up_kbps));
+        self.down.set_rate(kbps_to_bytes(limit.down_kbps));
+    }
+
+    /// 核心启动前调用：启用限速时为核心入站分配内部端口
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 是否启用限速转发
+    ///
+    /// # 异常
+    /// * 分配内部端口失败时返回错误
+    pub fn prepare(&self, config: &AppConfig) -> Result<bool> {
+        let enabled = config.bandwidth_limit.is_active();
+        if enabled && config.core != CoreKind::Xray {
+            log_warn!("限速仅支持 Xray 核心，已跳过");
+            *self.backend_ports.lock().unwrap() = None;
+            return Ok(false);
+        }
+        let ports = if enabled { Some((free_port()?, free_port()?)) } else { None };
+        *self.backend_ports.lock().unwrap() = ports;
+        self.set_rates(&config.bandwidth_limit);
+        Ok(enabled)
+    }
+
+    /// 核心启动后调用：在用户配置的端口上启动转发
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    ///
+    /// # 异常
+    /// * 监听端口失败时返回错误
+    pub async fn start_relays(&self, config: &AppConfig) -> Result<()> {
+        let Some((http_backend, socks_backend)) = self.backend_ports() else {
+            return Ok(());
+        };
+        let inbounds = config.effective_inbounds();
+        let mut relays = Vec::new();
+        for (port, backend) in [(config.http_port, http_backend), (config.socks_port, socks_backend)] {
+            let listen = inbounds.iter()
+                .find(|inbound| inbound.port == port)
+                .map(|inbound| config.listen_of(inbound))
+                .unwrap_or_else(|| config.inbound_listen().to_string());
+            let listener = TcpListener::bind((listen.as_str(), port)).await
+                .with_context(|| format!("限速转发监听 {}:{} 失败", listen, port))?;
+            relays.push(tauri::async_runtime::spawn(accept_loop(listener, backend)));
+        }
+        self.relays.lock().unwrap().extend(relays);
+        log_info!(
+            "限速已启用: 上传 {}，下载 {}",
+            format_limit(config.bandwidth_limit.up_kbps),
+            format_limit(config.bandwidth_limit.down_kbps)
+        );
+        Ok(())
+    }
+
+    /// 停止转发并释放内部端口
+    pub fn stop(&self) {
+        for relay in self.relays.lock().unwrap().drain(..) {
+            relay.abort();
+        }
+        *self.backend_ports.lock().unwrap() = None;
+    }
+}
+
+fn format_limit(kbps: u64) -> String {
+    if kbps == 0 { "不限".to_string() } else { format!("{} kbps", kbps) }
+}
+
+/// 分配一个本地空闲端口
+fn free_port() -> Result<u16> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").context("无法分配内部端口")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// 接受连接并逐个转发到核心的内部端口
+async fn accept_loop(listener: TcpListener, backend: u16) {
+    loop {
+        let client = match listener.accept().await {
+            Ok((client, _)) => client,
+            Err(e) => {
+                log_warn!("限速转发接受连接失败: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let upstream = match TcpStream::connect(("127.0.0.1", backend)).await {
+                Ok(upstream) => upstream,
+                Err(_) => return,
+            };
+            let _ = client.set_nodelay(true);
+            let _ = upstream.set_nodelay(true);
+            let limiter = BandwidthLimiter::instance();
+            let (client_read, client_write) = client.into_split();
+            let (upstream_read, upstream_write) = upstream.into_split();
+            let _ = tokio::join!(
+                pipe(client_read, upstream_write, &limiter.up),
+                pipe(upstream_read, client_write, &limiter.down)
+            );
+        });
+    }
+}
+
+/// 单向复制数据，每次写入前按令牌桶限速，读到 EOF 时关闭写端
+async fn pipe<R, W>(mut reader: R, mut writer: W, bucket: &TokenBucket) -> std::io::Result<()>
+where
+    R: AsyncReadExt + Unpin,
+    W: AsyncWriteExt + Unpin,
+{
+    let mut buf = vec![0u8; RELAY_CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return writer.shutdown().await;
+        }
+        bucket.consume(n).await;
+        writer.write_all(&buf[..n]).await?;
+    }
+}
@@ -0,0 +1,303 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use sysinfo::System;
+
+use crate::config::{AppConfig, ControlApiConfig};
+use crate::ipc::{self, IpcRequest, IpcResponse};
+
+/// 连接控制 API 的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// 等待控制 API 响应的超时时间（启动代理可能需要数秒）
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 命令行用法说明
+const USAGE: &str = "用法: ruray [选项]
+  --connect <名称或ID>  连接指定服务器（RuRay 未运行时以无窗口模式启动并连接）
+  --disconnect          断开代理
+  --list                以 JSON 输出服务器列表
+  --status              以 JSON 输出代理状态
+  --help                显示本帮助
+
+数据目录（可与以上选项同时使用）:
+  --portable            便携模式，数据保存在程序所在目录的 data 下
+                        （程序目录中存在 portable.flag 文件时自动启用）
+  --data-dir <目录>     使用指定的数据目录
+
+与正在运行的 RuRay 通信优先使用本机 IPC，无法连接时改用控制 API（需在设置中启用）。";
+
+/// 命令行命令
+#[derive(Debug, Clone)]
+pub enum CliCommand {
+    /// 连接指定服务器（名称或ID）
+    Connect(String),
+    /// 断开代理
+    Disconnect,
+    /// 输出服务器列表
+    List,
+    /// 输出代理状态
+    Status,
+    /// 显示帮助
+    Help,
+}
+
+/// 命令执行结果
+pub enum CliOutcome {
+    /// 命令已完成，进程以该退出码退出
+    Exit(i32),
+    /// RuRay 未运行，需要以无窗口模式启动应用并连接该服务器
+    Headless(String),
+}
+
+/// 数据目录选项
+#[derive(Debug, Clone, Default)]
+pub struct DataDirOptions {
+    /// 是否指定了 `--portable`
+    pub portable: bool,
+    /// `--data-dir` 指定的目录
+    pub data_dir: Option<PathBuf>,
+}
+
+/// 取出数据目录选项
+/// `--portable` 与 `--data-dir` 可与其他选项同时使用，需在读取配置前处理
+///
+/// # 参数
+/// * `args` - 命令行参数（不含程序路径）
+///
+/// # 返回值
+/// * `Result<(DataDirOptions, Vec<String>), String>` - 数据目录选项与其余参数，参数无效时返回错误说明
+pub fn take_data_dir_options(args: &[String]) -> Result<(DataDirOptions, Vec<String>), String> {
+    let mut options = DataDirOptions::default();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--portable" => options.portable = true,
+            "--data-dir" => match iter.next() {
+                Some(dir) => options.data_dir = Some(PathBuf::from(dir)),
+                None => return Err("--data-dir 需要指定目录".to_string()),
+            },
+            _ => rest.push(arg.clone()),
+        }
+    }
+    Ok((options, rest))
+}
+
+/// 解析命令行参数
+/// 不包含命令行选项时（如普通启动或深度链接）返回 None，按图形界面模式启动
+///
+/// # 参数
+/// * `args` - 命令行参数（不含程序路径）
+///
+/// # 返回值
+/// * `Option<Result<CliCommand, String>>` - 解析结果，参数无效时返回错误说明
+pub fn parse_args(args: &[String]) -> Option<Result<CliCommand, String>> {
+    let first = args.first()?;
+    let command = match first.as_str() {
+        "--connect" => match args.get(1) {
+            Some(target) => Ok(CliCommand::Connect(target.clone())),
+            None => Err("--connect 需要指定服务器名称或ID".to_string()),
+        },
+        "--disconnect" => Ok(CliCommand::Disconnect),
+        "--list" => Ok(CliCommand::List),
+        "--status" => Ok(CliCommand::Status),
+        "--help" | "-h" => Ok(CliCommand::Help),
+        other if other.starts_with("--") => Err(format!("未知的选项: {}", other)),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// 执行命令行命令
+/// 结果以 JSON 输出到标准输出，错误输出到标准错误
+///
+/// # 参数
+/// * `command` - 命令
+///
+/// # 返回值
+/// * `CliOutcome` - 退出码，或需要以无窗口模式启动时的服务器ID
+pub fn execute(command: CliCommand) -> CliOutcome {
+    match run_command(command) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("错误: {:#}", e);
+            CliOutcome::Exit(1)
+        }
+    }
+}
+
+/// 打印用法说明
+pub fn print_usage() {
+    println!("{}", USAGE);
+}
+
+/// 执行命令
+fn run_command(command: CliCommand) -> Result<CliOutcome> {
+    let config = AppConfig::load()?;
+
+    match command {
+        CliCommand::Help => {
+            print_usage();
+        }
+        CliCommand::List => {
+            let servers: Vec<serde_json::Value> = config.servers.iter()
+                .map(|s| json!({
+                    "id": s.id,
+                    "name": s.name,
+                    "protocol": s.protocol,
+                    "address": s.address,
+                    "port": s.port,
+                    "favorite": s.favorite,
+                    "current": config.current_server.as_deref() == Some(s.id.as_str()),
+                }))
+                .collect();
+            print_json(&json!(servers));
+        }
+        CliCommand::Status => match call_running(&config, IpcRequest::Status, "GET", "/status", None)? {
+            Some(status) => print_json(&status),
+            None => print_json(&json!({ "is_running": false, "app_running": false })),
+        },
+        CliCommand::Disconnect => match call_running(&config, IpcRequest::Stop, "POST", "/stop", None)? {
+            Some(result) => print_json(&result),
+            None => print_json(&json!({ "ok": true, "app_running": false })),
+        },
+        CliCommand::Connect(target) => {
+            let server = config.servers.iter()
+                .find(|s| s.id == target)
+                .or_else(|| config.servers.iter().find(|s| s.name.eq_ignore_ascii_case(&target)))
+                .with_context(|| format!("找不到服务器: {}", target))?;
+
+            let body = json!({ "server_id": server.id });
+            let request = IpcRequest::Start { server_id: server.id.clone() };
+            match call_running(&config, request, "POST", "/switch", Some(&body))? {
+                Some(result) => print_json(&result),
+                None => return Ok(CliOutcome::Headless(server.id.clone())),
+            }
+        }
+    }
+    Ok(CliOutcome::Exit(0))
+}
+
+/// 调用正在运行的实例
+/// 优先通过本机 IPC 发送请求，无法连接 IPC 时改用控制 API
+///
+/// # 返回值
+/// * `Result<Option<serde_json::Value>>` - 响应内容；RuRay 未运行时为 None
+fn call_running(
+    config: &AppConfig,
+    request: IpcRequest,
+    method: &str,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<Option<serde_json::Value>> {
+    match call_ipc(request) {
+        Some(response) => match response? {
+            IpcResponse::Ok => Ok(Some(json!({ "ok": true }))),
+            IpcResponse::Status(status) => Ok(Some(serde_json::to_value(status)?)),
+            IpcResponse::Error { message, .. } => Err(anyhow::anyhow!("RuRay 返回错误: {}", message)),
+            other => Err(anyhow::anyhow!("IPC 响应无效: {:?}", other)),
+        },
+        None => call_api(config, method, path, body),
+    }
+}
+
+/// 通过本机 IPC 发送请求
+///
+/// # 返回值
+/// * `Option<Result<IpcResponse>>` - 响应；无法连接或握手失败（RuRay 未运行）时为 None
+fn call_ipc(request: IpcRequest) -> Option<Result<IpcResponse>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().ok()?;
+    runtime.block_on(async {
+        let mut client = tokio::time::timeout(CONNECT_TIMEOUT, ipc::connect()).await.ok()?.ok()?;
+        let response = tokio::time::timeout(RESPONSE_TIMEOUT, client.request(request)).await;
+        Some(response.context("等待 IPC 响应超时").and_then(|response| response))
+    })
+}
+
+/// 调用正在运行实例的控制 API
+///
+/// # 返回值
+/// * `Result<Option<serde_json::Value>>` - 响应内容；RuRay 未运行时为 None
+///
+/// # 异常
+/// * RuRay 正在运行但未启用控制 API，或接口返回错误时返回错误
+fn call_api(config: &AppConfig, method: &str, path: &str, body: Option<&serde_json::Value>) -> Result<Option<serde_json::Value>> {
+    let api = &config.control_api;
+    if !api.enabled || api.token.is_empty() {
+        if is_app_running() {
+            return Err(anyhow::anyhow!("RuRay 正在运行，但未启用控制 API，请在设置中启用后重试"));
+        }
+        return Ok(None);
+    }
+
+    match request(api, method, path, body) {
+        Ok((200, response)) => Ok(Some(response)),
+        Ok((status, response)) => {
+            let message = response.get("error").and_then(|e| e.as_str()).unwrap_or("未知错误").to_string();
+            Err(anyhow::anyhow!("控制 API 返回 {}: {}", status, message))
+        }
+        // 连接失败且没有运行中的实例，视为 RuRay 未运行
+        Err(_) if !is_app_running() => Ok(None),
+        Err(e) => Err(e.context("无法连接控制 API")),
+    }
+}
+
+/// 发送 HTTP 请求到控制 API
+fn request(api: &ControlApiConfig, method: &str, path: &str, body: Option<&serde_json::Value>) -> Result<(u16, serde_json::Value)> {
+    let address = SocketAddr::from(([127, 0, 0, 1], api.port));
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).context("连接失败")?;
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT)).ok();
+
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        api.port,
+        api.token,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).context("发送请求失败")?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).context("读取响应失败")?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").context("响应格式无效")?;
+    let status = head.split_whitespace().nth(1)
+        .and_then(|code| code.parse().ok())
+        .context("响应格式无效")?;
+    let body = serde_json::from_str(body).unwrap_or(serde_json::Value::Null);
+    Ok((status, body))
+}
+
+/// 检查是否有其他 RuRay 进程正在运行
+fn is_app_running() -> bool {
+    let Some(exe_name) = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_lowercase()))
+    else {
+        return false;
+    };
+    let own_pid = std::process::id();
+
+    let mut system = System::new();
+    system.refresh_processes();
+    system.processes()
+        .iter()
+        .any(|(pid, process)| pid.as_u32() != own_pid && process.name().to_lowercase() == exe_name)
+}
+
+/// 以格式化 JSON 输出结果
+fn print_json(value: &serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+}
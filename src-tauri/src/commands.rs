@@ -6,14 +6,45 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::Emitter;
+use tauri::{Emitter, State};
 use uuid::Uuid;
 
-use crate::config::AppConfig;
-use crate::proxy::ProxyManager;
-use crate::system::SystemManager;
-use crate::tun::{TunConfig, TunManager, TunStatus};
-use crate::xray::XrayManager;
+use crate::api_server::ApiServer;
+use crate::app_lock::AppLockStatus;
+use crate::backup::{self, BackupManifest};
+use crate::bandwidth::BandwidthLimitConfig;
+use crate::config::{AppConfig, ConfigRecovery, ControlApiConfig, InboundUser, RoutingRule, ServerOverrides};
+use crate::connection_log;
+use crate::core_backend::{self, CoreKind, SingBoxBackend};
+use crate::domain_override::{DomainOverride, OverrideTarget};
+use crate::error::{AppError, ErrorCode, ResultExt};
+use crate::geodata::{self, GeoCategory, GeoFileInfo, GeoKind, GeoQueryMatch};
+use crate::history::TrafficHistoryPoint;
+use crate::hooks::{self, HookEvent};
+use crate::notify::{self, NotifyEvent};
+use crate::logger::{read_app_log_entries, read_log_tail, LogEntry, LogLevel, Logger};
+use crate::monitor::{lookup_external_ip, ExternalIpReport, UdpRelayStatus};
+use crate::privacy;
+use crate::pac::{self, PacTestResult};
+use crate::profile::Profile;
+use crate::proxy::{AdoptableCore, CrashReport};
+use crate::routing::{self, RouteSimulation};
+use crate::app_info::{self, AppInfo};
+use crate::self_test::{self, SelfTestReport};
+use crate::setup_audit::{self, SetupAuditReport};
+use crate::smart_routing::SmartRouteDecision;
+use crate::speed_test::{SpeedTestManager, SpeedTestResult};
+use crate::state::AppState;
+use crate::subscription::{self, Subscription, SubscriptionInfo, SubscriptionManager};
+use crate::template;
+use crate::system::{ConflictWarning, SystemManager, SystemProxyCapability, SystemProxyError};
+use crate::traffic::{TrafficManager, TrafficReport};
+use crate::tun::{TunConfig, TunRoute, TunStatus};
+use crate::validation;
+use crate::webdav::{WebDavSync, WebDavSyncStatus};
+use crate::window_state;
+use crate::xray::{GeoUpdateInfo, InstalledCore, XrayManager};
+use crate::{log_info, log_warn};
 
 /// 服务器信息结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,13 +57,42 @@ pub struct ServerInfo {
     pub config: HashMap<String, serde_json::Value>,
     pub created_at: String,
     pub updated_at: String,
+    /// 最近一次连接时间
+    #[serde(default)]
+    pub last_connected_at: Option<String>,
+    /// 最近一次连接测试的延迟（毫秒），测试失败时为空
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// 是否收藏
+    #[serde(default)]
+    pub favorite: bool,
+    /// 对全局设置的覆盖（代理模式、TUN 开关、流量嗅探、入站端口）
+    #[serde(default, skip_serializing_if = "ServerOverrides::is_empty")]
+    pub overrides: ServerOverrides,
+}
+
+/// 服务器列表过滤条件，各条件同时满足
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerFilter {
+    /// 协议
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// 分组（服务器配置中的 `group`，未设置时为所属订阅ID）
+    #[serde(default)]
+    pub group: Option<String>,
+    /// 标签（服务器配置中的 `tags` 数组）
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// 关键字，匹配名称和地址（不区分大小写）
+    #[serde(default)]
+    pub keyword: Option<String>,
 }
 
 /// 代理状态结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyStatus {
     pub is_running: bool,
-    pub status: String, // "connected" | "connecting" | "disconnected"
+    pub status: String, // "connected" | "connecting" | "restarting" | "disconnected"
     pub current_server: Option<String>,
     pub proxy_mode: String,
     pub uptime: u64,
@@ -40,6 +100,30 @@ pub struct ProxyStatus {
     pub download_speed: u64,
     pub total_upload: u64,
     pub total_download: u64,
+    /// 本次连接中 Xray Core 自动重启的次数
+    #[serde(default)]
+    pub restart_count: u32,
+    /// 最近一次异常退出记录
+    #[serde(default)]
+    pub last_crash: Option<CrashReport>,
+    /// 最近一次 UDP 中继测试结果，未测试时为空
+    #[serde(default)]
+    pub udp_relay: Option<UdpRelayStatus>,
+    /// 本次运行以来 Xray Core 自动重启的总次数（不随重新连接清零）
+    #[serde(default)]
+    pub total_restarts: u32,
+    /// 最近一次错误（连通性检测失败或核心异常退出），连接后尚未出错时为空
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// 最近一次连通性检测成功的时间（RFC 3339），连接后尚未检测成功时为空
+    #[serde(default)]
+    pub last_healthy_at: Option<String>,
+    /// 连通性检测是否正常；为 false 时代理进程仍在运行但隧道已失效
+    #[serde(default)]
+    pub healthy: bool,
+    /// 默认路由使用的出站标签
+    #[serde(default)]
+    pub active_outbound: Option<String>,
 }
 
 /// 系统统计信息结构体
@@ -51,66 +135,477 @@ pub struct SystemStats {
     pub memory_used: u64,
     pub network_upload: u64,
     pub network_download: u64,
+    /// 代理核心（xray/sing-box）子进程的资源占用，代理未运行时为空
+    pub core_process: Option<ProcessStats>,
+    /// RuRay 自身进程的资源占用
+    /// TUN 模式的数据包处理运行在本进程的后台任务中，其开销包含在内
+    pub app_process: Option<ProcessStats>,
+}
+
+/// 进程资源占用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessStats {
+    /// 进程ID
+    pub pid: u32,
+    /// 进程名称
+    pub name: String,
+    /// CPU 使用率（占全部核心的百分比，与 `cpu_usage` 可直接比较）
+    pub cpu_usage: f32,
+    /// 常驻内存（字节）
+    pub memory: u64,
 }
 
 /// 获取服务器列表
 #[tauri::command]
-pub async fn get_servers() -> Result<Vec<ServerInfo>, String> {
-    let config = AppConfig::load().map_err(|e| e.to_string())?;
+pub async fn get_servers(state: State<'_, AppState>) -> Result<Vec<ServerInfo>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
     Ok(config.servers)
 }
 
 /// 添加服务器
+/// 保存前规范化并校验，校验失败时返回 `VALIDATION_FAILED` 错误，`details` 为各字段的错误
 #[tauri::command]
-pub async fn add_server(server: ServerInfo) -> Result<String, String> {
-    let mut config = AppConfig::load().map_err(|e| e.to_string())?;
+pub async fn add_server(state: State<'_, AppState>, server: ServerInfo) -> Result<String, AppError> {
+    state.app_lock.ensure_unlocked()?;
     let mut new_server = server;
     new_server.id = Uuid::new_v4().to_string();
+    validation::sanitize_server(&mut new_server);
+    validation::validate_server(&new_server, &state.config.get()?.servers)?;
     new_server.created_at = chrono::Utc::now().to_rfc3339();
     new_server.updated_at = new_server.created_at.clone();
     
-    config.servers.push(new_server.clone());
-    config.save().map_err(|e| e.to_string())?;
+    let server_id = new_server.id.clone();
+    state.config.update(|config| config.servers.push(new_server))?;
+    state.config.emit_servers_changed("added", &[server_id.clone()]);
     
-    Ok(new_server.id)
+    Ok(server_id)
 }
 
 /// 更新服务器
+/// 保存前规范化并校验，校验失败时返回 `VALIDATION_FAILED` 错误，`details` 为各字段的错误
 #[tauri::command]
-pub async fn update_server(server: ServerInfo) -> Result<(), String> {
-    let mut config = AppConfig::load().map_err(|e| e.to_string())?;
+pub async fn update_server(state: State<'_, AppState>, server: ServerInfo) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let mut server = server;
+    validation::sanitize_server(&mut server);
+    validation::validate_server(&server, &state.config.get()?.servers)?;
     
-    if let Some(existing_server) = config.servers.iter_mut().find(|s| s.id == server.id) {
-        existing_server.name = server.name;
-        existing_server.protocol = server.protocol;
-        existing_server.address = server.address;
-        existing_server.port = server.port;
-        existing_server.config = server.config;
-        existing_server.updated_at = chrono::Utc::now().to_rfc3339();
-        
-        config.save().map_err(|e| e.to_string())?;
-        Ok(())
-    } else {
-        Err("服务器不存在".to_string())
-    }
+    let server_id = server.id.clone();
+    state.config
+        .update(|config| {
+            let existing_server = config.servers.iter_mut().find(|s| s.id == server.id)?;
+            existing_server.name = server.name;
+            existing_server.protocol = server.protocol;
+            existing_server.address = server.address;
+            existing_server.port = server.port;
+            existing_server.config = server.config;
+            existing_server.overrides = server.overrides;
+            existing_server.updated_at = chrono::Utc::now().to_rfc3339();
+            Some(())
+        })?
+        .ok_or_else(|| AppError::not_found("服务器不存在"))?;
+    state.config.emit_servers_changed("updated", &[server_id]);
+    Ok(())
 }
 
 /// 删除服务器
 #[tauri::command]
-pub async fn delete_server(server_id: String) -> Result<(), String> {
-    let mut config = AppConfig::load().map_err(|e| e.to_string())?;
-    
-    // 查找要删除的服务器信息，用于清理配置文件
-    let server_to_delete = config.servers.iter().find(|s| s.id == server_id);
+pub async fn delete_server(state: State<'_, AppState>, server_id: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let removed = state.config.update(|config| {
+        config.speed_test_results.remove(&server_id);
+        let index = config.servers.iter().position(|s| s.id == server_id)?;
+        Some(config.servers.remove(index))
+    })?;
     
-    if let Some(server) = server_to_delete {
+    if let Some(server) = removed {
         // 清理对应的配置文件
-        let proxy_manager = ProxyManager::instance();
-        let _ = proxy_manager.cleanup_server_config(&server.id, &server.name);
+        let _ = state.proxy.cleanup_server_config(&server.id, &server.name);
     }
     
-    config.servers.retain(|s| s.id != server_id);
-    config.save().map_err(|e| e.to_string())?;
+    state.config.emit_servers_changed("removed", &[server_id]);
+    Ok(())
+}
+
+// ==================== 订阅相关命令 ====================
+
+/// 获取订阅列表
+#[tauri::command]
+pub async fn get_subscriptions(state: State<'_, AppState>) -> Result<Vec<Subscription>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    Ok(config.subscriptions)
+}
+
+/// 添加订阅
+///
+/// # 参数
+/// * `name` - 订阅名称
+/// * `url` - 订阅地址
+/// * `name_template` - 节点命名模板，如 `{flag} {name} {latency}`，为空时保留原始名称
+///
+/// # 返回值
+/// * `Result<String, AppError>` - 新订阅的ID
+#[tauri::command]
+pub async fn add_subscription(state: State<'_, AppState>, name: String, url: String, name_template: Option<String>) -> Result<String, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    url::Url::parse(&url).map_err(|e| AppError::invalid_input(format!("订阅地址无效: {}", e)))?;
+
+    let subscription = Subscription {
+        id: Uuid::new_v4().to_string(),
+        name,
+        url,
+        info: None,
+        last_updated: None,
+        name_template: name_template.filter(|t| !t.trim().is_empty()),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let id = subscription.id.clone();
+
+    state.config.update(|config| config.subscriptions.push(subscription))?;
+
+    Ok(id)
+}
+
+/// 删除订阅
+///
+/// # 参数
+/// * `subscription_id` - 订阅ID
+/// * `remove_servers` - 是否同时删除该订阅导入的服务器
+///
+/// # 返回值
+/// * `Result<(), AppError>` - 删除结果
+#[tauri::command]
+pub async fn delete_subscription(state: State<'_, AppState>, subscription_id: String, remove_servers: bool) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let removed = state.config.update(|config| {
+        config.subscriptions.retain(|s| s.id != subscription_id);
+        if !remove_servers {
+            return Vec::new();
+        }
+        let (removed, rest): (Vec<ServerInfo>, Vec<ServerInfo>) = config.servers
+            .drain(..)
+            .partition(|server| server_subscription_id(server) == Some(subscription_id.as_str()));
+        config.servers = rest;
+        removed
+    })?;
+
+    for server in &removed {
+        let _ = state.proxy.cleanup_server_config(&server.id, &server.name);
+    }
+    if !removed.is_empty() {
+        let removed_ids: Vec<String> = removed.iter().map(|s| s.id.clone()).collect();
+        state.config.emit_servers_changed("removed", &removed_ids);
+    }
+    Ok(())
+}
+
+/// 更新订阅
+/// 拉取订阅内容替换该订阅下的服务器列表，并记录 `subscription-userinfo` 中的流量与到期信息。
+/// 名称、地址、端口和协议均未变化的节点沿用原有ID，以保留测速和流量统计。
+/// 订阅中完全相同的节点只保留一个，设置了命名模板时重新套用模板生成节点名称
+///
+/// # 参数
+/// * `subscription_id` - 订阅ID
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 更新后该订阅下的服务器数量
+#[tauri::command]
+pub async fn update_subscription(state: State<'_, AppState>, subscription_id: String) -> Result<usize, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let url = {
+        let config = state.config.get()?;
+        config.subscriptions.iter()
+            .find(|s| s.id == subscription_id)
+            .map(|s| s.url.clone())
+            .ok_or_else(|| AppError::not_found(format!("订阅不存在: {}", subscription_id)))?
+    };
+
+    let subscription_manager = SubscriptionManager::new();
+    let (mut servers, info) = subscription_manager.fetch(&url).await
+        .app_context("更新订阅失败")?;
+    let duplicates = subscription::dedup_servers(&mut servers);
+    if duplicates > 0 {
+        log_info!("订阅 {} 中有 {} 个重复节点已忽略", subscription_id, duplicates);
+    }
+
+    // 在配置存储的写锁内合并，避免覆盖拉取期间的其他修改
+    let (server_ids, subscription_name) = state.config.update(|config| {
+        let (old_servers, mut other_servers): (Vec<ServerInfo>, Vec<ServerInfo>) = config.servers
+            .drain(..)
+            .partition(|s| server_subscription_id(s) == Some(subscription_id.as_str()));
+
+        for server in &mut servers {
+            server.config.insert("subscription_id".to_string(), serde_json::json!(subscription_id));
+            // 旧节点的名称可能已套用模板，按原始名称比较
+            if let Some(old) = old_servers.iter().find(|o| {
+                original_server_name(o) == server.name && o.address == server.address && o.port == server.port && o.protocol == server.protocol
+            }) {
+                server.id = old.id.clone();
+                server.created_at = old.created_at.clone();
+                server.latency_ms = old.latency_ms;
+            }
+        }
+
+        let mut subscription_name = String::new();
+        if let Some(subscription) = config.subscriptions.iter_mut().find(|s| s.id == subscription_id) {
+            subscription.info = info;
+            subscription.last_updated = Some(chrono::Utc::now().to_rfc3339());
+            subscription_name = subscription.name.clone();
+            subscription::apply_name_template_to_servers(&mut servers, subscription.name_template.as_deref(), &subscription.name);
+        }
+
+        let server_ids: Vec<String> = servers.iter().map(|s| s.id.clone()).collect();
+        other_servers.extend(servers);
+        config.servers = other_servers;
+        (server_ids, subscription_name)
+    })?;
+    let count = server_ids.len();
+    state.config.emit_servers_changed("updated", &server_ids);
+    notify::notify(NotifyEvent::SubscriptionUpdated, &[&subscription_name, &count.to_string()]);
+
+    Ok(count)
+}
+
+/// 获取订阅的流量与到期信息
+///
+/// # 参数
+/// * `subscription_id` - 订阅ID
+///
+/// # 返回值
+/// * `Result<Option<SubscriptionInfo>, AppError>` - 最近一次更新时解析到的信息，服务商未提供时为 None
+#[tauri::command]
+pub async fn get_subscription_info(state: State<'_, AppState>, subscription_id: String) -> Result<Option<SubscriptionInfo>, AppError> {
+    let config = state.config.get()?;
+    config.subscriptions.iter()
+        .find(|s| s.id == subscription_id)
+        .map(|s| s.info.clone())
+        .ok_or_else(|| AppError::not_found(format!("订阅不存在: {}", subscription_id)))
+}
+
+/// 设置订阅的节点命名模板
+/// 立即按原始名称对该订阅下的现有节点重新命名，之后每次更新订阅都会重新套用
+///
+/// # 参数
+/// * `subscription_id` - 订阅ID
+/// * `name_template` - 命名模板，支持 `{flag}`、`{country}`、`{name}`、`{protocol}`、`{latency}`、`{subscription}`，为空时恢复原始名称
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 重新命名的服务器数量
+#[tauri::command]
+pub async fn set_subscription_name_template(state: State<'_, AppState>, subscription_id: String, name_template: Option<String>) -> Result<usize, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let name_template = name_template.filter(|t| !t.trim().is_empty());
+
+    let server_ids = state.config
+        .update(|config| {
+            let subscription = config.subscriptions.iter_mut().find(|s| s.id == subscription_id)?;
+            subscription.name_template = name_template.clone();
+            let subscription_name = subscription.name.clone();
+
+            let (mut servers, other_servers): (Vec<ServerInfo>, Vec<ServerInfo>) = config.servers
+                .drain(..)
+                .partition(|s| server_subscription_id(s) == Some(subscription_id.as_str()));
+            subscription::apply_name_template_to_servers(&mut servers, name_template.as_deref(), &subscription_name);
+
+            let server_ids: Vec<String> = servers.iter().map(|s| s.id.clone()).collect();
+            config.servers = other_servers;
+            config.servers.extend(servers);
+            Some(server_ids)
+        })?
+        .ok_or_else(|| AppError::not_found(format!("订阅不存在: {}", subscription_id)))?;
+    if !server_ids.is_empty() {
+        state.config.emit_servers_changed("updated", &server_ids);
+    }
+
+    Ok(server_ids.len())
+}
+
+/// 获取服务器所属的订阅ID
+fn server_subscription_id(server: &ServerInfo) -> Option<&str> {
+    server.config.get("subscription_id").and_then(|v| v.as_str())
+}
+
+/// 获取订阅节点的原始名称，未套用过命名模板时即为当前名称
+fn original_server_name(server: &ServerInfo) -> &str {
+    server.config.get(subscription::ORIGINAL_NAME_KEY)
+        .and_then(|v| v.as_str())
+        .unwrap_or(&server.name)
+}
+
+// ==================== 配置方案相关命令 ====================
+
+/// 获取配置方案列表
+/// 首次调用时会以当前设置创建默认方案
+///
+/// # 返回值
+/// * `Result<Vec<Profile>, AppError>` - 配置方案列表
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<Profile>, AppError> {
+    let config = state.config.get()?;
+    if !config.profiles.is_empty() {
+        return Ok(config.profiles);
+    }
+    state.config
+        .update(|config| {
+            config.ensure_profiles();
+            config.profiles.clone()
+        })
+        .map_err(AppError::from)
+}
+
+/// 切换配置方案
+/// 先把当前设置保存回原方案，再应用目标方案；代理运行中时按 停止 TUN 与核心 → 切换服务器 → 按新方案启用 TUN
+/// 的顺序重启代理并重新配置系统代理；代理未运行而 TUN 单独运行时，按新方案的 TUN 开关停止 TUN
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄，用于发送状态事件
+/// * `profile_id` - 目标方案ID
+///
+/// # 返回值
+/// * `Result<(), AppError>` - 切换结果
+#[tauri::command]
+pub async fn switch_profile(app_handle: tauri::AppHandle, state: State<'_, AppState>, profile_id: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let (config, profile) = state.config
+        .update(|config| {
+            config.ensure_profiles();
+            config.sync_active_profile();
+            let profile = config.profiles.iter().find(|p| p.id == profile_id).cloned()?;
+            profile.apply_to(config);
+            Some((config.clone(), profile))
+        })?
+        .ok_or_else(|| AppError::not_found(format!("配置方案不存在: {}", profile_id)))?;
+    log_info!("已切换到配置方案: {}", profile.name);
+
+    let _ = app_handle.emit("profile-changed", serde_json::json!({
+        "profile_id": profile.id,
+        "name": profile.name
+    }));
+
+    // 代理运行中时按新方案重新启动
+    let proxy_manager = state.proxy;
+    if proxy_manager.is_process_running() {
+        let current_server = proxy_manager.current_server_id();
+        let target_server = current_server
+            .filter(|id| profile.is_server_enabled(id))
+            .and_then(|id| config.servers.iter().find(|s| s.id == id))
+            .or_else(|| config.servers.iter().find(|s| profile.is_server_enabled(&s.id)))
+            .cloned();
+
+        stop_proxy(state.clone()).await?;
+
+        if let Some(server) = target_server {
+            // 端口和路由可能已变化，必须重新生成配置文件
+            proxy_manager.regenerate_config(&server).await
+                .app_context("重新生成配置文件失败")?;
+            start_proxy(state.clone(), server.id.clone()).await?;
+        } else {
+            log_warn!("配置方案 {} 中没有可用的服务器，代理已停止", profile.name);
+        }
+
+        let status = get_proxy_status(state.clone()).await?;
+        let _ = app_handle.emit("proxy-status-changed", serde_json::json!({
+            "is_running": status.is_running,
+            "current_server": proxy_manager.current_server_id()
+        }));
+    } else if !AppConfig::load_active()?.tun_enabled && state.tun.is_running().await {
+        // TUN 没有可转发的 SOCKS 端口，按新方案关闭
+        state.tun.set_system_route(false).await?;
+        state.tun.stop().await?;
+        log_info!("配置方案 {} 未启用 TUN 模式，已停止 TUN", profile.name);
+    }
+
+    Ok(())
+}
+
+/// 复制配置方案
+///
+/// # 参数
+/// * `profile_id` - 源方案ID
+/// * `name` - 新方案名称
+///
+/// # 返回值
+/// * `Result<Profile, AppError>` - 新创建的方案
+#[tauri::command]
+pub async fn duplicate_profile(state: State<'_, AppState>, profile_id: String, name: String) -> Result<Profile, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    state.config
+        .update(|config| {
+            config.ensure_profiles();
+            config.sync_active_profile();
+
+            let source = config.profiles.iter().find(|p| p.id == profile_id)?;
+            let now = chrono::Utc::now().to_rfc3339();
+            let profile = Profile {
+                id: Uuid::new_v4().to_string(),
+                name,
+                created_at: now.clone(),
+                updated_at: now,
+                ..source.clone()
+            };
+
+            config.profiles.push(profile.clone());
+            Some(profile)
+        })?
+        .ok_or_else(|| AppError::not_found(format!("配置方案不存在: {}", profile_id)))
+}
+
+/// 重命名配置方案
+///
+/// # 参数
+/// * `profile_id` - 方案ID
+/// * `name` - 新名称
+#[tauri::command]
+pub async fn rename_profile(state: State<'_, AppState>, profile_id: String, name: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    state.config
+        .update(|config| {
+            let profile = config.profiles.iter_mut().find(|p| p.id == profile_id)?;
+            profile.name = name;
+            profile.updated_at = chrono::Utc::now().to_rfc3339();
+            Some(())
+        })?
+        .ok_or_else(|| AppError::not_found(format!("配置方案不存在: {}", profile_id)))
+}
+
+/// 设置配置方案启用的服务器
+///
+/// # 参数
+/// * `profile_id` - 方案ID
+/// * `server_ids` - 启用的服务器ID列表，为空时表示启用全部服务器
+#[tauri::command]
+pub async fn set_profile_servers(state: State<'_, AppState>, profile_id: String, server_ids: Option<Vec<String>>) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    state.config
+        .update(|config| {
+            let profile = config.profiles.iter_mut().find(|p| p.id == profile_id)?;
+            profile.enabled_servers = server_ids;
+            profile.updated_at = chrono::Utc::now().to_rfc3339();
+            Some(())
+        })?
+        .ok_or_else(|| AppError::not_found(format!("配置方案不存在: {}", profile_id)))
+}
+
+/// 删除配置方案
+/// 当前激活的方案不能删除
+///
+/// # 参数
+/// * `profile_id` - 方案ID
+#[tauri::command]
+pub async fn delete_profile(state: State<'_, AppState>, profile_id: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let deleted = state.config.update(|config| {
+        if config.active_profile.as_deref() == Some(profile_id.as_str()) {
+            return false;
+        }
+        config.profiles.retain(|p| p.id != profile_id);
+        true
+    })?;
+    if !deleted {
+        return Err(AppError::invalid_input("不能删除当前正在使用的配置方案"));
+    }
     Ok(())
 }
 
@@ -122,50 +617,50 @@ pub async fn delete_server(server_id: String) -> Result<(), String> {
 /// * `config` - TUN配置
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 启动结果
+/// * `Result<(), AppError>` - 启动结果
 #[tauri::command]
-pub async fn start_tun_mode(config: TunConfig) -> Result<(), String> {
-    let tun_manager = TunManager::instance();
-    tun_manager.start(config).await.map_err(|e| e.to_string())
+pub async fn start_tun_mode(state: State<'_, AppState>, config: TunConfig) -> Result<(), AppError> {
+    let tun_manager = state.tun;
+    tun_manager.start(config).await.map_err(AppError::from)
 }
 
 /// 停止TUN模式
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 停止结果
+/// * `Result<(), AppError>` - 停止结果
 #[tauri::command]
-pub async fn stop_tun_mode() -> Result<(), String> {
-    let tun_manager = TunManager::instance();
-    tun_manager.stop().await.map_err(|e| e.to_string())
+pub async fn stop_tun_mode(state: State<'_, AppState>) -> Result<(), AppError> {
+    let tun_manager = state.tun;
+    tun_manager.stop().await.map_err(AppError::from)
 }
 
 /// 获取TUN模式状态
 /// 
 /// # 返回值
-/// * `Result<TunStatus, String>` - TUN状态
+/// * `Result<TunStatus, AppError>` - TUN状态
 #[tauri::command]
-pub async fn get_tun_status() -> Result<TunStatus, String> {
-    let tun_manager = TunManager::instance();
+pub async fn get_tun_status(state: State<'_, AppState>) -> Result<TunStatus, AppError> {
+    let tun_manager = state.tun;
     Ok(tun_manager.get_status().await)
 }
 
 /// 检查TUN模式是否运行中
 /// 
 /// # 返回值
-/// * `Result<bool, String>` - 是否运行中
+/// * `Result<bool, AppError>` - 是否运行中
 #[tauri::command]
-pub async fn is_tun_running() -> Result<bool, String> {
-    let tun_manager = TunManager::instance();
+pub async fn is_tun_running(state: State<'_, AppState>) -> Result<bool, AppError> {
+    let tun_manager = state.tun;
     Ok(tun_manager.is_running().await)
 }
 
 /// 获取TUN配置
 /// 
 /// # 返回值
-/// * `Result<TunConfig, String>` - TUN配置
+/// * `Result<TunConfig, AppError>` - TUN配置
 #[tauri::command]
-pub async fn get_tun_config() -> Result<TunConfig, String> {
-    let tun_manager = TunManager::instance();
+pub async fn get_tun_config(state: State<'_, AppState>) -> Result<TunConfig, AppError> {
+    let tun_manager = state.tun;
     Ok(tun_manager.get_config().await)
 }
 
@@ -175,11 +670,12 @@ pub async fn get_tun_config() -> Result<TunConfig, String> {
 /// * `config` - 新的TUN配置
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 更新结果
+/// * `Result<(), AppError>` - 更新结果
 #[tauri::command]
-pub async fn update_tun_config(config: TunConfig) -> Result<(), String> {
-    let tun_manager = TunManager::instance();
-    tun_manager.update_config(config).await.map_err(|e| e.to_string())
+pub async fn update_tun_config(state: State<'_, AppState>, config: TunConfig) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let tun_manager = state.tun;
+    tun_manager.update_config(config).await.map_err(AppError::from)
 }
 
 /// 保存TUN配置到文件
@@ -188,17 +684,16 @@ pub async fn update_tun_config(config: TunConfig) -> Result<(), String> {
 /// * `config` - 要保存的TUN配置
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 保存结果
+/// * `Result<(), AppError>` - 保存结果
 #[tauri::command]
-pub async fn save_tun_config(config: TunConfig) -> Result<(), String> {
+pub async fn save_tun_config(state: State<'_, AppState>, config: TunConfig) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
     // 更新TUN管理器中的配置
-    let tun_manager = TunManager::instance();
-    tun_manager.update_config(config.clone()).await.map_err(|e| e.to_string())?;
+    let tun_manager = state.tun;
+    tun_manager.update_config(config.clone()).await?;
     
     // 保存到应用配置文件
-    let mut app_config = AppConfig::load().map_err(|e| e.to_string())?;
-    app_config.tun_config = config;
-    app_config.save().map_err(|e| e.to_string())?;
+    state.config.update(|app_config| app_config.tun_config = config)?;
     
     Ok(())
 }
@@ -209,11 +704,30 @@ pub async fn save_tun_config(config: TunConfig) -> Result<(), String> {
 /// * `enable` - 是否启用路由
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 设置结果
+/// * `Result<(), AppError>` - 设置结果
+#[tauri::command]
+pub async fn set_tun_system_route(state: State<'_, AppState>, enable: bool) -> Result<(), AppError> {
+    let tun_manager = state.tun;
+    tun_manager.set_system_route(enable).await.map_err(AppError::from)
+}
+
+/// 获取指向 TUN 虚拟网卡的系统路由
+///
+/// # 返回值
+/// * `Result<Vec<TunRoute>, AppError>` - 路由列表，`stale` 为 true 的是 TUN 未运行时残留的路由
+#[tauri::command]
+pub async fn get_tun_routes(state: State<'_, AppState>) -> Result<Vec<TunRoute>, AppError> {
+    state.tun.get_tun_routes().await.map_err(AppError::from)
+}
+
+/// 删除崩溃后残留的 TUN 路由
+/// 残留的默认路由会使系统无法联网，TUN 运行中时不删除任何路由
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 删除的路由数量
 #[tauri::command]
-pub async fn set_tun_system_route(enable: bool) -> Result<(), String> {
-    let tun_manager = TunManager::instance();
-    tun_manager.set_system_route(enable).await.map_err(|e| e.to_string())
+pub async fn cleanup_orphan_routes(state: State<'_, AppState>) -> Result<usize, AppError> {
+    state.tun.cleanup_orphan_routes().await.map_err(AppError::from)
 }
 
 /// 切换TUN模式开关
@@ -222,36 +736,32 @@ pub async fn set_tun_system_route(enable: bool) -> Result<(), String> {
 /// * `enabled` - 是否启用TUN模式
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 切换结果
+/// * `Result<(), AppError>` - 切换结果
 #[tauri::command]
-pub async fn toggle_tun_mode(enabled: bool) -> Result<(), String> {
-    let mut config = AppConfig::load().map_err(|e| e.to_string())?;
-    config.tun_enabled = enabled;
-    config.save().map_err(|e| e.to_string())?;
+pub async fn toggle_tun_mode(state: State<'_, AppState>, enabled: bool) -> Result<(), AppError> {
+    let tun_config = state.config.update(|config| {
+        config.tun_enabled = enabled;
+        config.tun_config.clone()
+    })?;
     
-    let tun_manager = TunManager::instance();
+    let tun_manager = state.tun;
     
     if enabled {
         // 启用TUN模式
-        let tun_config = config.tun_config.clone();
         if let Err(e) = tun_manager.start(tun_config).await {
             // TUN启动失败时，重置配置并保存
-            let mut reset_config = AppConfig::load().map_err(|e| e.to_string())?;
-            reset_config.tun_enabled = false;
-            reset_config.save().map_err(|e| e.to_string())?;
-            return Err(e.to_string());
+            state.config.update(|config| config.tun_enabled = false)?;
+            return Err(e.into());
         }
         if let Err(e) = tun_manager.set_system_route(true).await {
             // 设置系统路由失败时，重置配置并保存
-            let mut reset_config = AppConfig::load().map_err(|e| e.to_string())?;
-            reset_config.tun_enabled = false;
-            reset_config.save().map_err(|e| e.to_string())?;
-            return Err(e.to_string());
+            state.config.update(|config| config.tun_enabled = false)?;
+            return Err(e.into());
         }
     } else {
         // 禁用TUN模式
-        tun_manager.set_system_route(false).await.map_err(|e| e.to_string())?;
-        tun_manager.stop().await.map_err(|e| e.to_string())?;
+        tun_manager.set_system_route(false).await?;
+        tun_manager.stop().await?;
     }
     
     Ok(())
@@ -260,247 +770,1604 @@ pub async fn toggle_tun_mode(enabled: bool) -> Result<(), String> {
 /// 测试服务器连接
 /// 使用真实的 Xray 环境进行连接测试
 #[tauri::command]
-pub async fn test_server_connection(server_id: String) -> Result<serde_json::Value, String> {
-    let config = AppConfig::load().map_err(|e| e.to_string())?;
+pub async fn test_server_connection(state: State<'_, AppState>, server_id: String) -> Result<serde_json::Value, AppError> {
+    let config = state.config.get()?;
     
     if let Some(server) = config.servers.iter().find(|s| s.id == server_id) {
-        // 创建临时的代理管理器进行测试
-        let proxy_manager = ProxyManager::instance();
-        
-        let start_time = std::time::Instant::now();
-        
-        match proxy_manager.test_connection(server).await {
-            Ok(success) => {
-                let latency = start_time.elapsed().as_millis() as u64;
-                
-                if success {
-                    Ok(serde_json::json!({
-                        "success": true,
-                        "ping": latency,
-                        "message": "连接测试成功"
-                    }))
-                } else {
-                    Ok(serde_json::json!({
-                        "success": false,
-                        "ping": 0,
-                        "message": "连接测试失败"
-                    }))
-                }
-            }
-            Err(e) => {
-                Ok(serde_json::json!({
-                    "success": false,
-                    "ping": 0,
-                    "message": format!("连接测试失败: {}", e)
-                }))
-            }
-        }
+        let result = run_connection_test(server).await;
+        record_latencies(&state, &[(server_id, test_latency(&result))])?;
+        Ok(result)
     } else {
-        Err("服务器不存在".to_string())
+        Err(AppError::not_found("服务器不存在"))
     }
 }
 
-/// 启动代理
-/// 启动代理服务并自动配置系统代理设置
+/// 按条件查询服务器列表
+///
+/// # 参数
+/// * `filter` - 过滤条件，为空时返回全部服务器
+/// * `sort_by` - 排序字段（name/latency/last_used/created_at），为空时保持原有顺序
+/// * `order` - 排序方向（asc/desc），默认 asc；缺少延迟或连接时间的服务器始终排在最后
+///
+/// # 返回值
+/// * `Result<Vec<ServerInfo>, AppError>` - 过滤并排序后的服务器列表
 #[tauri::command]
-pub async fn start_proxy(server_id: String) -> Result<(), String> {
-    let config = AppConfig::load().map_err(|e| e.to_string())?;
-    
-    if let Some(server) = config.servers.iter().find(|s| s.id == server_id) {
-        let proxy_manager = ProxyManager::instance();
-        
-        // 启动代理服务
-        proxy_manager.start(server).await.map_err(|e| e.to_string())?;
-        
-        // 自动配置系统代理
-        let system_manager = SystemManager::new();
-        
-        // 根据代理模式设置系统代理
-        match config.proxy_mode.as_str() {
-            "global" => {
-                // 全局模式：使用 SOCKS 代理
-                let socks_proxy = format!("socks5://127.0.0.1:{}", config.socks_port);
-                system_manager.set_proxy(&socks_proxy).await.map_err(|e| {
-                    format!("设置系统代理失败: {}", e)
-                })?;
-            },
-            "pac" => {
-                // PAC 模式：使用 HTTP 代理
-                let http_proxy = format!("127.0.0.1:{}", config.http_port);
-                system_manager.set_proxy(&http_proxy).await.map_err(|e| {
-                    format!("设置系统代理失败: {}", e)
-                })?;
-            },
-            "direct" => {
-                // 直连模式：不设置系统代理
-                // 仅启动代理服务，不修改系统设置
-            },
-            _ => {
-                // 默认使用 HTTP 代理
-                let http_proxy = format!("127.0.0.1:{}", config.http_port);
-                system_manager.set_proxy(&http_proxy).await.map_err(|e| {
-                    format!("设置系统代理失败: {}", e)
-                })?;
-            }
+pub async fn query_servers(
+    state: State<'_, AppState>,
+    filter: Option<ServerFilter>,
+    sort_by: Option<String>,
+    order: Option<String>,
+) -> Result<Vec<ServerInfo>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    let filter = filter.unwrap_or_default();
+    let keyword = filter.keyword.as_deref().map(str::trim).filter(|k| !k.is_empty()).map(str::to_lowercase);
+    let config_text = |server: &ServerInfo, key: &str| server.config.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+    let mut servers: Vec<ServerInfo> = config.servers.into_iter()
+        .filter(|server| filter.protocol.as_deref().is_none_or(|p| server.protocol.eq_ignore_ascii_case(p)))
+        .filter(|server| {
+            filter.group.as_deref().is_none_or(|group| {
+                config_text(server, "group").or_else(|| config_text(server, "subscription_id")).as_deref() == Some(group)
+            })
+        })
+        .filter(|server| {
+            filter.tag.as_deref().is_none_or(|tag| {
+                server.config.get("tags")
+                    .and_then(|v| v.as_array())
+                    .is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag)))
+            })
+        })
+        .filter(|server| {
+            keyword.as_deref().is_none_or(|keyword| {
+                server.name.to_lowercase().contains(keyword) || server.address.to_lowercase().contains(keyword)
+            })
+        })
+        .collect();
+
+    let descending = match order.as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => return Err(AppError::invalid_input(format!("无效的排序方向: {}", other))),
+    };
+    // 缺失值排在最后，不受排序方向影响
+    fn compare_optional<T: Ord>(a: &Option<T>, b: &Option<T>, descending: bool) -> std::cmp::Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if descending => b.cmp(a),
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
         }
-        
-        Ok(())
-    } else {
-        Err("服务器不存在".to_string())
     }
-}
 
-/// 停止代理
-/// 停止代理服务并自动清除系统代理设置
-#[tauri::command]
-pub async fn stop_proxy() -> Result<(), String> {
-    let proxy_manager = ProxyManager::instance();
-    
-    // 停止代理服务
-    proxy_manager.stop().await.map_err(|e| e.to_string())?;
-    
-    // 自动清除系统代理设置
-    let system_manager = SystemManager::new();
-    system_manager.unset_proxy().await.map_err(|e| {
-        format!("清除系统代理失败: {}", e)
-    })?;
-    
-    Ok(())
+    match sort_by.as_deref() {
+        None => {}
+        Some("name") => servers.sort_by(|a, b| {
+            let ordering = a.name.to_lowercase().cmp(&b.name.to_lowercase());
+            if descending { ordering.reverse() } else { ordering }
+        }),
+        Some("latency") => servers.sort_by(|a, b| compare_optional(&a.latency_ms, &b.latency_ms, descending)),
+        Some("last_used") => servers.sort_by(|a, b| compare_optional(&a.last_connected_at, &b.last_connected_at, descending)),
+        Some("created_at") => servers.sort_by(|a, b| {
+            let ordering = a.created_at.cmp(&b.created_at);
+            if descending { ordering.reverse() } else { ordering }
+        }),
+        Some(other) => return Err(AppError::invalid_input(format!("不支持的排序字段: {}", other))),
+    }
+
+    Ok(servers)
 }
 
-/// 获取代理状态
-#[tauri::command]
-pub async fn get_proxy_status() -> Result<ProxyStatus, String> {
-    let proxy_manager = ProxyManager::instance();
-    proxy_manager.get_status().await.map_err(|e| e.to_string())
+/// 记录连接测试得到的延迟
+///
+/// # 参数
+/// * `results` - 服务器ID与延迟（失败时为 None）
+fn record_latencies(state: &AppState, results: &[(String, Option<u64>)]) -> Result<(), AppError> {
+    state.config
+        .update(|config| {
+            for server in config.servers.iter_mut() {
+                if let Some((_, latency)) = results.iter().find(|(id, _)| id == &server.id) {
+                    server.latency_ms = *latency;
+                }
+            }
+        })
+        .map_err(AppError::from)
 }
 
-/// 设置代理模式
-#[tauri::command]
-pub async fn set_proxy_mode(mode: String) -> Result<(), String> {
-    let mut config = AppConfig::load().map_err(|e| e.to_string())?;
-    config.proxy_mode = mode;
-    config.save().map_err(|e| e.to_string())?;
-    Ok(())
+/// 从连接测试结果中取出延迟
+fn test_latency(result: &serde_json::Value) -> Option<u64> {
+    result.get("success")
+        .and_then(|v| v.as_bool())
+        .filter(|success| *success)
+        .and_then(|_| result.get("ping").and_then(|v| v.as_u64()))
 }
 
-/// 获取系统统计信息
-#[tauri::command]
-pub async fn get_system_stats() -> Result<SystemStats, String> {
-    let system_manager = SystemManager::new();
-    system_manager.get_stats().await.map_err(|e| e.to_string())
+/// 执行一次连接测试
+///
+/// # 返回值
+/// * `serde_json::Value` - 测试结果（success/ping/message）
+async fn run_connection_test(server: &ServerInfo) -> serde_json::Value {
+    match SpeedTestManager::new().measure_latency(server).await {
+        Ok(latency) => serde_json::json!({
+            "success": true,
+            "ping": latency,
+            "message": "连接测试成功"
+        }),
+        Err(e) => serde_json::json!({
+            "success": false,
+            "ping": 0,
+            "message": format!("连接测试失败: {}", e)
+        }),
+    }
 }
 
-/// 设置系统代理
+/// 设置服务器收藏状态
+/// 收藏的服务器在托盘菜单中排在最前
+///
+/// # 参数
+/// * `server_id` - 服务器ID
+/// * `favorite` - 是否收藏，为空时切换当前状态
+///
+/// # 返回值
+/// * `Result<bool, AppError>` - 设置后的收藏状态
 #[tauri::command]
-pub async fn set_system_proxy(proxy_url: String) -> Result<(), String> {
-    let system_manager = SystemManager::new();
-    system_manager.set_proxy(&proxy_url).await.map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn toggle_favorite_server(
+    state: State<'_, AppState>,
+    server_id: String,
+    favorite: Option<bool>,
+) -> Result<bool, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let favorite = state.config
+        .update(|config| {
+            let server = config.servers.iter_mut().find(|s| s.id == server_id)?;
+            server.favorite = favorite.unwrap_or(!server.favorite);
+            Some(server.favorite)
+        })?
+        .ok_or_else(|| AppError::not_found("服务器不存在"))?;
+    state.config.emit_servers_changed("updated", &[server_id]);
+    Ok(favorite)
 }
 
-/// 清除系统代理
+/// 获取最近使用的服务器
+///
+/// # 参数
+/// * `limit` - 最多返回的数量，默认 5
+///
+/// # 返回值
+/// * `Result<Vec<ServerInfo>, AppError>` - 按最近连接时间倒序排列的服务器
 #[tauri::command]
-pub async fn clear_system_proxy() -> Result<(), String> {
-    let system_manager = SystemManager::new();
-    system_manager.unset_proxy().await.map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn get_recent_servers(state: State<'_, AppState>, limit: Option<usize>) -> Result<Vec<ServerInfo>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    let mut servers: Vec<ServerInfo> = config.servers.into_iter()
+        .filter(|s| s.last_connected_at.is_some())
+        .collect();
+    servers.sort_by(|a, b| b.last_connected_at.cmp(&a.last_connected_at));
+    servers.truncate(limit.unwrap_or(5));
+    Ok(servers)
 }
 
-/// 获取系统代理状态
+/// 批量删除服务器
+/// 清理对应的配置文件，只写入一次配置
+///
+/// # 参数
+/// * `ids` - 服务器ID列表
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 实际删除的服务器数量
 #[tauri::command]
-pub async fn get_system_proxy_status() -> Result<serde_json::Value, String> {
-    let system_manager = SystemManager::new();
-    system_manager.get_proxy_status().await.map_err(|e| e.to_string())
+pub async fn delete_servers(state: State<'_, AppState>, ids: Vec<String>) -> Result<usize, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let ids: std::collections::HashSet<String> = ids.into_iter().collect();
+    let removed = state.config.update(|config| {
+        let (removed, rest): (Vec<ServerInfo>, Vec<ServerInfo>) = config.servers
+            .drain(..)
+            .partition(|s| ids.contains(&s.id));
+        config.servers = rest;
+        config.speed_test_results.retain(|id, _| !ids.contains(id));
+        removed
+    })?;
+
+    for server in &removed {
+        let _ = state.proxy.cleanup_server_config(&server.id, &server.name);
+    }
+    let removed_ids: Vec<String> = removed.iter().map(|s| s.id.clone()).collect();
+    let deleted = removed_ids.len();
+
+    state.config.emit_servers_changed("removed", &removed_ids);
+    log_info!("已批量删除 {} 个服务器", deleted);
+    Ok(deleted)
 }
 
-/// 清理未使用的配置文件
-/// 根据当前服务器列表，清理不再使用的配置文件
+/// 批量移动服务器
+/// 将选中的服务器按原有顺序移动到列表中的指定位置
+///
+/// # 参数
+/// * `ids` - 服务器ID列表
+/// * `index` - 目标位置（以移除选中服务器后的列表计），超出范围时移动到末尾
 #[tauri::command]
-pub async fn cleanup_unused_configs() -> Result<(), String> {
-    let config = AppConfig::load().map_err(|e| e.to_string())?;
-    let active_server_ids: Vec<String> = config.servers.iter().map(|s| s.id.clone()).collect();
-    
-    let proxy_manager = ProxyManager::instance();
-    proxy_manager.cleanup_unused_configs(&active_server_ids).map_err(|e| e.to_string())?;
-    
+pub async fn move_servers(state: State<'_, AppState>, ids: Vec<String>, index: usize) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let ids: std::collections::HashSet<String> = ids.into_iter().collect();
+    let moved_ids = state.config.update(|config| {
+        let (selected, mut rest): (Vec<ServerInfo>, Vec<ServerInfo>) = config.servers
+            .drain(..)
+            .partition(|s| ids.contains(&s.id));
+        let index = index.min(rest.len());
+        let moved_ids: Vec<String> = selected.iter().map(|s| s.id.clone()).collect();
+        rest.splice(index..index, selected);
+        config.servers = rest;
+        moved_ids
+    })?;
+
+    state.config.emit_servers_changed("moved", &moved_ids);
     Ok(())
 }
 
-/// 检查 Xray Core 更新
+/// 批量测试服务器连接
+/// 测试共用同一个临时配置文件，因此依次执行
+///
+/// # 参数
+/// * `ids` - 服务器ID列表
+///
+/// # 返回值
+/// * `Result<Vec<serde_json::Value>, AppError>` - 各服务器的测试结果（包含 server_id）
 #[tauri::command]
-pub async fn check_xray_update() -> Result<Option<String>, String> {
-    let xray_manager = XrayManager::new();
-    xray_manager.check_update().await.map_err(|e| e.to_string())
-}
+pub async fn test_servers(state: State<'_, AppState>, ids: Vec<String>) -> Result<Vec<serde_json::Value>, AppError> {
+    let config = state.config.get()?;
+    let mut results = Vec::with_capacity(ids.len());
 
-/// 下载 Xray Core 更新
-#[tauri::command]
-pub async fn download_xray_update(version: String) -> Result<(), String> {
-    let xray_manager = XrayManager::new();
-    xray_manager.download_update(&version).await.map_err(|e| e.to_string())?;
-    Ok(())
+    for id in &ids {
+        let mut result = match config.servers.iter().find(|s| &s.id == id) {
+            Some(server) => run_connection_test(server).await,
+            None => serde_json::json!({
+                "success": false,
+                "ping": 0,
+                "message": "服务器不存在"
+            }),
+        };
+        result["server_id"] = serde_json::json!(id);
+        results.push(result);
+    }
+
+    let latencies: Vec<(String, Option<u64>)> = results.iter()
+        .filter_map(|r| Some((r.get("server_id")?.as_str()?.to_string(), test_latency(r))))
+        .collect();
+    record_latencies(&state, &latencies)?;
+    Ok(results)
 }
 
-/// 下载 Xray Core 更新（带进度回调）
+/// 服务器测速
+/// 通过临时 Xray 实例测量指定服务器的真实下载/上传带宽，并记录测速结果
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄，用于发送进度事件
+/// * `server_id` - 服务器ID
+///
+/// # 返回值
+/// * `Result<SpeedTestResult, AppError>` - 测速结果
 #[tauri::command]
-pub async fn download_xray_update_with_progress(
-    app_handle: tauri::AppHandle,
-    version: String,
-) -> Result<(), String> {
-    let xray_manager = XrayManager::new();
-    
-    xray_manager.download_update_with_progress(&version, |current, total, message| {
-        let progress = if total > 0 { (current * 100 / total) as u32 } else { 0 };
-        
-        // 发送进度事件到前端
-        let _ = app_handle.emit("xray-download-progress", serde_json::json!({
+pub async fn run_speed_test(app_handle: tauri::AppHandle, state: State<'_, AppState>, server_id: String) -> Result<SpeedTestResult, AppError> {
+    let config = state.config.get()?;
+    let server = config.servers.iter()
+        .find(|s| s.id == server_id)
+        .cloned()
+        .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+
+    let speed_test_manager = SpeedTestManager::new();
+    let result = speed_test_manager.run(&server, |progress, total, message| {
+        let _ = app_handle.emit("speed-test-progress", serde_json::json!({
+            "server_id": server_id,
             "progress": progress,
+            "total": total,
             "message": message
         }));
-    }).await.map_err(|e| e.to_string())?;
-    
-    Ok(())
-}
+    }).await.app_context("测速失败")?;
 
-/// 获取 Xray Core 版本
-#[tauri::command]
-pub async fn get_xray_version() -> Result<String, String> {
-    let xray_manager = XrayManager::new();
-    xray_manager.get_version().await.map_err(|e| e.to_string())
+    // 在配置存储的写锁内写入，避免覆盖测速期间的其他修改
+    state.config.update(|config| config.speed_test_results.insert(server_id, result.clone()))?;
+
+    Ok(result)
 }
 
-/// 检查 Xray Core 是否存在
+/// 获取所有服务器的测速结果
+///
+/// # 返回值
+/// * `Result<HashMap<String, SpeedTestResult>, AppError>` - 以服务器ID为键的测速结果
 #[tauri::command]
-pub async fn check_xray_exists() -> Result<bool, String> {
-    AppConfig::check_xray_exists().map_err(|e| e.to_string())
+pub async fn get_speed_test_results(state: State<'_, AppState>) -> Result<HashMap<String, SpeedTestResult>, AppError> {
+    let config = state.config.get()?;
+    Ok(config.speed_test_results)
 }
 
-/// 获取 Xray Core 可执行文件路径
+/// 获取流量统计
+///
+/// # 参数
+/// * `month` - 统计月份（YYYY-MM），为空时为当前月份
+///
+/// # 返回值
+/// * `Result<TrafficReport, AppError>` - 各服务器和各订阅的流量用量及配额
 #[tauri::command]
-pub async fn get_xray_path() -> Result<String, String> {
-    let path = AppConfig::xray_executable().map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
+pub async fn get_traffic_usage(state: State<'_, AppState>, month: Option<String>) -> Result<TrafficReport, AppError> {
+    let config = state.config.get()?;
+    Ok(state.traffic.report(month, &config))
 }
 
-/// 下载地理位置数据文件（geoip.dat 和 geosite.dat）
-/// 
+/// 设置流量配额
+///
 /// # 参数
-/// * `app_handle` - Tauri 应用句柄，用于发送进度事件
-/// 
+/// * `target_id` - 服务器ID或订阅ID
+/// * `limit` - 每月配额（字节），为空或 0 时移除配额
+///
 /// # 返回值
-/// * `Result<(), String>` - 下载结果
+/// * `Result<(), AppError>` - 设置结果
 #[tauri::command]
-pub async fn download_geo_files(app_handle: tauri::AppHandle) -> Result<(), String> {
-    let xray_manager = XrayManager::new();
-    
-    xray_manager.download_geo_files(|progress, total, message| {
+pub async fn set_traffic_quota(state: State<'_, AppState>, target_id: String, limit: Option<u64>) -> Result<(), AppError> {
+    state.config
+        .update(|config| match limit {
+            Some(limit) if limit > 0 => {
+                config.traffic_quotas.insert(target_id, limit);
+            }
+            _ => {
+                config.traffic_quotas.remove(&target_id);
+            }
+        })
+        .map_err(AppError::from)
+}
+
+/// 清空流量统计
+///
+/// # 参数
+/// * `server_id` - 指定时只清空该服务器当月数据，否则清空全部统计
+///
+/// # 返回值
+/// * `Result<(), AppError>` - 清空结果
+#[tauri::command]
+pub async fn reset_traffic_usage(state: State<'_, AppState>, server_id: Option<String>) -> Result<(), AppError> {
+    state.traffic
+        .reset(server_id.as_deref())
+        .map_err(AppError::from)
+}
+
+/// 获取统计历史
+/// 返回按时间段聚合的流量、平均速度、服务器与运行时长，供仪表盘绘制图表
+///
+/// # 参数
+/// * `range` - 时间范围，如 `1h`、`24h`、`7d`、`30d`
+/// * `resolution` - 聚合粒度（minute/hour/day），默认 hour
+///
+/// # 返回值
+/// * `Result<Vec<TrafficHistoryPoint>, AppError>` - 按时间顺序排列的数据点
+#[tauri::command]
+pub async fn get_traffic_history(state: State<'_, AppState>, range: String, resolution: Option<String>) -> Result<Vec<TrafficHistoryPoint>, AppError> {
+    let range = range.trim();
+    let range_secs = [("m", 60), ("h", 3600), ("d", 24 * 3600)]
+        .iter()
+        .find_map(|(unit, secs)| {
+            let amount: i64 = range.strip_suffix(unit)?.parse().ok()?;
+            Some(amount.max(0) * secs)
+        })
+        .ok_or_else(|| AppError::invalid_input(format!("无效的时间范围: {}", range)))?;
+    let resolution = resolution.unwrap_or_else(|| "hour".to_string());
+
+    let history = state.history;
+    tokio::task::spawn_blocking(move || history.query(range_secs, &resolution))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(AppError::from)
+}
+
+/// 清空统计历史
+#[tauri::command]
+pub async fn clear_history(state: State<'_, AppState>) -> Result<(), AppError> {
+    let history = state.history;
+    tokio::task::spawn_blocking(move || history.clear())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(AppError::from)
+}
+
+/// 启动代理
+/// 启动代理服务并自动配置系统代理设置
+#[tauri::command]
+pub async fn start_proxy(state: State<'_, AppState>, server_id: String) -> Result<(), AppError> {
+    let config = state.config.get()?;
+    
+    if let Some(server) = config.servers.iter().find(|s| s.id == server_id).cloned() {
+        let proxy_manager = state.proxy;
+        // 运行中切换服务器时记录上一个服务器，供 server-switched 钩子使用
+        let previous_server_id = proxy_manager.current_server_id()
+            .filter(|_| proxy_manager.is_process_running());
+        
+        // 运行中切换服务器时优先热切换，本地入站端口保持监听；无法热切换时完整重启
+        let hot_switched = match previous_server_id {
+            Some(_) => proxy_manager.hot_switch(&server).await.unwrap_or_else(|e| {
+                log_warn!("热切换失败，改为重启代理: {}", e);
+                false
+            }),
+            None => false,
+        };
+        if !hot_switched {
+            proxy_manager.start(&server).await?;
+        }
+        
+        // 根据代理模式自动配置系统代理（端口可能已被自动调整，重新读取配置并应用服务器覆盖设置）
+        let config = AppConfig::load_active()?;
+        if let Err(e) = apply_system_proxy(&config, false).await {
+            if !e.is_permission_denied() {
+                return Err(e.into());
+            }
+            // 没有权限修改系统代理（如受管理的企业设备）时保持代理运行，
+            // 应用可手动配置使用本地入站，界面据 `system-proxy-error` 事件提示用户
+            log_warn!("没有权限修改系统代理，代理已启动但未设置系统代理: {}", e.message);
+            proxy_manager.emit_event("system-proxy-error", serde_json::json!(e));
+        }
+
+        // 记录本次连接使用的 Xray Core 版本
+        let core_version = match config.core_version.clone() {
+            Some(version) => Some(version),
+            None => XrayManager::new().get_version().await.ok(),
+        };
+
+        // 记录最近使用的服务器，供启动时自动连接使用
+        state.config
+            .update(|config| {
+                if let Some(version) = core_version {
+                    config.core_version_usage.insert(server_id.clone(), version);
+                }
+                if let Some(server) = config.servers.iter_mut().find(|s| s.id == server_id) {
+                    server.last_connected_at = Some(chrono::Utc::now().to_rfc3339());
+                }
+                config.current_server = Some(server_id.clone());
+            })?;
+        proxy_manager.emit_status("connected");
+
+        hooks::fire(HookEvent::ProxyStarted, Some(&server), None);
+        match previous_server_id.filter(|previous| *previous != server_id) {
+            Some(previous) => {
+                hooks::fire(HookEvent::ServerSwitched, Some(&server), Some(&previous));
+                notify::notify(NotifyEvent::ServerSwitched, &[&server.name]);
+            }
+            None => notify::notify(NotifyEvent::Connected, &[&server.name]),
+        }
+        
+        Ok(())
+    } else {
+        Err(AppError::not_found("服务器不存在"))
+    }
+}
+
+/// 停止代理
+/// 停止代理服务并自动清除系统代理设置
+#[tauri::command]
+pub async fn stop_proxy(state: State<'_, AppState>) -> Result<(), AppError> {
+    let proxy_manager = state.proxy;
+    let was_running = proxy_manager.is_process_running();
+    
+    // 停止代理服务
+    proxy_manager.stop().await?;
+    
+    // 自动清除系统代理设置
+    let system_manager = SystemManager::new();
+    system_manager.unset_proxy().await.map_err(|e| {
+        SystemProxyError::from_error(&e.context("清除系统代理失败"))
+    })?;
+    proxy_manager.emit_status("disconnected");
+    if was_running {
+        hooks::fire(HookEvent::ProxyStopped, None, None);
+        notify::notify(NotifyEvent::Disconnected, &[]);
+    }
+    
+    Ok(())
+}
+
+/// 强制终止本机所有 xray/sing-box 进程
+/// 包括其他用户或其他程序启动的实例，仅在核心进程失控、端口无法释放时由用户明确触发；
+/// 会先停止代理并清除系统代理设置
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 终止的进程数量
+#[tauri::command]
+pub async fn force_cleanup_all(state: State<'_, AppState>) -> Result<usize, AppError> {
+    let proxy_manager = state.proxy;
+    let killed = proxy_manager.force_cleanup_all().await?;
+
+    let system_manager = SystemManager::new();
+    system_manager.unset_proxy().await.map_err(|e| {
+        SystemProxyError::from_error(&e.context("清除系统代理失败"))
+    })?;
+    proxy_manager.emit_status("disconnected");
+    log_warn!("已强制终止 {} 个核心进程", killed);
+    Ok(killed)
+}
+
+/// 查找上次运行遗留、可接管的 Xray 进程
+///
+/// # 返回值
+/// * `Result<Option<AdoptableCore>, AppError>` - 可接管的进程及其服务器与端口，没有时为 None
+#[tauri::command]
+pub async fn find_adoptable_core(state: State<'_, AppState>) -> Result<Option<AdoptableCore>, AppError> {
+    Ok(state.proxy.find_adoptable_core()?)
+}
+
+/// 接管上次运行遗留的 Xray 进程
+/// 不重复启动核心，恢复其端口并开始监视状态，随后与启动代理一样设置系统代理
+///
+/// # 参数
+/// * `pid` - 由 `find_adoptable_core` 返回的进程ID
+#[tauri::command]
+pub async fn adopt_core(state: State<'_, AppState>, pid: u32) -> Result<(), AppError> {
+    let proxy_manager = state.proxy;
+    let server = proxy_manager.adopt(pid).await?;
+
+    // 端口可能已按接管的进程恢复，重新读取配置并应用服务器覆盖设置
+    let config = AppConfig::load_active()?;
+    if let Err(e) = apply_system_proxy(&config, false).await {
+        if !e.is_permission_denied() {
+            return Err(e.into());
+        }
+        log_warn!("没有权限修改系统代理，已接管核心但未设置系统代理: {}", e.message);
+        proxy_manager.emit_event("system-proxy-error", serde_json::json!(e));
+    }
+
+    state.config.update(|config| config.current_server = Some(server.id.clone()))?;
+    proxy_manager.emit_status("connected");
+    hooks::fire(HookEvent::ProxyStarted, Some(&server), None);
+    notify::notify(NotifyEvent::Connected, &[&server.name]);
+    Ok(())
+}
+
+/// 获取代理状态
+#[tauri::command]
+pub async fn get_proxy_status(state: State<'_, AppState>) -> Result<ProxyStatus, AppError> {
+    let proxy_manager = state.proxy;
+    proxy_manager.get_status().await.map_err(AppError::from)
+}
+
+/// 根据代理模式设置系统代理
+///
+/// # 参数
+/// * `config` - 应用配置
+/// * `clear_on_direct` - 直连模式下是否清除系统代理（运行中切换到直连模式时需要清除）
+///
+/// # 异常
+/// * 修改失败时返回 `SystemProxyError`，没有修改权限时错误代码为 `permission_denied`
+pub(crate) async fn apply_system_proxy(config: &AppConfig, clear_on_direct: bool) -> Result<(), SystemProxyError> {
+    let system_manager = SystemManager::new();
+
+    match config.proxy_mode.as_str() {
+        "global" => {
+            // 全局模式：使用 SOCKS 代理
+            let socks_proxy = format!("socks5://127.0.0.1:{}", config.socks_port);
+            system_manager.set_proxy(&socks_proxy).await.map_err(|e| {
+                SystemProxyError::from_error(&e.context("设置系统代理失败"))
+            })?;
+        },
+        "direct" => {
+            // 直连模式：仅启动代理服务，不设置系统代理
+            if clear_on_direct {
+                system_manager.unset_proxy().await.map_err(|e| {
+                    SystemProxyError::from_error(&e.context("清除系统代理失败"))
+                })?;
+            }
+        },
+        _ => {
+            // PAC 模式及默认：使用 HTTP 代理
+            let http_proxy = format!("127.0.0.1:{}", config.http_port);
+            system_manager.set_proxy(&http_proxy).await.map_err(|e| {
+                SystemProxyError::from_error(&e.context("设置系统代理失败"))
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 本地代理地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyEndpoints {
+    /// 代理是否正在运行
+    pub is_running: bool,
+    /// HTTP 代理地址，如 `http://127.0.0.1:10086`
+    pub http: String,
+    /// SOCKS5 代理地址，如 `socks5://127.0.0.1:10087`
+    pub socks: String,
+    /// PAC 文件地址，PAC 服务未提供时为空
+    pub pac: Option<String>,
+    /// 局域网内其他设备使用的地址，未允许局域网连接时为空
+    pub lan: Option<LanProxyEndpoints>,
+    pub http_port: u16,
+    pub socks_port: u16,
+    pub pac_port: u16,
+}
+
+/// 局域网代理地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanProxyEndpoints {
+    /// 本机局域网 IP
+    pub ip: String,
+    /// HTTP 代理地址
+    pub http: String,
+    /// SOCKS5 代理地址
+    pub socks: String,
+}
+
+/// 获取本地代理地址
+/// 供界面生成“复制代理命令”（如 `export https_proxy=...`）或提供给其他工具使用，
+/// 端口以当前配置为准（启动时端口可能已被自动调整）
+///
+/// # 返回值
+/// * `Result<ProxyEndpoints, AppError>` - 本机与局域网的代理地址
+#[tauri::command]
+pub async fn get_proxy_endpoints(state: State<'_, AppState>) -> Result<ProxyEndpoints, AppError> {
+    let config = AppConfig::load_active()?;
+    Ok(build_proxy_endpoints(&config, state.proxy.is_process_running()))
+}
+
+/// 根据配置生成代理地址
+fn build_proxy_endpoints(config: &AppConfig, is_running: bool) -> ProxyEndpoints {
+    let lan = if config.allow_lan {
+        SystemManager::lan_ip().map(|ip| LanProxyEndpoints {
+            ip: ip.to_string(),
+            http: format!("http://{}:{}", ip, config.http_port),
+            socks: format!("socks5://{}:{}", ip, config.socks_port),
+        })
+    } else {
+        None
+    };
+
+    ProxyEndpoints {
+        is_running,
+        http: format!("http://127.0.0.1:{}", config.http_port),
+        socks: format!("socks5://127.0.0.1:{}", config.socks_port),
+        pac: None,
+        lan,
+        http_port: config.http_port,
+        socks_port: config.socks_port,
+        pac_port: config.pac_port,
+    }
+}
+
+/// 不经过代理的地址
+const NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
+/// 终端代理配置片段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxySnippets {
+    /// bash/zsh 环境变量
+    pub bash: String,
+    /// PowerShell 环境变量
+    pub powershell: String,
+    /// Windows 命令提示符环境变量
+    pub cmd: String,
+    /// Git 全局代理设置
+    pub git: String,
+    /// npm 代理设置
+    pub npm: String,
+    /// Docker 客户端配置（~/.docker/config.json 中的 proxies 字段），容器经 host.docker.internal 访问本机代理
+    pub docker: String,
+}
+
+/// 生成终端代理配置片段
+/// 按当前端口生成可直接粘贴的 bash、PowerShell、cmd、git、npm、docker 代理设置
+///
+/// # 参数
+/// * `lan` - 是否使用局域网地址（供局域网内其他设备使用，需允许局域网连接）
+///
+/// # 返回值
+/// * `Result<ProxySnippets, AppError>` - 各终端与工具的代理设置
+#[tauri::command]
+pub async fn get_proxy_snippets(state: State<'_, AppState>, lan: Option<bool>) -> Result<ProxySnippets, AppError> {
+    let config = state.config.get()?;
+    build_proxy_snippets(&config, lan.unwrap_or(false))
+}
+
+/// 写入 `.proxyrc` 文件
+/// 文件内容为 bash 代理环境变量，可在 shell 配置中 `source ~/.proxyrc` 引用
+///
+/// # 参数
+/// * `path` - 文件路径，为空时写入用户目录下的 `.proxyrc`
+///
+/// # 返回值
+/// * `Result<String, AppError>` - 写入的文件路径
+#[tauri::command]
+pub async fn write_proxyrc(state: State<'_, AppState>, path: Option<String>) -> Result<String, AppError> {
+    let config = state.config.get()?;
+    let snippets = build_proxy_snippets(&config, false)?;
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => dirs::home_dir().ok_or("无法获取用户目录")?.join(".proxyrc"),
+    };
+    let content = format!(
+        "# 由 RuRay 生成，端口变化后请重新生成\n# 使用方法：source {}\n{}\n",
+        path.display(),
+        snippets.bash
+    );
+    std::fs::write(&path, content).app_context(&format!("写入 {} 失败", path.display()))?;
+
+    log_info!("已写入代理环境变量文件: {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 根据配置生成终端代理配置片段
+fn build_proxy_snippets(config: &AppConfig, lan: bool) -> Result<ProxySnippets, AppError> {
+    let endpoints = build_proxy_endpoints(config, false);
+    let (http, socks) = if lan {
+        let lan = endpoints.lan.ok_or("未允许局域网连接或无法获取局域网地址")?;
+        (lan.http, lan.socks)
+    } else {
+        (endpoints.http, endpoints.socks)
+    };
+    let docker_http = if lan { http.clone() } else { format!("http://host.docker.internal:{}", config.http_port) };
+
+    let vars = [
+        ("http_proxy", http.as_str()),
+        ("https_proxy", http.as_str()),
+        ("all_proxy", socks.as_str()),
+        ("no_proxy", NO_PROXY),
+    ];
+
+    let bash = vars.iter()
+        .flat_map(|(name, value)| [
+            format!("export {}={}", name, value),
+            format!("export {}={}", name.to_uppercase(), value),
+        ])
+        .collect::<Vec<_>>()
+        .join("\n");
+    let powershell = vars.iter()
+        .map(|(name, value)| format!("$env:{}=\"{}\"", name.to_uppercase(), value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let cmd = vars.iter()
+        .map(|(name, value)| format!("set {}={}", name.to_uppercase(), value))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    let git = format!(
+        "git config --global http.proxy {}\ngit config --global https.proxy {}",
+        http, http
+    );
+    let npm = format!(
+        "npm config set proxy {}\nnpm config set https-proxy {}",
+        http, http
+    );
+    let docker = serde_json::to_string_pretty(&serde_json::json!({
+        "proxies": {
+            "default": {
+                "httpProxy": docker_http,
+                "httpsProxy": docker_http,
+                "noProxy": NO_PROXY
+            }
+        }
+    }))
+    .map_err(|e| e.to_string())?;
+
+    Ok(ProxySnippets { bash, powershell, cmd, git, npm, docker })
+}
+
+/// 设置本地控制 API
+/// 保存后按新配置重新启动服务
+///
+/// # 参数
+/// * `enabled` - 是否启用
+/// * `port` - 监听端口
+/// * `regenerate_token` - 是否重新生成访问令牌（旧令牌立即失效）
+///
+/// # 返回值
+/// * `Result<ControlApiConfig, AppError>` - 保存后的配置（包含访问令牌）
+#[tauri::command]
+pub async fn set_control_api(
+    state: State<'_, AppState>,
+    enabled: bool,
+    port: u16,
+    regenerate_token: bool,
+) -> Result<ControlApiConfig, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    if port == 0 {
+        return Err(AppError::invalid_input("端口必须在 1-65535 之间"));
+    }
+
+    state.config
+        .update(|config| {
+            config.control_api.enabled = enabled;
+            config.control_api.port = port;
+            if regenerate_token || config.control_api.token.is_empty() {
+                config.control_api.token = ApiServer::generate_token();
+            }
+        })?;
+
+    state.api_server.restart().await?;
+    Ok(state.config.get()?.control_api)
+}
+
+/// 入站用户流量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundUserTraffic {
+    /// 用户名
+    pub username: String,
+    /// 本次代理运行以来的上传字节数
+    pub upload: u64,
+    /// 本次代理运行以来的下载字节数
+    pub download: u64,
+}
+
+/// 获取入站认证用户列表
+#[tauri::command]
+pub async fn get_inbound_users(state: State<'_, AppState>) -> Result<Vec<InboundUser>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    Ok(config.inbound_users)
+}
+
+/// 添加或更新入站认证用户
+/// 用户名已存在时更新密码；修改在代理重新启动后生效
+///
+/// # 参数
+/// * `username` - 用户名
+/// * `password` - 密码
+#[tauri::command]
+pub async fn add_inbound_user(state: State<'_, AppState>, username: String, password: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let username = username.trim().to_string();
+    if username.is_empty() || password.is_empty() {
+        return Err(AppError::invalid_input("用户名和密码不能为空"));
+    }
+    // SOCKS5 认证的用户名和密码长度上限为 255 字节，HTTP Basic 认证的用户名不能包含冒号
+    if username.len() > 255 || password.len() > 255 {
+        return Err(AppError::invalid_input("用户名和密码不能超过 255 字节"));
+    }
+    if username.contains(':') {
+        return Err(AppError::invalid_input("用户名不能包含冒号"));
+    }
+
+    state.config
+        .update(|config| {
+            match config.inbound_users.iter_mut().find(|u| u.username == username) {
+                Some(user) => user.password = password,
+                None => config.inbound_users.push(InboundUser { username, password }),
+            }
+        })
+        .map_err(AppError::from)
+}
+
+/// 删除入站认证用户
+/// 修改在代理重新启动后生效
+///
+/// # 参数
+/// * `username` - 用户名
+#[tauri::command]
+pub async fn remove_inbound_user(state: State<'_, AppState>, username: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let removed = state.config
+        .update(|config| {
+            let before = config.inbound_users.len();
+            config.inbound_users.retain(|u| u.username != username);
+            before != config.inbound_users.len()
+        })?;
+
+    if removed {
+        Ok(())
+    } else {
+        Err(AppError::not_found("用户不存在"))
+    }
+}
+
+/// 获取各入站用户的流量
+/// 通过 Xray 统计服务按用户名统计，代理未运行或未启用认证时流量为 0
+///
+/// # 返回值
+/// * `Result<Vec<InboundUserTraffic>, AppError>` - 每个已配置用户的流量
+#[tauri::command]
+pub async fn get_inbound_user_traffic(state: State<'_, AppState>) -> Result<Vec<InboundUserTraffic>, AppError> {
+    let config = state.config.get()?;
+    let stats = if state.proxy.is_process_running() && config.inbound_auth_enabled() && config.core == CoreKind::Xray {
+        TrafficManager::query_user_stats(config.api_port).await?
+    } else {
+        HashMap::new()
+    };
+
+    Ok(config.inbound_users.iter()
+        .map(|user| {
+            let usage = stats.get(&user.username).cloned().unwrap_or_default();
+            InboundUserTraffic {
+                username: user.username.clone(),
+                upload: usage.upload,
+                download: usage.download,
+            }
+        })
+        .collect())
+}
+
+/// 测试 UDP 中继
+/// 通过本地 SOCKS 入站发送一次 DNS 查询，验证游戏、语音等 UDP 流量能否经代理转发
+///
+/// # 返回值
+/// * `Result<UdpRelayStatus, AppError>` - 测试结果，同时记录到代理状态中
+#[tauri::command]
+pub async fn test_udp_relay(state: State<'_, AppState>) -> Result<UdpRelayStatus, AppError> {
+    if !state.proxy.is_process_running() {
+        return Err(AppError::new(ErrorCode::ProxyNotRunning, "代理未运行"));
+    }
+    let config = state.config.get()?;
+    Ok(state.monitor.test_udp_relay(&config).await)
+}
+
+/// 运行自检
+/// 依次检查核心存在、配置有效、端口空闲、进程启动、本地入站、出口 IP 与 DNS 泄漏，
+/// 运行检查使用临时核心实例，不影响正在运行的代理
+///
+/// # 参数
+/// * `server_id` - 服务器ID，为空时使用当前服务器，没有当前服务器时使用第一个服务器
+///
+/// # 返回值
+/// * `Result<SelfTestReport, AppError>` - 自检报告，界面按 `steps` 顺序显示为检查清单
+#[tauri::command]
+pub async fn run_self_test(state: State<'_, AppState>, server_id: Option<String>) -> Result<SelfTestReport, AppError> {
+    let config = state.config.get()?;
+    let server_id = server_id.or_else(|| config.current_server.clone());
+    let server = match server_id {
+        Some(id) => config.servers.iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", id)))?,
+        None => config.servers.first().ok_or_else(|| AppError::not_found("没有可用的服务器"))?,
+    };
+    self_test::run(server).await.map_err(AppError::from)
+}
+
+/// 获取版本与构建信息
+/// 用于关于页面与问题反馈
+///
+/// # 返回值
+/// * `Result<AppInfo, AppError>` - 应用版本、git 提交、构建时间、tauri/rust 版本、tun 库版本与操作系统/架构
+#[tauri::command]
+pub async fn get_app_info() -> Result<AppInfo, AppError> {
+    Ok(app_info::app_info())
+}
+
+/// 获取启动依赖检查结果
+/// 启动时的检查完成后会发射 `setup-audit` 事件，界面加载晚于事件时通过此命令读取
+///
+/// # 参数
+/// * `refresh` - 是否重新检查（如执行修复动作后），为 false 时返回启动时的结果
+///
+/// # 返回值
+/// * `Result<SetupAuditReport, AppError>` - 检查报告，启动检查尚未完成时立即执行一次
+#[tauri::command]
+pub async fn get_setup_audit(app_handle: tauri::AppHandle, refresh: Option<bool>) -> Result<SetupAuditReport, AppError> {
+    if !refresh.unwrap_or(false) {
+        if let Some(report) = setup_audit::last_report() {
+            return Ok(report);
+        }
+    }
+    Ok(setup_audit::run(&app_handle).await)
+}
+
+/// 获取出口 IP
+/// 直连查询本机出口，`via_proxy` 为 true 时同时经本地 HTTP 入站查询代理出口，用于确认隧道生效
+///
+/// # 参数
+/// * `via_proxy` - 是否同时查询代理出口
+///
+/// # 返回值
+/// * `Result<ExternalIpReport, AppError>` - 直连与代理出口的 IP、国家和 ASN
+#[tauri::command]
+pub async fn get_external_ip(state: State<'_, AppState>, via_proxy: bool) -> Result<ExternalIpReport, AppError> {
+    let config = AppConfig::load_active()?;
+    let proxy_url = config.local_http_proxy_url();
+
+    let direct = lookup_external_ip(None);
+    let proxied = async {
+        if !via_proxy {
+            return None;
+        }
+        if !state.proxy.is_process_running() {
+            return Some(Err(anyhow::anyhow!("代理未运行")));
+        }
+        Some(lookup_external_ip(Some(&proxy_url)).await)
+    };
+    let (direct, proxied) = tokio::join!(direct, proxied);
+
+    let (direct, direct_error) = match direct {
+        Ok(info) => (Some(info), None),
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+    let (proxy, proxy_error) = match proxied {
+        Some(Ok(info)) => (Some(info), None),
+        Some(Err(e)) => (None, Some(format!("{:#}", e))),
+        None => (None, None),
+    };
+
+    Ok(ExternalIpReport { direct, direct_error, proxy, proxy_error })
+}
+
+/// 设置代理模式
+/// 代理运行中时立即按新模式重新设置系统代理
+///
+/// # 参数
+/// * `mode` - 代理模式（pac/global/direct）
+#[tauri::command]
+pub async fn set_proxy_mode(state: State<'_, AppState>, mode: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    if !matches!(mode.as_str(), "pac" | "global" | "direct") {
+        return Err(AppError::invalid_input(format!("无效的代理模式: {}", mode)));
+    }
+
+    state.config.update(|config| config.proxy_mode = mode.clone())?;
+    log_info!("代理模式已切换为: {}", mode);
+
+    if state.proxy.is_process_running() {
+        let config = AppConfig::load_active()?;
+        if config.proxy_mode != mode {
+            log_info!("当前服务器固定使用 {} 模式，新模式在切换到其他服务器后生效", config.proxy_mode);
+        }
+        apply_system_proxy(&config, true).await?;
+    }
+    Ok(())
+}
+
+/// 获取系统统计信息
+#[tauri::command]
+pub async fn get_system_stats() -> Result<SystemStats, AppError> {
+    let system_manager = SystemManager::new();
+    system_manager.get_stats().await.map_err(AppError::from)
+}
+
+/// 设置系统代理
+#[tauri::command]
+pub async fn set_system_proxy(proxy_url: String) -> Result<(), AppError> {
+    let system_manager = SystemManager::new();
+    system_manager.set_proxy(&proxy_url).await
+        .map_err(|e| SystemProxyError::from_error(&e))?;
+    Ok(())
+}
+
+/// 清除系统代理
+#[tauri::command]
+pub async fn clear_system_proxy() -> Result<(), AppError> {
+    let system_manager = SystemManager::new();
+    system_manager.unset_proxy().await
+        .map_err(|e| SystemProxyError::from_error(&e))?;
+    Ok(())
+}
+
+/// 检查能否修改系统代理
+/// 受组策略管理或非管理员账户可能无法修改，界面可据此提前禁用相关选项
+///
+/// # 返回值
+/// * `Result<SystemProxyCapability, AppError>` - 能否修改及原因
+#[tauri::command]
+pub async fn can_modify_system_proxy() -> Result<SystemProxyCapability, AppError> {
+    Ok(SystemManager::new().can_modify_system_proxy())
+}
+
+/// 检测冲突的代理软件
+/// 系统代理反复被改回或代理不生效时，多为其他代理软件、VPN 客户端同时运行所致
+///
+/// # 返回值
+/// * `Result<Vec<ConflictWarning>, AppError>` - 冲突列表，没有冲突时为空
+#[tauri::command]
+pub async fn detect_conflicting_software(state: State<'_, AppState>) -> Result<Vec<ConflictWarning>, AppError> {
+    let config = state.config.get()?;
+    let warnings = SystemManager::new().detect_conflicts(&config).await;
+    for warning in &warnings {
+        log_warn!("检测到可能冲突的软件: {}", warning.detail);
+    }
+    Ok(warnings)
+}
+
+/// 获取系统代理状态
+#[tauri::command]
+pub async fn get_system_proxy_status() -> Result<serde_json::Value, AppError> {
+    let system_manager = SystemManager::new();
+    system_manager.get_proxy_status().await.map_err(AppError::from)
+}
+
+/// 设置开机自启动
+/// 注册或移除系统启动项，并保存到应用配置
+///
+/// # 参数
+/// * `enabled` - 是否启用开机自启动
+#[tauri::command]
+pub async fn set_auto_start(state: State<'_, AppState>, enabled: bool) -> Result<(), AppError> {
+    let system_manager = SystemManager::new();
+    system_manager.set_auto_start(enabled)?;
+
+    state.config
+        .update(|config| config.auto_start = enabled)?;
+
+    log_info!("开机自启动已{}", if enabled { "启用" } else { "禁用" });
+    Ok(())
+}
+
+/// 获取开机自启动状态（以系统中实际注册的启动项为准）
+#[tauri::command]
+pub async fn get_auto_start_status() -> Result<bool, AppError> {
+    Ok(SystemManager::new().is_auto_start_enabled())
+}
+
+/// 获取最近的日志
+/// 合并应用日志文件末尾与代理核心输出缓存，在后端完成过滤
+///
+/// # 参数
+/// * `level` - 最低日志级别（debug/info/warn/error），为空时不过滤
+/// * `source` - 日志来源（app/core），为空时返回全部来源
+/// * `since` - 起始时间（RFC3339），只返回此时间之后的日志
+/// * `limit` - 最多返回的条数，默认 500
+///
+/// # 返回值
+/// * `Result<Vec<LogEntry>, AppError>` - 按时间顺序排列的日志条目
+#[tauri::command]
+pub async fn get_logs(
+    state: State<'_, AppState>,
+    level: Option<String>,
+    source: Option<String>,
+    since: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, AppError> {
+    let config = state.config.get()?;
+    let min_level = match level.as_deref().filter(|l| !l.is_empty()) {
+        Some(level) => Some(LogLevel::parse(level).ok_or_else(|| AppError::invalid_input(format!("无效的日志级别: {}", level)))?),
+        None => None,
+    };
+    let source = source.filter(|s| !s.is_empty()).map(|s| s.to_lowercase());
+    if let Some(source) = source.as_deref() {
+        if source != "app" && source != "core" {
+            return Err(AppError::invalid_input(format!("无效的日志来源: {}", source)));
+        }
+    }
+    let since = match since.as_deref().filter(|s| !s.is_empty()) {
+        Some(since) => Some(
+            chrono::DateTime::parse_from_rfc3339(since)
+                .map_err(|e| AppError::invalid_input(format!("无效的起始时间: {}", e)))?
+                .with_timezone(&chrono::Utc),
+        ),
+        None => None,
+    };
+    let limit = limit.unwrap_or(500);
+
+    let mut entries = Vec::new();
+
+    if source.as_deref() != Some("core") {
+        let path = config.log_file_path();
+        // 按时间过滤时需要多读一些行，避免过滤后数量不足
+        let read_lines = if since.is_some() { limit.saturating_mul(4) } else { limit };
+        let lines = tokio::task::spawn_blocking(move || read_log_tail(&path, read_lines, min_level))
+            .await
+            .map_err(|e| e.to_string())?
+            .app_context("读取日志失败")?;
+        // 开启隐私模式前写入的日志在读取时隐藏
+        entries.extend(lines.iter().map(|line| LogEntry::from_app_line(&privacy::redact(line))));
+    }
+
+    if source.as_deref() != Some("app") {
+        entries.extend(
+            state.proxy.core_output()
+                .iter()
+                .map(|(time, line)| LogEntry::from_core_line(*time, line))
+                // 无法识别级别的核心输出只在不过滤级别时返回
+                .filter(|entry| min_level.is_none_or(|min| entry.log_level().is_some_and(|l| l >= min))),
+        );
+    }
+
+    if let Some(since) = since {
+        entries.retain(|entry| {
+            chrono::DateTime::parse_from_rfc3339(&entry.time)
+                .is_some_and(|time| time.with_timezone(&chrono::Utc) >= since)
+        });
+    }
+
+    // RFC3339 的 UTC 时间可直接按字符串排序，稳定排序保持同一来源内的原有顺序
+    entries.sort_by(|a, b| a.time.cmp(&b.time));
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+/// 导出日志
+/// 包含全部应用日志（含轮转日志）与代理核心输出缓存，时间统一为 RFC3339 格式；
+/// 开头附带一条版本与系统信息，便于排查问题
+///
+/// # 参数
+/// * `path` - 导出文件路径
+/// * `format` - 导出格式（json/text）
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 导出的日志条数
+#[tauri::command]
+pub async fn export_logs(state: State<'_, AppState>, path: String, format: String) -> Result<usize, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let format = format.to_lowercase();
+    if format != "json" && format != "text" {
+        return Err(AppError::invalid_input(format!("不支持的导出格式: {}", format)));
+    }
+
+    let config = state.config.get()?;
+    let log_path = config.log_file_path();
+    let mut entries = tokio::task::spawn_blocking(move || read_app_log_entries(&log_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .app_context("读取日志失败")?;
+    for entry in entries.iter_mut() {
+        entry.message = privacy::redact(&entry.message).into_owned();
+    }
+    entries.extend(
+        state.proxy.core_output()
+            .iter()
+            .map(|(time, line)| LogEntry::from_core_line(*time, line)),
+    );
+    entries.sort_by(|a, b| a.time.cmp(&b.time));
+    entries.insert(0, LogEntry {
+        time: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        source: "app".to_string(),
+        message: app_info::app_info().summary(),
+    });
+
+    let content = if format == "json" {
+        serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?
+    } else {
+        entries.iter()
+            .map(LogEntry::to_text_line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    std::fs::write(&path, content).app_context("写入导出文件失败")?;
+
+    log_info!("已导出 {} 条日志到: {}", entries.len(), path);
+    Ok(entries.len())
+}
+
+/// 导出连接记录
+/// 从 Xray 访问日志中读取时间范围内的连接（时间、目标域名、出站等），需先启用访问日志
+///
+/// # 参数
+/// * `path` - 导出文件路径
+/// * `format` - 导出格式（csv/json）
+/// * `from` - 起始时间（RFC3339 或 YYYY-MM-DD），为空时不限制
+/// * `to` - 结束时间（RFC3339 或 YYYY-MM-DD，含当天），为空时不限制
+///
+/// # 返回值
+/// * `Result<usize, AppError>` - 导出的记录条数
+#[tauri::command]
+pub async fn export_connection_log(
+    state: State<'_, AppState>,
+    path: String,
+    format: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<usize, AppError> {
+    let format = format.to_lowercase();
+    if format != "csv" && format != "json" {
+        return Err(AppError::invalid_input(format!("不支持的导出格式: {}", format)));
+    }
+    let parse_bound = |value: Option<String>, end_of_day: bool| {
+        value.filter(|v| !v.trim().is_empty())
+            .map(|v| connection_log::parse_time_bound(v.trim(), end_of_day))
+            .transpose()
+            .map_err(|e| AppError::invalid_input(e.to_string()))
+    };
+    let from = parse_bound(from, false)?;
+    let to = parse_bound(to, true)?;
+
+    let config = state.config.get()?;
+    if !config.access_log_enabled {
+        log_warn!("访问日志未启用，导出的连接记录可能不完整");
+    }
+    let access_log = connection_log::access_log_path(&config);
+    let records = tokio::task::spawn_blocking(move || connection_log::read_records(&access_log, from, to))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let content = if format == "json" {
+        serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?
+    } else {
+        connection_log::to_csv(&records)
+    };
+    std::fs::write(&path, content).app_context("写入导出文件失败")?;
+
+    log_info!("已导出 {} 条连接记录到: {}", records.len(), path);
+    Ok(records.len())
+}
+
+/// 规范化日志级别
+fn normalize_log_level(level: &str) -> Result<String, AppError> {
+    match level.to_lowercase().as_str() {
+        "warn" | "warning" => Ok("warning".to_string()),
+        value @ ("debug" | "info" | "error" | "none") => Ok(value.to_string()),
+        _ => Err(AppError::invalid_input(format!("无效的日志级别: {}", level))),
+    }
+}
+
+/// 设置应用日志级别
+/// 只作用于应用日志，立即生效；代理核心的日志级别由 `set_core_log_level` 设置
+///
+/// # 参数
+/// * `level` - 日志级别（debug/info/warning/error/none）
+#[tauri::command]
+pub async fn set_log_level(state: State<'_, AppState>, level: String) -> Result<(), AppError> {
+    let level = normalize_log_level(&level)?;
+    state.config.update(|config| config.app_log_level = level.clone())?;
+    if let Some(logger) = crate::logger::get_logger_internal() {
+        logger.set_level(Logger::level_from_config(&level));
+    }
+    log_info!("应用日志级别已设置为: {}", level);
+    Ok(())
+}
+
+/// 设置代理核心日志级别
+/// 写入生成的核心配置，代理运行中时重新生成配置并重启核心以生效
+///
+/// # 参数
+/// * `level` - 日志级别（debug/info/warning/error/none）
+#[tauri::command]
+pub async fn set_core_log_level(state: State<'_, AppState>, level: String) -> Result<(), AppError> {
+    let level = normalize_log_level(&level)?;
+    state.config.update(|config| config.core_log_level = level.clone())?;
+    log_info!("核心日志级别已设置为: {}", level);
+    state.proxy.reload().await?;
+    Ok(())
+}
+
+/// 设置访问日志
+/// 代理运行中时重新生成配置并重启核心以生效
+///
+/// # 参数
+/// * `enabled` - 是否记录访问日志
+/// * `path` - 访问日志路径，为空时使用应用日志目录下的 access.log
+#[tauri::command]
+pub async fn set_access_log(state: State<'_, AppState>, enabled: bool, path: Option<String>) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let path = path.map(|path| path.trim().to_string()).filter(|path| !path.is_empty());
+    state.config.update(|config| {
+        config.access_log_enabled = enabled;
+        config.access_log_path = path.clone();
+    })?;
+    let config = state.config.get()?;
+    log_info!(
+        "访问日志已{}: {}",
+        if enabled { "启用" } else { "关闭" },
+        connection_log::access_log_path(&config).display()
+    );
+    state.proxy.reload().await?;
+    Ok(())
+}
+
+/// 清理未使用的配置文件
+/// 根据当前服务器列表，清理不再使用的配置文件
+#[tauri::command]
+pub async fn cleanup_unused_configs(state: State<'_, AppState>) -> Result<(), AppError> {
+    let config = state.config.get()?;
+    let active_server_ids: Vec<String> = config.servers.iter().map(|s| s.id.clone()).collect();
+    
+    let proxy_manager = state.proxy;
+    proxy_manager.cleanup_unused_configs(&active_server_ids)?;
+    
+    Ok(())
+}
+
+/// 切换代理核心
+/// 设置保存到当前配置方案；代理运行中时使用同一服务器以新核心重新启动
+///
+/// # 参数
+/// * `core` - 代理核心（xray 或 sing-box）
+#[tauri::command]
+pub async fn set_core_backend(state: State<'_, AppState>, core: CoreKind) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let executable = core_backend::backend(core).executable()?;
+    if !executable.exists() {
+        return Err(AppError::new(ErrorCode::XrayMissing, format!("{} 未安装，请先下载", core_backend::backend(core).name())));
+    }
+
+    let running_server = state.proxy.current_server_id()
+        .filter(|_| state.proxy.is_process_running());
+    if running_server.is_some() {
+        stop_proxy(state.clone()).await?;
+    }
+
+    state.config
+        .update(|config| {
+            config.core = core;
+            config.sync_active_profile();
+        })?;
+
+    if let Some(server_id) = running_server {
+        start_proxy(state.clone(), server_id).await?;
+    }
+
+    log_info!("代理核心已切换为 {}", core_backend::backend(core).name());
+    Ok(())
+}
+
+/// 获取指定代理核心的版本
+#[tauri::command]
+pub async fn get_core_version(core: CoreKind) -> Result<String, AppError> {
+    core_backend::backend(core).version().map_err(AppError::from)
+}
+
+/// 检查指定代理核心的更新
+///
+/// # 返回值
+/// * `Result<Option<String>, AppError>` - 有新版本（或未安装）时返回最新版本号
+#[tauri::command]
+pub async fn check_core_update(core: CoreKind) -> Result<Option<String>, AppError> {
+    let latest = match core {
+        CoreKind::Xray => XrayManager::new().check_update().await,
+        CoreKind::SingBox => SingBoxBackend.check_update().await,
+    }?;
+    // 未安装时同样返回最新版本号，此时不算有更新
+    let installed = core_backend::backend(core).version().is_ok();
+    if let Some(version) = latest.as_ref().filter(|_| installed) {
+        let name = match core {
+            CoreKind::Xray => "Xray",
+            CoreKind::SingBox => "sing-box",
+        };
+        notify::notify(NotifyEvent::UpdateAvailable, &[name, version]);
+    }
+    Ok(latest)
+}
+
+/// 下载并安装指定代理核心的最新版本
+/// 通过 `core-download-progress` 事件报告进度
+///
+/// # 返回值
+/// * `Result<String, AppError>` - 安装的版本号
+#[tauri::command]
+pub async fn download_core(state: State<'_, AppState>, app_handle: tauri::AppHandle, core: CoreKind) -> Result<String, AppError> {
+    let progress = |current: u64, total: u64, message: String| {
+        let _ = app_handle.emit("core-download-progress", serde_json::json!({
+            "core": core,
+            "progress": if total > 0 { current * 100 / total } else { 0 },
+            "message": message
+        }));
+    };
+
+    match core {
+        CoreKind::Xray => {
+            let xray_manager = XrayManager::new();
+            let version = xray_manager.get_latest_version().await?;
+            xray_manager.download_update_with_progress(&version, progress).await?;
+            activate_core_version(state, &version).await?;
+            Ok(version)
+        }
+        CoreKind::SingBox => SingBoxBackend.download_latest(progress).await.map_err(AppError::from),
+    }
+}
+
+/// 检查 Xray Core 更新
+#[tauri::command]
+pub async fn check_xray_update() -> Result<Option<String>, AppError> {
+    let xray_manager = XrayManager::new();
+    xray_manager.check_update().await.map_err(AppError::from)
+}
+
+/// 下载 Xray Core 更新
+#[tauri::command]
+pub async fn download_xray_update(state: State<'_, AppState>, version: String) -> Result<(), AppError> {
+    let xray_manager = XrayManager::new();
+    xray_manager.download_update(&version).await?;
+    activate_core_version(state, &version).await
+}
+
+/// 下载 Xray Core 更新（带进度回调）
+#[tauri::command]
+pub async fn download_xray_update_with_progress(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    version: String,
+) -> Result<(), AppError> {
+    let xray_manager = XrayManager::new();
+    
+    xray_manager.download_update_with_progress(&version, |current, total, message| {
+        let progress = if total > 0 { (current * 100 / total) as u32 } else { 0 };
+        
+        // 发送进度事件到前端
+        let _ = app_handle.emit("xray-download-progress", serde_json::json!({
+            "progress": progress,
+            "message": message
+        }));
+    }).await?;
+    
+    activate_core_version(state, &version).await
+}
+
+/// 获取 Xray Core 版本
+#[tauri::command]
+pub async fn get_xray_version() -> Result<String, AppError> {
+    let xray_manager = XrayManager::new();
+    xray_manager.get_version().await.map_err(AppError::from)
+}
+
+/// 列出已安装的 Xray Core 版本
+#[tauri::command]
+pub async fn list_installed_cores() -> Result<Vec<InstalledCore>, AppError> {
+    let xray_manager = XrayManager::new();
+    xray_manager.list_installed_cores().map_err(AppError::from)
+}
+
+/// 切换 Xray Core 版本
+/// 代理运行中时会先停止代理，切换后使用同一服务器重新启动
+///
+/// # 参数
+/// * `version` - 已安装的版本号，必须是 `list_installed_cores` 返回的版本之一
+#[tauri::command]
+pub async fn switch_core_version(state: State<'_, AppState>, version: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let xray_manager = XrayManager::new();
+    if !xray_manager.list_installed_cores()?.iter().any(|core| core.version == version) {
+        return Err(AppError::invalid_input(format!("Xray Core {} 未安装", version)));
+    }
+
+    activate_core_version(state, &version).await?;
+    log_info!("已切换到 Xray Core {}", version);
+    Ok(())
+}
+
+/// 将已安装的 Xray Core 版本切换为当前使用的版本
+/// 替换可执行文件前需停止核心（Windows 上文件被占用，Linux 上返回 ETXTBSY），
+/// 代理运行中时先停止代理，无论切换是否成功都使用同一服务器重新启动
+///
+/// # 参数
+/// * `version` - 已安装的版本号
+async fn activate_core_version(state: State<'_, AppState>, version: &str) -> Result<(), AppError> {
+    let running_server = state.proxy.current_server_id()
+        .filter(|_| state.proxy.is_process_running());
+    if running_server.is_some() {
+        stop_proxy(state.clone()).await?;
+    }
+
+    let result = XrayManager::new().activate_core(version).map_err(AppError::from);
+
+    if let Some(server_id) = running_server {
+        start_proxy(state.clone(), server_id).await?;
+    }
+    result
+}
+
+/// 回滚到上一个使用的 Xray Core 版本
+///
+/// # 返回值
+/// * `Result<String, AppError>` - 回滚后的版本号
+#[tauri::command]
+pub async fn rollback_core(state: State<'_, AppState>) -> Result<String, AppError> {
+    let config = state.config.get()?;
+    let version = config.previous_core_version
+        .ok_or_else(|| AppError::not_found("没有可回滚的 Xray Core 版本"))?;
+
+    switch_core_version(state, version.clone()).await?;
+    Ok(version)
+}
+
+/// 检查 Xray Core 是否存在
+#[tauri::command]
+pub async fn check_xray_exists() -> Result<bool, AppError> {
+    AppConfig::check_xray_exists().map_err(AppError::from)
+}
+
+/// 获取 Xray Core 可执行文件路径
+#[tauri::command]
+pub async fn get_xray_path() -> Result<String, AppError> {
+    let path = AppConfig::xray_executable()?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 下载地理位置数据文件（geoip.dat 和 geosite.dat）
+/// 
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄，用于发送进度事件
+/// 
+/// # 返回值
+/// * `Result<(), AppError>` - 下载结果
+#[tauri::command]
+pub async fn download_geo_files(app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    let xray_manager = XrayManager::new();
+    
+    xray_manager.download_geo_files(|progress, total, message| {
         let _ = app_handle.emit("geo-download-progress", serde_json::json!({
             "progress": progress,
             "total": total,
             "message": message
         }));
-    }).await.map_err(|e| e.to_string())?;
+    }).await?;
     
     Ok(())
 }
@@ -508,11 +2375,97 @@ pub async fn download_geo_files(app_handle: tauri::AppHandle) -> Result<(), Stri
 /// 检查地理位置数据文件是否存在
 /// 
 /// # 返回值
-/// * `Result<bool, String>` - 文件是否都存在
+/// * `Result<bool, AppError>` - 文件是否都存在
+#[tauri::command]
+pub async fn check_geo_files_exist() -> Result<bool, AppError> {
+    let xray_manager = XrayManager::new();
+    xray_manager.check_geo_files_exist().map_err(AppError::from)
+}
+
+/// 检查地理位置数据文件是否有更新
+/// 
+/// # 返回值
+/// * `Result<GeoUpdateInfo, AppError>` - 各文件状态与上游最新版本
+#[tauri::command]
+pub async fn check_geo_files_update(state: State<'_, AppState>) -> Result<GeoUpdateInfo, AppError> {
+    let xray_manager = XrayManager::new();
+    let info = xray_manager.check_geo_files_update().await?;
+
+    state.config.update(|config| config.geo_last_checked = Some(chrono::Utc::now().to_rfc3339()))?;
+
+    Ok(info)
+}
+
+/// 获取地理位置数据文件状态
+/// 包括 geoip.dat、geosite.dat 与设置中添加的规则文件
+///
+/// # 返回值
+/// * `Result<Vec<GeoFileInfo>, AppError>` - 各文件的下载地址、引用方式、大小、修改时间与 SHA256
+#[tauri::command]
+pub async fn get_geo_files(state: State<'_, AppState>) -> Result<Vec<GeoFileInfo>, AppError> {
+    let sources = state.config.get()?.geo_sources;
+    tokio::task::spawn_blocking(move || geodata::file_statuses(&sources))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(AppError::from)
+}
+
+/// 更新单个地理位置数据文件
+/// 下载后校验 SHA256 与文件格式，校验失败时保留原有文件；进度通过 `geo-download-progress` 事件发送
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄，用于发送进度事件
+/// * `name` - 文件名，geoip.dat、geosite.dat 或设置中添加的规则文件名
 #[tauri::command]
-pub async fn check_geo_files_exist() -> Result<bool, String> {
+pub async fn update_geo_file(app_handle: tauri::AppHandle, name: String) -> Result<(), AppError> {
     let xray_manager = XrayManager::new();
-    xray_manager.check_geo_files_exist().map_err(|e| e.to_string())
+    xray_manager.update_geo_file(&name, |progress, total, message| {
+        let _ = app_handle.emit("geo-download-progress", serde_json::json!({
+            "progress": progress,
+            "total": total,
+            "message": message
+        }));
+    }).await?;
+    Ok(())
+}
+
+/// 列出地理位置数据文件中的分类
+///
+/// # 参数
+/// * `kind` - `geosite` 或 `geoip`
+/// * `file` - 数据文件名（Xray 目录下），为空时使用 geosite.dat / geoip.dat
+///
+/// # 返回值
+/// * `Result<Vec<GeoCategory>, AppError>` - 分类代码、条目数与域名属性
+#[tauri::command]
+pub async fn list_geo_categories(kind: GeoKind, file: Option<String>) -> Result<Vec<GeoCategory>, AppError> {
+    tokio::task::spawn_blocking(move || geodata::list_categories(kind, file.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(AppError::from)
+}
+
+/// 查询域名或 IP 属于哪些地理位置分类，如 example.com 是否属于 geosite:cn
+///
+/// # 参数
+/// * `kind` - `geosite`（查询域名）或 `geoip`（查询 IP）
+/// * `value` - 域名或 IP
+/// * `category` - 只检查该分类，如 `cn`、`geosite:cn`、`category-ads-all@ads`，为空时检查全部分类
+/// * `file` - 数据文件名（Xray 目录下），为空时使用 geosite.dat / geoip.dat
+///
+/// # 返回值
+/// * `Result<Vec<GeoQueryMatch>, AppError>` - 命中的分类与条目，为空表示不属于
+#[tauri::command]
+pub async fn query_geo_category(
+    kind: GeoKind,
+    value: String,
+    category: Option<String>,
+    file: Option<String>,
+) -> Result<Vec<GeoQueryMatch>, AppError> {
+    tokio::task::spawn_blocking(move || geodata::query(kind, &value, category.as_deref(), file.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(AppError::from)
 }
 
 /// 确保所有 Xray 文件都存在（可执行文件和地理位置数据文件）
@@ -521,9 +2474,9 @@ pub async fn check_geo_files_exist() -> Result<bool, String> {
 /// * `app_handle` - Tauri 应用句柄，用于发送进度事件
 /// 
 /// # 返回值
-/// * `Result<(), String>` - 检查和下载结果
+/// * `Result<(), AppError>` - 检查和下载结果
 #[tauri::command]
-pub async fn ensure_xray_files(app_handle: tauri::AppHandle) -> Result<(), String> {
+pub async fn ensure_xray_files(app_handle: tauri::AppHandle) -> Result<(), AppError> {
     let xray_manager = XrayManager::new();
     
     xray_manager.ensure_all_files(|progress, total, message| {
@@ -532,7 +2485,7 @@ pub async fn ensure_xray_files(app_handle: tauri::AppHandle) -> Result<(), Strin
             "total": total,
             "message": message
         }));
-    }).await.map_err(|e| e.to_string())?;
+    }).await?;
     
     Ok(())
 }
@@ -545,7 +2498,7 @@ pub async fn ensure_xray_files(app_handle: tauri::AppHandle) -> Result<(), Strin
 /// 
 /// # 返回值
 /// * `Ok(String)` - 配置验证成功的消息
-/// * `Err(String)` - 配置验证失败的错误信息
+/// * `Err(AppError)` - 配置验证失败的错误信息
 /// 
 /// # 异常
 /// * 当服务器不存在时返回错误
@@ -553,31 +2506,33 @@ pub async fn ensure_xray_files(app_handle: tauri::AppHandle) -> Result<(), Strin
 /// * 当配置生成失败时返回错误
 /// * 当配置验证失败时返回错误
 #[tauri::command]
-pub async fn test_xray_config(server_id: String) -> Result<String, String> {
-    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+pub async fn test_xray_config(state: State<'_, AppState>, server_id: String) -> Result<String, AppError> {
+    let config = state.config.get().app_context("加载配置失败")?;
     
     if let Some(server) = config.servers.iter().find(|s| s.id == server_id) {
-        let proxy_manager = ProxyManager::instance();
+        let proxy_manager = state.proxy;
         
         // 检查 Xray Core 是否存在
-        let xray_executable = AppConfig::xray_executable().map_err(|e| format!("获取 Xray 路径失败: {}", e))?;
+        let xray_executable = AppConfig::xray_executable().app_context("获取 Xray 路径失败")?;
         if !xray_executable.exists() {
-            return Err(format!("Xray Core 可执行文件不存在: {}", xray_executable.display()));
+            return Err(AppError::new(ErrorCode::XrayMissing, format!("Xray Core 可执行文件不存在: {}", xray_executable.display())));
         }
 
         // 生成 Xray 配置
-        let xray_config = proxy_manager.generate_xray_config(server).map_err(|e| format!("生成配置失败: {}", e))?;
+        let xray_config = proxy_manager.generate_xray_config(server).app_context("生成配置失败")?;
         
         // 保存测试配置到临时文件
-        let config_path = proxy_manager.save_test_config(&xray_config).map_err(|e| format!("保存测试配置失败: {}", e))?;
+        let config_path = proxy_manager.save_test_config(&xray_config).app_context("保存测试配置失败")?;
         
         // 使用 Xray 的 -test 参数验证配置
-        let output = std::process::Command::new(&xray_executable)
+        let mut command = std::process::Command::new(&xray_executable);
+        core_backend::prepare_command(core_backend::backend(CoreKind::Xray), &mut command)?;
+        let output = command
             .arg("-config")
             .arg(&config_path)
             .arg("-test")
             .output()
-            .map_err(|e| format!("执行 Xray Core 失败: {}", e))?;
+            .app_context("执行 Xray Core 失败")?;
 
         // 清理测试配置文件
         let _ = std::fs::remove_file(&config_path);
@@ -597,37 +2552,779 @@ pub async fn test_xray_config(server_id: String) -> Result<String, String> {
                 format!("配置验证失败 (退出码: {})", output.status.code().unwrap_or(-1))
             };
             
-            Err(error_msg)
+            Err(AppError::new(ErrorCode::ValidationFailed, error_msg))
         }
     } else {
-        Err(format!("服务器不存在: {}", server_id))
+        Err(AppError::not_found(format!("服务器不存在: {}", server_id)))
+    }
+}
+
+/// 核心配置预览
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigPreview {
+    /// 使用的代理核心
+    pub core: CoreKind,
+    /// 连接时将生成的完整配置
+    pub config: serde_json::Value,
+    /// 已保存配置文件的路径
+    pub saved_path: String,
+    /// 已保存的配置文件是否存在
+    pub saved_exists: bool,
+    /// 已保存配置与预览配置的统一差异格式（unified diff），内容相同时为空
+    pub diff: String,
+}
+
+/// 敏感字段，预览时替换为掩码
+const SECRET_FIELDS: [&str; 7] = ["id", "password", "pass", "privateKey", "preSharedKey", "uuid", "psk"];
+
+/// 将配置中的密码、UUID、私钥等敏感字段替换为掩码
+fn mask_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) && item.is_string() {
+                    *item = serde_json::json!("******");
+                } else {
+                    mask_secrets(item);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        _ => {}
+    }
+}
+
+/// 预览连接服务器时将生成的核心配置
+/// 返回完整配置及其与已保存配置文件的差异，不写入任何文件
+///
+/// # 参数
+/// * `server_id` - 服务器ID
+/// * `mask` - 是否隐藏密码、UUID、私钥等敏感字段，默认隐藏
+///
+/// # 返回值
+/// * `Result<ConfigPreview, AppError>` - 配置预览
+#[tauri::command]
+pub async fn preview_xray_config(state: State<'_, AppState>, server_id: String, mask: Option<bool>) -> Result<ConfigPreview, AppError> {
+    let mask = mask.unwrap_or(true);
+    if !mask {
+        state.app_lock.ensure_unlocked()?;
+    }
+    let config = state.config.get()?;
+    let server = config.servers.iter()
+        .find(|s| s.id == server_id)
+        .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+
+    let backend = core_backend::backend(config.core);
+    let mut generated = backend.generate_config(server)?;
+    let saved_path = backend.config_path(server)?;
+    let mut saved = match std::fs::read_to_string(&saved_path) {
+        Ok(content) => Some(serde_json::from_str::<serde_json::Value>(&content).unwrap_or(serde_json::Value::String(content))),
+        Err(_) => None,
+    };
+
+    if mask {
+        mask_secrets(&mut generated);
+        if let Some(saved) = saved.as_mut() {
+            mask_secrets(saved);
+        }
     }
+
+    // 两侧使用相同的格式化方式，避免缩进、字段顺序等格式差异出现在差异中
+    let pretty = |value: &serde_json::Value| serde_json::to_string_pretty(value).unwrap_or_default() + "\n";
+    let new_text = pretty(&generated);
+    let old_text = saved.as_ref().map(pretty).unwrap_or_default();
+    let diff = if old_text == new_text {
+        String::new()
+    } else {
+        similar::TextDiff::from_lines(&old_text, &new_text)
+            .unified_diff()
+            .context_radius(3)
+            .header("saved", "preview")
+            .to_string()
+    };
+
+    Ok(ConfigPreview {
+        core: config.core,
+        config: generated,
+        saved_path: saved_path.to_string_lossy().to_string(),
+        saved_exists: saved.is_some(),
+        diff,
+    })
 }
 
 /// 获取应用配置
+/// 需处于解锁状态，返回的配置不含应用锁的 PIN 哈希与盐
+#[tauri::command]
+pub async fn get_app_config(state: State<'_, AppState>) -> Result<AppConfig, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let mut config = state.config.get()?;
+    // PIN 哈希与盐只用于后端校验，不返回给界面，避免被离线穷举
+    config.app_lock.pin_hash.clear();
+    config.app_lock.salt.clear();
+    Ok(config)
+}
+
+/// 获取本次启动时从备份恢复配置的记录
+/// 界面据此提示配置文件曾损坏以及损坏文件的位置
+///
+/// # 返回值
+/// * `Option<ConfigRecovery>` - 恢复记录，配置未损坏时为 None
 #[tauri::command]
-pub async fn get_app_config() -> Result<AppConfig, String> {
-    AppConfig::load().map_err(|e| e.to_string())
+pub async fn get_config_recovery() -> Result<Option<ConfigRecovery>, AppError> {
+    Ok(AppConfig::last_recovery())
 }
 
 /// 保存应用配置
 #[tauri::command]
-pub async fn save_app_config(config: AppConfig) -> Result<(), String> {
-    config.save().map_err(|e| e.to_string())
+pub async fn save_app_config(state: State<'_, AppState>, config: AppConfig) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    validate_template_placeholders(&config)?;
+    config.geo_sources.validate().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    config.validate_inbounds().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    config.core_launch.validate().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    let mut config = config;
+    state.config
+        .update(|current| {
+            // 应用锁设置只能通过应用锁命令修改
+            config.app_lock = current.app_lock.clone();
+            // 同步基准只由 WebDAV 同步更新
+            config.webdav.last_sync = current.webdav.last_sync.clone();
+            *current = config;
+        })
+        .map_err(AppError::from)
+}
+
+/// 检查路由规则与自定义入站/出站中的模板变量
+fn validate_template_placeholders(config: &AppConfig) -> Result<(), AppError> {
+    let rules = serde_json::to_value(&config.routing_config.rules).map_err(|e| e.to_string())?;
+    template::validate(&rules, "路由规则")
+        .and_then(|_| template::validate(&serde_json::json!(config.custom_inbounds), "自定义入站"))
+        .and_then(|_| template::validate(&serde_json::json!(config.custom_outbounds), "自定义出站"))
+        .map_err(|e| AppError::invalid_input(e.to_string()))
+}
+
+/// 模拟路由
+/// 按当前路由规则与域名策略检查访问目标时命中的规则与出站，用于排查规则
+///
+/// # 参数
+/// * `target` - 域名、IP 或 URL
+///
+/// # 返回值
+/// * `Result<RouteSimulation, AppError>` - 命中的规则与出站标签，没有规则命中时使用默认出站
+#[tauri::command]
+pub async fn simulate_route(state: State<'_, AppState>, target: String) -> Result<RouteSimulation, AppError> {
+    let config = state.config.get()?;
+    routing::simulate(&config, &target).await.map_err(AppError::from)
+}
+
+/// 获取根据路由规则生成的 PAC 脚本
+///
+/// # 返回值
+/// * `Result<String, AppError>` - PAC 脚本内容
+#[tauri::command]
+pub async fn get_pac_content() -> Result<String, AppError> {
+    let config = AppConfig::load_active()?;
+    pac::generate(&config).map_err(AppError::from)
+}
+
+/// 按 PAC 规则测试 URL，返回 PAC 给出的 PROXY 或 DIRECT
+///
+/// # 参数
+/// * `url` - URL、域名或 IP
+///
+/// # 返回值
+/// * `Result<PacTestResult, AppError>` - PAC 返回值与命中的规则
+#[tauri::command]
+pub async fn test_pac(url: String) -> Result<PacTestResult, AppError> {
+    let config = AppConfig::load_active()?;
+    pac::test(&config, &url).map_err(|e| AppError::invalid_input(e.to_string()))
+}
+
+/// 获取智能路由最近一次的测量结果
+///
+/// # 返回值
+/// * `Result<Vec<SmartRouteDecision>, AppError>` - 各域名的直连与代理延迟及选择的出站
+#[tauri::command]
+pub async fn get_smart_routing_status(state: State<'_, AppState>) -> Result<Vec<SmartRouteDecision>, AppError> {
+    Ok(state.smart_routing.decisions())
+}
+
+/// 立即重新测量智能路由中的域名
+/// 选择的出站变化且代理正在运行时会重启核心
+///
+/// # 返回值
+/// * `Result<Vec<SmartRouteDecision>, AppError>` - 测量结果
+#[tauri::command]
+pub async fn refresh_smart_routing(state: State<'_, AppState>) -> Result<Vec<SmartRouteDecision>, AppError> {
+    state.smart_routing.refresh().await.map_err(AppError::from)
+}
+
+/// 临时让域名走代理，到期后自动恢复
+///
+/// # 参数
+/// * `domain` - 域名（含子域名），也可以粘贴 URL
+/// * `ttl` - 有效期（秒），默认 1 小时
+///
+/// # 返回值
+/// * `Result<DomainOverride, AppError>` - 添加的临时路由
+#[tauri::command]
+pub async fn force_proxy_domain(state: State<'_, AppState>, domain: String, ttl: Option<u64>) -> Result<DomainOverride, AppError> {
+    state.domain_overrides.add(&domain, OverrideTarget::Proxy, ttl).await
+}
+
+/// 临时让域名直连，到期后自动恢复
+///
+/// # 参数
+/// * `domain` - 域名（含子域名），也可以粘贴 URL
+/// * `ttl` - 有效期（秒），默认 1 小时
+///
+/// # 返回值
+/// * `Result<DomainOverride, AppError>` - 添加的临时路由
+#[tauri::command]
+pub async fn force_direct_domain(state: State<'_, AppState>, domain: String, ttl: Option<u64>) -> Result<DomainOverride, AppError> {
+    state.domain_overrides.add(&domain, OverrideTarget::Direct, ttl).await
+}
+
+/// 获取当前有效的临时域名路由
+#[tauri::command]
+pub async fn list_domain_overrides(state: State<'_, AppState>) -> Result<Vec<DomainOverride>, AppError> {
+    Ok(state.domain_overrides.list())
+}
+
+/// 提前移除域名的临时路由
+///
+/// # 参数
+/// * `domain` - 域名
+///
+/// # 返回值
+/// * `Result<bool, AppError>` - 是否存在并已移除
+#[tauri::command]
+pub async fn remove_domain_override(state: State<'_, AppState>, domain: String) -> Result<bool, AppError> {
+    state.domain_overrides.remove(&domain).await
+}
+
+/// 设置本地代理限速
+/// 运行中只调整速率时立即生效；启用或关闭限速需要切换入站端口，会重启核心
+///
+/// # 参数
+/// * `up_kbps` - 上传速率上限（kbps），0 表示不限制
+/// * `down_kbps` - 下载速率上限（kbps），0 表示不限制
+///
+/// # 返回值
+/// * `Result<BandwidthLimitConfig, AppError>` - 保存后的限速设置
+#[tauri::command]
+pub async fn set_bandwidth_limit(state: State<'_, AppState>, up_kbps: u64, down_kbps: u64) -> Result<BandwidthLimitConfig, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let limit = BandwidthLimitConfig { up_kbps, down_kbps };
+    state.config.update(|config| config.bandwidth_limit = limit.clone())?;
+
+    let limiter = state.bandwidth;
+    if state.proxy.is_process_running() && limiter.is_active() != limit.is_active() {
+        state.proxy.reload().await?;
+    } else {
+        limiter.set_rates(&limit);
+    }
+    log_info!("限速已设置: 上传 {} kbps，下载 {} kbps（0 为不限）", up_kbps, down_kbps);
+    Ok(limit)
+}
+
+/// 查询应用锁状态
+#[tauri::command]
+pub async fn get_app_lock_status(state: State<'_, AppState>) -> Result<AppLockStatus, AppError> {
+    Ok(state.app_lock.status())
+}
+
+/// 使用 PIN 解锁应用
+///
+/// # 参数
+/// * `pin` - PIN
+///
+/// # 异常
+/// * PIN 错误或输错次数过多时返回 `APP_LOCKED` 错误
+#[tauri::command]
+pub async fn unlock(state: State<'_, AppState>, pin: String) -> Result<(), AppError> {
+    let app_lock = state.app_lock;
+    tokio::task::spawn_blocking(move || app_lock.unlock(&pin))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 立即锁定应用
+#[tauri::command]
+pub async fn lock_app(state: State<'_, AppState>) -> Result<(), AppError> {
+    state.app_lock.lock();
+    Ok(())
+}
+
+/// 启用应用锁或修改 PIN、自动锁定时间
+/// 已启用时需处于解锁状态
+///
+/// # 参数
+/// * `pin` - 新 PIN，为空时保留原 PIN（首次启用时必填）
+/// * `auto_lock_minutes` - 无操作多少分钟后自动锁定，0 表示不自动锁定
+#[tauri::command]
+pub async fn enable_app_lock(state: State<'_, AppState>, pin: Option<String>, auto_lock_minutes: Option<u64>) -> Result<(), AppError> {
+    let app_lock = state.app_lock;
+    tokio::task::spawn_blocking(move || app_lock.enable(pin.as_deref(), auto_lock_minutes))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 关闭应用锁
+///
+/// # 参数
+/// * `pin` - 当前 PIN
+#[tauri::command]
+pub async fn disable_app_lock(state: State<'_, AppState>, pin: String) -> Result<(), AppError> {
+    let app_lock = state.app_lock;
+    tokio::task::spawn_blocking(move || app_lock.disable(&pin))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 修改界面语言
+/// 托盘菜单和桌面通知随之切换语言：配置修改会发射 `config-changed` 事件，托盘菜单据此重建
+///
+/// # 参数
+/// * `language` - 语言代码，如 `zh-CN`、`en`
+#[tauri::command]
+pub async fn update_language_config(state: State<'_, AppState>, language: String) -> Result<(), AppError> {
+    let code = crate::i18n::resolve_language(&language).ok_or_else(|| {
+        AppError::invalid_input(format!(
+            "不支持的语言: {}（可选: {}）",
+            language,
+            crate::i18n::supported_languages().join(", ")
+        ))
+    })?;
+
+    state.config.update(|config| config.language = code.to_string())?;
+    log_info!("界面语言已切换为: {}", code);
+    Ok(())
+}
+
+/// 设置自定义入站与出站
+/// 保存前将其合并到当前服务器（或第一个服务器）的生成配置中，并使用 `xray -test` 校验
+///
+/// # 参数
+/// * `inbounds` - 自定义入站列表（Xray 入站 JSON 对象）
+/// * `outbounds` - 自定义出站列表（Xray 出站 JSON 对象）
+#[tauri::command]
+pub async fn set_custom_injections(
+    state: State<'_, AppState>,
+    inbounds: Vec<serde_json::Value>,
+    outbounds: Vec<serde_json::Value>,
+) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let mut tags = std::collections::HashSet::new();
+    for (kind, item) in inbounds.iter().map(|v| ("入站", v)).chain(outbounds.iter().map(|v| ("出站", v))) {
+        if !item.is_object() {
+            return Err(AppError::invalid_input(format!("自定义{}必须是 JSON 对象", kind)));
+        }
+        if let Some(tag) = item.get("tag").and_then(|t| t.as_str()) {
+            if crate::proxy::RESERVED_TAGS.contains(&tag) || tag.starts_with("chain-") {
+                return Err(AppError::invalid_input(format!("自定义{}不能使用内置标签: {}", kind, tag)));
+            }
+            if !tags.insert(tag.to_string()) {
+                return Err(AppError::invalid_input(format!("自定义{}标签重复: {}", kind, tag)));
+            }
+        }
+    }
+
+    for (kind, items) in [("自定义入站", &inbounds), ("自定义出站", &outbounds)] {
+        template::validate(&serde_json::json!(items), kind).map_err(|e| AppError::invalid_input(e.to_string()))?;
+    }
+
+    let config = state.config.get()?;
+    let server = config.current_server.as_ref()
+        .and_then(|id| config.servers.iter().find(|s| &s.id == id))
+        .or_else(|| config.servers.first())
+        .cloned();
+    match server {
+        Some(server) => {
+            let mut candidate = config.clone();
+            candidate.custom_inbounds = inbounds.clone();
+            candidate.custom_outbounds = outbounds.clone();
+            let xray_config = state.proxy.build_xray_config(&server, &candidate)?;
+            state.proxy.validate_config(&xray_config).await?;
+        }
+        None => log_warn!("没有可用于校验的服务器，跳过 Xray 配置校验"),
+    }
+
+    let (inbound_count, outbound_count) = (inbounds.len(), outbounds.len());
+    state.config.update(|config| {
+        config.custom_inbounds = inbounds;
+        config.custom_outbounds = outbounds;
+    })?;
+    log_info!("已更新自定义入站 {} 个、出站 {} 个", inbound_count, outbound_count);
+    Ok(())
+}
+
+/// 记录高级日志窗口是否打开
+/// 退出时与主窗口状态一并保存，下次启动时界面据 `window_state.log_window_open` 重新打开
+///
+/// # 参数
+/// * `open` - 日志窗口是否打开
+#[tauri::command]
+pub async fn set_log_window_open(open: bool) -> Result<(), AppError> {
+    window_state::set_log_window_open(open);
+    Ok(())
 }
 
 /// 导出配置
 #[tauri::command]
-pub async fn export_config() -> Result<String, String> {
-    let config = AppConfig::load().map_err(|e| e.to_string())?;
-    serde_json::to_string_pretty(&config).map_err(|e| e.to_string())
+pub async fn export_config(state: State<'_, AppState>) -> Result<String, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    serde_json::to_string_pretty(&config).map_err(|e| AppError::from(e.to_string()))
+}
+
+/// 配置导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigImportResult {
+    /// 导入的服务器数（合并时为新增的数量）
+    pub servers: usize,
+    /// 导入的订阅数
+    pub subscriptions: usize,
+    /// 导入的配置方案数
+    pub profiles: usize,
 }
 
 /// 导入配置
+/// 先校验配置结构（版本兼容性、端口、重复ID、服务器字段），校验失败时返回各字段的错误且不修改本地配置。
+/// 本机相关的设置（核心路径、日志路径、窗口状态、WebDAV、应用锁、钩子与核心启动参数）始终保留本地的值
+///
+/// # 参数
+/// * `config_json` - 配置 JSON
+/// * `mode` - `replace`（默认）以导入的配置替换本地配置；`merge` 保留本地设置，只按ID加入本地没有的服务器、订阅与配置方案
+///
+/// # 返回值
+/// * `Result<ConfigImportResult, AppError>` - 导入的条目数
+#[tauri::command]
+pub async fn import_config(state: State<'_, AppState>, config_json: String, mode: Option<String>) -> Result<ConfigImportResult, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let merge = match mode.as_deref().unwrap_or("replace") {
+        "replace" => false,
+        "merge" => true,
+        other => return Err(AppError::invalid_input(format!("无效的导入方式: {}", other))),
+    };
+    let mut imported: AppConfig = serde_json::from_str(&config_json)
+        .map_err(|e| AppError::invalid_input(format!("配置格式错误: {}", e)))?;
+    for server in &mut imported.servers {
+        validation::sanitize_server(server);
+    }
+    validation::validate_app_config(&imported)?;
+
+    state.config.update(|config| {
+        if merge {
+            imported.servers.retain(|s| !config.servers.iter().any(|local| local.id == s.id));
+            imported.subscriptions.retain(|s| !config.subscriptions.iter().any(|local| local.id == s.id));
+            imported.profiles.retain(|p| !config.profiles.iter().any(|local| local.id == p.id));
+            Profile::keep_local_core_launch(&mut imported.profiles, &config.profiles);
+            config.servers.extend(imported.servers.iter().cloned());
+            config.subscriptions.extend(imported.subscriptions.iter().cloned());
+            config.profiles.extend(imported.profiles.iter().cloned());
+        } else {
+            imported.keep_local_settings(config);
+            *config = imported.clone();
+        }
+    })?;
+
+    let server_ids: Vec<String> = imported.servers.iter().map(|s| s.id.clone()).collect();
+    state.config.emit_servers_changed("updated", &server_ids);
+    log_info!("已{}配置，服务器 {} 个", if merge { "合并导入" } else { "导入" }, server_ids.len());
+    Ok(ConfigImportResult {
+        servers: imported.servers.len(),
+        subscriptions: imported.subscriptions.len(),
+        profiles: imported.profiles.len(),
+    })
+}
+
+/// 服务器交换文件的格式标识
+const SERVER_EXCHANGE_FORMAT: &str = "ruray-servers";
+/// 服务器交换文件的格式版本
+const SERVER_EXCHANGE_VERSION: u32 = 1;
+/// 导出服务器时去除的本机字段（订阅关系与订阅原始名称在其他设备上无意义）
+const LOCAL_SERVER_FIELDS: [&str; 2] = ["subscription_id", subscription::ORIGINAL_NAME_KEY];
+
+/// 服务器交换文件
+/// 只包含选中的服务器和可选的路由规则，不含端口、界面偏好等本机设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerExchange {
+    /// 格式标识，固定为 `ruray-servers`
+    pub format: String,
+    /// 格式版本
+    pub version: u32,
+    /// 导出时间
+    pub exported_at: String,
+    /// 服务器列表
+    pub servers: Vec<ServerInfo>,
+    /// 路由规则，导出时未选择则为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_rules: Option<Vec<RoutingRule>>,
+}
+
+/// 服务器导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerImportResult {
+    /// 导入的服务器ID（新分配）
+    pub imported: Vec<String>,
+    /// 未通过校验而跳过的服务器及原因
+    pub skipped: Vec<String>,
+    /// 新增的路由规则数
+    pub rules_added: usize,
+}
+
+/// 批量导出服务器
+/// 去除最近连接时间、延迟、所属订阅等本机信息；中转服务器未一并导出时去除中转设置
+///
+/// # 参数
+/// * `ids` - 服务器ID列表
+/// * `include_routing` - 是否同时导出路由规则，默认否
+///
+/// # 返回值
+/// * `Ok(String)` - 交换文件 JSON
+/// * `Err(AppError)` - 导出失败的错误信息
+///
+/// # 异常
+/// * 服务器不存在时返回错误
+#[tauri::command]
+pub async fn export_servers(state: State<'_, AppState>, ids: Vec<String>, include_routing: Option<bool>) -> Result<String, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get()?;
+    let mut servers = Vec::with_capacity(ids.len());
+    for server_id in &ids {
+        let server = config.servers.iter()
+            .find(|s| &s.id == server_id)
+            .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+        let mut server = server.clone();
+        server.last_connected_at = None;
+        server.latency_ms = None;
+        for field in LOCAL_SERVER_FIELDS {
+            server.config.remove(field);
+        }
+        let relay_exported = server.config.get("dial_through")
+            .and_then(|v| v.as_str())
+            .is_none_or(|relay| ids.iter().any(|id| id == relay));
+        if !relay_exported {
+            server.config.remove("dial_through");
+        }
+        servers.push(server);
+    }
+
+    let exchange = ServerExchange {
+        format: SERVER_EXCHANGE_FORMAT.to_string(),
+        version: SERVER_EXCHANGE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        servers,
+        routing_rules: include_routing.unwrap_or(false).then(|| config.routing_config.rules.clone()),
+    };
+    serde_json::to_string_pretty(&exchange).map_err(|e| AppError::from(e.to_string()))
+}
+
+/// 导入服务器交换文件
+/// 每个服务器分配新ID并单独校验，未通过校验的跳过；中转设置随ID一并更新。
+/// 路由规则只追加本地尚不存在的规则。也接受旧版本导出的服务器 JSON 数组
+///
+/// # 参数
+/// * `content` - 交换文件 JSON
+///
+/// # 返回值
+/// * `Ok(ServerImportResult)` - 导入结果
+/// * `Err(AppError)` - 文件格式不正确时的错误信息
+#[tauri::command]
+pub async fn import_servers(state: State<'_, AppState>, content: String) -> Result<ServerImportResult, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| AppError::invalid_input(format!("无法解析服务器文件: {}", e)))?;
+    let exchange: ServerExchange = if value.is_array() {
+        ServerExchange {
+            format: SERVER_EXCHANGE_FORMAT.to_string(),
+            version: SERVER_EXCHANGE_VERSION,
+            exported_at: String::new(),
+            servers: serde_json::from_value(value).map_err(|e| AppError::invalid_input(format!("无法解析服务器文件: {}", e)))?,
+            routing_rules: None,
+        }
+    } else {
+        serde_json::from_value(value).map_err(|e| AppError::invalid_input(format!("无法解析服务器文件: {}", e)))?
+    };
+    if exchange.format != SERVER_EXCHANGE_FORMAT {
+        return Err(AppError::invalid_input(format!("不是 RuRay 服务器文件: {}", exchange.format)));
+    }
+    if exchange.version > SERVER_EXCHANGE_VERSION {
+        return Err(AppError::invalid_input(format!("服务器文件版本 {} 高于当前支持的版本 {}", exchange.version, SERVER_EXCHANGE_VERSION)));
+    }
+
+    let result = state.config.update(|config| {
+        let id_map: HashMap<String, String> = exchange.servers.iter()
+            .map(|s| (s.id.clone(), Uuid::new_v4().to_string()))
+            .collect();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut result = ServerImportResult { imported: Vec::new(), skipped: Vec::new(), rules_added: 0 };
+        let mut accepted = Vec::new();
+        for mut server in exchange.servers {
+            server.id = id_map[&server.id].clone();
+            if let Some(relay) = server.config.get("dial_through").and_then(|v| v.as_str()).map(str::to_string) {
+                match id_map.get(&relay) {
+                    Some(new_id) => { server.config.insert("dial_through".to_string(), serde_json::json!(new_id)); }
+                    None => { server.config.remove("dial_through"); }
+                }
+            }
+            validation::sanitize_server(&mut server);
+            server.created_at = now.clone();
+            server.updated_at = now.clone();
+            server.last_connected_at = None;
+            server.latency_ms = None;
+            accepted.push(server);
+        }
+        // 中转服务器可能排在后面，全部加入后再校验
+        let all_servers: Vec<ServerInfo> = config.servers.iter().chain(accepted.iter()).cloned().collect();
+        for server in accepted {
+            match validation::validate_server(&server, &all_servers) {
+                Ok(()) => {
+                    result.imported.push(server.id.clone());
+                    config.servers.push(server);
+                }
+                Err(errors) => {
+                    let reason = errors.errors.first().map(|e| e.message.clone()).unwrap_or_default();
+                    result.skipped.push(format!("{}: {}", server.name, reason));
+                }
+            }
+        }
+
+        if let Some(rules) = exchange.routing_rules {
+            for rule in rules {
+                let value = serde_json::to_value(&rule).unwrap_or_default();
+                let exists = config.routing_config.rules.iter()
+                    .any(|existing| serde_json::to_value(existing).unwrap_or_default() == value);
+                if !exists {
+                    config.routing_config.rules.push(rule);
+                    result.rules_added += 1;
+                }
+            }
+        }
+        result
+    })?;
+    if !result.imported.is_empty() {
+        state.config.emit_servers_changed("added", &result.imported);
+    }
+    log_info!("已导入 {} 个服务器，跳过 {} 个，新增 {} 条路由规则", result.imported.len(), result.skipped.len(), result.rules_added);
+    Ok(result)
+}
+
+/// 备份数据目录
+/// 将配置文件和服务器核心配置打包为 zip 文件，不包含核心可执行文件与地理位置数据
+///
+/// # 参数
+/// * `path` - 备份文件路径
+///
+/// # 返回值
+/// * `Ok(BackupManifest)` - 备份说明
+/// * `Err(AppError)` - 备份失败的错误信息
 #[tauri::command]
-pub async fn import_config(config_json: String) -> Result<(), String> {
-    let config: AppConfig = serde_json::from_str(&config_json).map_err(|e| e.to_string())?;
-    config.save().map_err(|e| e.to_string())
+pub async fn create_backup(state: State<'_, AppState>, path: String) -> Result<BackupManifest, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    tokio::task::spawn_blocking(move || backup::create_backup(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .app_context("创建备份失败")
+}
+
+/// 从备份恢复数据目录
+/// 恢复后立即重新加载配置；正在运行的代理需重新连接后才会使用恢复的配置
+///
+/// # 参数
+/// * `path` - 备份文件路径
+///
+/// # 返回值
+/// * `Ok(BackupManifest)` - 备份说明
+/// * `Err(AppError)` - 恢复失败的错误信息
+///
+/// # 异常
+/// * 文件不是有效的备份或备份来自更新的版本时返回错误
+#[tauri::command]
+pub async fn restore_backup(state: State<'_, AppState>, path: String) -> Result<BackupManifest, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    tokio::task::spawn_blocking(move || backup::restore_backup(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| AppError::invalid_input(format!("恢复备份失败: {}", e)))
+}
+
+/// 查询 WebDAV 同步状态
+/// 返回本地配置与远端备份的修改时间，不上传也不下载
+///
+/// # 返回值
+/// * `Ok(WebDavSyncStatus)` - 同步状态
+/// * `Err(AppError)` - 未启用同步或无法访问服务器时的错误信息
+#[tauri::command]
+pub async fn webdav_sync_status() -> Result<WebDavSyncStatus, AppError> {
+    let sync = WebDavSync::from_config().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    sync.status().await.map_err(AppError::from)
+}
+
+/// 上传配置到 WebDAV
+///
+/// # 参数
+/// * `force` - 远端配置比本地新时是否仍然覆盖，默认否
+///
+/// # 返回值
+/// * `Ok(WebDavSyncStatus)` - 同步结果，`conflict` 为 true 且 `synced` 为 false 时表示因冲突未上传
+/// * `Err(AppError)` - 上传失败的错误信息
+#[tauri::command]
+pub async fn webdav_upload(state: State<'_, AppState>, force: Option<bool>) -> Result<WebDavSyncStatus, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let sync = WebDavSync::from_config().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    sync.upload(force.unwrap_or(false)).await.map_err(AppError::from)
+}
+
+/// 从 WebDAV 下载配置
+///
+/// # 参数
+/// * `merge` - 是否保留远端没有的本地服务器、订阅和配置方案，默认是
+/// * `force` - 本地配置比远端新时是否仍然覆盖，默认否
+///
+/// # 返回值
+/// * `Ok(WebDavSyncStatus)` - 同步结果，`conflict` 为 true 且 `synced` 为 false 时表示因冲突未下载
+/// * `Err(AppError)` - 下载失败的错误信息
+#[tauri::command]
+pub async fn webdav_download(state: State<'_, AppState>, merge: Option<bool>, force: Option<bool>) -> Result<WebDavSyncStatus, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let sync = WebDavSync::from_config().map_err(|e| AppError::invalid_input(e.to_string()))?;
+    sync.download(merge.unwrap_or(true), force.unwrap_or(false))
+        .await
+        .map_err(AppError::from)
+}
+
+/// 导出完整的 Xray 配置包
+/// 将指定服务器的完整 Xray 配置及地理位置数据文件写入所选目录，便于无界面运行
+///
+/// # 参数
+/// * `server_id` - 服务器ID
+/// * `path` - 导出目录
+///
+/// # 返回值
+/// * `Ok(Vec<String>)` - 已写入的文件路径列表
+/// * `Err(AppError)` - 导出失败的错误信息
+///
+/// # 异常
+/// * 当服务器不存在时返回错误
+/// * 当生成或写入配置失败时返回错误
+#[tauri::command]
+pub async fn export_full_xray_config(state: State<'_, AppState>, server_id: String, path: String) -> Result<Vec<String>, AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get().app_context("加载配置失败")?;
+
+    let server = config.servers.iter()
+        .find(|s| s.id == server_id)
+        .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+
+    let proxy_manager = state.proxy;
+    let files = proxy_manager
+        .export_config_bundle(server, std::path::Path::new(&path))
+        .app_context("导出配置失败")?;
+
+    Ok(files.iter().map(|p| p.to_string_lossy().to_string()).collect())
 }
 
 /// 重新生成服务器配置文件
@@ -638,25 +3335,24 @@ pub async fn import_config(config_json: String) -> Result<(), String> {
 /// 
 /// # 返回值
 /// * `Ok(())` - 成功重新生成配置文件
-/// * `Err(String)` - 重新生成失败的错误信息
+/// * `Err(AppError)` - 重新生成失败的错误信息
 /// 
 /// # 异常
 /// * 当服务器不存在时返回错误
 /// * 当生成配置文件失败时返回错误
 #[tauri::command]
-pub async fn regenerate_server_config(server_id: String) -> Result<(), String> {
-    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+pub async fn regenerate_server_config(state: State<'_, AppState>, server_id: String) -> Result<(), AppError> {
+    let config = state.config.get().app_context("加载配置失败")?;
     
     if let Some(server) = config.servers.iter().find(|s| s.id == server_id) {
-        let proxy_manager = ProxyManager::instance();
+        let proxy_manager = state.proxy;
         
-        proxy_manager.regenerate_config(server).await.map_err(|e| {
-            format!("重新生成配置文件失败: {}", e)
-        })?;
+        proxy_manager.regenerate_config(server).await
+            .app_context("重新生成配置文件失败")?;
         
         Ok(())
     } else {
-        Err("服务器不存在".to_string())
+        Err(AppError::not_found("服务器不存在"))
     }
 }
 
@@ -668,17 +3364,18 @@ pub async fn regenerate_server_config(server_id: String) -> Result<(), String> {
 /// 
 /// # 返回值
 /// * `Ok(())` - 成功打开文件或目录
-/// * `Err(String)` - 打开失败的错误信息
+/// * `Err(AppError)` - 打开失败的错误信息
 /// 
 /// # 异常
 /// * 当服务器不存在时返回错误
 /// * 当无法打开文件或目录时返回错误
 #[tauri::command]
-pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
-    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+pub async fn open_server_config_file(state: State<'_, AppState>, server_id: String) -> Result<(), AppError> {
+    state.app_lock.ensure_unlocked()?;
+    let config = state.config.get().app_context("加载配置失败")?;
     
     if let Some(server) = config.servers.iter().find(|s| s.id == server_id) {
-        let proxy_manager = ProxyManager::instance();
+        let proxy_manager = state.proxy;
         
         // 获取服务器配置文件路径
         let config_file_path = proxy_manager.get_server_config_path(&server.id, &server.name);
@@ -690,7 +3387,7 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("cmd")
                     .args(["/C", "start", "", &config_file_path.to_string_lossy()])
                     .spawn()
-                    .map_err(|e| format!("打开配置文件失败: {}", e))?;
+                    .app_context("打开配置文件失败")?;
             }
             
             #[cfg(target_os = "macos")]
@@ -698,7 +3395,7 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("open")
                     .arg(&config_file_path)
                     .spawn()
-                    .map_err(|e| format!("打开配置文件失败: {}", e))?;
+                    .app_context("打开配置文件失败")?;
             }
             
             #[cfg(target_os = "linux")]
@@ -706,7 +3403,7 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("xdg-open")
                     .arg(&config_file_path)
                     .spawn()
-                    .map_err(|e| format!("打开配置文件失败: {}", e))?;
+                    .app_context("打开配置文件失败")?;
             }
         } else {
             // 配置文件不存在，打开配置目录
@@ -721,7 +3418,7 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("explorer")
                     .arg(&config_dir)
                     .spawn()
-                    .map_err(|e| format!("打开配置目录失败: {}", e))?;
+                    .app_context("打开配置目录失败")?;
             }
             
             #[cfg(target_os = "macos")]
@@ -729,7 +3426,7 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("open")
                     .arg(&config_dir)
                     .spawn()
-                    .map_err(|e| format!("打开配置目录失败: {}", e))?;
+                    .app_context("打开配置目录失败")?;
             }
             
             #[cfg(target_os = "linux")]
@@ -737,12 +3434,12 @@ pub async fn open_server_config_file(server_id: String) -> Result<(), String> {
                 std::process::Command::new("xdg-open")
                     .arg(&config_dir)
                     .spawn()
-                    .map_err(|e| format!("打开配置目录失败: {}", e))?;
+                    .app_context("打开配置目录失败")?;
             }
         }
         
         Ok(())
     } else {
-        Err(format!("服务器不存在: {}", server_id))
+        Err(AppError::not_found(format!("服务器不存在: {}", server_id)))
     }
 }
\ No newline at end of file
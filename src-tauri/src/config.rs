@@ -7,12 +7,113 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use fs2::FileExt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// 导入日志宏
+use crate::log_warn;
 
 use crate::commands::ServerInfo;
+use crate::config_store::ConfigStore;
+use crate::core_backend::CoreKind;
+use crate::geodata::GeoSourceConfig;
+use crate::hooks::HooksConfig;
+use crate::app_lock::AppLockConfig;
+use crate::bandwidth::BandwidthLimitConfig;
+use crate::notify::NotificationConfig;
+use crate::webdav::WebDavConfig;
+use crate::profile::Profile;
+use crate::smart_routing::SmartRoutingConfig;
+use crate::speed_test::SpeedTestResult;
+use crate::subscription::Subscription;
+use crate::transparent::TransparentConfig;
 use crate::tun::TunConfig;
 
+/// 便携模式标记文件，放在可执行文件所在目录时启用便携模式
+pub const PORTABLE_FLAG_FILE: &str = "portable.flag";
+
+/// 便携模式下数据目录相对可执行文件所在目录的名称
+const PORTABLE_DATA_DIR_NAME: &str = "data";
+
+/// 数据目录与是否为便携模式，首次使用时确定，运行期间不变
+static DATA_DIR: OnceLock<(PathBuf, bool)> = OnceLock::new();
+
+/// 配置文件被占用时的最大重试次数
+const CONFIG_WRITE_RETRIES: u32 = 10;
+/// 配置文件被占用时的重试间隔，按重试次数递增
+const CONFIG_WRITE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// 串行化本进程内的配置文件写入
+static CONFIG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 保留的配置备份数量
+const CONFIG_BACKUP_COUNT: usize = 5;
+/// 最新备份早于此间隔时才轮转备份，间隔内的写入只覆盖最新的备份
+const CONFIG_BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// 配置备份目录名称（位于数据目录下）
+const CONFIG_BACKUP_DIR_NAME: &str = "config_backups";
+
+/// 本次运行中从备份恢复配置的记录
+static CONFIG_RECOVERY: Mutex<Option<ConfigRecovery>> = Mutex::new(None);
+
+/// 配置文件损坏后从备份恢复的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigRecovery {
+    /// 损坏的配置文件另存的路径，供排查
+    pub corrupt_path: String,
+    /// 恢复所用的备份
+    pub backup_path: String,
+    /// 配置文件的解析错误
+    pub error: String,
+}
+
+/// 以临时文件加重命名的方式替换文件内容
+///
+/// # 参数
+/// * `path` - 目标文件
+/// * `content` - 新内容
+fn replace_file(path: &Path, content: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    let written = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .context("无法写入临时配置文件")
+        .and_then(|_| retry_on_busy(|| fs::rename(&temp_path, path)).context("无法替换配置文件"));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+/// 执行文件操作，文件或锁被占用时按递增间隔重试
+fn retry_on_busy<T>(mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < CONFIG_WRITE_RETRIES && is_busy(&e) => {
+                attempt += 1;
+                std::thread::sleep(CONFIG_WRITE_RETRY_DELAY * attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 错误是否表示文件或锁暂时被占用
+/// 只识别锁争用和 Windows 的共享冲突（ERROR_SHARING_VIOLATION = 32、ERROR_LOCK_VIOLATION = 33），
+/// 权限不足等其他错误立即返回，不做无意义的重试
+fn is_busy(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::WouldBlock
+        || e.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+        || (cfg!(target_os = "windows") && matches!(e.raw_os_error(), Some(32 | 33)))
+}
+
 /// 为 rule_type 字段提供默认值
 fn default_rule_type() -> String {
     "field".to_string()
@@ -31,6 +132,67 @@ pub struct RoutingRule {
     pub outbound_tag: String,
 }
 
+/// 单个服务器对全局设置的覆盖
+/// 未设置的字段沿用全局设置，在启动该服务器时生效，用于兼容对设置有特殊要求的节点
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerOverrides {
+    /// 代理模式（pac/global/direct）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_mode: Option<String>,
+    /// 入站是否启用流量嗅探
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sniffing_enabled: Option<bool>,
+    /// HTTP 入站端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_port: Option<u16>,
+    /// SOCKS 入站端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socks_port: Option<u16>,
+    /// 是否启用TUN模式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tun_enabled: Option<bool>,
+}
+
+impl ServerOverrides {
+    /// 是否没有任何覆盖
+    pub fn is_empty(&self) -> bool {
+        self.proxy_mode.is_none()
+            && self.sniffing_enabled.is_none()
+            && self.http_port.is_none()
+            && self.socks_port.is_none()
+            && self.tun_enabled.is_none()
+    }
+
+    /// 将覆盖应用到配置
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(mode) = &self.proxy_mode {
+            config.proxy_mode = mode.clone();
+        }
+        if let Some(sniffing) = self.sniffing_enabled {
+            config.inbound_sniffing_enabled = sniffing;
+            for inbound_sniffing in config.inbounds.iter_mut().filter_map(|i| i.sniffing.as_mut()) {
+                inbound_sniffing.enabled = sniffing;
+            }
+        }
+        // 自定义入站列表中对应端口的入站随之改用覆盖的端口
+        if let Some(port) = self.http_port {
+            for inbound in config.inbounds.iter_mut().filter(|i| i.port == config.http_port) {
+                inbound.port = port;
+            }
+            config.http_port = port;
+        }
+        if let Some(port) = self.socks_port {
+            for inbound in config.inbounds.iter_mut().filter(|i| i.port == config.socks_port) {
+                inbound.port = port;
+            }
+            config.socks_port = port;
+        }
+        if let Some(tun_enabled) = self.tun_enabled {
+            config.tun_enabled = tun_enabled;
+        }
+    }
+}
+
 /// 路由配置结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingConfig {
@@ -56,6 +218,298 @@ impl Default for RoutingConfig {
     }
 }
 
+/// 多路复用（Mux）配置，作用于所有 VMess/VLESS/Trojan 出站
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MuxConfig {
+    /// 是否启用 Mux（服务器配置中的 `mux` 字段可单独覆盖）
+    #[serde(default)]
+    pub enabled: bool,
+    /// TCP 连接的最大并发数
+    #[serde(default = "default_mux_concurrency")]
+    pub concurrency: i32,
+    /// UDP（XUDP）连接的最大并发数
+    #[serde(default = "default_mux_xudp_concurrency")]
+    pub xudp_concurrency: i32,
+    /// 对 UDP 443（QUIC）流量的处理方式：reject/allow/skip
+    #[serde(default = "default_mux_xudp_proxy_udp443")]
+    pub xudp_proxy_udp443: String,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            concurrency: default_mux_concurrency(),
+            xudp_concurrency: default_mux_xudp_concurrency(),
+            xudp_proxy_udp443: default_mux_xudp_proxy_udp443(),
+        }
+    }
+}
+
+/// TLS 分片配置
+/// 启用后代理出站经由带 fragment 设置的 freedom 出站拨号，拆分 TLS ClientHello
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FragmentConfig {
+    /// 是否启用分片
+    #[serde(default)]
+    pub enabled: bool,
+    /// 分片的数据包：tlshello 或数据包序号范围（如 1-3）
+    #[serde(default = "default_fragment_packets")]
+    pub packets: String,
+    /// 分片长度范围（字节）
+    #[serde(default = "default_fragment_length")]
+    pub length: String,
+    /// 分片间隔范围（毫秒）
+    #[serde(default = "default_fragment_interval")]
+    pub interval: String,
+}
+
+impl Default for FragmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            packets: default_fragment_packets(),
+            length: default_fragment_length(),
+            interval: default_fragment_interval(),
+        }
+    }
+}
+
+/// 本地控制 API 配置
+/// 启用后在 127.0.0.1 上提供 HTTP 接口，供脚本、Stream Deck 等外部程序控制代理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlApiConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 监听端口
+    #[serde(default = "default_control_api_port")]
+    pub port: u16,
+    /// 访问令牌，请求需携带 `Authorization: Bearer <令牌>`；为空时在启用时自动生成
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Default for ControlApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_control_api_port(),
+            token: String::new(),
+        }
+    }
+}
+
+/// 本地入站用户
+/// 入站认证方式为 `password` 时，HTTP 与 SOCKS 入站接受其中任一用户名和密码，
+/// 用户名同时作为 Xray 统计中的用户标识，用于按用户统计流量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundUser {
+    /// 用户名
+    pub username: String,
+    /// 密码
+    pub password: String,
+}
+
+/// 入站协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InboundProtocol {
+    /// HTTP 代理
+    Http,
+    /// SOCKS5 代理
+    Socks,
+    /// HTTP 与 SOCKS5 共用端口
+    Mixed,
+    /// 任意门，将收到的连接转发到固定目标
+    Dokodemo,
+}
+
+impl InboundProtocol {
+    /// 配置与标签中使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            InboundProtocol::Http => "http",
+            InboundProtocol::Socks => "socks",
+            InboundProtocol::Mixed => "mixed",
+            InboundProtocol::Dokodemo => "dokodemo",
+        }
+    }
+
+    /// 是否可作为 HTTP 代理使用
+    pub fn serves_http(self) -> bool {
+        matches!(self, InboundProtocol::Http | InboundProtocol::Mixed)
+    }
+
+    /// 是否可作为 SOCKS 代理使用
+    pub fn serves_socks(self) -> bool {
+        matches!(self, InboundProtocol::Socks | InboundProtocol::Mixed)
+    }
+}
+
+/// 启动代理核心时额外传递的环境变量与参数，用于调试（如 `XRAY_BUF_READV=enable`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoreLaunchOptions {
+    /// 环境变量，覆盖 RuRay 默认设置的同名变量
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// 追加在启动参数之后的参数，只用于代理运行，不用于配置检查与测速
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl CoreLaunchOptions {
+    /// 校验环境变量名
+    ///
+    /// # 异常
+    /// * 变量名为空或包含 `=`、空字符时返回错误
+    pub fn validate(&self) -> Result<()> {
+        for name in self.env.keys() {
+            if name.is_empty() || name.contains('=') || name.contains('\0') {
+                return Err(anyhow::anyhow!("无效的环境变量名: {:?}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 入站流量嗅探设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SniffingConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 嗅探的协议（http/tls/quic/fakedns），识别出的域名用于路由
+    #[serde(default = "default_sniffing_dest_override")]
+    pub dest_override: Vec<String>,
+    /// 为 true 时嗅探结果只用于路由匹配，连接仍使用原始目标地址
+    #[serde(default)]
+    pub route_only: bool,
+}
+
+impl SniffingConfig {
+    /// 校验嗅探协议
+    ///
+    /// # 参数
+    /// * `tag` - 所属入站标签，用于错误信息
+    fn validate(&self, tag: &str) -> Result<()> {
+        if let Some(protocol) = self.dest_override.iter().find(|p| !SNIFFING_PROTOCOLS.contains(&p.as_str())) {
+            return Err(anyhow::anyhow!("入站 {} 的嗅探协议无效: {}", tag, protocol));
+        }
+        if self.enabled && self.dest_override.is_empty() {
+            return Err(anyhow::anyhow!("入站 {} 启用嗅探时至少需要一种协议", tag));
+        }
+        Ok(())
+    }
+}
+
+/// 入站配置
+/// 未配置入站列表时使用 `http_port` 上的 HTTP 入站与 `socks_port` 上的 Mixed 入站
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundConfig {
+    /// 标签，供路由规则的 `inboundTag` 引用；为空时按 `协议-端口` 生成，如 `socks-1081`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// 协议
+    pub protocol: InboundProtocol,
+    /// 监听地址，为空时按 `allow_lan` 监听 0.0.0.0 或 127.0.0.1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+    /// 监听端口
+    pub port: u16,
+    /// 任意门的转发目标地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_address: Option<String>,
+    /// 任意门的转发目标端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_port: Option<u16>,
+    /// 任意门接收的网络（tcp、udp 或 tcp,udp），默认 tcp,udp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// 流量嗅探设置，为空时使用全局设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sniffing: Option<SniffingConfig>,
+}
+
+impl InboundConfig {
+    /// 路由规则中使用的标签
+    pub fn tag(&self) -> String {
+        self.tag.clone()
+            .filter(|tag| !tag.is_empty())
+            .unwrap_or_else(|| format!("{}-{}", self.protocol.name(), self.port))
+    }
+
+    /// 默认入站
+    fn builtin(tag: &str, protocol: InboundProtocol, port: u16) -> Self {
+        Self {
+            tag: Some(tag.to_string()),
+            protocol,
+            listen: None,
+            port,
+            target_address: None,
+            target_port: None,
+            network: None,
+            sniffing: None,
+        }
+    }
+}
+
+/// 主窗口状态
+/// 位置与尺寸均为物理像素，最大化时保留最大化前的尺寸和位置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// 窗口宽度
+    pub width: u32,
+    /// 窗口高度
+    pub height: u32,
+    /// 窗口左上角横坐标
+    pub x: i32,
+    /// 窗口左上角纵坐标
+    pub y: i32,
+    /// 是否最大化
+    #[serde(default)]
+    pub maximized: bool,
+    /// 高级日志窗口是否打开
+    #[serde(default)]
+    pub log_window_open: bool,
+}
+
+/// 为 MuxConfig 字段提供默认值
+fn default_mux_concurrency() -> i32 {
+    8
+}
+
+fn default_mux_xudp_concurrency() -> i32 {
+    16
+}
+
+fn default_mux_xudp_proxy_udp443() -> String {
+    "reject".to_string()
+}
+
+/// 为 FragmentConfig 字段提供默认值
+fn default_fragment_packets() -> String {
+    "tlshello".to_string()
+}
+
+fn default_fragment_length() -> String {
+    "100-200".to_string()
+}
+
+fn default_fragment_interval() -> String {
+    "10-20".to_string()
+}
+
+/// 为 ControlApiConfig 的 port 字段提供默认值
+fn default_control_api_port() -> u16 {
+    10099
+}
+
+/// 为 stats_scope 字段提供默认值
+fn default_stats_scope() -> String {
+    "system".to_string()
+}
+
 /// 为 domain_strategy 字段提供默认值
 fn default_domain_strategy() -> String {
     "AsIs".to_string()
@@ -66,21 +520,85 @@ fn default_theme_color() -> String {
     "green".to_string()
 }
 
+/// 为 connectivity_check_url 字段提供默认值
+fn default_connectivity_check_url() -> String {
+    "https://www.gstatic.com/generate_204".to_string()
+}
+
+/// 为 connectivity_check_direct_url 字段提供默认值
+fn default_connectivity_check_direct_url() -> String {
+    "http://connect.rom.miui.com/generate_204".to_string()
+}
+
+/// 为 connectivity_check_interval 字段提供默认值（秒）
+fn default_connectivity_check_interval() -> u64 {
+    60
+}
+
+/// 为 connectivity_check_timeout 字段提供默认值（秒）
+fn default_connectivity_check_timeout() -> u64 {
+    10
+}
+
+/// 为 api_port 字段提供默认值
+fn default_api_port() -> u16 {
+    10085
+}
+
+/// 为 xray_max_restarts 字段提供默认值
+pub(crate) fn default_xray_max_restarts() -> u32 {
+    5
+}
+
+/// 为 core_stop_grace_secs 字段提供默认值
+fn default_core_stop_grace_secs() -> u64 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_auth_method() -> String {
     "noauth".to_string()
 }
 
+/// 为流量嗅探的 destOverride 提供默认值
+fn default_sniffing_dest_override() -> Vec<String> {
+    vec!["http".to_string(), "tls".to_string()]
+}
+
+/// 支持的嗅探协议
+const SNIFFING_PROTOCOLS: [&str; 4] = ["http", "tls", "quic", "fakedns"];
+
+/// 为 log_max_size_mb 字段提供默认值
+pub(crate) fn default_log_max_size_mb() -> u64 {
+    10
+}
+
+/// 为 log_retention_days 字段提供默认值
+pub(crate) fn default_log_retention_days() -> u32 {
+    7
+}
+
+/// 为 core_log_level 字段提供默认值
+fn default_core_log_level() -> String {
+    "warning".to_string()
+}
+
 /// 为 log_path 字段提供默认值
 fn default_log_path() -> String {
-    // 默认日志路径为配置目录下的 log/ruray.log
-    match dirs::config_dir() {
-        Some(config_dir) => config_dir
-            .join("RuRay")
+    // 默认日志路径为数据目录下的 log/ruray.log；便携模式使用相对路径，移动目录后仍然有效
+    if AppConfig::is_portable() {
+        return "log/ruray.log".to_string();
+    }
+    match AppConfig::data_dir() {
+        Ok(data_dir) => data_dir
             .join("log")
             .join("ruray.log")
             .to_string_lossy()
             .to_string(),
-        None => "./log/ruray.log".to_string(),
+        Err(_) => "./log/ruray.log".to_string(),
     }
 }
 
@@ -89,32 +607,101 @@ fn default_log_path() -> String {
 pub struct AppConfig {
     pub version: String,
     pub servers: Vec<ServerInfo>,
+    /// 订阅列表
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    /// 配置方案列表
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// 当前激活的配置方案ID
+    #[serde(default)]
+    pub active_profile: Option<String>,
     pub current_server: Option<String>,
     pub proxy_mode: String,
     pub auto_start: bool,
     pub minimize_to_tray: bool,
     pub start_minimized: bool,
+    /// 代理核心（xray 或 sing-box）
+    #[serde(default)]
+    pub core: CoreKind,
+    /// 当前使用的 Xray Core 版本（由版本管理切换，使用自定义路径时为空）
+    #[serde(default)]
+    pub core_version: Option<String>,
+    /// 切换前使用的 Xray Core 版本，用于回滚
+    #[serde(default)]
+    pub previous_core_version: Option<String>,
+    /// 各服务器最近一次成功连接时使用的 Xray Core 版本（服务器ID -> 版本号）
+    #[serde(default)]
+    pub core_version_usage: HashMap<String, String>,
+    /// Xray Core 异常退出后的最大连续重启次数
+    #[serde(default = "default_xray_max_restarts")]
+    pub xray_max_restarts: u32,
+    /// 停止核心时等待其正常退出的时间（秒），超时后强制终止；0 表示直接强制终止
+    #[serde(default = "default_core_stop_grace_secs")]
+    pub core_stop_grace_secs: u64,
+    /// 启动代理核心时额外的环境变量与参数
+    #[serde(default)]
+    pub core_launch: CoreLaunchOptions,
+    /// 启动时自动连接上次使用的服务器
+    #[serde(default)]
+    pub connect_on_startup: bool,
     pub theme: String,
     /// 主题色配置
     #[serde(default = "default_theme_color")]
     pub theme_color: String,
     pub language: String,
-    pub log_level: String,
+    /// 应用日志级别（debug/info/warning/error/none）
+    #[serde(alias = "log_level")]
+    pub app_log_level: String,
+    /// 代理核心日志级别（debug/info/warning/error/none），写入生成的核心配置
+    #[serde(default = "default_core_log_level")]
+    pub core_log_level: String,
     /// 日志文件路径配置
     #[serde(default = "default_log_path")]
     pub log_path: String,
+    /// 单个日志文件的大小上限（MB），超过后轮转
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// 轮转日志的保留天数
+    #[serde(default = "default_log_retention_days")]
+    pub log_retention_days: u32,
+    /// 是否记录 Xray 访问日志（日志目录下的 access.log），用于导出连接记录
+    #[serde(default)]
+    pub access_log_enabled: bool,
+    /// 访问日志路径，为空时使用应用日志目录下的 access.log；相对路径相对数据目录
+    #[serde(default)]
+    pub access_log_path: Option<String>,
     pub http_port: u16,
     pub socks_port: u16,
     pub pac_port: u16,
+    /// Xray API（统计服务）监听端口
+    #[serde(default = "default_api_port")]
+    pub api_port: u16,
     /// inbound 配置
     #[serde(default)]
     pub inbound_sniffing_enabled: bool,
+    /// 全局嗅探的协议（http/tls/quic/fakedns）
+    #[serde(default = "default_sniffing_dest_override")]
+    pub inbound_sniffing_dest_override: Vec<String>,
+    /// 全局嗅探结果是否只用于路由
+    #[serde(default)]
+    pub inbound_sniffing_route_only: bool,
     #[serde(default)]
     pub inbound_udp_enabled: bool,
     #[serde(default = "default_auth_method")]
     pub inbound_auth_method: String,
     #[serde(default)]
     pub inbound_allow_transparent: bool,
+    /// 入站认证用户，认证方式为 `password` 时生效
+    #[serde(default)]
+    pub inbound_users: Vec<InboundUser>,
+    /// 允许局域网内其他设备连接 HTTP/SOCKS 入站（监听 0.0.0.0）
+    #[serde(default)]
+    pub allow_lan: bool,
+    /// 入站列表，为空时使用默认的 HTTP 与 Mixed 入站
+    /// 配置后需包含 `http_port` 上的 HTTP/Mixed 入站和 `socks_port` 上的 SOCKS/Mixed 入站，供系统代理与 TUN 使用
+    #[serde(default)]
+    pub inbounds: Vec<InboundConfig>,
     /// Xray Core 可执行文件路径
     pub xray_path: Option<String>,
     /// 路由配置
@@ -126,6 +713,88 @@ pub struct AppConfig {
     /// 是否启用TUN模式
     #[serde(default)]
     pub tun_enabled: bool,
+    /// 透明代理配置（仅 Linux）
+    #[serde(default)]
+    pub transparent: TransparentConfig,
+    /// 是否启用代理连通性监控
+    #[serde(default = "default_true")]
+    pub connectivity_check_enabled: bool,
+    /// 经代理访问的探测地址，用于连通性监控和服务器延迟测试
+    #[serde(default = "default_connectivity_check_url")]
+    pub connectivity_check_url: String,
+    /// 直连访问的探测地址，经代理探测失败时用于区分本地网络故障与隧道故障，应选择无需代理即可访问的地址
+    #[serde(default = "default_connectivity_check_direct_url")]
+    pub connectivity_check_direct_url: String,
+    /// 连通性探测间隔（秒）
+    #[serde(default = "default_connectivity_check_interval")]
+    pub connectivity_check_interval: u64,
+    /// 单次探测和延迟测试的超时时间（秒）
+    #[serde(default = "default_connectivity_check_timeout")]
+    pub connectivity_check_timeout: u64,
+    /// 网络变化或休眠恢复后是否自动重新应用系统代理并重启 TUN
+    #[serde(default = "default_true")]
+    pub reapply_on_network_change: bool,
+    /// 是否每周自动更新地理位置数据文件
+    #[serde(default)]
+    pub geo_auto_update: bool,
+    /// 最近一次检查地理位置数据文件更新的时间
+    #[serde(default)]
+    pub geo_last_checked: Option<String>,
+    /// 地理位置数据下载来源与额外的规则文件
+    #[serde(default)]
+    pub geo_sources: GeoSourceConfig,
+    /// 智能路由（直连更快时优先直连）设置
+    #[serde(default)]
+    pub smart_routing: SmartRoutingConfig,
+    /// 本地代理限速设置
+    #[serde(default)]
+    pub bandwidth_limit: BandwidthLimitConfig,
+    /// 每月流量配额（键为服务器ID或订阅ID，值为字节数）
+    #[serde(default)]
+    pub traffic_quotas: HashMap<String, u64>,
+    /// 各服务器最近一次测速结果（键为服务器ID）
+    #[serde(default)]
+    pub speed_test_results: HashMap<String, SpeedTestResult>,
+    /// 启动时端口被占用则自动改用空闲端口
+    #[serde(default)]
+    pub auto_port: bool,
+    /// 多路复用配置
+    #[serde(default)]
+    pub mux: MuxConfig,
+    /// TLS 分片配置
+    #[serde(default)]
+    pub fragment: FragmentConfig,
+    /// 自定义入站，原样追加到每个生成的 Xray 配置中
+    #[serde(default)]
+    pub custom_inbounds: Vec<serde_json::Value>,
+    /// 自定义出站，原样追加到每个生成的 Xray 配置中
+    #[serde(default)]
+    pub custom_outbounds: Vec<serde_json::Value>,
+    /// 系统统计中网络流量的统计范围："system" 统计所有网卡，
+    /// "proxy" 只统计经代理入站端口（TUN 模式下为 TUN 网卡）的流量
+    #[serde(default = "default_stats_scope")]
+    pub stats_scope: String,
+    /// 本地控制 API 配置
+    #[serde(default)]
+    pub control_api: ControlApiConfig,
+    /// 代理启停等事件的钩子命令
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// 桌面通知开关
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// 隐私模式：日志与导出内容中隐藏 UUID、密码、服务器地址等
+    #[serde(default)]
+    pub privacy_mode: bool,
+    /// WebDAV 配置同步设置
+    #[serde(default)]
+    pub webdav: WebDavConfig,
+    /// 应用锁设置
+    #[serde(default)]
+    pub app_lock: AppLockConfig,
+    /// 主窗口状态，首次启动时为空
+    #[serde(default)]
+    pub window_state: Option<WindowState>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -153,27 +822,76 @@ impl Default for AppConfig {
         Self {
             version: "1.0.0".to_string(),
             servers: Vec::new(),
+            subscriptions: Vec::new(),
+            profiles: Vec::new(),
+            active_profile: None,
             current_server: None,
             proxy_mode: "pac".to_string(),
             auto_start: false,
             minimize_to_tray: true,
             start_minimized: false,
+            core: CoreKind::Xray,
+            core_version: None,
+            previous_core_version: None,
+            core_version_usage: HashMap::new(),
+            xray_max_restarts: default_xray_max_restarts(),
+            core_stop_grace_secs: default_core_stop_grace_secs(),
+            core_launch: CoreLaunchOptions::default(),
+            connect_on_startup: false,
             theme: "dark".to_string(),
             theme_color: "green".to_string(),
             language: "zh-CN".to_string(),
-            log_level: "info".to_string(),
+            app_log_level: "info".to_string(),
+            core_log_level: default_core_log_level(),
             log_path: default_log_path(),
+            log_max_size_mb: default_log_max_size_mb(),
+            log_retention_days: default_log_retention_days(),
+            access_log_enabled: false,
+            access_log_path: None,
             http_port: 10086,
             socks_port: 10087,
             pac_port: 8090,
+            api_port: default_api_port(),
             inbound_sniffing_enabled: false,
+            inbound_sniffing_dest_override: default_sniffing_dest_override(),
+            inbound_sniffing_route_only: false,
             inbound_udp_enabled: false,
             inbound_auth_method: "noauth".to_string(),
             inbound_allow_transparent: false,
+            inbound_users: Vec::new(),
+            allow_lan: false,
+            inbounds: Vec::new(),
             xray_path: None,
             routing_config: RoutingConfig::default(),
             tun_config: TunConfig::default(),
             tun_enabled: false,
+            transparent: TransparentConfig::default(),
+            connectivity_check_enabled: true,
+            connectivity_check_url: default_connectivity_check_url(),
+            connectivity_check_direct_url: default_connectivity_check_direct_url(),
+            connectivity_check_interval: default_connectivity_check_interval(),
+            connectivity_check_timeout: default_connectivity_check_timeout(),
+            reapply_on_network_change: true,
+            geo_auto_update: false,
+            geo_last_checked: None,
+            geo_sources: GeoSourceConfig::default(),
+            smart_routing: SmartRoutingConfig::default(),
+            bandwidth_limit: BandwidthLimitConfig::default(),
+            traffic_quotas: HashMap::new(),
+            speed_test_results: HashMap::new(),
+            auto_port: false,
+            mux: MuxConfig::default(),
+            fragment: FragmentConfig::default(),
+            custom_inbounds: Vec::new(),
+            custom_outbounds: Vec::new(),
+            stats_scope: default_stats_scope(),
+            control_api: ControlApiConfig::default(),
+            hooks: HooksConfig::default(),
+            notifications: NotificationConfig::default(),
+            privacy_mode: false,
+            webdav: WebDavConfig::default(),
+            app_lock: AppLockConfig::default(),
+            window_state: None,
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
         }
@@ -181,11 +899,200 @@ impl Default for AppConfig {
 }
 
 impl AppConfig {
-    /// 获取配置文件路径
-    pub fn config_path() -> Result<PathBuf> {
+    /// HTTP/SOCKS 入站的监听地址
+    /// 允许局域网连接时监听所有网卡，否则只监听本机回环地址
+    pub fn inbound_listen(&self) -> &'static str {
+        if self.allow_lan { "0.0.0.0" } else { "127.0.0.1" }
+    }
+
+    /// 实际生成的入站列表，未配置时为默认的 HTTP（标签 `http`）与 Mixed（标签 `socks`）入站
+    pub fn effective_inbounds(&self) -> Vec<InboundConfig> {
+        if self.inbounds.is_empty() {
+            vec![
+                InboundConfig::builtin("http", InboundProtocol::Http, self.http_port),
+                InboundConfig::builtin("socks", InboundProtocol::Mixed, self.socks_port),
+            ]
+        } else {
+            self.inbounds.clone()
+        }
+    }
+
+    /// 入站的监听地址
+    ///
+    /// # 参数
+    /// * `inbound` - 入站配置
+    pub fn listen_of(&self, inbound: &InboundConfig) -> String {
+        inbound.listen.clone()
+            .filter(|listen| !listen.is_empty())
+            .unwrap_or_else(|| self.inbound_listen().to_string())
+    }
+
+    /// 入站的流量嗅探设置，入站未单独设置时使用全局设置
+    ///
+    /// # 参数
+    /// * `inbound` - 入站配置
+    pub fn sniffing_of(&self, inbound: &InboundConfig) -> SniffingConfig {
+        inbound.sniffing.clone().unwrap_or_else(|| SniffingConfig {
+            enabled: self.inbound_sniffing_enabled,
+            dest_override: self.inbound_sniffing_dest_override.clone(),
+            route_only: self.inbound_sniffing_route_only,
+        })
+    }
+
+    /// 校验入站列表与嗅探设置
+    ///
+    /// # 异常
+    /// * 标签或端口重复、地址无效、任意门缺少目标、嗅探协议无效，或缺少系统代理使用的 HTTP/SOCKS 入站时返回错误
+    pub fn validate_inbounds(&self) -> Result<()> {
+        for inbound in self.effective_inbounds() {
+            self.sniffing_of(&inbound).validate(&inbound.tag())?;
+        }
+        if self.inbounds.is_empty() {
+            return Ok(());
+        }
+        let mut tags = Vec::new();
+        let mut ports = Vec::new();
+        for inbound in &self.inbounds {
+            let tag = inbound.tag();
+            if tag == "api" || tag == crate::transparent::TRANSPARENT_INBOUND_TAG || tags.contains(&tag) {
+                return Err(anyhow::anyhow!("入站标签重复或为保留标签: {}", tag));
+            }
+            if inbound.port == 0 || [self.api_port, self.pac_port].contains(&inbound.port) || ports.contains(&inbound.port) {
+                return Err(anyhow::anyhow!("入站 {} 的端口 {} 无效或已被占用", tag, inbound.port));
+            }
+            if let Some(listen) = inbound.listen.as_deref().filter(|l| !l.is_empty()) {
+                listen.parse::<std::net::IpAddr>()
+                    .with_context(|| format!("入站 {} 的监听地址无效: {}", tag, listen))?;
+            }
+            if inbound.protocol == InboundProtocol::Dokodemo
+                && (inbound.target_address.as_deref().unwrap_or_default().is_empty() || inbound.target_port.is_none())
+            {
+                return Err(anyhow::anyhow!("任意门入站 {} 需要设置转发目标地址和端口", tag));
+            }
+            if let Some(network) = &inbound.network {
+                if !matches!(network.as_str(), "tcp" | "udp" | "tcp,udp") {
+                    return Err(anyhow::anyhow!("入站 {} 的网络类型无效: {}", tag, network));
+                }
+            }
+            tags.push(tag);
+            ports.push(inbound.port);
+        }
+        // 系统代理、TUN 与连通性检测通过 127.0.0.1 访问这两个入站
+        let local = |inbound: &&InboundConfig| {
+            inbound.listen.as_deref()
+                .filter(|listen| !listen.is_empty())
+                .and_then(|listen| listen.parse::<std::net::IpAddr>().ok())
+                .is_none_or(|ip| ip == std::net::IpAddr::from([127, 0, 0, 1]) || ip.is_unspecified())
+        };
+        if !self.inbounds.iter().filter(local).any(|i| i.port == self.http_port && i.protocol.serves_http()) {
+            return Err(anyhow::anyhow!("入站列表需要包含端口 {} 上监听本机的 HTTP 或 Mixed 入站", self.http_port));
+        }
+        if !self.inbounds.iter().filter(local).any(|i| i.port == self.socks_port && i.protocol.serves_socks()) {
+            return Err(anyhow::anyhow!("入站列表需要包含端口 {} 上监听本机的 SOCKS 或 Mixed 入站", self.socks_port));
+        }
+        Ok(())
+    }
+
+    /// 入站是否启用用户名密码认证
+    /// 认证方式为 `password` 且至少配置了一个用户时启用，否则入站不做认证
+    pub fn inbound_auth_enabled(&self) -> bool {
+        self.inbound_auth_method == "password" && !self.inbound_users.is_empty()
+    }
+
+    /// 应用自身访问本地入站时使用的用户（认证启用时取第一个用户）
+    pub fn inbound_credentials(&self) -> Option<&InboundUser> {
+        self.inbound_users.first().filter(|_| self.inbound_auth_enabled())
+    }
+
+    /// 应用自身访问本地 HTTP 入站时使用的代理地址
+    /// 启用认证时地址中包含用户名和密码
+    pub fn local_http_proxy_url(&self) -> String {
+        let address = format!("http://127.0.0.1:{}", self.http_port);
+        let Some(user) = self.inbound_credentials() else { return address };
+        match url::Url::parse(&address) {
+            Ok(mut url) => {
+                let _ = url.set_username(&user.username);
+                let _ = url.set_password(Some(&user.password));
+                url.to_string()
+            }
+            Err(_) => address,
+        }
+    }
+
+    /// 确定数据目录，需在首次读取配置前调用，之后调用不再生效
+    /// `custom` 优先；`portable` 为 true 或可执行文件旁存在 `portable.flag` 时使用便携模式
+    ///
+    /// # 参数
+    /// * `portable` - 命令行是否指定了 `--portable`
+    /// * `custom` - 命令行 `--data-dir` 指定的目录
+    ///
+    /// # 异常
+    /// * 便携模式下无法获取可执行文件所在目录时返回错误
+    pub fn init_data_dir(portable: bool, custom: Option<PathBuf>) -> Result<()> {
+        let resolved = match custom {
+            Some(dir) => (dir, true),
+            None => Self::resolve_data_dir(portable)?,
+        };
+        let _ = DATA_DIR.set(resolved);
+        Ok(())
+    }
+
+    /// 按便携模式标记确定数据目录
+    fn resolve_data_dir(portable: bool) -> Result<(PathBuf, bool)> {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let portable = portable || exe_dir.as_ref().is_some_and(|dir| dir.join(PORTABLE_FLAG_FILE).exists());
+        if portable {
+            let exe_dir = exe_dir.context("无法获取可执行文件所在目录")?;
+            return Ok((exe_dir.join(PORTABLE_DATA_DIR_NAME), true));
+        }
         let config_dir = dirs::config_dir()
             .context("无法获取配置目录")?
             .join("RuRay");
+        Ok((config_dir, false))
+    }
+
+    /// 获取数据目录
+    /// 配置、核心、日志与服务器配置均位于此目录下：便携模式为可执行文件所在目录下的 `data`，
+    /// 指定 `--data-dir` 时为该目录，否则为系统配置目录下的 `RuRay`
+    pub fn data_dir() -> Result<PathBuf> {
+        if let Some((dir, _)) = DATA_DIR.get() {
+            return Ok(dir.clone());
+        }
+        let resolved = Self::resolve_data_dir(false)?;
+        Ok(DATA_DIR.get_or_init(|| resolved).0.clone())
+    }
+
+    /// 是否为便携模式（包括指定了 `--data-dir`）
+    /// 便携模式下日志等路径按相对数据目录的路径保存
+    pub fn is_portable() -> bool {
+        Self::data_dir().is_ok() && DATA_DIR.get().is_some_and(|(_, portable)| *portable)
+    }
+
+    /// 将配置中的路径解析为绝对路径，相对路径相对数据目录
+    ///
+    /// # 参数
+    /// * `path` - 配置中的路径
+    pub fn resolve_data_path(path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+        match Self::data_dir() {
+            Ok(data_dir) => data_dir.join(path),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    /// 获取应用日志文件路径
+    pub fn log_file_path(&self) -> PathBuf {
+        Self::resolve_data_path(&self.log_path)
+    }
+
+    /// 获取配置文件路径
+    pub fn config_path() -> Result<PathBuf> {
+        let config_dir = Self::data_dir()?;
         
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)
@@ -196,45 +1103,245 @@ impl AppConfig {
     }
 
     /// 加载配置
+    /// 返回配置存储中缓存的副本，首次调用时从磁盘读取
     pub fn load() -> Result<Self> {
+        ConfigStore::instance().get()
+    }
+
+    /// 加载配置并应用当前服务器的覆盖设置
+    /// 用于读取运行中实际生效的代理模式、端口等；结果只用于读取，不能保存
+    pub fn load_active() -> Result<Self> {
+        let config = Self::load()?;
+        let server_id = crate::proxy::ProxyManager::instance().current_server_id()
+            .or_else(|| config.current_server.clone());
+        let server = server_id.and_then(|id| config.servers.iter().find(|s| s.id == id).cloned());
+        Ok(match server {
+            Some(server) => config.with_server_overrides(&server),
+            None => config,
+        })
+    }
+
+    /// 应用服务器覆盖设置后的配置副本，结果只用于读取，不能保存
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    pub fn with_server_overrides(&self, server: &ServerInfo) -> AppConfig {
+        let mut config = self.clone();
+        server.overrides.apply(&mut config);
+        config
+    }
+
+    /// 从磁盘读取配置，配置文件损坏时从最近的可用备份恢复
+    /// 损坏的文件重命名为 `config.json.corrupt-<时间>` 保留，恢复记录由 `last_recovery` 获取，
+    /// 启动后通知用户。没有可用的备份时返回解析错误
+    pub(crate) fn read_or_recover() -> Result<Self> {
+        let parse_error = match Self::read_from_disk() {
+            Ok(config) => return Ok(config),
+            Err(e) if e.downcast_ref::<serde_json::Error>().is_some() => e,
+            Err(e) => return Err(e),
+        };
+
+        let config_path = Self::config_path()?;
+        for index in 1..=CONFIG_BACKUP_COUNT {
+            let backup_path = Self::backup_path(index)?;
+            let Ok(content) = fs::read_to_string(&backup_path) else { continue };
+            let mut config: AppConfig = match serde_json::from_str(&content) {
+                Ok(config) => config,
+                Err(e) => {
+                    log_warn!("配置备份 {} 无法解析: {}", backup_path.display(), e);
+                    continue;
+                }
+            };
+
+            let corrupt_path = config_path.with_extension(format!(
+                "json.corrupt-{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            fs::rename(&config_path, &corrupt_path).context("无法保存损坏的配置文件")?;
+            config.updated_at = chrono::Utc::now().to_rfc3339();
+            config.write_to_disk()?;
+
+            *CONFIG_RECOVERY.lock().unwrap() = Some(ConfigRecovery {
+                corrupt_path: corrupt_path.to_string_lossy().to_string(),
+                backup_path: backup_path.to_string_lossy().to_string(),
+                error: format!("{:#}", parse_error),
+            });
+            return Ok(config);
+        }
+        Err(parse_error.context("没有可用的配置备份"))
+    }
+
+    /// 获取本次运行中从备份恢复配置的记录
+    ///
+    /// # 返回值
+    /// * `Option<ConfigRecovery>` - 恢复记录，配置未损坏时为 None
+    pub fn last_recovery() -> Option<ConfigRecovery> {
+        CONFIG_RECOVERY.lock().unwrap().clone()
+    }
+
+    /// 获取配置备份路径，序号 1 为最新的备份
+    fn backup_path(index: usize) -> Result<PathBuf> {
+        Ok(Self::data_dir()?
+            .join(CONFIG_BACKUP_DIR_NAME)
+            .join(format!("config.{}.json", index)))
+    }
+
+    /// 轮转配置备份，将刚写入的配置保存为最新的备份，超出数量的最旧备份被覆盖
+    /// 内容与最新备份相同时不写入；最新备份在 `CONFIG_BACKUP_INTERVAL` 内写入时只覆盖最新备份，
+    /// 避免频繁修改配置时所有备份都变成最近几秒内的副本
+    ///
+    /// # 参数
+    /// * `content` - 已成功写入的配置内容
+    fn rotate_backups(content: &str) -> Result<()> {
+        let latest = Self::backup_path(1)?;
+        if let Some(dir) = latest.parent() {
+            fs::create_dir_all(dir).context("无法创建配置备份目录")?;
+        }
+        if fs::read_to_string(&latest).is_ok_and(|previous| previous == content) {
+            return Ok(());
+        }
+
+        let recent = fs::metadata(&latest)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < CONFIG_BACKUP_INTERVAL);
+        if !recent {
+            for index in (1..CONFIG_BACKUP_COUNT).rev() {
+                let from = Self::backup_path(index)?;
+                if from.exists() {
+                    fs::rename(&from, Self::backup_path(index + 1)?).context("无法轮转配置备份")?;
+                }
+            }
+        }
+        fs::write(&latest, content).context("无法写入配置备份")?;
+        Ok(())
+    }
+
+    /// 从磁盘读取配置，配置文件不存在时创建默认配置
+    pub(crate) fn read_from_disk() -> Result<Self> {
         let config_path = Self::config_path()?;
         
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)
                 .context("无法读取配置文件")?;
             
-            let mut config: AppConfig = serde_json::from_str(&content)
+            let config: AppConfig = serde_json::from_str(&content)
                 .context("无法解析配置文件")?;
-            
-            config.updated_at = chrono::Utc::now().to_rfc3339();
+
             Ok(config)
         } else {
             let config = AppConfig::default();
-            config.save()?;
+            config.write_to_disk()?;
             Ok(config)
         }
     }
 
-    /// 保存配置
-    pub fn save(&self) -> Result<()> {
+    /// 将配置写入磁盘
+    /// 先写入临时文件并刷新到磁盘，再重命名覆盖 config.json，写入中断时不会留下截断的配置；
+    /// 写入成功后轮转保存最近 `CONFIG_BACKUP_COUNT` 份备份（见 `rotate_backups`）。
+    /// 本进程内的写入以互斥锁串行化，与其他进程（如命令行）之间以 `config.json.lock` 上的建议性文件锁互斥；
+    /// 锁或文件被占用（如 Windows 上杀毒软件正在扫描）时按递增间隔重试
+    pub(crate) fn write_to_disk(&self) -> Result<()> {
         let config_path = Self::config_path()?;
-        let mut config = self.clone();
-        config.updated_at = chrono::Utc::now().to_rfc3339();
         
-        let content = serde_json::to_string_pretty(&config)
+        let content = serde_json::to_string_pretty(self)
             .context("无法序列化配置")?;
-        
-        fs::write(&config_path, content)
-            .context("无法写入配置文件")?;
-        
-        Ok(())
+
+        let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let lock_file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(config_path.with_extension("json.lock"))
+            .context("无法打开配置文件锁")?;
+        retry_on_busy(|| lock_file.try_lock_exclusive())
+            .context("配置文件正被其他进程写入")?;
+
+        let result = replace_file(&config_path, content.as_bytes());
+        if result.is_ok() {
+            if let Err(e) = Self::rotate_backups(&content) {
+                log_warn!("保存配置备份失败: {}", e);
+            }
+        }
+        let _ = FileExt::unlock(&lock_file);
+        result
+    }
+
+    /// 确保至少存在一个配置方案
+    /// 首次使用时以当前设置创建默认方案并激活
+    pub fn ensure_profiles(&mut self) {
+        if self.profiles.is_empty() {
+            let profile = Profile::capture(self, "默认");
+            self.active_profile = Some(profile.id.clone());
+            self.profiles.push(profile);
+        } else if self.active_profile.is_none() {
+            self.active_profile = Some(self.profiles[0].id.clone());
+        }
+    }
+
+    /// 将顶层的当前设置同步回激活的配置方案
+    pub fn sync_active_profile(&mut self) {
+        let Some(active_id) = self.active_profile.clone() else { return };
+        let snapshot = self.clone();
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.id == active_id) {
+            profile.update_from(&snapshot);
+        }
+    }
+
+    /// 检查服务器在当前配置方案中是否启用
+    ///
+    /// # 参数
+    /// * `server_id` - 服务器ID
+    pub fn is_server_enabled(&self, server_id: &str) -> bool {
+        self.active_profile
+            .as_ref()
+            .and_then(|id| self.profiles.iter().find(|p| &p.id == id))
+            .is_none_or(|profile| profile.is_server_enabled(server_id))
+    }
+
+    /// 以本机配置的值替换来自其他来源（导入、备份恢复、WebDAV 同步）的配置中与本机相关的设置
+    /// 核心路径、日志路径、窗口状态与 WebDAV 只对本机有效，应用锁只保护本机；
+    /// 钩子与核心启动参数会在本机执行命令，也不能随其他来源的配置带入
+    ///
+    /// # 参数
+    /// * `local` - 本机当前的配置
+    pub fn keep_local_settings(&mut self, local: &AppConfig) {
+        self.xray_path = local.xray_path.clone();
+        self.log_path = local.log_path.clone();
+        self.window_state = local.window_state.clone();
+        self.webdav = local.webdav.clone();
+        self.app_lock = local.app_lock.clone();
+        self.hooks = local.hooks.clone();
+        self.core_launch = local.core_launch.clone();
+        Profile::keep_local_core_launch(&mut self.profiles, &local.profiles);
+    }
+
+    /// 检查两份配置中会同步到其他设备的内容是否相同
+    /// 忽略本机相关的设置（见 `keep_local_settings`）以及运行中记录的状态（当前服务器、测速结果、
+    /// 核心版本记录、地理数据检查时间等）；只有这些内容之外的修改才更新 `updated_at`，WebDAV 同步据此判断配置是否有修改
+    ///
+    /// # 参数
+    /// * `other` - 另一份配置
+    pub fn same_synced_content(&self, other: &AppConfig) -> bool {
+        let mut this = self.clone();
+        this.keep_local_settings(other);
+        this.current_server = other.current_server.clone();
+        this.speed_test_results = other.speed_test_results.clone();
+        this.core_version = other.core_version.clone();
+        this.previous_core_version = other.previous_core_version.clone();
+        this.core_version_usage = other.core_version_usage.clone();
+        this.geo_last_checked = other.geo_last_checked.clone();
+        this.updated_at = other.updated_at.clone();
+        match (serde_json::to_value(&this), serde_json::to_value(other)) {
+            (Ok(this), Ok(other)) => this == other,
+            _ => false,
+        }
     }
 
     /// 获取服务器配置目录
     pub fn servers_dir() -> Result<PathBuf> {
-        let config_dir = dirs::config_dir()
-            .context("无法获取配置目录")?
-            .join("RuRay")
+        let config_dir = Self::data_dir()?
             .join("server")
             .join("conf");
         
@@ -248,12 +1355,23 @@ impl AppConfig {
 
 
 
+    /// 获取流量统计数据文件路径
+    pub fn traffic_path() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        let config_dir = config_path.parent().context("无法获取配置目录")?;
+        Ok(config_dir.join("traffic.json"))
+    }
+
+    /// 获取统计历史数据库目录
+    pub fn history_path() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        let config_dir = config_path.parent().context("无法获取配置目录")?;
+        Ok(config_dir.join("history.db"))
+    }
+
     /// 获取 Xray Core 目录
     pub fn xray_dir() -> Result<PathBuf> {
-        let xray_dir = dirs::config_dir()
-            .context("无法获取配置目录")?
-            .join("RuRay")
-            .join("xray");
+        let xray_dir = Self::data_dir()?.join("xray");
         
         if !xray_dir.exists() {
             fs::create_dir_all(&xray_dir)
@@ -269,7 +1387,7 @@ impl AppConfig {
         // 尝试加载配置获取用户自定义路径
         if let Ok(config) = Self::load() {
             if let Some(custom_path) = config.xray_path {
-                return Ok(PathBuf::from(custom_path));
+                return Ok(Self::resolve_data_path(&custom_path));
             }
         }
         
@@ -285,6 +1403,31 @@ impl AppConfig {
         Ok(executable)
     }
 
+    /// 获取 sing-box 目录
+    pub fn sing_box_dir() -> Result<PathBuf> {
+        let sing_box_dir = Self::data_dir()?.join("sing-box");
+        
+        if !sing_box_dir.exists() {
+            fs::create_dir_all(&sing_box_dir)
+                .context("无法创建 sing-box 目录")?;
+        }
+        
+        Ok(sing_box_dir)
+    }
+
+    /// 获取 sing-box 可执行文件路径
+    pub fn sing_box_executable() -> Result<PathBuf> {
+        let sing_box_dir = Self::sing_box_dir()?;
+        
+        #[cfg(target_os = "windows")]
+        let executable = sing_box_dir.join("sing-box.exe");
+        
+        #[cfg(not(target_os = "windows"))]
+        let executable = sing_box_dir.join("sing-box");
+        
+        Ok(executable)
+    }
+
     /// 检查 Xray Core 是否存在
     /// 
     /// # Returns
@@ -0,0 +1,279 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock, RwLock};
+use std::time::SystemTime;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_error, log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::privacy;
+
+/// 检查配置文件外部修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// 修改后延迟写盘的时间，期间的多次修改合并为一次写盘
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 配置存储
+/// 在内存中缓存 AppConfig，所有模块通过 `AppConfig::load()` 读取、`ConfigStore::update()` 修改同一份配置：
+/// 读取直接返回缓存副本，修改只更新缓存，由后台任务在 `SAVE_DEBOUNCE` 后合并写盘，
+/// 命令处理中不会因写盘（包括文件被占用时的重试）而阻塞异步运行时。
+/// 后台写盘失败时记录日志并发射 `config-save-failed` 事件，修改保留在缓存中，下次写盘时一并写入；
+/// 需要确认已写入磁盘的调用方（如创建备份、退出前）调用 `flush()` 获取写盘结果。
+/// 只有会同步到其他设备的内容变化时才更新 `updated_at`（见 `AppConfig::same_synced_content`），
+/// 窗口位置、当前服务器等本机状态的写入不会让配置看起来比远端新。
+/// 同时监视 config.json 的外部修改，发生变化时重新加载；任何变化都会发射 `config-changed` 事件。
+/// 由于托盘、日志等同步代码也需要读取配置，缓存使用标准库读写锁，且锁不会跨越 await 持有
+pub struct ConfigStore {
+    config: RwLock<Option<AppConfig>>,
+    /// 缓存的修改次数，在缓存写锁内递增
+    revision: AtomicU64,
+    /// 最近一次成功写盘时的修改次数，与 `revision` 不同表示有尚未写盘的修改，期间不从磁盘重新加载
+    saved_revision: AtomicU64,
+    /// 是否已安排延迟写盘任务
+    save_scheduled: AtomicBool,
+    /// 最近一次读写时配置文件的修改时间，用于识别外部修改
+    last_modified: Mutex<Option<SystemTime>>,
+    /// 写入锁，串行化写盘与外部修改的重新加载
+    write_lock: Mutex<()>,
+    app_handle: Mutex<Option<AppHandle>>,
+    watcher: Mutex<Option<JoinHandle<()>>>,
+}
+
+// 全局单例实例
+static CONFIG_STORE: OnceLock<ConfigStore> = OnceLock::new();
+
+impl ConfigStore {
+    /// 获取全局配置存储实例（单例模式）
+    pub fn instance() -> &'static ConfigStore {
+        CONFIG_STORE.get_or_init(|| Self {
+            config: RwLock::new(None),
+            revision: AtomicU64::new(0),
+            saved_revision: AtomicU64::new(0),
+            save_scheduled: AtomicBool::new(false),
+            last_modified: Mutex::new(None),
+            write_lock: Mutex::new(()),
+            app_handle: Mutex::new(None),
+            watcher: Mutex::new(None),
+        })
+    }
+
+    /// 获取当前配置的副本
+    ///
+    /// # 返回值
+    /// * `Result<AppConfig>` - 配置副本，首次读取磁盘失败时返回错误
+    pub fn get(&self) -> Result<AppConfig> {
+        if let Some(config) = self.config.read().unwrap().as_ref() {
+            return Ok(config.clone());
+        }
+
+        let config = AppConfig::read_or_recover()?;
+        let cached = self.config.write().unwrap().get_or_insert(config).clone();
+        *self.last_modified.lock().unwrap() = Self::modified_time();
+        privacy::refresh(&cached);
+        Ok(cached)
+    }
+
+    /// 替换当前配置，稍后由后台任务写盘
+    /// 需要确认写盘结果时随后调用 `flush()`
+    ///
+    /// # 参数
+    /// * `config` - 新配置
+    pub fn set(&self, mut config: AppConfig) {
+        {
+            let mut guard = self.config.write().unwrap();
+            config.updated_at = match guard.as_ref() {
+                Some(current) if config.same_synced_content(current) => current.updated_at.clone(),
+                _ => chrono::Utc::now().to_rfc3339(),
+            };
+            *guard = Some(config);
+            self.revision.fetch_add(1, Ordering::SeqCst);
+        }
+        self.emit_changed("app");
+        self.schedule_save();
+    }
+
+    /// 在缓存写锁内修改配置，避免“读取-修改-保存”之间被其他修改覆盖；稍后由后台任务写盘
+    ///
+    /// # 参数
+    /// * `f` - 修改函数
+    ///
+    /// # 返回值
+    /// * `Result<T>` - 修改函数的返回值，首次读取磁盘失败时返回错误
+    pub fn update<T>(&self, f: impl FnOnce(&mut AppConfig) -> T) -> Result<T> {
+        self.get()?;
+        let result = {
+            let mut guard = self.config.write().unwrap();
+            let config = guard.as_mut().expect("配置已加载");
+            let before = config.clone();
+            let result = f(config);
+            if !config.same_synced_content(&before) {
+                config.updated_at = chrono::Utc::now().to_rfc3339();
+            }
+            self.revision.fetch_add(1, Ordering::SeqCst);
+            result
+        };
+        self.emit_changed("app");
+        self.schedule_save();
+        Ok(result)
+    }
+
+    /// 立即将尚未写盘的修改写入磁盘
+    /// 同步执行，在异步代码中需通过 `spawn_blocking` 调用
+    ///
+    /// # 异常
+    /// * 写盘失败时返回错误，修改保留在缓存中，下次写盘时一并写入
+    pub fn flush(&self) -> Result<()> {
+        let _write = self.lock_write();
+        // 在缓存读锁内同时取得配置与修改次数，保证两者对应
+        let (config, revision) = {
+            let guard = self.config.read().unwrap();
+            (guard.clone(), self.revision.load(Ordering::SeqCst))
+        };
+        if revision == self.saved_revision.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Some(config) = config {
+            config.write_to_disk()?;
+            *self.last_modified.lock().unwrap() = Self::modified_time();
+        }
+        self.saved_revision.store(revision, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// 是否有尚未写盘的修改
+    fn has_unsaved_changes(&self) -> bool {
+        self.revision.load(Ordering::SeqCst) != self.saved_revision.load(Ordering::SeqCst)
+    }
+
+    /// 安排延迟写盘，已安排时合并到同一次写盘
+    /// 写盘在阻塞线程池中执行；开始写盘前清除标记，写盘期间的新修改会再安排一次写盘
+    fn schedule_save(&self) {
+        if self.save_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        tauri::async_runtime::spawn(async {
+            tokio::time::sleep(SAVE_DEBOUNCE).await;
+            let store = ConfigStore::instance();
+            store.save_scheduled.store(false, Ordering::SeqCst);
+            let result = tokio::task::spawn_blocking(|| ConfigStore::instance().flush())
+                .await
+                .unwrap_or_else(|e| Err(anyhow::anyhow!("写盘任务异常退出: {}", e)));
+            if let Err(e) = result {
+                log_error!("保存配置失败: {:#}", e);
+                let app_handle = store.app_handle.lock().unwrap().clone();
+                if let Some(app_handle) = app_handle {
+                    let _ = app_handle.emit("config-save-failed", serde_json::json!({ "error": format!("{:#}", e) }));
+                }
+            }
+        });
+    }
+
+    /// 获取写入锁，持锁线程崩溃时继续使用
+    fn lock_write(&self) -> MutexGuard<'_, ()> {
+        self.write_lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 配置文件被外部修改时重新加载
+    /// 与写盘互斥执行；有尚未写盘的修改时不重新加载，避免磁盘上的旧配置覆盖内存中较新的修改
+    fn reload_if_modified(&self) {
+        let _write = self.lock_write();
+        if self.has_unsaved_changes() {
+            return;
+        }
+
+        let modified = Self::modified_time();
+        if modified.is_none() || modified == *self.last_modified.lock().unwrap() {
+            return;
+        }
+
+        let result = AppConfig::read_from_disk();
+        *self.last_modified.lock().unwrap() = modified;
+        match result {
+            Ok(config) => {
+                {
+                    // 读取磁盘期间缓存可能又被修改，此时保留内存中的修改
+                    let mut guard = self.config.write().unwrap();
+                    if self.has_unsaved_changes() {
+                        return;
+                    }
+                    *guard = Some(config);
+                }
+                log_info!("检测到配置文件被外部修改，已重新加载");
+                self.emit_changed("file");
+            }
+            Err(e) => log_warn!("配置文件被外部修改但无法加载，继续使用当前配置: {}", e),
+        }
+    }
+
+    /// 启动配置文件监视任务
+    ///
+    /// # 参数
+    /// * `app_handle` - Tauri 应用句柄，用于发射 `config-changed` 事件
+    pub fn start(&self, app_handle: AppHandle) {
+        *self.app_handle.lock().unwrap() = Some(app_handle);
+
+        let mut watcher = self.watcher.lock().unwrap();
+        if watcher.is_some() {
+            return;
+        }
+
+        *watcher = Some(tauri::async_runtime::spawn(async move {
+            let store = ConfigStore::instance();
+            loop {
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                // 与写盘互斥，写盘重试期间会等待写入锁，放到阻塞线程池中执行
+                let _ = tokio::task::spawn_blocking(move || store.reload_if_modified()).await;
+            }
+        }));
+    }
+
+    /// 更新隐私模式并发射配置变化事件
+    ///
+    /// # 参数
+    /// * `source` - 变化来源："app" 表示应用内修改，"file" 表示配置文件被外部修改
+    fn emit_changed(&self, source: &str) {
+        // 隐私模式需要隐藏的内容取自配置，随配置更新
+        if let Some(config) = self.config.read().unwrap().as_ref() {
+            privacy::refresh(config);
+        }
+        let app_handle = self.app_handle.lock().unwrap().clone();
+        if let Some(app_handle) = app_handle {
+            let _ = app_handle.emit("config-changed", serde_json::json!({ "source": source }));
+        }
+    }
+
+    /// 发射服务器列表变化事件
+    /// 托盘菜单与界面据此刷新服务器列表
+    ///
+    /// # 参数
+    /// * `action` - 变化类型：added、updated、removed、moved
+    /// * `server_ids` - 涉及的服务器ID
+    pub fn emit_servers_changed(&self, action: &str, server_ids: &[String]) {
+        let app_handle = self.app_handle.lock().unwrap().clone();
+        if let Some(app_handle) = app_handle {
+            let _ = app_handle.emit("servers-changed", serde_json::json!({
+                "action": action,
+                "server_ids": server_ids
+            }));
+        }
+    }
+
+    /// 获取配置文件的修改时间
+    fn modified_time() -> Option<SystemTime> {
+        AppConfig::config_path()
+            .ok()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+}
@@ -0,0 +1,181 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 连接记录（Xray 访问日志）
+// 启用 `access_log_enabled` 后 Xray 将每个连接写入应用日志目录下的 access.log（或 `access_log_path` 指定的文件），格式如：
+// `2026/10/16 12:34:56.789012 from 127.0.0.1:51234 accepted tcp:www.example.com:443 [socks -> proxy] email: alice`
+// 这里解析访问日志并按时间范围导出为 CSV 或 JSON，供需要审计使用情况的用户使用。
+// Xray 访问日志不包含单个连接的流量，导出的 bytes 字段为空；时间为本地时间
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+
+/// 访问日志文件名
+const ACCESS_LOG_NAME: &str = "access.log";
+/// 访问日志中的时间格式
+const ACCESS_LOG_TIME_FORMAT: &str = "%Y/%m/%d %H:%M:%S%.f";
+/// CSV 表头
+const CSV_HEADER: &str = "time,source,network,domain,port,inbound,outbound,status,user,bytes";
+
+/// 单条连接记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionRecord {
+    /// 连接时间（RFC3339）
+    pub time: String,
+    /// 来源地址
+    pub source: String,
+    /// 网络类型（tcp/udp）
+    pub network: String,
+    /// 目标域名或 IP
+    pub domain: String,
+    /// 目标端口
+    pub port: u16,
+    /// 入站标签
+    pub inbound: String,
+    /// 出站标签
+    pub outbound: String,
+    /// 连接状态（accepted/rejected）
+    pub status: String,
+    /// 入站认证用户
+    pub user: Option<String>,
+    /// 流量（字节），Xray 访问日志不提供
+    pub bytes: Option<u64>,
+}
+
+/// 访问日志路径，未设置时与应用日志位于同一目录
+///
+/// # 参数
+/// * `config` - 应用配置
+pub fn access_log_path(config: &AppConfig) -> PathBuf {
+    if let Some(path) = config.access_log_path.as_deref().filter(|path| !path.trim().is_empty()) {
+        return AppConfig::resolve_data_path(path);
+    }
+    config.log_file_path()
+        .parent()
+        .map(|dir| dir.join(ACCESS_LOG_NAME))
+        .unwrap_or_else(|| PathBuf::from(ACCESS_LOG_NAME))
+}
+
+/// 解析访问日志中的一行，无法识别的行返回 None
+fn parse_line(line: &str) -> Option<(DateTime<Utc>, ConnectionRecord)> {
+    let (time_part, rest) = line.split_once(" from ")?;
+    let time = NaiveDateTime::parse_from_str(time_part.trim(), ACCESS_LOG_TIME_FORMAT).ok()?;
+    let time = Local.from_local_datetime(&time).earliest()?.with_timezone(&Utc);
+
+    let mut parts = rest.splitn(3, ' ');
+    let source = parts.next()?;
+    let source = source.strip_prefix("tcp:").or_else(|| source.strip_prefix("udp:")).unwrap_or(source);
+    let status = parts.next()?;
+    let rest = parts.next()?;
+
+    let (destination, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let (network, target) = destination.split_once(':')?;
+    let (domain, port) = target.rsplit_once(':')?;
+
+    let route = rest.split_once('[')
+        .and_then(|(_, r)| r.split_once(']'))
+        .map(|(route, _)| route);
+    let (inbound, outbound) = route
+        .and_then(|route| route.split_once(" -> ").or_else(|| route.split_once(" >> ")))
+        .map(|(i, o)| (i.trim().to_string(), o.trim().to_string()))
+        .unwrap_or_default();
+    let user = rest.split_once("email: ").map(|(_, user)| user.trim().to_string());
+
+    Some((time, ConnectionRecord {
+        time: time.to_rfc3339(),
+        source: source.to_string(),
+        network: network.to_string(),
+        domain: domain.trim_start_matches('[').trim_end_matches(']').to_string(),
+        port: port.parse().ok()?,
+        inbound,
+        outbound,
+        status: status.to_string(),
+        user,
+        bytes: None,
+    }))
+}
+
+/// 读取时间范围内的连接记录
+///
+/// # 参数
+/// * `path` - 访问日志路径
+/// * `from` - 起始时间（含），为空时不限制
+/// * `to` - 结束时间（含），为空时不限制
+///
+/// # 返回值
+/// * `Result<Vec<ConnectionRecord>>` - 按时间顺序排列的连接记录
+pub fn read_records(path: &Path, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<Vec<ConnectionRecord>> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("无法读取访问日志: {}", path.display())),
+    };
+    Ok(String::from_utf8_lossy(&content)
+        .lines()
+        .filter_map(parse_line)
+        .filter(|(time, _)| from.is_none_or(|from| *time >= from) && to.is_none_or(|to| *time <= to))
+        .map(|(_, record)| record)
+        .collect())
+}
+
+/// CSV 字段转义：包含逗号、引号或换行时加引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 将连接记录转换为 CSV 文本
+pub fn to_csv(records: &[ConnectionRecord]) -> String {
+    let mut output = String::from(CSV_HEADER);
+    output.push('\n');
+    for record in records {
+        let fields = [
+            record.time.clone(),
+            record.source.clone(),
+            record.network.clone(),
+            record.domain.clone(),
+            record.port.to_string(),
+            record.inbound.clone(),
+            record.outbound.clone(),
+            record.status.clone(),
+            record.user.clone().unwrap_or_default(),
+            record.bytes.map(|b| b.to_string()).unwrap_or_default(),
+        ];
+        output.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// 解析时间范围参数，支持 RFC3339 与 `YYYY-MM-DD`（按本地时间的当天开始/结束）
+///
+/// # 参数
+/// * `value` - 时间文本
+/// * `end_of_day` - 只有日期时是否取当天结束时间
+pub fn parse_time_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("无效的时间: {}", value))?;
+    let time = if end_of_day {
+        date.and_hms_milli_opt(23, 59, 59, 999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+    .context("无效的时间")?;
+    Local.from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .with_context(|| format!("无效的时间: {}", value))
+}
@@ -0,0 +1,601 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tokio::io::AsyncWriteExt;
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::commands::ServerInfo;
+use crate::config::{AppConfig, InboundProtocol, RoutingRule};
+use crate::proxy::ProxyManager;
+use crate::template::TemplateVars;
+use crate::transparent::TransparentManager;
+use crate::xray::XrayManager;
+
+/// sing-box 发布信息接口
+const SING_BOX_RELEASE_API: &str = "https://api.github.com/repos/SagerNet/sing-box/releases/latest";
+
+/// 代理核心类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreKind {
+    /// Xray Core
+    #[default]
+    #[serde(rename = "xray")]
+    Xray,
+    /// sing-box
+    #[serde(rename = "sing-box")]
+    SingBox,
+}
+
+/// 代理核心后端
+/// 封装不同代理核心在配置生成、配置文件保存、启动参数和版本查询上的差异，
+/// ProxyManager 通过该接口启动和守护核心进程
+pub trait CoreBackend: Send + Sync {
+    /// 显示名称
+    fn name(&self) -> &'static str;
+
+    /// 可执行文件路径
+    fn executable(&self) -> Result<PathBuf>;
+
+    /// 生成服务器的核心配置
+    fn generate_config(&self, server: &ServerInfo) -> Result<serde_json::Value>;
+
+    /// 保存核心配置文件
+    fn write_config(&self, server: &ServerInfo, config: &serde_json::Value) -> Result<PathBuf>;
+
+    /// 服务器核心配置文件的保存路径
+    fn config_path(&self, server: &ServerInfo) -> Result<PathBuf>;
+
+    /// 启动参数
+    fn command_args(&self, config_path: &Path) -> Vec<OsString>;
+
+    /// 核心进程的工作目录
+    fn working_dir(&self) -> Result<PathBuf>;
+
+    /// 启动核心进程时设置的环境变量
+    fn process_env(&self) -> Result<Vec<(OsString, OsString)>>;
+
+    /// 查询已安装的核心版本
+    fn version(&self) -> Result<String>;
+}
+
+/// Xray Core 后端
+pub struct XrayBackend;
+
+/// sing-box 后端
+pub struct SingBoxBackend;
+
+static XRAY_BACKEND: XrayBackend = XrayBackend;
+static SING_BOX_BACKEND: SingBoxBackend = SingBoxBackend;
+
+/// 设置核心进程的工作目录与环境变量
+/// 依次设置后端需要的环境变量与设置中用于调试的额外环境变量，后者可覆盖前者；
+/// 不依赖 RuRay 的当前目录，核心总能找到资源文件
+///
+/// # 参数
+/// * `backend` - 核心后端
+/// * `command` - 启动命令，tokio 的命令通过 `as_std_mut` 传入
+///
+/// # 异常
+/// * 无法获取核心目录时返回错误
+pub fn prepare_command(backend: &dyn CoreBackend, command: &mut Command) -> Result<()> {
+    command.current_dir(backend.working_dir()?);
+    command.envs(backend.process_env()?);
+    if let Ok(config) = AppConfig::load() {
+        command.envs(&config.core_launch.env);
+    }
+    Ok(())
+}
+
+/// 获取指定类型的核心后端
+pub fn backend(kind: CoreKind) -> &'static dyn CoreBackend {
+    match kind {
+        CoreKind::Xray => &XRAY_BACKEND,
+        CoreKind::SingBox => &SING_BOX_BACKEND,
+    }
+}
+
+impl CoreBackend for XrayBackend {
+    fn name(&self) -> &'static str {
+        "Xray Core"
+    }
+
+    fn executable(&self) -> Result<PathBuf> {
+        AppConfig::xray_executable()
+    }
+
+    fn generate_config(&self, server: &ServerInfo) -> Result<serde_json::Value> {
+        ProxyManager::instance().generate_xray_config(server)
+    }
+
+    fn write_config(&self, server: &ServerInfo, config: &serde_json::Value) -> Result<PathBuf> {
+        // 如果配置文件已存在则不重新创建，保留用户手动修改的内容；
+        // 入站与本次启动不一致（限速内部端口重新分配、透明代理开关变化）时必须重新生成。
+        // 透明代理规则生效时出站必须带防火墙标记，否则核心的出站连接会被重定向回自身，始终重新生成
+        let manager = ProxyManager::instance();
+        let stale = TransparentManager::instance().is_active()
+            || manager.saved_inbounds_differ(&self.config_path(server)?, config);
+        if stale {
+            log_info!("重新生成 {} 的配置文件", server.name);
+        }
+        manager.save_temp_config(config, server, stale)
+    }
+
+    fn config_path(&self, server: &ServerInfo) -> Result<PathBuf> {
+        Ok(ProxyManager::instance().get_server_config_path(&server.id, &server.name))
+    }
+
+    fn command_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec!["-config".into(), config_path.into()]
+    }
+
+    fn working_dir(&self) -> Result<PathBuf> {
+        AppConfig::xray_dir()
+    }
+
+    fn process_env(&self) -> Result<Vec<(OsString, OsString)>> {
+        // geoip.dat/geosite.dat 下载到 Xray 目录，Xray 默认只在可执行文件旁查找，
+        // 使用自定义路径的核心时需显式指定资源目录
+        Ok(vec![("XRAY_LOCATION_ASSET".into(), AppConfig::xray_dir()?.into())])
+    }
+
+    fn version(&self) -> Result<String> {
+        XrayManager::executable_version(&self.executable()?)
+    }
+}
+
+impl CoreBackend for SingBoxBackend {
+    fn name(&self) -> &'static str {
+        "sing-box"
+    }
+
+    fn executable(&self) -> Result<PathBuf> {
+        AppConfig::sing_box_executable()
+    }
+
+    fn generate_config(&self, server: &ServerInfo) -> Result<serde_json::Value> {
+        let config = AppConfig::load()?.with_server_overrides(server);
+        let outbound = self.generate_outbound(server)?;
+
+        let log = match config.core_log_level.as_str() {
+            "none" => json!({ "disabled": true }),
+            "warning" => json!({ "level": "warn" }),
+            level => json!({ "level": level }),
+        };
+
+        let users: Vec<serde_json::Value> = if config.inbound_auth_enabled() {
+            config.inbound_users.iter()
+                .map(|user| json!({ "username": user.username, "password": user.password }))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let inbounds: Vec<serde_json::Value> = config.effective_inbounds().iter()
+            .map(|inbound| {
+                // sing-box 嗅探结果默认只用于路由，对应 Xray 的 routeOnly
+                let sniffing = config.sniffing_of(inbound);
+                let mut value = json!({
+                    "type": inbound.protocol.name(),
+                    "tag": inbound.tag(),
+                    "listen": config.listen_of(inbound),
+                    "listen_port": inbound.port,
+                    "sniff": sniffing.enabled,
+                    "sniff_override_destination": sniffing.enabled && !sniffing.route_only
+                });
+                if inbound.protocol == InboundProtocol::Dokodemo {
+                    value["type"] = json!("direct");
+                    value["override_address"] = json!(inbound.target_address);
+                    value["override_port"] = json!(inbound.target_port);
+                    if let Some(network) = inbound.network.as_deref().filter(|n| *n != "tcp,udp") {
+                        value["network"] = json!(network);
+                    }
+                } else {
+                    value["users"] = json!(users);
+                }
+                value
+            })
+            .collect();
+
+        let mut sing_box_config = json!({
+            "log": log,
+            "inbounds": inbounds,
+            "outbounds": [
+                outbound,
+                { "type": "direct", "tag": "direct" },
+                { "type": "block", "tag": "block" }
+            ],
+            "route": {
+                "rules": config.routing_config.rules.iter()
+                    .filter_map(Self::convert_rule)
+                    .collect::<Vec<_>>(),
+                "final": "proxy",
+                "auto_detect_interface": true
+            }
+        });
+        TemplateVars::new(&config, Some(server)).apply(&mut sing_box_config["route"]["rules"], "路由规则")?;
+        Ok(sing_box_config)
+    }
+
+    fn write_config(&self, server: &ServerInfo, config: &serde_json::Value) -> Result<PathBuf> {
+        // sing-box 配置每次启动时重新生成
+        let config_path = self.config_path(server)?;
+        let content = serde_json::to_string_pretty(config).context("无法序列化 sing-box 配置")?;
+        std::fs::write(&config_path, content).context("无法写入配置文件")?;
+        Ok(config_path)
+    }
+
+    fn config_path(&self, server: &ServerInfo) -> Result<PathBuf> {
+        Ok(AppConfig::servers_dir()?.join(format!("{}_sing-box_config.json", server.id)))
+    }
+
+    fn command_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec!["run".into(), "-c".into(), config_path.into()]
+    }
+
+    fn working_dir(&self) -> Result<PathBuf> {
+        AppConfig::sing_box_dir()
+    }
+
+    fn process_env(&self) -> Result<Vec<(OsString, OsString)>> {
+        Ok(Vec::new())
+    }
+
+    fn version(&self) -> Result<String> {
+        let output = Command::new(self.executable()?)
+            .arg("version")
+            .output()
+            .context("无法执行 sing-box")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("获取版本信息失败"));
+        }
+
+        // 输出形如 `sing-box version 1.9.0`
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("sing-box version "))
+            .map(|version| format!("v{}", version.trim()))
+            .context("无法解析版本信息")
+    }
+}
+
+impl SingBoxBackend {
+    /// 生成代理出站配置
+    fn generate_outbound(&self, server: &ServerInfo) -> Result<serde_json::Value> {
+        let text = |key: &str| server.config.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+
+        let mut outbound = json!({
+            "tag": "proxy",
+            "server": server.address,
+            "server_port": server.port
+        });
+
+        match server.protocol.as_str() {
+            "vmess" => {
+                outbound["type"] = json!("vmess");
+                outbound["uuid"] = json!(text("uuid").context("VMess 配置缺少 UUID")?);
+                outbound["security"] = json!(text("security").unwrap_or("auto"));
+                outbound["alter_id"] = json!(server.config.get("alterId").and_then(|v| v.as_u64()).unwrap_or(0));
+            }
+            "vless" => {
+                outbound["type"] = json!("vless");
+                outbound["uuid"] = json!(text("uuid").context("VLESS 配置缺少 UUID")?);
+                if let Some(flow) = text("flow") {
+                    outbound["flow"] = json!(flow);
+                }
+            }
+            "trojan" => {
+                outbound["type"] = json!("trojan");
+                outbound["password"] = json!(text("password").context("Trojan 配置缺少密码")?);
+            }
+            "hysteria2" => {
+                outbound["type"] = json!("hysteria2");
+                outbound["password"] = json!(text("password").context("Hysteria2 配置缺少密码")?);
+                if let Some(obfs_password) = text("obfsPassword") {
+                    outbound["obfs"] = json!({ "type": text("obfs").unwrap_or("salamander"), "password": obfs_password });
+                }
+            }
+            "shadowsocks" => {
+                outbound["type"] = json!("shadowsocks");
+                outbound["method"] = json!(text("method").context("Shadowsocks 配置缺少加密方式")?);
+                outbound["password"] = json!(text("password").context("Shadowsocks 配置缺少密码")?);
+            }
+            "socks5" | "http" => {
+                outbound["type"] = json!(if server.protocol == "http" { "http" } else { "socks" });
+                if let (Some(username), Some(password)) = (text("username"), text("password")) {
+                    outbound["username"] = json!(username);
+                    outbound["password"] = json!(password);
+                }
+            }
+            _ => return Err(anyhow::anyhow!("sing-box 不支持的协议: {}", server.protocol)),
+        }
+
+        // TLS：trojan 与 hysteria2 默认启用
+        let tls_default = matches!(server.protocol.as_str(), "trojan" | "hysteria2");
+        let tls_enabled = server.config.get("tls").and_then(|v| v.as_bool()).unwrap_or(tls_default);
+        if tls_enabled {
+            let mut tls = json!({
+                "enabled": true,
+                "server_name": text("sni").or(text("host")).unwrap_or(&server.address),
+                "insecure": server.config.get("allowInsecure").and_then(|v| v.as_bool()).unwrap_or(false)
+            });
+            if let Some(alpn) = server.config.get("alpn").and_then(|v| v.as_array()).filter(|a| !a.is_empty()) {
+                tls["alpn"] = json!(alpn);
+            }
+            if let Some(fingerprint) = text("fingerprint") {
+                tls["utls"] = json!({ "enabled": true, "fingerprint": fingerprint });
+            }
+            if text("security") == Some("reality") {
+                tls["reality"] = json!({
+                    "enabled": true,
+                    "public_key": text("publicKey").unwrap_or_default(),
+                    "short_id": text("shortId").unwrap_or_default()
+                });
+            }
+            outbound["tls"] = tls;
+        }
+
+        // 传输层
+        match text("network").unwrap_or("tcp") {
+            "ws" => {
+                let mut transport = json!({ "type": "ws", "path": text("path").unwrap_or("/") });
+                if let Some(host) = text("host") {
+                    transport["headers"] = json!({ "Host": host });
+                }
+                outbound["transport"] = transport;
+            }
+            "grpc" => {
+                outbound["transport"] = json!({ "type": "grpc", "service_name": text("serviceName").unwrap_or_default() });
+            }
+            "h2" | "http" => {
+                let mut transport = json!({ "type": "http", "path": text("path").unwrap_or("/") });
+                if let Some(host) = text("host") {
+                    transport["host"] = json!([host]);
+                }
+                outbound["transport"] = transport;
+            }
+            _ => {}
+        }
+
+        Ok(outbound)
+    }
+
+    /// 将 Xray 风格的路由规则转换为 sing-box 路由规则
+    /// sing-box 不读取 geoip.dat/geosite.dat，除 `geoip:private` 外的 geo 条目会被跳过
+    fn convert_rule(rule: &RoutingRule) -> Option<serde_json::Value> {
+        let mut converted = json!({ "outbound": rule.outbound_tag });
+        let mut matched = false;
+        let mut push = |key: &str, value: &str| {
+            let entry = converted.as_object_mut().unwrap()
+                .entry(key)
+                .or_insert_with(|| json!([]));
+            entry.as_array_mut().unwrap().push(json!(value));
+        };
+
+        for domain in rule.domain.iter().flatten() {
+            match domain.split_once(':') {
+                Some(("domain", value)) => push("domain_suffix", value),
+                Some(("full", value)) => push("domain", value),
+                Some(("regexp", value)) => push("domain_regex", value),
+                Some(("keyword", value)) => push("domain_keyword", value),
+                Some(("geosite", _)) => {
+                    log_warn!("sing-box 不支持 geosite 规则，已跳过: {}", domain);
+                    continue;
+                }
+                _ => push("domain_keyword", domain),
+            }
+            matched = true;
+        }
+
+        let mut private_ip = false;
+        for ip in rule.ip.iter().flatten() {
+            if ip == "geoip:private" {
+                private_ip = true;
+            } else if ip.starts_with("geoip:") {
+                log_warn!("sing-box 不支持 geoip 规则，已跳过: {}", ip);
+                continue;
+            } else {
+                push("ip_cidr", ip);
+            }
+            matched = true;
+        }
+        if private_ip {
+            converted["ip_is_private"] = json!(true);
+        }
+
+        matched.then_some(converted)
+    }
+
+    /// 检查 sing-box 更新
+    ///
+    /// # 返回值
+    /// * `Result<Option<String>>` - 有新版本（或未安装）时返回最新版本号
+    pub async fn check_update(&self) -> Result<Option<String>> {
+        let latest = Self::latest_release().await?.tag_name;
+        match self.version() {
+            Ok(current) if current == latest => Ok(None),
+            _ => Ok(Some(latest)),
+        }
+    }
+
+    /// 下载并安装最新版本的 sing-box
+    ///
+    /// # 参数
+    /// * `progress_callback` - 进度回调函数，接收 (当前进度, 总进度, 状态消息)
+    ///
+    /// # 返回值
+    /// * `Result<String>` - 安装的版本号
+    pub async fn download_latest<F>(&self, mut progress_callback: F) -> Result<String>
+    where
+        F: FnMut(u64, u64, String) + Send,
+    {
+        progress_callback(0, 100, "正在获取 sing-box 下载信息...".to_string());
+        let release = Self::latest_release().await?;
+        let asset_name = Self::asset_name(&release.tag_name)?;
+        let asset = release.assets.iter()
+            .find(|asset| asset.name == asset_name)
+            .context(format!("未找到适合的下载资源: {}", asset_name))?;
+        let expected_sha256 = asset.digest.as_deref()
+            .and_then(|digest| digest.strip_prefix("sha256:"))
+            .context(format!("发布信息中没有 {} 的 SHA256 值", asset_name))?
+            .to_lowercase();
+
+        let response = Client::new()
+            .get(&asset.browser_download_url)
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .context("无法下载 sing-box")?;
+        let total_size = response.content_length().unwrap_or(0);
+
+        let sing_box_dir = AppConfig::sing_box_dir()?;
+        let temp_file = sing_box_dir.join(format!("{}.download", asset_name));
+        let mut file = tokio::fs::File::create(&temp_file).await.context("无法创建临时文件")?;
+        let mut downloaded = 0u64;
+        let mut hasher = Sha256::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("下载过程中出现错误")?;
+            file.write_all(&chunk).await.context("无法写入临时文件")?;
+            hasher.update(&chunk);
+            downloaded += chunk.len() as u64;
+            if let Some(percent) = (downloaded * 80).checked_div(total_size) {
+                progress_callback(10 + percent, 100, format!("下载 sing-box... {:.1}MB",
+                    downloaded as f64 / 1024.0 / 1024.0));
+            }
+        }
+        file.flush().await.context("无法写入临时文件")?;
+        drop(file);
+
+        // 解压前校验压缩包的 SHA256，与发布信息中的摘要不一致时放弃安装
+        let actual_sha256 = format!("{:x}", hasher.finalize());
+        if actual_sha256 != expected_sha256 {
+            let _ = tokio::fs::remove_file(&temp_file).await;
+            return Err(anyhow::anyhow!(
+                "sing-box 压缩包 SHA256 校验失败，文件可能已损坏或被篡改（期望 {}，实际 {}）",
+                expected_sha256,
+                actual_sha256
+            ));
+        }
+
+        progress_callback(90, 100, "正在解压文件...".to_string());
+        let result = Self::extract(&temp_file, &self.executable()?);
+        let _ = tokio::fs::remove_file(&temp_file).await;
+        result?;
+
+        progress_callback(100, 100, "sing-box 安装完成！".to_string());
+        log_info!("已安装 sing-box {}", release.tag_name);
+        Ok(release.tag_name)
+    }
+
+    /// 获取最新发布信息
+    async fn latest_release() -> Result<GitHubRelease> {
+        Client::new()
+            .get(SING_BOX_RELEASE_API)
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .context("无法获取 sing-box 版本信息")?
+            .json()
+            .await
+            .context("无法解析 sing-box 版本信息")
+    }
+
+    /// 获取当前平台的发布包名称，如 `sing-box-1.9.0-linux-amd64.tar.gz`
+    fn asset_name(tag: &str) -> Result<String> {
+        let os = match std::env::consts::OS {
+            "windows" => "windows",
+            "macos" => "darwin",
+            "linux" => "linux",
+            other => return Err(anyhow::anyhow!("sing-box 不支持当前系统: {}", other)),
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            "x86" => "386",
+            other => return Err(anyhow::anyhow!("sing-box 不支持当前架构: {}", other)),
+        };
+        let extension = if os == "windows" { "zip" } else { "tar.gz" };
+        Ok(format!("sing-box-{}-{}-{}.{}", tag.trim_start_matches('v'), os, arch, extension))
+    }
+
+    /// 从发布包中解压出可执行文件
+    fn extract(archive_path: &Path, executable: &Path) -> Result<()> {
+        let file_name = executable.file_name().and_then(|n| n.to_str()).unwrap_or("sing-box");
+        let file = std::fs::File::open(archive_path).context("无法打开压缩文件")?;
+
+        let found = if archive_path.to_string_lossy().contains(".zip") {
+            let mut archive = zip::ZipArchive::new(file).context("无法读取压缩文件")?;
+            let mut found = false;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).context("无法读取压缩文件内容")?;
+                if entry.name().rsplit('/').next() == Some(file_name) {
+                    let mut output = std::fs::File::create(executable).context("无法创建输出文件")?;
+                    std::io::copy(&mut entry, &mut output).context("无法复制文件内容")?;
+                    found = true;
+                    break;
+                }
+            }
+            found
+        } else {
+            let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+            let mut found = false;
+            for entry in archive.entries().context("无法读取压缩文件")? {
+                let mut entry = entry.context("无法读取压缩文件内容")?;
+                let is_executable = entry.path()
+                    .ok()
+                    .and_then(|p| p.file_name().map(|n| n == file_name))
+                    .unwrap_or(false);
+                if is_executable {
+                    entry.unpack(executable).context("无法解压 sing-box")?;
+                    found = true;
+                    break;
+                }
+            }
+            found
+        };
+
+        if !found {
+            return Err(anyhow::anyhow!("压缩包中没有 {}", file_name));
+        }
+
+        // 在 Unix 系统上设置执行权限
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(executable, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// GitHub Release 信息
+#[derive(Debug, Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    assets: Vec<GitHubAsset>,
+}
+
+/// GitHub Asset 信息
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+    /// 资源摘要，格式为 `sha256:<十六进制>`
+    #[serde(default)]
+    digest: Option<String>,
+}
@@ -0,0 +1,125 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+// 导入日志宏
+use crate::{log_error, log_info, log_warn};
+
+use crate::app_lock::AppLock;
+use crate::config_store::ConfigStore;
+use crate::share_link;
+use crate::validation;
+
+/// 应用注册的 URI 协议
+pub const DEEP_LINK_SCHEME: &str = "ruray";
+
+/// 从深度链接中提取分享链接
+/// 支持以下格式：
+/// * `ruray://import?url=<百分号编码的分享链接>`
+/// * `ruray://import/<Base64 编码的分享链接>`
+/// * 直接传入的 vmess://、vless:// 等分享链接
+///
+/// # 参数
+/// * `url` - 收到的链接
+///
+/// # 返回值
+/// * `Result<String>` - 分享链接
+///
+/// # 异常
+/// * 当链接格式无法识别时返回错误
+pub fn extract_share_link(url: &str) -> Result<String> {
+    let url = url.trim();
+    let prefix = format!("{}://", DEEP_LINK_SCHEME);
+    let Some(rest) = url.strip_prefix(&prefix) else {
+        return Ok(url.to_string());
+    };
+
+    let parsed = url::Url::parse(url).context("深度链接格式无效")?;
+    if parsed.host_str() != Some("import") {
+        return Err(anyhow::anyhow!("不支持的深度链接操作: {}", rest));
+    }
+
+    if let Some((_, link)) = parsed.query_pairs().find(|(key, _)| key == "url") {
+        return Ok(link.into_owned());
+    }
+
+    let payload = parsed.path().trim_start_matches('/');
+    if payload.is_empty() {
+        return Err(anyhow::anyhow!("深度链接缺少分享链接"));
+    }
+    let decoded = share_link::decode_base64(payload)?;
+    String::from_utf8(decoded).context("深度链接中的分享链接不是有效的 UTF-8 文本")
+}
+
+/// 处理收到的深度链接
+/// 逐个解析为服务器，与 `add_server` 一样规范化并校验后，经用户在对话框中确认再添加到服务器列表，
+/// 并发射 `servers-changed` 事件。应用锁定时不导入
+///
+/// # 参数
+/// * `app` - 应用句柄
+/// * `urls` - 收到的链接列表
+pub fn handle_urls<R: Runtime>(app: &AppHandle<R>, urls: Vec<String>) {
+    if let Err(e) = AppLock::instance().ensure_unlocked() {
+        log_warn!("应用已锁定，忽略深度链接导入");
+        app.dialog()
+            .message(e.to_string())
+            .title("导入服务器")
+            .kind(MessageDialogKind::Warning)
+            .show(|_| {});
+        return;
+    }
+
+    for url in urls {
+        let server = extract_share_link(&url)
+            .and_then(|link| share_link::parse_share_link(&link))
+            .and_then(|mut server| {
+                validation::sanitize_server(&mut server);
+                validation::validate_server(&server, &ConfigStore::instance().get()?.servers)?;
+                Ok(server)
+            });
+        let server = match server {
+            Ok(server) => server,
+            Err(e) => {
+                log_warn!("无法导入深度链接: {}", e);
+                app.dialog()
+                    .message(format!("无法导入链接：{}", e))
+                    .title("导入服务器")
+                    .kind(MessageDialogKind::Error)
+                    .show(|_| {});
+                continue;
+            }
+        };
+
+        let message = format!(
+            "是否添加以下服务器？\n\n名称：{}\n协议：{}\n地址：{}:{}",
+            server.name, server.protocol, server.address, server.port
+        );
+        app.dialog()
+            .message(message)
+            .title("导入服务器")
+            .kind(MessageDialogKind::Info)
+            .buttons(MessageDialogButtons::OkCancel)
+            .show(move |confirmed| {
+                if !confirmed {
+                    return;
+                }
+
+                let server_id = server.id.clone();
+                let server_name = server.name.clone();
+                let store = ConfigStore::instance();
+                match store.update(|config| config.servers.push(server)) {
+                    Ok(()) => {
+                        log_info!("已通过深度链接添加服务器: {}", server_name);
+                        store.emit_servers_changed("added", &[server_id]);
+                    }
+                    Err(e) => log_error!("保存深度链接导入的服务器失败: {}", e),
+                }
+            });
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 临时域名路由覆盖
+// 用于"这个网站打不开，先让它走代理一小时"之类的场景：为域名临时指定走代理或直连，
+// 生成的路由规则优先于智能路由与用户规则，到期后自动移除。覆盖只保存在内存中，应用重启后失效；
+// 增加、移除或到期时代理正在运行则重启核心使规则生效。仅 Xray 核心支持
+
+use serde::Serialize;
+use serde_json::json;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::error::AppError;
+use crate::proxy::ProxyManager;
+
+/// 未指定有效期时的默认值（秒）
+const DEFAULT_TTL_SECS: u64 = 60 * 60;
+/// 有效期上限（秒）
+const MAX_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+/// 到期检查间隔
+const EXPIRE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 全局临时覆盖实例
+static DOMAIN_OVERRIDES: OnceLock<DomainOverrides> = OnceLock::new();
+
+/// 临时覆盖的出站
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideTarget {
+    /// 走代理
+    Proxy,
+    /// 直连
+    Direct,
+}
+
+impl OverrideTarget {
+    /// 核心配置中的出站标签
+    fn outbound_tag(self) -> &'static str {
+        match self {
+            OverrideTarget::Proxy => "proxy",
+            OverrideTarget::Direct => "direct",
+        }
+    }
+}
+
+/// 单个域名的临时覆盖
+#[derive(Debug, Clone, Serialize)]
+pub struct DomainOverride {
+    /// 域名（匹配其子域名）
+    pub domain: String,
+    /// 出站
+    pub target: OverrideTarget,
+    /// 到期时间
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 临时域名路由覆盖
+pub struct DomainOverrides {
+    entries: Mutex<Vec<DomainOverride>>,
+}
+
+impl DomainOverrides {
+    /// 获取全局临时覆盖实例（单例模式）
+    pub fn instance() -> &'static DomainOverrides {
+        DOMAIN_OVERRIDES.get_or_init(|| Self { entries: Mutex::new(Vec::new()) })
+    }
+
+    /// 当前有效的覆盖
+    pub fn list(&self) -> Vec<DomainOverride> {
+        let now = chrono::Utc::now();
+        self.entries.lock().unwrap().iter().filter(|e| e.expires_at > now).cloned().collect()
+    }
+
+    /// 生成核心配置中的路由规则，按出站分为代理与直连两条规则
+    pub fn routing_rules(&self) -> Vec<serde_json::Value> {
+        let entries = self.list();
+        [OverrideTarget::Proxy, OverrideTarget::Direct].iter()
+            .filter_map(|target| {
+                let domains: Vec<String> = entries.iter()
+                    .filter(|e| e.target == *target)
+                    .map(|e| format!("domain:{}", e.domain))
+                    .collect();
+                (!domains.is_empty()).then(|| json!({
+                    "type": "field",
+                    "domain": domains,
+                    "outboundTag": target.outbound_tag()
+                }))
+            })
+            .collect()
+    }
+
+    /// 为域名添加临时覆盖，已存在时替换
+    ///
+    /// # 参数
+    /// * `domain` - 域名，也可以是 URL
+    /// * `target` - 出站
+    /// * `ttl_secs` - 有效期（秒），为空时为 1 小时
+    ///
+    /// # 返回值
+    /// * `Result<DomainOverride, AppError>` - 添加的覆盖
+    pub async fn add(&self, domain: &str, target: OverrideTarget, ttl_secs: Option<u64>) -> Result<DomainOverride, AppError> {
+        let domain = normalize_domain(domain)?;
+        let ttl_secs = ttl_secs.unwrap_or(DEFAULT_TTL_SECS);
+        if ttl_secs == 0 || ttl_secs > MAX_TTL_SECS {
+            return Err(AppError::invalid_input(format!("有效期需在 1 秒到 {} 天之间", MAX_TTL_SECS / 86400)));
+        }
+
+        let entry = DomainOverride {
+            domain: domain.clone(),
+            target,
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(ttl_secs as i64),
+        };
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|e| e.domain != domain);
+            entries.push(entry.clone());
+        }
+        log_info!("{} 临时{}，{} 秒后恢复", domain, match target {
+            OverrideTarget::Proxy => "走代理",
+            OverrideTarget::Direct => "直连",
+        }, ttl_secs);
+        ProxyManager::instance().reload().await?;
+        Ok(entry)
+    }
+
+    /// 移除域名的临时覆盖
+    ///
+    /// # 参数
+    /// * `domain` - 域名
+    ///
+    /// # 返回值
+    /// * `Result<bool, AppError>` - 是否存在并已移除
+    pub async fn remove(&self, domain: &str) -> Result<bool, AppError> {
+        let domain = normalize_domain(domain)?;
+        let removed = {
+            let mut entries = self.entries.lock().unwrap();
+            let before = entries.len();
+            entries.retain(|e| e.domain != domain);
+            entries.len() != before
+        };
+        if removed {
+            log_info!("已移除 {} 的临时路由", domain);
+            ProxyManager::instance().reload().await?;
+        }
+        Ok(removed)
+    }
+
+    /// 移除已到期的覆盖
+    ///
+    /// # 返回值
+    /// * `Vec<String>` - 到期的域名
+    fn take_expired(&self) -> Vec<String> {
+        let now = chrono::Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        let expired: Vec<String> = entries.iter()
+            .filter(|e| e.expires_at <= now)
+            .map(|e| e.domain.clone())
+            .collect();
+        entries.retain(|e| e.expires_at > now);
+        expired
+    }
+}
+
+/// 规范化域名：支持直接粘贴 URL，去掉协议、路径、端口与末尾的点
+fn normalize_domain(input: &str) -> Result<String, AppError> {
+    let input = input.trim();
+    let host = match input.split_once("://") {
+        Some((_, rest)) => rest,
+        None => input,
+    };
+    let host = host.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+    let valid = !host.is_empty()
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && !host.starts_with('.');
+    if !valid {
+        return Err(AppError::invalid_input(format!("无效的域名: {}", input)));
+    }
+    Ok(host)
+}
+
+/// 启动到期检查任务
+/// 有覆盖到期时移除并重启运行中的核心，随后发射 `domain-overrides-changed` 事件
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄
+pub fn start_expiry_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(EXPIRE_CHECK_INTERVAL).await;
+
+            let overrides = DomainOverrides::instance();
+            let expired = overrides.take_expired();
+            if expired.is_empty() {
+                continue;
+            }
+            log_info!("临时路由已到期: {}", expired.join(", "));
+            if let Err(e) = ProxyManager::instance().reload().await {
+                log_warn!("临时路由到期后重启核心失败: {}", e);
+            }
+            let _ = app_handle.emit("domain-overrides-changed", json!(overrides.list()));
+        }
+    });
+}
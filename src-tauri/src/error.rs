@@ -0,0 +1,177 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::i18n;
+use crate::system::SystemProxyError;
+use crate::validation::ValidationErrors;
+
+/// 错误代码
+/// 界面根据错误代码区分处理，每个错误代码在 `i18n` 文本表中有对应的本地化说明
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// 代理核心可执行文件不存在
+    XrayMissing,
+    /// 入站端口被占用
+    PortInUse,
+    /// 需要管理员权限（启动 TUN、修改受管理的系统代理等）
+    AdminRequired,
+    /// 服务器、用户等资源不存在
+    NotFound,
+    /// 参数无效
+    InvalidInput,
+    /// 服务器配置校验失败，`details` 为各字段的错误
+    ValidationFailed,
+    /// 代理未运行
+    ProxyNotRunning,
+    /// 网络请求失败
+    Network,
+    /// 应用已锁定，需先解锁
+    AppLocked,
+    /// 其他错误
+    Internal,
+}
+
+/// 命令返回的错误
+/// 序列化为 `{ code, summary, message, details }` 返回给前端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppError {
+    /// 错误代码
+    pub code: ErrorCode,
+    /// 错误代码的本地化说明，按 `config.language` 选择语言
+    #[serde(default)]
+    pub summary: String,
+    /// 具体错误说明
+    pub message: String,
+    /// 附加信息，如字段校验错误、完整的错误链
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl AppError {
+    /// 创建错误
+    ///
+    /// # 参数
+    /// * `code` - 错误代码
+    /// * `message` - 错误说明
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            summary: i18n::error_summary(code).to_string(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// 附加信息
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// 资源不存在
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    /// 参数无效
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// 模块内部的错误转换为命令错误
+/// 错误链中包含 `AppError`（由出错位置通过 `AppError::into` 标记）时使用其错误代码，
+/// 否则根据底层 IO 错误和网络错误推断，无法识别时为 `INTERNAL`
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        // 校验错误保留字段明细
+        if let Some(errors) = error.chain().find_map(|cause| cause.downcast_ref::<ValidationErrors>()) {
+            return errors.clone().into();
+        }
+
+        let code = error.chain()
+            .find_map(|cause| {
+                if let Some(app_error) = cause.downcast_ref::<AppError>() {
+                    return Some(app_error.code);
+                }
+                if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+                    return match io_error.kind() {
+                        std::io::ErrorKind::AddrInUse => Some(ErrorCode::PortInUse),
+                        std::io::ErrorKind::PermissionDenied => Some(ErrorCode::AdminRequired),
+                        _ => None,
+                    };
+                }
+                cause.downcast_ref::<reqwest::Error>().map(|_| ErrorCode::Network)
+            })
+            .unwrap_or(ErrorCode::Internal);
+
+        let message = error.to_string();
+        let chain = format!("{:#}", error);
+        let app_error = Self::new(code, message.clone());
+        if chain != message {
+            app_error.with_details(serde_json::json!(chain))
+        } else {
+            app_error
+        }
+    }
+}
+
+/// 为模块错误附加操作说明并转换为命令错误
+/// 与 `map_err(|e| format!(..))` 不同，会保留根据错误链推断的错误代码
+pub trait ResultExt<T> {
+    /// 转换错误，说明格式为 `{context}: {原始错误}`
+    ///
+    /// # 参数
+    /// * `context` - 操作说明，如 "更新订阅失败"
+    fn app_context(self, context: &str) -> Result<T, AppError>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn app_context(self, context: &str) -> Result<T, AppError> {
+        self.map_err(|error| {
+            let mut app_error = AppError::from(error.into());
+            app_error.message = format!("{}: {}", context, app_error.message);
+            app_error
+        })
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<SystemProxyError> for AppError {
+    fn from(error: SystemProxyError) -> Self {
+        let code = if error.is_permission_denied() { ErrorCode::AdminRequired } else { ErrorCode::Internal };
+        Self::new(code, error.message.clone()).with_details(serde_json::json!(error))
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::new(ErrorCode::ValidationFailed, i18n::error_summary(ErrorCode::ValidationFailed)).with_details(serde_json::json!(errors.errors))
+    }
+}
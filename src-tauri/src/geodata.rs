@@ -0,0 +1,693 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 地理位置数据（geosite.dat / geoip.dat）解析
+// 两个文件都是 protobuf 编码的列表，每个条目以分类代码（如 `CN`、`GOOGLE`、`PRIVATE`）标识，
+// 分别包含域名匹配规则和 CIDR 列表。路由规则中的 `geosite:xxx`、`geoip:xxx` 与 `ext:文件:xxx`
+// 引用的就是这些条目，文件从 Xray 目录（核心的资源目录）读取。
+// 除供路由模拟使用外，还提供分类列表与查询，便于在编写规则时确认某个域名或 IP 属于哪些分类。
+// 下载来源可在设置中替换，并可添加额外的规则文件：.dat 文件原样保存，纯文本规则列表（每行一个域名或
+// CIDR）转换为只含一个分类的 .dat 文件，路由规则中以 `ext:文件名:分类` 引用
+
+use anyhow::{Context, Result};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::config::AppConfig;
+
+/// 默认域名数据文件
+pub const GEOSITE_FILE: &str = "geosite.dat";
+/// 默认 IP 数据文件
+pub const GEOIP_FILE: &str = "geoip.dat";
+/// geoip.dat 默认下载地址
+const DEFAULT_GEOIP_URL: &str = "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geoip.dat";
+/// geosite.dat 默认下载地址
+const DEFAULT_GEOSITE_URL: &str = "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geosite.dat";
+
+/// 数据文件类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoKind {
+    /// 域名数据（geosite）
+    #[default]
+    Geosite,
+    /// IP 数据（geoip）
+    Geoip,
+}
+
+impl GeoKind {
+    /// 规则中的前缀，如 `geosite`
+    fn prefix(self) -> &'static str {
+        match self {
+            GeoKind::Geosite => "geosite",
+            GeoKind::Geoip => "geoip",
+        }
+    }
+
+    /// 默认数据文件名
+    fn default_file(self) -> &'static str {
+        match self {
+            GeoKind::Geosite => GEOSITE_FILE,
+            GeoKind::Geoip => GEOIP_FILE,
+        }
+    }
+}
+
+/// 规则文件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoFileFormat {
+    /// Xray 的 .dat 格式
+    #[default]
+    Dat,
+    /// 纯文本列表，每行一个域名规则或 CIDR，`#` 开头为注释
+    Text,
+}
+
+/// 额外的规则文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoRuleFile {
+    /// 保存到 Xray 目录的文件名，需以 .dat 结尾
+    pub name: String,
+    /// 下载地址
+    pub url: String,
+    /// 文件类型，纯文本文件据此决定转换为域名还是 IP 数据
+    #[serde(default)]
+    pub kind: GeoKind,
+    /// 文件格式
+    #[serde(default)]
+    pub format: GeoFileFormat,
+    /// 纯文本文件转换后的分类代码，为空时使用文件名（不含扩展名）
+    #[serde(default)]
+    pub category: String,
+    /// 期望的 SHA256（十六进制），为空时尝试读取下载地址旁的 `.sha256sum` 文件
+    #[serde(default)]
+    pub sha256: String,
+}
+
+/// 地理位置数据下载来源
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeoSourceConfig {
+    /// geoip.dat 下载地址，为空时使用默认地址
+    #[serde(default)]
+    pub geoip_url: String,
+    /// geosite.dat 下载地址，为空时使用默认地址
+    #[serde(default)]
+    pub geosite_url: String,
+    /// 额外的规则文件
+    #[serde(default)]
+    pub extra_files: Vec<GeoRuleFile>,
+}
+
+/// 单个文件的下载信息
+#[derive(Debug, Clone)]
+pub struct GeoDownloadSource {
+    /// 保存的文件名
+    pub name: String,
+    /// 下载地址
+    pub url: String,
+    /// 文件类型
+    pub kind: GeoKind,
+    /// 文件格式
+    pub format: GeoFileFormat,
+    /// 纯文本文件转换后的分类代码
+    pub category: String,
+    /// 配置中指定的 SHA256
+    pub sha256: Option<String>,
+}
+
+/// 数据文件状态
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoFileInfo {
+    /// 文件名
+    pub name: String,
+    /// 下载地址
+    pub url: String,
+    /// 文件类型
+    pub kind: GeoKind,
+    /// 是否为额外添加的规则文件
+    pub custom: bool,
+    /// 路由规则中的引用方式，如 `geosite:`、`ext:my.dat:direct`
+    pub reference: String,
+    /// 本地文件是否存在
+    pub exists: bool,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 本地文件修改时间
+    pub modified_at: Option<String>,
+    /// 本地文件的 SHA256
+    pub sha256: Option<String>,
+}
+
+impl GeoRuleFile {
+    /// 纯文本文件转换后的分类代码
+    fn category_code(&self) -> String {
+        let code = if self.category.trim().is_empty() {
+            self.name.trim_end_matches(".dat")
+        } else {
+            self.category.trim()
+        };
+        code.to_ascii_uppercase()
+    }
+}
+
+impl GeoSourceConfig {
+    /// 检查下载来源设置，保存配置时调用
+    ///
+    /// # 异常
+    /// * 地址、文件名或 SHA256 无效，或文件名重复时返回错误
+    pub fn validate(&self) -> Result<()> {
+        let check_url = |url: &str| -> Result<()> {
+            let parsed = url::Url::parse(url).with_context(|| format!("无效的下载地址: {}", url))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(anyhow::anyhow!("下载地址只支持 http/https: {}", url));
+            }
+            Ok(())
+        };
+        for url in [&self.geoip_url, &self.geosite_url] {
+            if !url.trim().is_empty() {
+                check_url(url.trim())?;
+            }
+        }
+
+        let mut names: Vec<&str> = vec![GEOIP_FILE, GEOSITE_FILE];
+        for file in &self.extra_files {
+            check_file_name(&file.name)?;
+            if !file.name.ends_with(".dat") {
+                return Err(anyhow::anyhow!("规则文件名需以 .dat 结尾: {}", file.name));
+            }
+            if names.iter().any(|n| n.eq_ignore_ascii_case(&file.name)) {
+                return Err(anyhow::anyhow!("规则文件名重复: {}", file.name));
+            }
+            names.push(&file.name);
+            check_url(file.url.trim())?;
+            let sha256 = file.sha256.trim();
+            if !sha256.is_empty() && (sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit())) {
+                return Err(anyhow::anyhow!("{} 的 SHA256 无效", file.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取文件的下载信息
+    ///
+    /// # 参数
+    /// * `name` - 文件名，geoip.dat、geosite.dat 或额外规则文件名
+    ///
+    /// # 返回值
+    /// * `Option<GeoDownloadSource>` - 下载信息，文件未配置时为 None
+    pub fn source(&self, name: &str) -> Option<GeoDownloadSource> {
+        let builtin = |kind: GeoKind, url: &str, default_url: &str| GeoDownloadSource {
+            name: name.to_string(),
+            url: if url.trim().is_empty() { default_url.to_string() } else { url.trim().to_string() },
+            kind,
+            format: GeoFileFormat::Dat,
+            category: String::new(),
+            sha256: None,
+        };
+        match name {
+            GEOIP_FILE => Some(builtin(GeoKind::Geoip, &self.geoip_url, DEFAULT_GEOIP_URL)),
+            GEOSITE_FILE => Some(builtin(GeoKind::Geosite, &self.geosite_url, DEFAULT_GEOSITE_URL)),
+            _ => self.extra_files.iter().find(|f| f.name == name).map(|file| GeoDownloadSource {
+                name: file.name.clone(),
+                url: file.url.trim().to_string(),
+                kind: file.kind,
+                format: file.format,
+                category: file.category_code(),
+                sha256: Some(file.sha256.trim().to_lowercase()).filter(|s| !s.is_empty()),
+            }),
+        }
+    }
+
+    /// 所有数据文件名，默认文件在前
+    pub fn file_names(&self) -> Vec<String> {
+        [GEOIP_FILE, GEOSITE_FILE].iter()
+            .map(|n| n.to_string())
+            .chain(self.extra_files.iter().map(|f| f.name.clone()))
+            .collect()
+    }
+}
+
+/// 数据文件中的分类
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoCategory {
+    /// 分类代码（小写，与路由规则中的写法一致）
+    pub code: String,
+    /// 条目数（域名规则数或网段数）
+    pub count: usize,
+    /// 域名分类中出现的属性，如 `ads`、`cn`
+    pub attributes: Vec<String>,
+}
+
+/// 分类查询结果
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoQueryMatch {
+    /// 命中的分类，为路由规则中的写法，如 `geosite:cn`
+    pub category: String,
+    /// 命中的条目，如 `domain:example.com`、`1.0.1.0/24`
+    pub matched_by: String,
+}
+
+// 以下消息与 Xray-core 的 app/router/routercommon/common.proto 保持一致（字段编号不可修改）
+
+/// 域名匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainType {
+    /// 包含关键字
+    Plain = 0,
+    /// 正则表达式
+    Regex = 1,
+    /// 域名及其子域名
+    Domain = 2,
+    /// 完整匹配
+    Full = 3,
+}
+
+/// 域名属性，如 `@ads`、`@cn`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DomainAttribute {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(bool, tag = "2")]
+    pub bool_value: bool,
+    #[prost(int64, tag = "3")]
+    pub int_value: i64,
+}
+
+/// 域名匹配规则
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Domain {
+    #[prost(int32, tag = "1")]
+    pub r#type: i32,
+    #[prost(string, tag = "2")]
+    pub value: String,
+    #[prost(message, repeated, tag = "3")]
+    pub attribute: Vec<DomainAttribute>,
+}
+
+/// geosite.dat 中的一个分类
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GeoSite {
+    #[prost(string, tag = "1")]
+    pub country_code: String,
+    #[prost(message, repeated, tag = "2")]
+    pub domain: Vec<Domain>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GeoSiteList {
+    #[prost(message, repeated, tag = "1")]
+    pub entry: Vec<GeoSite>,
+}
+
+/// CIDR
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Cidr {
+    #[prost(bytes = "vec", tag = "1")]
+    pub ip: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub prefix: u32,
+}
+
+/// geoip.dat 中的一个分类
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GeoIp {
+    #[prost(string, tag = "1")]
+    pub country_code: String,
+    #[prost(message, repeated, tag = "2")]
+    pub cidr: Vec<Cidr>,
+    #[prost(bool, tag = "3")]
+    pub reverse_match: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GeoIpList {
+    #[prost(message, repeated, tag = "1")]
+    pub entry: Vec<GeoIp>,
+}
+
+impl Domain {
+    /// 匹配方式，未知值按关键字处理
+    pub fn domain_type(&self) -> DomainType {
+        match self.r#type {
+            1 => DomainType::Regex,
+            2 => DomainType::Domain,
+            3 => DomainType::Full,
+            _ => DomainType::Plain,
+        }
+    }
+
+    /// 按路由规则的写法输出该条目，如 `domain:example.com`
+    pub fn rule_text(&self) -> String {
+        let prefix = match self.domain_type() {
+            DomainType::Plain => "keyword",
+            DomainType::Regex => "regexp",
+            DomainType::Domain => "domain",
+            DomainType::Full => "full",
+        };
+        format!("{}:{}", prefix, self.value)
+    }
+
+    /// 是否带有指定属性
+    pub fn has_attribute(&self, key: &str) -> bool {
+        self.attribute.iter().any(|a| a.key.eq_ignore_ascii_case(key))
+    }
+
+    /// 判断域名是否匹配该规则
+    ///
+    /// # 参数
+    /// * `domain` - 小写域名
+    pub fn matches(&self, domain: &str) -> bool {
+        let value = self.value.to_ascii_lowercase();
+        match self.domain_type() {
+            DomainType::Plain => domain.contains(&value),
+            DomainType::Regex => regex::Regex::new(&self.value).map(|re| re.is_match(domain)).unwrap_or(false),
+            DomainType::Domain => domain_matches(domain, &value),
+            DomainType::Full => domain == value,
+        }
+    }
+}
+
+impl GeoSite {
+    /// 查找匹配域名的规则，`attribute` 为 `@属性` 过滤条件，`!` 开头表示排除该属性
+    ///
+    /// # 参数
+    /// * `domain` - 小写域名
+    /// * `attribute` - 属性过滤条件
+    pub fn find_match(&self, domain: &str, attribute: Option<&str>) -> Option<&Domain> {
+        self.domain.iter()
+            .filter(|d| match attribute {
+                Some(attr) => match attr.strip_prefix('!') {
+                    Some(excluded) => !d.has_attribute(excluded),
+                    None => d.has_attribute(attr),
+                },
+                None => true,
+            })
+            .find(|d| d.matches(domain))
+    }
+}
+
+impl Cidr {
+    /// 转换为网段，数据无效时返回 None
+    pub fn to_net(&self) -> Option<ipnet::IpNet> {
+        let addr = match self.ip.len() {
+            4 => IpAddr::from(<[u8; 4]>::try_from(self.ip.as_slice()).ok()?),
+            16 => IpAddr::from(<[u8; 16]>::try_from(self.ip.as_slice()).ok()?),
+            _ => return None,
+        };
+        ipnet::IpNet::new(addr, self.prefix as u8).ok()
+    }
+}
+
+impl GeoIp {
+    /// 查找包含 IP 的网段；`reverse_match` 分类在不包含时视为匹配
+    ///
+    /// # 返回值
+    /// * `Option<String>` - 匹配的网段（反向匹配时为 `!分类代码`）
+    pub fn find_match(&self, ip: IpAddr) -> Option<String> {
+        let found = self.cidr.iter()
+            .filter_map(Cidr::to_net)
+            .find(|net| net.contains(&ip))
+            .map(|net| net.to_string());
+        match (found, self.reverse_match) {
+            (found, false) => found,
+            (None, true) => Some(format!("!{}", self.country_code)),
+            (Some(_), true) => None,
+        }
+    }
+}
+
+/// `domain:` 规则匹配：与规则相同或是其子域名
+///
+/// # 参数
+/// * `domain` - 小写域名
+/// * `rule` - 小写规则域名
+pub fn domain_matches(domain: &str, rule: &str) -> bool {
+    domain == rule || domain.strip_suffix(rule).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// 数据文件路径，位于 Xray 目录
+///
+/// # 参数
+/// * `file_name` - 文件名，如 `geosite.dat`
+pub fn asset_path(file_name: &str) -> Result<PathBuf> {
+    Ok(AppConfig::xray_dir()?.join(file_name))
+}
+
+/// 检查数据文件名，只允许 Xray 目录下的文件
+fn check_file_name(file_name: &str) -> Result<()> {
+    if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.contains("..") {
+        return Err(anyhow::anyhow!("无效的数据文件名: {}", file_name));
+    }
+    Ok(())
+}
+
+/// 读取数据文件
+fn read_asset(file_name: &str) -> Result<Vec<u8>> {
+    check_file_name(file_name)?;
+    let path = asset_path(file_name)?;
+    std::fs::read(&path).with_context(|| format!("无法读取地理位置数据: {}", path.display()))
+}
+
+impl GeoSiteList {
+    /// 从 Xray 目录加载域名数据文件
+    ///
+    /// # 参数
+    /// * `file_name` - 文件名，如 `geosite.dat`
+    pub fn load(file_name: &str) -> Result<Self> {
+        let data = read_asset(file_name)?;
+        Self::decode(data.as_slice()).with_context(|| format!("无法解析 {}", file_name))
+    }
+
+    /// 按分类代码查找（不区分大小写）
+    pub fn find(&self, code: &str) -> Option<&GeoSite> {
+        self.entry.iter().find(|e| e.country_code.eq_ignore_ascii_case(code))
+    }
+}
+
+impl GeoIpList {
+    /// 从 Xray 目录加载 IP 数据文件
+    ///
+    /// # 参数
+    /// * `file_name` - 文件名，如 `geoip.dat`
+    pub fn load(file_name: &str) -> Result<Self> {
+        let data = read_asset(file_name)?;
+        Self::decode(data.as_slice()).with_context(|| format!("无法解析 {}", file_name))
+    }
+
+    /// 按分类代码查找（不区分大小写）
+    pub fn find(&self, code: &str) -> Option<&GeoIp> {
+        self.entry.iter().find(|e| e.country_code.eq_ignore_ascii_case(code))
+    }
+}
+
+/// 列出数据文件中的分类，按代码排序
+///
+/// # 参数
+/// * `kind` - 数据文件类型
+/// * `file_name` - 数据文件名，为空时使用默认文件（`ext:` 引用的文件也可查看）
+///
+/// # 返回值
+/// * `Result<Vec<GeoCategory>>` - 分类列表
+pub fn list_categories(kind: GeoKind, file_name: Option<&str>) -> Result<Vec<GeoCategory>> {
+    let file_name = file_name.unwrap_or(kind.default_file());
+    let mut categories: Vec<GeoCategory> = match kind {
+        GeoKind::Geosite => GeoSiteList::load(file_name)?.entry.into_iter()
+            .map(|site| {
+                let mut attributes: Vec<String> = site.domain.iter()
+                    .flat_map(|d| d.attribute.iter().map(|a| a.key.to_ascii_lowercase()))
+                    .collect();
+                attributes.sort();
+                attributes.dedup();
+                GeoCategory { code: site.country_code.to_ascii_lowercase(), count: site.domain.len(), attributes }
+            })
+            .collect(),
+        GeoKind::Geoip => GeoIpList::load(file_name)?.entry.into_iter()
+            .map(|geoip| GeoCategory {
+                code: geoip.country_code.to_ascii_lowercase(),
+                count: geoip.cidr.len(),
+                attributes: Vec::new(),
+            })
+            .collect(),
+    };
+    categories.sort_by(|a, b| a.code.cmp(&b.code));
+    Ok(categories)
+}
+
+/// 查询域名或 IP 属于哪些分类
+///
+/// # 参数
+/// * `kind` - 数据文件类型，geosite 查询域名，geoip 查询 IP
+/// * `value` - 域名或 IP
+/// * `category` - 只检查该分类（可写作 `cn`、`geosite:cn` 或 `cn@ads`），为空时检查全部分类
+/// * `file_name` - 数据文件名，为空时使用默认文件
+///
+/// # 返回值
+/// * `Result<Vec<GeoQueryMatch>>` - 命中的分类，为空表示不属于任何（指定的）分类
+///
+/// # 异常
+/// * 指定的分类不存在或 IP 格式无效时返回错误
+pub fn query(kind: GeoKind, value: &str, category: Option<&str>, file_name: Option<&str>) -> Result<Vec<GeoQueryMatch>> {
+    let file_name = file_name.unwrap_or(kind.default_file());
+    let category = category
+        .map(|c| c.trim())
+        .map(|c| c.strip_prefix(&format!("{}:", kind.prefix())).unwrap_or(c));
+    let label = |code: &str, attribute: Option<&str>| match attribute {
+        Some(attribute) => format!("{}:{}@{}", kind.prefix(), code.to_ascii_lowercase(), attribute),
+        None => format!("{}:{}", kind.prefix(), code.to_ascii_lowercase()),
+    };
+
+    match kind {
+        GeoKind::Geosite => {
+            let domain = value.trim().trim_end_matches('.').to_ascii_lowercase();
+            let list = GeoSiteList::load(file_name)?;
+            let (code, attribute) = match category.map(|c| c.split_once('@').map_or((c, None), |(c, a)| (c, Some(a)))) {
+                Some((code, attribute)) => (Some(code), attribute),
+                None => (None, None),
+            };
+            let sites: Vec<&GeoSite> = match code {
+                Some(code) => vec![list.find(code).with_context(|| format!("{} 中没有分类 {}", file_name, code))?],
+                None => list.entry.iter().collect(),
+            };
+            Ok(sites.into_iter()
+                .filter_map(|site| site.find_match(&domain, attribute).map(|d| GeoQueryMatch {
+                    category: label(&site.country_code, attribute),
+                    matched_by: d.rule_text(),
+                }))
+                .collect())
+        }
+        GeoKind::Geoip => {
+            let ip: IpAddr = value.trim().parse().with_context(|| format!("无效的 IP 地址: {}", value))?;
+            let list = GeoIpList::load(file_name)?;
+            let entries: Vec<&GeoIp> = match category {
+                Some(code) => vec![list.find(code).with_context(|| format!("{} 中没有分类 {}", file_name, code))?],
+                None => list.entry.iter().collect(),
+            };
+            Ok(entries.into_iter()
+                .filter_map(|geoip| geoip.find_match(ip).map(|net| GeoQueryMatch {
+                    category: label(&geoip.country_code, None),
+                    matched_by: net,
+                }))
+                .collect())
+        }
+    }
+}
+
+/// 检查下载的 .dat 文件能否解析且包含分类
+///
+/// # 返回值
+/// * `Result<usize>` - 分类数
+pub fn verify_dat(kind: GeoKind, data: &[u8]) -> Result<usize> {
+    let count = match kind {
+        GeoKind::Geosite => GeoSiteList::decode(data).context("不是有效的 geosite 数据文件")?.entry.len(),
+        GeoKind::Geoip => GeoIpList::decode(data).context("不是有效的 geoip 数据文件")?.entry.len(),
+    };
+    if count == 0 {
+        return Err(anyhow::anyhow!("数据文件中没有任何分类"));
+    }
+    Ok(count)
+}
+
+/// 将纯文本规则列表转换为只含一个分类的 .dat 文件
+/// 域名列表每行一个规则，支持 `domain:`、`full:`、`regexp:`、`keyword:` 前缀，无前缀时按 `domain:` 处理；
+/// IP 列表每行一个 IP 或 CIDR。空行与 `#`、`//` 开头的行忽略，行内空白后的内容忽略
+///
+/// # 参数
+/// * `kind` - 转换为域名还是 IP 数据
+/// * `code` - 分类代码
+/// * `text` - 文本内容
+///
+/// # 返回值
+/// * `Result<Vec<u8>>` - .dat 文件内容
+///
+/// # 异常
+/// * 存在无法解析的行或没有任何规则时返回错误
+pub fn compile_text_rules(kind: GeoKind, code: &str, text: &str) -> Result<Vec<u8>> {
+    let lines = text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| line.split_whitespace().next().map(|value| (index + 1, value)))
+        .filter(|(_, value)| !value.starts_with('#') && !value.starts_with("//"));
+
+    let data = match kind {
+        GeoKind::Geosite => {
+            let mut domains = Vec::new();
+            for (line_no, value) in lines {
+                let (domain_type, value) = match value.split_once(':') {
+                    Some(("domain", v)) => (DomainType::Domain, v),
+                    Some(("full", v)) => (DomainType::Full, v),
+                    Some(("keyword", v)) => (DomainType::Plain, v),
+                    Some(("regexp", v)) => {
+                        regex::Regex::new(v).with_context(|| format!("第 {} 行正则表达式无效", line_no))?;
+                        (DomainType::Regex, v)
+                    }
+                    Some(_) => return Err(anyhow::anyhow!("第 {} 行无法识别: {}", line_no, value)),
+                    None => (DomainType::Domain, value),
+                };
+                domains.push(Domain { r#type: domain_type as i32, value: value.to_string(), attribute: Vec::new() });
+            }
+            if domains.is_empty() {
+                return Err(anyhow::anyhow!("规则文件中没有任何域名"));
+            }
+            GeoSiteList { entry: vec![GeoSite { country_code: code.to_string(), domain: domains }] }.encode_to_vec()
+        }
+        GeoKind::Geoip => {
+            let mut cidrs = Vec::new();
+            for (line_no, value) in lines {
+                let net = value.parse::<ipnet::IpNet>()
+                    .or_else(|_| value.parse::<IpAddr>().map(ipnet::IpNet::from))
+                    .map_err(|_| anyhow::anyhow!("第 {} 行不是有效的 IP 或 CIDR: {}", line_no, value))?;
+                let ip = match net.network() {
+                    IpAddr::V4(v4) => v4.octets().to_vec(),
+                    IpAddr::V6(v6) => v6.octets().to_vec(),
+                };
+                cidrs.push(Cidr { ip, prefix: net.prefix_len() as u32 });
+            }
+            if cidrs.is_empty() {
+                return Err(anyhow::anyhow!("规则文件中没有任何 IP"));
+            }
+            GeoIpList { entry: vec![GeoIp { country_code: code.to_string(), cidr: cidrs, reverse_match: false }] }.encode_to_vec()
+        }
+    };
+    Ok(data)
+}
+
+/// 查询所有数据文件的状态
+///
+/// # 参数
+/// * `sources` - 下载来源设置
+///
+/// # 返回值
+/// * `Result<Vec<GeoFileInfo>>` - 默认文件与额外规则文件的状态
+pub fn file_statuses(sources: &GeoSourceConfig) -> Result<Vec<GeoFileInfo>> {
+    let mut files = Vec::new();
+    for name in sources.file_names() {
+        let Some(source) = sources.source(&name) else { continue };
+        let path = asset_path(&name)?;
+        let metadata = std::fs::metadata(&path).ok();
+        let custom = name != GEOIP_FILE && name != GEOSITE_FILE;
+        let reference = match (custom, source.format) {
+            (false, _) => format!("{}:", source.kind.prefix()),
+            (true, GeoFileFormat::Text) => format!("ext:{}:{}", name, source.category.to_ascii_lowercase()),
+            (true, GeoFileFormat::Dat) => format!("ext:{}:", name),
+        };
+        files.push(GeoFileInfo {
+            name,
+            url: source.url,
+            kind: source.kind,
+            custom,
+            reference,
+            exists: metadata.is_some(),
+            size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified_at: metadata.and_then(|m| m.modified().ok())
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+            sha256: std::fs::read(&path).ok().map(|data| format!("{:x}", Sha256::digest(&data))),
+        });
+    }
+    Ok(files)
+}
@@ -0,0 +1,208 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use chrono::{Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+// 导入日志宏
+use crate::{log_error, log_warn};
+
+use crate::config::AppConfig;
+use crate::traffic::TrafficUsage;
+
+/// 历史样本保留时长（秒）
+const HISTORY_RETENTION_SECS: i64 = 90 * 24 * 3600;
+
+/// 每分钟一条的统计样本
+/// 以分钟起始时间（Unix 秒，大端序）为键存储，键的字节顺序即时间顺序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HistorySample {
+    /// 上传字节数
+    upload: u64,
+    /// 下载字节数
+    download: u64,
+    /// 本分钟内代理运行的采样秒数
+    seconds: u64,
+    /// 最近一次采样时的服务器ID
+    server_id: Option<String>,
+    /// 最近一次采样时的运行时长（秒）
+    uptime: u64,
+}
+
+/// 历史统计数据点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficHistoryPoint {
+    /// 时间段起始时间（RFC3339）
+    pub time: String,
+    /// 上传字节数
+    pub upload: u64,
+    /// 下载字节数
+    pub download: u64,
+    /// 平均上传速度（字节/秒，按代理运行时间计算）
+    pub upload_speed: u64,
+    /// 平均下载速度（字节/秒，按代理运行时间计算）
+    pub download_speed: u64,
+    /// 时间段内代理运行的秒数
+    pub active_seconds: u64,
+    /// 时间段内最后使用的服务器ID
+    pub server_id: Option<String>,
+    /// 时间段内最长的连续运行时长（秒）
+    pub uptime: u64,
+}
+
+/// 统计历史存储
+/// 使用 sled 嵌入式数据库保存按分钟聚合的速度、服务器与运行时长样本，
+/// 重启后仍可绘制小时/天级别的图表
+pub struct HistoryStore {
+    db: Option<sled::Db>,
+}
+
+// 全局单例实例
+static HISTORY_STORE: OnceLock<HistoryStore> = OnceLock::new();
+
+impl HistoryStore {
+    /// 获取全局统计历史存储实例（单例模式）
+    /// 数据库打开失败时不记录历史，查询返回空结果
+    pub fn instance() -> &'static HistoryStore {
+        HISTORY_STORE.get_or_init(|| {
+            let db = AppConfig::history_path()
+                .and_then(|path| sled::open(&path).context("无法打开统计历史数据库"))
+                .map_err(|e| log_warn!("统计历史不可用: {}", e))
+                .ok();
+            let store = Self { db };
+            if let Err(e) = store.prune() {
+                log_warn!("清理过期统计历史失败: {}", e);
+            }
+            store
+        })
+    }
+
+    /// 记录一次流量采样
+    /// 采样累加到所在分钟的样本中
+    ///
+    /// # 参数
+    /// * `server_id` - 当前服务器ID
+    /// * `delta` - 采样间隔内的流量增量
+    /// * `seconds` - 采样间隔（秒）
+    /// * `uptime` - 代理运行时长（秒）
+    pub fn record(&self, server_id: &str, delta: &TrafficUsage, seconds: u64, uptime: u64) {
+        let Some(db) = &self.db else { return };
+
+        let minute = Local::now().timestamp() / 60 * 60;
+        let key = minute.to_be_bytes();
+        let result = db.get(key)
+            .context("无法读取统计历史")
+            .and_then(|existing| {
+                let mut sample: HistorySample = existing
+                    .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                    .unwrap_or_default();
+                sample.upload += delta.upload;
+                sample.download += delta.download;
+                sample.seconds += seconds;
+                sample.server_id = Some(server_id.to_string());
+                sample.uptime = uptime;
+
+                let value = serde_json::to_vec(&sample).context("无法序列化统计历史")?;
+                db.insert(key, value).context("无法写入统计历史")?;
+                Ok(())
+            });
+
+        if let Err(e) = result {
+            log_error!("记录统计历史失败: {}", e);
+        }
+    }
+
+    /// 查询统计历史
+    ///
+    /// # 参数
+    /// * `range_secs` - 查询最近多少秒的数据
+    /// * `resolution` - 聚合粒度（minute/hour/day），小时与天按本地时间划分
+    ///
+    /// # 返回值
+    /// * `Result<Vec<TrafficHistoryPoint>>` - 按时间顺序排列的数据点，没有样本的时间段不返回
+    pub fn query(&self, range_secs: i64, resolution: &str) -> Result<Vec<TrafficHistoryPoint>> {
+        let Some(db) = &self.db else { return Ok(Vec::new()) };
+        if !matches!(resolution, "minute" | "hour" | "day") {
+            return Err(anyhow::anyhow!("不支持的统计粒度: {}", resolution));
+        }
+
+        let start = Local::now().timestamp() - range_secs;
+        let mut points: Vec<(i64, TrafficHistoryPoint)> = Vec::new();
+
+        for item in db.range(start.to_be_bytes()..) {
+            let (key, value) = item.context("无法读取统计历史")?;
+            let Ok(key) = <[u8; 8]>::try_from(key.as_ref()) else { continue };
+            let Ok(sample) = serde_json::from_slice::<HistorySample>(&value) else { continue };
+            let bucket = Self::bucket_start(i64::from_be_bytes(key), resolution);
+
+            match points.last_mut() {
+                Some((last_bucket, point)) if *last_bucket == bucket => {
+                    point.upload += sample.upload;
+                    point.download += sample.download;
+                    point.active_seconds += sample.seconds;
+                    point.server_id = sample.server_id.or(point.server_id.take());
+                    point.uptime = point.uptime.max(sample.uptime);
+                }
+                _ => points.push((bucket, TrafficHistoryPoint {
+                    time: Local.timestamp_opt(bucket, 0)
+                        .single()
+                        .map(|t| t.to_rfc3339())
+                        .unwrap_or_default(),
+                    upload: sample.upload,
+                    download: sample.download,
+                    upload_speed: 0,
+                    download_speed: 0,
+                    active_seconds: sample.seconds,
+                    server_id: sample.server_id,
+                    uptime: sample.uptime,
+                })),
+            }
+        }
+
+        Ok(points
+            .into_iter()
+            .map(|(_, mut point)| {
+                point.upload_speed = point.upload.checked_div(point.active_seconds).unwrap_or(0);
+                point.download_speed = point.download.checked_div(point.active_seconds).unwrap_or(0);
+                point
+            })
+            .collect())
+    }
+
+    /// 清空统计历史
+    pub fn clear(&self) -> Result<()> {
+        if let Some(db) = &self.db {
+            db.clear().context("无法清空统计历史")?;
+        }
+        Ok(())
+    }
+
+    /// 删除超过保留时长的样本
+    fn prune(&self) -> Result<()> {
+        let Some(db) = &self.db else { return Ok(()) };
+        let cutoff = (Local::now().timestamp() - HISTORY_RETENTION_SECS).to_be_bytes();
+        for item in db.range(..cutoff) {
+            let (key, _) = item.context("无法读取统计历史")?;
+            db.remove(key).context("无法删除统计历史")?;
+        }
+        Ok(())
+    }
+
+    /// 计算时间戳所在时间段的起始时间
+    fn bucket_start(timestamp: i64, resolution: &str) -> i64 {
+        let Some(time) = Local.timestamp_opt(timestamp, 0).single() else { return timestamp };
+        let start = match resolution {
+            "hour" => time.with_minute(0).and_then(|t| t.with_second(0)),
+            "day" => time.date_naive()
+                .and_hms_opt(0, 0, 0)
+                .and_then(|t| t.and_local_timezone(Local).earliest()),
+            _ => time.with_second(0),
+        };
+        start.map(|t| t.timestamp()).unwrap_or(timestamp)
+    }
+}
@@ -0,0 +1,180 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::process::Command as TokioCommand;
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_error, log_info, log_warn};
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+
+/// 钩子事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// 代理已启动
+    ProxyStarted,
+    /// 代理已停止
+    ProxyStopped,
+    /// 运行中切换了服务器
+    ServerSwitched,
+    /// TUN 模式已启动
+    TunStarted,
+}
+
+impl HookEvent {
+    /// 事件名称，同时作为 `RURAY_EVENT` 环境变量的值
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::ProxyStarted => "proxy-started",
+            HookEvent::ProxyStopped => "proxy-stopped",
+            HookEvent::ServerSwitched => "server-switched",
+            HookEvent::TunStarted => "tun-started",
+        }
+    }
+}
+
+/// 事件钩子配置
+/// 每个事件对应一条由系统 shell 执行的命令（Windows 为 `cmd /C`，其他系统为 `sh -c`），为空时不执行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// 代理启动后执行
+    #[serde(default)]
+    pub proxy_started: Option<String>,
+    /// 代理停止后执行
+    #[serde(default)]
+    pub proxy_stopped: Option<String>,
+    /// 运行中切换服务器后执行（同时也会执行 proxy_started）
+    #[serde(default)]
+    pub server_switched: Option<String>,
+    /// TUN 模式启动后执行
+    #[serde(default)]
+    pub tun_started: Option<String>,
+    /// 命令超时时间（秒），超时后终止命令
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            proxy_started: None,
+            proxy_stopped: None,
+            server_switched: None,
+            tun_started: None,
+            timeout_secs: default_hook_timeout(),
+        }
+    }
+}
+
+impl HooksConfig {
+    /// 获取事件对应的命令
+    fn command_for(&self, event: HookEvent) -> Option<&str> {
+        let command = match event {
+            HookEvent::ProxyStarted => &self.proxy_started,
+            HookEvent::ProxyStopped => &self.proxy_stopped,
+            HookEvent::ServerSwitched => &self.server_switched,
+            HookEvent::TunStarted => &self.tun_started,
+        };
+        command.as_deref().map(str::trim).filter(|c| !c.is_empty())
+    }
+}
+
+/// 为 timeout_secs 字段提供默认值
+fn default_hook_timeout() -> u64 {
+    30
+}
+
+/// 在后台执行事件钩子
+/// 钩子不阻塞代理操作，执行结果只写入日志。命令可通过以下环境变量获取上下文：
+/// `RURAY_EVENT`、`RURAY_SERVER_ID`、`RURAY_SERVER_NAME`、`RURAY_SERVER_ADDRESS`、`RURAY_SERVER_PORT`、
+/// `RURAY_PREVIOUS_SERVER_ID`、`RURAY_HTTP_PORT`、`RURAY_SOCKS_PORT`、`RURAY_PAC_PORT`、
+/// `RURAY_PROXY_MODE`、`RURAY_TUN_ENABLED`
+///
+/// # 参数
+/// * `event` - 事件
+/// * `server` - 相关服务器，停止事件等没有服务器时为 None
+/// * `previous_server_id` - 切换服务器时的上一个服务器ID
+pub fn fire(event: HookEvent, server: Option<&ServerInfo>, previous_server_id: Option<&str>) {
+    let config = match server {
+        Some(server) => AppConfig::load().map(|config| config.with_server_overrides(server)),
+        None => AppConfig::load_active(),
+    };
+    let Ok(config) = config else { return };
+    let Some(command) = config.hooks.command_for(event).map(str::to_string) else { return };
+
+    let mut env = vec![
+        ("RURAY_EVENT", event.name().to_string()),
+        ("RURAY_HTTP_PORT", config.http_port.to_string()),
+        ("RURAY_SOCKS_PORT", config.socks_port.to_string()),
+        ("RURAY_PAC_PORT", config.pac_port.to_string()),
+        ("RURAY_PROXY_MODE", config.proxy_mode.clone()),
+        ("RURAY_TUN_ENABLED", config.tun_enabled.to_string()),
+    ];
+    if let Some(server) = server {
+        env.push(("RURAY_SERVER_ID", server.id.clone()));
+        env.push(("RURAY_SERVER_NAME", server.name.clone()));
+        env.push(("RURAY_SERVER_ADDRESS", server.address.clone()));
+        env.push(("RURAY_SERVER_PORT", server.port.to_string()));
+    }
+    if let Some(previous) = previous_server_id {
+        env.push(("RURAY_PREVIOUS_SERVER_ID", previous.to_string()));
+    }
+
+    let timeout = Duration::from_secs(config.hooks.timeout_secs.max(1));
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run(event, &command, env, timeout).await {
+            log_error!("执行 {} 钩子失败: {:#}", event.name(), e);
+        }
+    });
+}
+
+/// 执行钩子命令
+async fn run(event: HookEvent, command: &str, env: Vec<(&'static str, String)>, timeout: Duration) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut process = {
+        let mut process = TokioCommand::new("cmd");
+        process.arg("/C").arg(command).creation_flags(0x08000000); // CREATE_NO_WINDOW
+        process
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut process = {
+        let mut process = TokioCommand::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+
+    process
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    log_info!("执行 {} 钩子: {}", event.name(), command);
+    let child = process.spawn().context("无法启动钩子命令")?;
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.context("等待钩子命令失败")?,
+        Err(_) => return Err(anyhow::anyhow!("钩子命令超时（{} 秒），已终止", timeout.as_secs())),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        if !stdout.trim().is_empty() {
+            log_info!("{} 钩子输出: {}", event.name(), stdout.trim());
+        }
+        Ok(())
+    } else {
+        log_warn!("{} 钩子输出: {}{}", event.name(), stdout.trim(), stderr.trim());
+        Err(anyhow::anyhow!("钩子命令退出状态: {}", output.status))
+    }
+}
@@ -0,0 +1,209 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use crate::config::AppConfig;
+use crate::error::ErrorCode;
+
+/// 默认语言，其他语言缺少翻译时回退到该语言
+pub const DEFAULT_LANGUAGE: &str = "zh-CN";
+
+/// 支持的语言及其文本表
+/// 新增语言时添加文本表并在此登记即可，缺少的条目自动回退到默认语言
+const LANGUAGES: &[(&str, &[(&str, &str)])] = &[
+    ("zh-CN", ZH_CN),
+    ("en", EN),
+];
+
+/// 简体中文
+const ZH_CN: &[(&str, &str)] = &[
+    // 错误代码
+    ("error.xray_missing", "代理核心不存在"),
+    ("error.port_in_use", "端口已被占用"),
+    ("error.admin_required", "需要管理员权限"),
+    ("error.not_found", "资源不存在"),
+    ("error.invalid_input", "参数无效"),
+    ("error.validation_failed", "服务器配置无效"),
+    ("error.proxy_not_running", "代理未运行"),
+    ("error.network", "网络请求失败"),
+    ("error.app_locked", "应用已锁定"),
+    ("error.internal", "操作失败"),
+    // 托盘菜单
+    ("tray.no_servers", "无可用服务器"),
+    ("tray.start_proxy", "开启代理"),
+    ("tray.switch_server", "切换服务器"),
+    ("tray.other_servers", "其他服务器"),
+    ("tray.current_server", "当前服务器"),
+    ("tray.stop_proxy", "关闭代理"),
+    ("tray.proxy_mode", "代理模式"),
+    ("tray.mode_pac", "PAC 模式"),
+    ("tray.mode_global", "全局模式"),
+    ("tray.mode_direct", "直连模式"),
+    ("tray.tun_mode", "TUN 模式"),
+    ("tray.open_config", "查看配置"),
+    ("tray.show", "显示主窗口"),
+    ("tray.hide", "隐藏窗口"),
+    ("tray.quit", "退出"),
+    ("tray.tooltip_disconnected", "RuRay - 未连接"),
+    ("tray.tooltip_connected", "RuRay - 已连接"),
+    ("tray.tooltip_tun", "RuRay - 已连接（TUN 模式）"),
+    ("tray.tooltip_error", "RuRay - 代理异常"),
+    // 桌面通知
+    ("notify.title", "RuRay"),
+    ("notify.connection_restored", "代理连接已恢复"),
+    ("notify.connection_lost", "代理连接已失效，请检查服务器或切换节点"),
+    ("notify.connected", "已连接到 {}"),
+    ("notify.disconnected", "代理已断开"),
+    ("notify.server_switched", "已切换到服务器 {}"),
+    ("notify.core_crashed", "代理核心异常退出: {}"),
+    ("notify.subscription_updated", "订阅 {} 已更新，共 {} 个服务器"),
+    ("notify.update_available", "{} 有新版本: {}"),
+    ("notify.config_restored", "配置文件已损坏，已从备份恢复。损坏的文件保存为 {}"),
+];
+
+/// English
+const EN: &[(&str, &str)] = &[
+    // 错误代码
+    ("error.xray_missing", "Proxy core not found"),
+    ("error.port_in_use", "Port is already in use"),
+    ("error.admin_required", "Administrator privileges required"),
+    ("error.not_found", "Not found"),
+    ("error.invalid_input", "Invalid input"),
+    ("error.validation_failed", "Invalid server configuration"),
+    ("error.proxy_not_running", "Proxy is not running"),
+    ("error.network", "Network request failed"),
+    ("error.app_locked", "App is locked"),
+    ("error.internal", "Operation failed"),
+    // 托盘菜单
+    ("tray.no_servers", "No servers available"),
+    ("tray.start_proxy", "Start Proxy"),
+    ("tray.switch_server", "Switch Server"),
+    ("tray.other_servers", "Other Servers"),
+    ("tray.current_server", "Current server"),
+    ("tray.stop_proxy", "Stop Proxy"),
+    ("tray.proxy_mode", "Proxy Mode"),
+    ("tray.mode_pac", "PAC Mode"),
+    ("tray.mode_global", "Global Mode"),
+    ("tray.mode_direct", "Direct Mode"),
+    ("tray.tun_mode", "TUN Mode"),
+    ("tray.open_config", "View Config"),
+    ("tray.show", "Show Window"),
+    ("tray.hide", "Hide Window"),
+    ("tray.quit", "Quit"),
+    ("tray.tooltip_disconnected", "RuRay - Disconnected"),
+    ("tray.tooltip_connected", "RuRay - Connected"),
+    ("tray.tooltip_tun", "RuRay - Connected (TUN mode)"),
+    ("tray.tooltip_error", "RuRay - Proxy error"),
+    // 桌面通知
+    ("notify.title", "RuRay"),
+    ("notify.connection_restored", "Proxy connection restored"),
+    ("notify.connection_lost", "Proxy connection lost, check the server or switch to another one"),
+    ("notify.connected", "Connected to {}"),
+    ("notify.disconnected", "Proxy disconnected"),
+    ("notify.server_switched", "Switched to server {}"),
+    ("notify.core_crashed", "Proxy core exited unexpectedly: {}"),
+    ("notify.subscription_updated", "Subscription {} updated, {} servers"),
+    ("notify.update_available", "New version of {} available: {}"),
+    ("notify.config_restored", "The config file was corrupted and has been restored from a backup. The corrupted file was saved as {}"),
+];
+
+/// 查找语言在登记表中的代码
+/// 优先完全匹配（不区分大小写），其次只匹配主语言部分（如 `en-US` 使用 `en`）
+///
+/// # 参数
+/// * `language` - 语言代码
+///
+/// # 返回值
+/// * `Option<&'static str>` - 登记的语言代码，不支持的语言为 None
+pub fn resolve_language(language: &str) -> Option<&'static str> {
+    let primary = |code: &str| code.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    LANGUAGES.iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(language))
+        .or_else(|| LANGUAGES.iter().find(|(code, _)| primary(code) == primary(language)))
+        .map(|(code, _)| *code)
+}
+
+/// 支持的语言代码列表
+pub fn supported_languages() -> Vec<&'static str> {
+    LANGUAGES.iter().map(|(code, _)| *code).collect()
+}
+
+/// 获取语言对应的文本表，不支持的语言使用默认语言
+fn catalog(language: &str) -> &'static [(&'static str, &'static str)] {
+    let code = resolve_language(language).unwrap_or(DEFAULT_LANGUAGE);
+    LANGUAGES.iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, table)| *table)
+        .unwrap_or(ZH_CN)
+}
+
+/// 当前界面语言，取自 `config.language`
+pub fn current_language() -> String {
+    AppConfig::load()
+        .map(|config| config.language)
+        .unwrap_or_else(|_| DEFAULT_LANGUAGE.to_string())
+}
+
+/// 按指定语言获取文本
+/// 缺少翻译时回退到默认语言，仍然没有时返回键本身
+///
+/// # 参数
+/// * `language` - 语言代码
+/// * `key` - 文本键
+pub fn text_for(language: &str, key: &'static str) -> &'static str {
+    let lookup = |table: &'static [(&'static str, &'static str)]| {
+        table.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+    };
+    lookup(catalog(language))
+        .or_else(|| lookup(ZH_CN))
+        .unwrap_or(key)
+}
+
+/// 按当前语言获取文本
+///
+/// # 参数
+/// * `key` - 文本键
+pub fn t(key: &'static str) -> &'static str {
+    text_for(&current_language(), key)
+}
+
+/// 按当前语言获取文本，并依次用参数替换文本中的 `{}`
+///
+/// # 参数
+/// * `key` - 文本键
+/// * `args` - 参数
+pub fn format(key: &'static str, args: &[&str]) -> String {
+    let mut parts = t(key).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        text.push_str(args.get(index).copied().unwrap_or_default());
+        text.push_str(part);
+    }
+    text
+}
+
+/// 错误代码对应的文本键
+fn error_key(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::XrayMissing => "error.xray_missing",
+        ErrorCode::PortInUse => "error.port_in_use",
+        ErrorCode::AdminRequired => "error.admin_required",
+        ErrorCode::NotFound => "error.not_found",
+        ErrorCode::InvalidInput => "error.invalid_input",
+        ErrorCode::ValidationFailed => "error.validation_failed",
+        ErrorCode::ProxyNotRunning => "error.proxy_not_running",
+        ErrorCode::Network => "error.network",
+        ErrorCode::AppLocked => "error.app_locked",
+        ErrorCode::Internal => "error.internal",
+    }
+}
+
+/// 按当前语言获取错误代码的说明
+///
+/// # 参数
+/// * `code` - 错误代码
+pub fn error_summary(code: ErrorCode) -> &'static str {
+    t(error_key(code))
+}
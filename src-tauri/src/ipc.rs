@@ -0,0 +1,485 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 进程间通信协议
+// 图形界面、命令行与特权辅助进程等组件之间共用的通信约定：
+// * 传输层：Windows 使用命名管道 `\\.\pipe\ruray-ipc`，其他系统使用配置目录下的 Unix 套接字 `ruray-ipc.sock`
+// * 帧格式：4 字节大端长度前缀 + UTF-8 JSON 消息体，单帧不超过 `MAX_FRAME_BYTES`
+// * 握手：客户端连接后先发送 `IpcRequest::Hello`，携带协议版本和令牌文件中的令牌，
+//   服务端校验通过后回复 `IpcResponse::Hello`，之后才处理其他请求
+// * 每个请求带有递增的 `id`，响应回传相同的 `id`
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// 导入日志宏
+use crate::{log_debug, log_info};
+
+use crate::api_server::constant_time_eq;
+use crate::config::AppConfig;
+
+/// 协议版本，消息格式发生不兼容变化时递增
+pub const PROTOCOL_VERSION: u32 = 1;
+/// 单帧最大长度
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+/// Windows 命名管道名称
+#[cfg(target_os = "windows")]
+pub const PIPE_NAME: &str = r"\\.\pipe\ruray-ipc";
+/// 令牌文件名称（位于配置目录）
+const TOKEN_FILE_NAME: &str = "ipc.token";
+/// Unix 套接字文件名称（位于配置目录）
+#[cfg(not(target_os = "windows"))]
+const SOCKET_FILE_NAME: &str = "ruray-ipc.sock";
+
+/// 请求消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcRequest {
+    /// 握手，必须是连接上的第一条消息
+    Hello {
+        /// 客户端协议版本
+        version: u32,
+        /// 令牌文件中的访问令牌
+        token: String,
+    },
+    /// 启动代理
+    Start {
+        /// 服务器ID
+        server_id: String,
+    },
+    /// 停止代理
+    Stop,
+    /// 查询代理状态
+    Status,
+}
+
+/// 响应消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcResponse {
+    /// 握手成功
+    Hello {
+        /// 服务端协议版本
+        version: u32,
+    },
+    /// 请求已完成
+    Ok,
+    /// 代理状态
+    Status(IpcStatus),
+    /// 请求失败
+    Error {
+        /// 错误代码
+        code: IpcErrorCode,
+        /// 错误说明
+        message: String,
+    },
+}
+
+/// 代理状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IpcStatus {
+    /// 代理是否正在运行
+    pub is_running: bool,
+    /// 当前服务器ID
+    pub server_id: Option<String>,
+    /// 核心进程ID
+    pub core_pid: Option<u32>,
+    /// 运行时长（秒）
+    pub uptime_secs: u64,
+}
+
+/// 错误代码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcErrorCode {
+    /// 令牌无效或尚未握手
+    Unauthorized,
+    /// 协议版本不兼容
+    VersionMismatch,
+    /// 请求格式无效
+    InvalidRequest,
+    /// 请求执行失败
+    Failed,
+}
+
+/// 消息信封，用于关联请求与响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcMessage<T> {
+    /// 请求ID，响应回传相同的值
+    pub id: u64,
+    /// 消息内容
+    pub payload: T,
+}
+
+impl IpcResponse {
+    /// 构造错误响应
+    pub fn error(code: IpcErrorCode, message: impl Into<String>) -> Self {
+        IpcResponse::Error { code, message: message.into() }
+    }
+}
+
+/// 写入一帧消息
+///
+/// # 参数
+/// * `stream` - 传输流
+/// * `message` - 消息
+pub async fn write_frame<W, T>(stream: &mut W, message: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let body = serde_json::to_vec(message).context("无法序列化消息")?;
+    if body.len() > MAX_FRAME_BYTES {
+        return Err(anyhow::anyhow!("消息过大: {} 字节", body.len()));
+    }
+    stream.write_all(&(body.len() as u32).to_be_bytes()).await.context("写入消息失败")?;
+    stream.write_all(&body).await.context("写入消息失败")?;
+    stream.flush().await.context("写入消息失败")?;
+    Ok(())
+}
+
+/// 读取一帧消息
+///
+/// # 参数
+/// * `stream` - 传输流
+///
+/// # 返回值
+/// * `Result<Option<T>>` - 消息，对端正常关闭连接时为 None
+pub async fn read_frame<R, T>(stream: &mut R) -> Result<Option<T>>
+where
+    R: AsyncRead + Unpin,
+    T: for<'de> Deserialize<'de>,
+{
+    let mut length = [0u8; 4];
+    match stream.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("读取消息失败"),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_BYTES {
+        return Err(anyhow::anyhow!("消息过大: {} 字节", length));
+    }
+
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body).await.context("读取消息失败")?;
+    let message = serde_json::from_slice(&body).context("消息格式无效")?;
+    Ok(Some(message))
+}
+
+/// 令牌文件路径
+pub fn token_path() -> Result<PathBuf> {
+    let config_path = AppConfig::config_path()?;
+    let config_dir = config_path.parent().context("无法获取配置目录")?;
+    Ok(config_dir.join(TOKEN_FILE_NAME))
+}
+
+/// 读取访问令牌
+/// 客户端使用，令牌文件由服务端创建
+pub fn read_token() -> Result<String> {
+    let path = token_path()?;
+    let token = std::fs::read_to_string(&path)
+        .with_context(|| format!("无法读取令牌文件: {}", path.display()))?;
+    Ok(token.trim().to_string())
+}
+
+/// 生成新的访问令牌并写入令牌文件
+/// 服务端每次启动时调用，旧令牌随之失效；Unix 系统上令牌文件仅当前用户可读写
+pub fn create_token() -> Result<String> {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    let path = token_path()?;
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|| format!("无法写入令牌文件: {}", path.display()))?;
+        file.write_all(token.as_bytes()).context("无法写入令牌文件")?;
+    }
+    #[cfg(not(unix))]
+    std::fs::write(&path, &token).with_context(|| format!("无法写入令牌文件: {}", path.display()))?;
+
+    Ok(token)
+}
+
+/// Unix 套接字路径
+#[cfg(not(target_os = "windows"))]
+pub fn socket_path() -> Result<PathBuf> {
+    let config_path = AppConfig::config_path()?;
+    let config_dir = config_path.parent().context("无法获取配置目录")?;
+    Ok(config_dir.join(SOCKET_FILE_NAME))
+}
+
+/// 在已建立的连接上处理请求（服务端）
+/// 先校验握手，然后逐条读取请求并交给处理函数，直到对端关闭连接
+///
+/// # 参数
+/// * `stream` - 传输流
+/// * `token` - 服务端的访问令牌
+/// * `handler` - 请求处理函数
+pub async fn serve_connection<S, F, Fut>(mut stream: S, token: &str, handler: F) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: Fn(IpcRequest) -> Fut,
+    Fut: Future<Output = IpcResponse>,
+{
+    let Some(hello) = read_frame::<_, IpcMessage<IpcRequest>>(&mut stream).await? else { return Ok(()) };
+    let response = match hello.payload {
+        IpcRequest::Hello { version, .. } if version != PROTOCOL_VERSION => Err(IpcResponse::error(
+            IpcErrorCode::VersionMismatch,
+            format!("协议版本不兼容: 客户端 {}，服务端 {}", version, PROTOCOL_VERSION),
+        )),
+        IpcRequest::Hello { token: client_token, .. } if constant_time_eq(&client_token, token) => {
+            Ok(IpcResponse::Hello { version: PROTOCOL_VERSION })
+        }
+        IpcRequest::Hello { .. } => Err(IpcResponse::error(IpcErrorCode::Unauthorized, "访问令牌无效")),
+        _ => Err(IpcResponse::error(IpcErrorCode::Unauthorized, "请先握手")),
+    };
+    let authorized = response.is_ok();
+    let payload = response.unwrap_or_else(|error| error);
+    write_frame(&mut stream, &IpcMessage { id: hello.id, payload }).await?;
+    if !authorized {
+        return Ok(());
+    }
+
+    while let Some(request) = read_frame::<_, IpcMessage<IpcRequest>>(&mut stream).await? {
+        let payload = match request.payload {
+            IpcRequest::Hello { .. } => IpcResponse::error(IpcErrorCode::InvalidRequest, "重复握手"),
+            payload => handler(payload).await,
+        };
+        write_frame(&mut stream, &IpcMessage { id: request.id, payload }).await?;
+    }
+    Ok(())
+}
+
+/// 启动 IPC 服务端
+/// 生成新的访问令牌后开始监听，每个连接在独立任务中处理；正常情况下不会返回
+///
+/// # 参数
+/// * `handler` - 请求处理函数，只会收到握手之后的请求
+///
+/// # 异常
+/// * 无法写入令牌文件、无法创建套接字或命名管道时返回错误
+#[cfg(not(target_os = "windows"))]
+pub async fn listen<F, Fut>(handler: F) -> Result<()>
+where
+    F: Fn(IpcRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = IpcResponse> + Send,
+{
+    use std::os::unix::fs::PermissionsExt;
+
+    let token = create_token()?;
+    let path = socket_path()?;
+    // 上次运行遗留的套接字文件会导致绑定失败
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path)
+        .with_context(|| format!("无法创建 IPC 套接字: {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .context("无法设置 IPC 套接字权限")?;
+    log_info!("IPC 服务已启动: {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await.context("接受 IPC 连接失败")?;
+        spawn_connection(stream, token.clone(), handler.clone());
+    }
+}
+
+/// 启动 IPC 服务端
+/// 生成新的访问令牌后开始监听，每个连接在独立任务中处理；正常情况下不会返回
+///
+/// # 参数
+/// * `handler` - 请求处理函数，只会收到握手之后的请求
+///
+/// # 异常
+/// * 无法写入令牌文件、无法创建套接字或命名管道时返回错误
+#[cfg(target_os = "windows")]
+pub async fn listen<F, Fut>(handler: F) -> Result<()>
+where
+    F: Fn(IpcRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = IpcResponse> + Send,
+{
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let token = create_token()?;
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(PIPE_NAME)
+        .context("无法创建 IPC 命名管道")?;
+    log_info!("IPC 服务已启动: {}", PIPE_NAME);
+
+    loop {
+        server.connect().await.context("接受 IPC 连接失败")?;
+        // 先创建下一个管道实例再处理当前连接，避免客户端在间隙中连接失败
+        let next = ServerOptions::new().create(PIPE_NAME).context("无法创建 IPC 命名管道")?;
+        spawn_connection(std::mem::replace(&mut server, next), token.clone(), handler.clone());
+    }
+}
+
+/// 在独立任务中处理一个连接
+fn spawn_connection<S, F, Fut>(stream: S, token: String, handler: F)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: Fn(IpcRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = IpcResponse> + Send,
+{
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve_connection(stream, &token, handler).await {
+            log_debug!("IPC 连接异常结束: {:#}", e);
+        }
+    });
+}
+
+/// IPC 客户端
+pub struct IpcClient<S> {
+    stream: S,
+    next_id: u64,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> IpcClient<S> {
+    /// 在已建立的连接上完成握手
+    ///
+    /// # 参数
+    /// * `stream` - 传输流
+    /// * `token` - 访问令牌
+    pub async fn handshake(stream: S, token: &str) -> Result<Self> {
+        let mut client = Self { stream, next_id: 1 };
+        let hello = IpcRequest::Hello { version: PROTOCOL_VERSION, token: token.to_string() };
+        match client.request(hello).await? {
+            IpcResponse::Hello { .. } => Ok(client),
+            IpcResponse::Error { message, .. } => Err(anyhow::anyhow!("IPC 握手失败: {}", message)),
+            other => Err(anyhow::anyhow!("IPC 握手响应无效: {:?}", other)),
+        }
+    }
+
+    /// 发送请求并等待响应
+    pub async fn request(&mut self, request: IpcRequest) -> Result<IpcResponse> {
+        let id = self.next_id;
+        self.next_id += 1;
+        write_frame(&mut self.stream, &IpcMessage { id, payload: request }).await?;
+
+        let response: IpcMessage<IpcResponse> = read_frame(&mut self.stream)
+            .await?
+            .context("IPC 连接已关闭")?;
+        if response.id != id {
+            return Err(anyhow::anyhow!("IPC 响应ID不匹配: 期望 {}，收到 {}", id, response.id));
+        }
+        Ok(response.payload)
+    }
+}
+
+/// 连接本机的 IPC 服务端并完成握手
+/// 令牌从令牌文件读取
+#[cfg(target_os = "windows")]
+pub async fn connect() -> Result<IpcClient<tokio::net::windows::named_pipe::NamedPipeClient>> {
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(PIPE_NAME)
+        .context("无法连接 IPC 命名管道")?;
+    IpcClient::handshake(stream, &read_token()?).await
+}
+
+/// 连接本机的 IPC 服务端并完成握手
+/// 令牌从令牌文件读取
+#[cfg(not(target_os = "windows"))]
+pub async fn connect() -> Result<IpcClient<tokio::net::UnixStream>> {
+    let stream = tokio::net::UnixStream::connect(socket_path()?)
+        .await
+        .context("无法连接 IPC 套接字")?;
+    IpcClient::handshake(stream, &read_token()?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 处理函数：状态请求返回固定状态，其他请求返回成功
+    async fn echo_handler(request: IpcRequest) -> IpcResponse {
+        match request {
+            IpcRequest::Status => IpcResponse::Status(IpcStatus { is_running: true, ..Default::default() }),
+            _ => IpcResponse::Ok,
+        }
+    }
+
+    #[tokio::test]
+    async fn frame_round_trip() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let sent = IpcMessage { id: 7, payload: IpcRequest::Start { server_id: "server-1".to_string() } };
+        let writer = tokio::spawn(async move {
+            write_frame(&mut client, &sent).await.unwrap();
+        });
+
+        let received: IpcMessage<IpcRequest> = read_frame(&mut server).await.unwrap().unwrap();
+        writer.await.unwrap();
+        assert_eq!(received.id, 7);
+        assert!(matches!(received.payload, IpcRequest::Start { server_id } if server_id == "server-1"));
+        assert!(read_frame::<_, IpcMessage<IpcRequest>>(&mut server).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_frame_is_rejected() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(&((MAX_FRAME_BYTES + 1) as u32).to_be_bytes()).await.unwrap();
+        assert!(read_frame::<_, IpcMessage<IpcRequest>>(&mut server).await.is_err());
+
+        let message = IpcMessage { id: 1, payload: "x".repeat(MAX_FRAME_BYTES) };
+        assert!(write_frame(&mut client, &message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn handshake_then_requests() {
+        let (client, server) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move { serve_connection(server, "secret", echo_handler).await });
+
+        let mut client = IpcClient::handshake(client, "secret").await.unwrap();
+        assert!(matches!(client.request(IpcRequest::Stop).await.unwrap(), IpcResponse::Ok));
+        match client.request(IpcRequest::Status).await.unwrap() {
+            IpcResponse::Status(status) => assert!(status.is_running),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        let repeated = IpcRequest::Hello { version: PROTOCOL_VERSION, token: "secret".to_string() };
+        assert!(matches!(
+            client.request(repeated).await.unwrap(),
+            IpcResponse::Error { code: IpcErrorCode::InvalidRequest, .. }
+        ));
+
+        drop(client);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshake_rejects_wrong_token_and_version() {
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(async move { serve_connection(server, "secret", echo_handler).await });
+        assert!(IpcClient::handshake(client, "wrong").await.is_err());
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(async move { serve_connection(server, "secret", echo_handler).await });
+        let hello = IpcRequest::Hello { version: PROTOCOL_VERSION + 1, token: "secret".to_string() };
+        write_frame(&mut client, &IpcMessage { id: 1, payload: hello }).await.unwrap();
+        let response: IpcMessage<IpcResponse> = read_frame(&mut client).await.unwrap().unwrap();
+        assert!(matches!(response.payload, IpcResponse::Error { code: IpcErrorCode::VersionMismatch, .. }));
+        assert!(read_frame::<_, IpcMessage<IpcResponse>>(&mut client).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn requests_before_handshake_are_unauthorized() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(async move { serve_connection(server, "secret", echo_handler).await });
+        write_frame(&mut client, &IpcMessage { id: 1, payload: IpcRequest::Status }).await.unwrap();
+        let response: IpcMessage<IpcResponse> = read_frame(&mut client).await.unwrap().unwrap();
+        assert_eq!(response.id, 1);
+        assert!(matches!(response.payload, IpcResponse::Error { code: IpcErrorCode::Unauthorized, .. }));
+    }
+}
@@ -2,19 +2,65 @@
 // Author: Lander
 // CreateAt: 2024-01-01
 
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use tauri::{
-    menu::{Menu, MenuItem, Submenu},
+    menu::{CheckMenuItem, Menu, MenuItem, MenuItemKind, PredefinedMenuItem, Submenu},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
-    Emitter, Manager, Runtime, WindowEvent,
+    Listener, Manager, RunEvent, Runtime, WindowEvent,
 };
 
+mod api_server;
+mod app_info;
+mod app_lock;
+mod backup;
+mod bandwidth;
+mod cli;
 mod commands;
 mod config;
+mod config_store;
+mod connection_log;
+mod core_backend;
+mod deeplink;
+mod domain_override;
+mod error;
+mod geodata;
+mod history;
+mod hooks;
+mod i18n;
+// 进程间通信协议，命令行与正在运行的实例通过它通信，也供特权辅助进程等其他组件共用
+mod ipc;
 mod logger;
+mod monitor;
+mod network_watch;
+mod notify;
+mod pac;
+mod privacy;
+mod profile;
 mod proxy;
+mod routing;
+mod self_test;
+mod setup_audit;
+mod share_link;
+mod smart_routing;
+mod speed_test;
+mod state;
+mod subscription;
 mod system;
+mod template;
+mod traffic;
+mod transparent;
+mod tray_icon;
 mod tun;
+mod tun_bypass;
+mod validation;
+mod webdav;
+mod window_state;
 mod xray;
+// Xray gRPC API 客户端，运行中查询统计、增删出站
+mod xray_api;
+
+use error::AppError;
 
 /// 构建系统托盘菜单
 /// 
@@ -25,7 +71,7 @@ mod xray;
 /// * `Result<Menu<R>, tauri::Error>` - 托盘菜单对象
 async fn build_tray_menu<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<Menu<R>, tauri::Error> {
     // 获取当前代理状态
-    let proxy_status = match commands::get_proxy_status().await {
+    let proxy_status = match commands::get_proxy_status(app.state::<state::AppState>()).await {
         Ok(status) => status,
         Err(_) => commands::ProxyStatus {
             is_running: false,
@@ -37,53 +83,236 @@ async fn build_tray_menu<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<Menu<R
             download_speed: 0,
             total_upload: 0,
             total_download: 0,
+            restart_count: 0,
+            last_crash: None,
+            udp_relay: None,
+            total_restarts: 0,
+            last_error: None,
+            last_healthy_at: None,
+            healthy: false,
+            active_outbound: None,
         }
     };
 
-    // 获取服务器列表
-    let servers = match commands::get_servers().await {
-        Ok(servers) => servers,
-        Err(_) => vec![]
+    // 获取当前配置方案中启用的服务器列表
+    let app_config = config::AppConfig::load().ok();
+    let servers: Vec<commands::ServerInfo> = match &app_config {
+        Some(config) => config.servers.iter()
+            .filter(|server| config.is_server_enabled(&server.id))
+            .cloned()
+            .collect(),
+        None => vec![]
     };
 
-    // 创建代理管理子菜单
-    let proxy_submenu = if proxy_status.is_running {
-        // 如果代理正在运行，只显示关闭代理选项
-        let stop_proxy_item = MenuItem::with_id(app, "stop_proxy", "关闭代理", true, None::<&str>)?;
-        Submenu::with_id_and_items(app, "proxy_menu", "代理管理", true, &[&stop_proxy_item])?
+    // 菜单文字按配置的界面语言显示
+    let language = app_config.as_ref().map(|c| c.language.as_str()).unwrap_or(i18n::DEFAULT_LANGUAGE);
+    let text = |key: &'static str| i18n::text_for(language, key);
+    // 服务器列表：运行中作为“切换服务器”子菜单并勾选当前服务器，未运行时作为“开启代理”子菜单
+    let current_server_id = app.state::<state::AppState>().proxy.current_server_id();
+    let server_submenu = if servers.is_empty() {
+        let no_servers_item = MenuItem::with_id(app, "no_servers", text("tray.no_servers"), false, None::<&str>)?;
+        Submenu::with_id_and_items(app, "proxy_menu", text("tray.start_proxy"), true, &[&no_servers_item])?
     } else {
-        // 如果代理未运行，显示服务器列表供选择
-        if servers.is_empty() {
-            let no_servers_item = MenuItem::with_id(app, "no_servers", "无可用服务器", false, None::<&str>)?;
-            Submenu::with_id_and_items(app, "proxy_menu", "开启代理", true, &[&no_servers_item])?
-        } else {
-            let mut server_items = Vec::new();
-            for server in &servers {
-                let server_item = MenuItem::with_id(
-                    app, 
-                    &format!("start_server_{}", server.id), 
-                    &format!("{} ({}:{})", server.name, server.address, server.port), 
-                    true, 
-                    None::<&str>
-                )?;
-                server_items.push(server_item);
+        let server_item = |server: &commands::ServerInfo| {
+            let prefix = if server.favorite { "★ " } else { "" };
+            CheckMenuItem::with_id(
+                app,
+                &format!("start_server_{}", server.id),
+                &format!("{}{} ({}:{})", prefix, server.name, server.address, server.port),
+                true,
+                proxy_status.is_running && current_server_id.as_deref() == Some(server.id.as_str()),
+                None::<&str>
+            )
+        };
+
+        // 收藏的服务器排在最前，其次是最近使用的服务器，其余服务器收进子菜单
+        let favorites: Vec<&commands::ServerInfo> = servers.iter().filter(|s| s.favorite).collect();
+        let mut recent: Vec<&commands::ServerInfo> = servers.iter()
+            .filter(|s| !s.favorite && s.last_connected_at.is_some())
+            .collect();
+        recent.sort_by(|a, b| b.last_connected_at.cmp(&a.last_connected_at));
+        recent.truncate(TRAY_RECENT_SERVERS);
+        let others: Vec<&commands::ServerInfo> = servers.iter()
+            .filter(|s| !favorites.iter().chain(recent.iter()).any(|p| p.id == s.id))
+            .collect();
+
+        let mut server_items: Vec<MenuItemKind<R>> = Vec::new();
+        for group in [&favorites, &recent] {
+            if group.is_empty() {
+                continue;
             }
-            
-            // 将MenuItem转换为&dyn IsMenuItem<R>
-            let server_item_refs: Vec<&dyn tauri::menu::IsMenuItem<R>> = server_items.iter()
+            if !server_items.is_empty() {
+                server_items.push(MenuItemKind::Predefined(PredefinedMenuItem::separator(app)?));
+            }
+            for server in group.iter() {
+                server_items.push(MenuItemKind::Check(server_item(server)?));
+            }
+        }
+        if server_items.is_empty() {
+            for server in &others {
+                server_items.push(MenuItemKind::Check(server_item(server)?));
+            }
+        } else if !others.is_empty() {
+            let other_items = others.iter()
+                .map(|server| server_item(server))
+                .collect::<Result<Vec<_>, _>>()?;
+            let other_refs: Vec<&dyn tauri::menu::IsMenuItem<R>> = other_items.iter()
                 .map(|item| item as &dyn tauri::menu::IsMenuItem<R>)
                 .collect();
-            
-            Submenu::with_id_and_items(app, "proxy_menu", "开启代理", true, &server_item_refs)?
+            server_items.push(MenuItemKind::Predefined(PredefinedMenuItem::separator(app)?));
+            server_items.push(MenuItemKind::Submenu(
+                Submenu::with_id_and_items(app, "other_servers_menu", text("tray.other_servers"), true, &other_refs)?
+            ));
         }
+
+        // 将菜单项转换为&dyn IsMenuItem<R>
+        let server_item_refs: Vec<&dyn tauri::menu::IsMenuItem<R>> = server_items.iter()
+            .map(|item| item as &dyn tauri::menu::IsMenuItem<R>)
+            .collect();
+
+        let title = if proxy_status.is_running { text("tray.switch_server") } else { text("tray.start_proxy") };
+        Submenu::with_id_and_items(app, "proxy_menu", title, true, &server_item_refs)?
     };
-    
-    let config_item = MenuItem::with_id(app, "open_config", "查看配置", true, None::<&str>)?;
-    let show_item = MenuItem::with_id(app, "show", "显示主窗口", true, None::<&str>)?;
-    let hide_item = MenuItem::with_id(app, "hide", "隐藏窗口", true, None::<&str>)?;
-    let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
 
-    Menu::with_items(app, &[&proxy_submenu, &config_item, &show_item, &hide_item, &quit_item])
+    // 运行中显示当前服务器与实时速度（不可点击），速度项由定时任务刷新文字
+    let server_label = format!("{}: {}", text("tray.current_server"), proxy_status.current_server.as_deref().unwrap_or("-"));
+    let server_info_item = MenuItem::with_id(app, "current_server", &server_label, false, None::<&str>)?;
+    let speed_item = MenuItem::with_id(
+        app,
+        "current_speed",
+        &format_tray_speed(proxy_status.upload_speed, proxy_status.download_speed),
+        false,
+        None::<&str>
+    )?;
+    if let Some(tray_items) = app.try_state::<TrayStatusItems<R>>() {
+        *tray_items.speed.lock().unwrap() = proxy_status.is_running.then(|| speed_item.clone());
+    }
+    let stop_proxy_item = MenuItem::with_id(app, "stop_proxy", text("tray.stop_proxy"), true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+
+    // 代理模式与 TUN 模式快捷开关
+    let current_mode = app_config.as_ref().map(|c| c.proxy_mode.as_str()).unwrap_or("pac");
+    let mode_items = [("pac", "tray.mode_pac"), ("global", "tray.mode_global"), ("direct", "tray.mode_direct")]
+        .iter()
+        .map(|(mode, key)| {
+            CheckMenuItem::with_id(app, &format!("proxy_mode_{}", mode), text(*key), true, current_mode == *mode, None::<&str>)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mode_item_refs: Vec<&dyn tauri::menu::IsMenuItem<R>> = mode_items.iter()
+        .map(|item| item as &dyn tauri::menu::IsMenuItem<R>)
+        .collect();
+    let mode_submenu = Submenu::with_id_and_items(app, "proxy_mode_menu", text("tray.proxy_mode"), true, &mode_item_refs)?;
+    let tun_enabled = app_config.as_ref().map(|c| c.tun_enabled).unwrap_or(false);
+    let tun_item = CheckMenuItem::with_id(app, "toggle_tun", text("tray.tun_mode"), true, tun_enabled, None::<&str>)?;
+    let mode_separator = PredefinedMenuItem::separator(app)?;
+
+    let config_item = MenuItem::with_id(app, "open_config", text("tray.open_config"), true, None::<&str>)?;
+    let show_item = MenuItem::with_id(app, "show", text("tray.show"), true, None::<&str>)?;
+    let hide_item = MenuItem::with_id(app, "hide", text("tray.hide"), true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", text("tray.quit"), true, None::<&str>)?;
+
+    if proxy_status.is_running {
+        Menu::with_items(app, &[
+            &server_info_item,
+            &speed_item,
+            &separator,
+            &server_submenu,
+            &stop_proxy_item,
+            &mode_separator,
+            &mode_submenu,
+            &tun_item,
+            &config_item,
+            &show_item,
+            &hide_item,
+            &quit_item,
+        ])
+    } else {
+        Menu::with_items(app, &[
+            &server_submenu,
+            &mode_separator,
+            &mode_submenu,
+            &tun_item,
+            &config_item,
+            &show_item,
+            &hide_item,
+            &quit_item,
+        ])
+    }
+}
+
+/// 托盘服务器菜单中显示的最近使用服务器数量
+const TRAY_RECENT_SERVERS: usize = 5;
+
+/// 托盘实时速度刷新间隔
+const TRAY_SPEED_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 托盘菜单中需要定时刷新的菜单项
+/// 托盘菜单重建时替换为新菜单中的菜单项，代理未运行时为空
+struct TrayStatusItems<R: Runtime> {
+    speed: Mutex<Option<MenuItem<R>>>,
+}
+
+/// 格式化托盘中显示的速度
+///
+/// # Arguments
+/// * `upload` - 上传速度（字节/秒）
+/// * `download` - 下载速度（字节/秒）
+fn format_tray_speed(upload: u64, download: u64) -> String {
+    fn format_rate(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", bytes, UNITS[0])
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        }
+    }
+    format!("↑ {}  ↓ {}", format_rate(upload), format_rate(download))
+}
+
+/// 托盘菜单需要重建的事件
+/// 代理启停、服务器列表变化以及代理模式、TUN 开关等配置修改都会影响菜单内容
+const TRAY_REFRESH_EVENTS: [&str; 3] = ["proxy-status-changed", "servers-changed", "config-changed"];
+
+/// 重新构建托盘菜单
+/// 界面与托盘中的操作都通过事件触发此函数，托盘菜单因此始终与当前状态一致
+///
+/// # Arguments
+/// * `app` - 应用句柄
+async fn refresh_tray<R: Runtime>(app: &tauri::AppHandle<R>) {
+    match build_tray_menu(app).await {
+        Ok(new_menu) => {
+            if let Some(tray) = app.tray_by_id("main-tray") {
+                if let Err(e) = tray.set_menu(Some(new_menu)) {
+                    log_error!("更新托盘菜单失败: {}", e);
+                }
+            }
+        }
+        Err(e) => log_error!("构建托盘菜单失败: {}", e),
+    }
+}
+
+/// 定时刷新托盘中的实时速度
+/// 只更新速度菜单项的文字，不重建菜单，避免已打开的托盘菜单被关闭
+///
+/// # Arguments
+/// * `app` - 应用句柄
+fn start_tray_speed_refresh<R: Runtime>(app: tauri::AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TRAY_SPEED_REFRESH_INTERVAL).await;
+
+            let Some(tray_items) = app.try_state::<TrayStatusItems<R>>() else { continue };
+            let Some(speed_item) = tray_items.speed.lock().unwrap().clone() else { continue };
+            let speed = app.state::<state::AppState>().traffic.current_speed();
+            let _ = speed_item.set_text(format_tray_speed(speed.upload, speed.download));
+        }
+    });
 }
 
 /// 处理系统托盘图标事件
@@ -141,6 +370,21 @@ fn handle_menu_event<R: Runtime>(app: &tauri::AppHandle<R>, event: tauri::menu::
                     log_error!("打开配置目录失败: {}", e);
                 }
             }
+            "toggle_tun" => {
+                let enabled = !config::AppConfig::load().map(|c| c.tun_enabled).unwrap_or(false);
+                if let Err(e) = commands::toggle_tun_mode(app_handle.state::<state::AppState>(), enabled).await {
+                    log_error!("切换TUN模式失败: {}", e);
+                }
+                // 切换失败时配置未变化，需要重建菜单恢复勾选状态
+                refresh_tray(&app_handle).await;
+            }
+            id if id.starts_with("proxy_mode_") => {
+                let mode = id.strip_prefix("proxy_mode_").unwrap_or("pac").to_string();
+                if let Err(e) = commands::set_proxy_mode(app_handle.state::<state::AppState>(), mode).await {
+                    log_error!("切换代理模式失败: {}", e);
+                }
+                refresh_tray(&app_handle).await;
+            }
             id if id.starts_with("start_server_") => {
                 // 处理启动特定服务器
                 let server_id = id.strip_prefix("start_server_").unwrap_or("");
@@ -159,36 +403,21 @@ fn handle_menu_event<R: Runtime>(app: &tauri::AppHandle<R>, event: tauri::menu::
 /// * `app` - 应用句柄
 /// 
 /// # Returns
-/// * `Result<(), String>` - 操作结果
-async fn handle_stop_proxy<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<(), String> {
+/// * `Result<(), AppError>` - 操作结果
+async fn handle_stop_proxy<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<(), AppError> {
     // 获取当前代理状态
-    let proxy_status = commands::get_proxy_status().await.map_err(|e| e.to_string())?;
+    let proxy_status = commands::get_proxy_status(app.state::<state::AppState>()).await?;
     
     if proxy_status.is_running {
         // 当前代理正在运行，停止代理
-        commands::stop_proxy().await?;
+        // stop_proxy 会发射代理状态变化事件，托盘菜单随之重建
+        commands::stop_proxy(app.state::<state::AppState>()).await?;
         log_info!("代理已停止");
-        
-        // 发射代理状态变化事件
-        let _ = app.emit("proxy-status-changed", serde_json::json!({
-            "is_running": false,
-            "current_server": null
-        }));
     } else {
         // 代理未运行，什么也不做
         log_info!("代理未运行，无需停止");
     }
     
-    // 重新构建托盘菜单以更新状态
-    if let Ok(new_menu) = build_tray_menu(app).await {
-        // 获取托盘实例并更新菜单
-        if let Some(tray) = app.tray_by_id("main-tray") {
-            if let Err(e) = tray.set_menu(Some(new_menu)) {
-                log_error!("更新托盘菜单失败: {}", e);
-            }
-        }
-    }
-    
     Ok(())
 }
 
@@ -199,38 +428,158 @@ async fn handle_stop_proxy<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<(),
 /// * `server_id` - 服务器ID
 /// 
 /// # Returns
-/// * `Result<(), String>` - 操作结果
-async fn handle_start_server<R: Runtime>(app: &tauri::AppHandle<R>, server_id: &str) -> Result<(), String> {
-    // 检查当前代理状态
-    let proxy_status = commands::get_proxy_status().await.map_err(|e| e.to_string())?;
-    
-    if proxy_status.is_running {
-        // 如果代理正在运行，先停止当前代理
-        commands::stop_proxy().await?;
-        log_info!("已停止当前代理");
-    }
-    
-    // 启动指定的服务器
-    commands::start_proxy(server_id.to_string()).await?;
+/// * `Result<(), AppError>` - 操作结果
+async fn handle_start_server<R: Runtime>(app: &tauri::AppHandle<R>, server_id: &str) -> Result<(), AppError> {
+    // 代理运行中时不先停止，由 start_proxy 尽量热切换（无法热切换时完整重启）；
+    // start_proxy 会发射代理状态变化事件，托盘菜单随之重建
+    commands::start_proxy(app.state::<state::AppState>(), server_id.to_string()).await?;
     log_info!("已启动服务器: {}", server_id);
     
-    // 发射代理状态变化事件
-    let _ = app.emit("proxy-status-changed", serde_json::json!({
-        "is_running": true,
-        "current_server": server_id
-    }));
-    
-    // 重新构建托盘菜单以更新状态
-    if let Ok(new_menu) = build_tray_menu(app).await {
-        // 获取托盘实例并更新菜单
-        if let Some(tray) = app.tray_by_id("main-tray") {
-            if let Err(e) = tray.set_menu(Some(new_menu)) {
-                log_error!("更新托盘菜单失败: {}", e);
+    Ok(())
+}
+
+/// 清理上次异常退出遗留的状态
+/// 上次运行的 Xray 仍在运行时先询问是否接管（自动连接的正是该服务器时直接接管），
+/// 再终止其余由 RuRay 启动但仍在运行的核心进程；系统代理仍指向本应用端口时询问用户是否重置。
+/// 启动时会自动连接的情况下不询问，连接后系统代理会重新设置
+///
+/// # Arguments
+/// * `app` - 应用句柄
+///
+/// # Returns
+/// * `bool` - 是否已接管遗留的核心进程，已接管时不再自动连接
+async fn cleanup_orphaned_state<R: Runtime>(app: &tauri::AppHandle<R>) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let adopted = match proxy::ProxyManager::instance().find_adoptable_core() {
+        Ok(Some(core)) => offer_adopt_core(app, core).await,
+        Ok(None) => false,
+        Err(e) => {
+            log_error!("查找可接管的核心进程失败: {}", e);
+            false
+        }
+    };
+
+    match proxy::ProxyManager::instance().kill_orphaned_cores().await {
+        Ok(0) => {}
+        Ok(count) => log_info!("已清理 {} 个上次运行遗留的核心进程", count),
+        Err(e) => log_error!("清理遗留的核心进程失败: {}", e),
+    }
+
+    // 上次崩溃时 TUN 的默认路由与分割路由可能仍在，会使系统无法联网
+    match tun::TunManager::instance().cleanup_orphan_routes().await {
+        Ok(0) => {}
+        Ok(count) => log_info!("已清理 {} 条上次运行遗留的 TUN 路由", count),
+        Err(e) => log_warn!("清理遗留的 TUN 路由失败: {}", e),
+    }
+
+    // 上次崩溃时透明代理的重定向规则可能仍在，本机的 TCP 连接会被重定向到无人监听的端口
+    match transparent::TransparentManager::instance().cleanup_orphaned_rules(adopted).await {
+        Ok(true) => log_info!("已清理上次运行遗留的透明代理规则"),
+        Ok(false) => {}
+        Err(e) => log_warn!("清理遗留的透明代理规则失败: {}", e),
+    }
+
+    let Ok(app_config) = config::AppConfig::load() else { return adopted };
+    if adopted || (app_config.connect_on_startup && app_config.current_server.is_some()) {
+        return adopted;
+    }
+
+    let system_manager = system::SystemManager::new();
+    let ports = [app_config.http_port, app_config.socks_port, app_config.pac_port];
+    if !system_manager.is_proxy_pointing_to(&ports).await {
+        return false;
+    }
+
+    log_warn!("系统代理仍指向 RuRay 的端口，但代理未运行");
+    app.dialog()
+        .message("检测到系统代理仍指向 RuRay 的端口，可能是上次未正常退出所致。当前代理未运行，浏览器等应用将无法联网。\n\n是否重置系统代理？")
+        .title("系统代理残留")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .show(|confirmed| {
+            if !confirmed {
+                return;
             }
+            tauri::async_runtime::spawn(async {
+                match system::SystemManager::new().unset_proxy().await {
+                    Ok(()) => log_info!("已重置残留的系统代理"),
+                    Err(e) => log_error!("重置系统代理失败: {}", e),
+                }
+            });
+        });
+    false
+}
+
+/// 询问是否接管上次运行遗留的核心进程
+/// 启动时会自动连接同一服务器时不询问，直接接管
+///
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `core` - 可接管的核心进程
+///
+/// # Returns
+/// * `bool` - 是否接管成功
+async fn offer_adopt_core<R: Runtime>(app: &tauri::AppHandle<R>, core: proxy::AdoptableCore) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    log_info!("检测到上次运行的 Xray Core 仍在运行: PID {}，服务器 {}", core.pid, core.server_name);
+    let auto_adopt = config::AppConfig::load()
+        .map(|c| c.connect_on_startup && c.current_server.as_deref() == Some(core.server_id.as_str()))
+        .unwrap_or(false);
+    let confirmed = auto_adopt || {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.dialog()
+            .message(format!(
+                "检测到上次运行的 Xray Core 仍在运行（服务器: {}，PID {}），可能是上次未正常退出所致。\n\n是否接管该进程？选择取消将终止它。",
+                core.server_name, core.pid
+            ))
+            .title("接管核心进程")
+            .kind(MessageDialogKind::Info)
+            .buttons(MessageDialogButtons::OkCancel)
+            .show(move |confirmed| {
+                let _ = tx.send(confirmed);
+            });
+        rx.await.unwrap_or(false)
+    };
+    if !confirmed {
+        return false;
+    }
+
+    match commands::adopt_core(app.state::<state::AppState>(), core.pid).await {
+        Ok(()) => true,
+        Err(e) => {
+            log_error!("接管核心进程失败: {}", e);
+            false
         }
     }
-    
-    Ok(())
+}
+
+/// 处理重复启动
+/// 已有实例运行时再次启动程序，聚焦现有窗口，并导入命令行中的分享链接
+/// （`ruray://` 链接由深度链接插件转发，这里只处理直接传入的分享链接）
+///
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `argv` - 第二个实例的命令行参数
+fn handle_second_instance<R: Runtime>(app: &tauri::AppHandle<R>, argv: Vec<String>) {
+    log_info!("检测到重复启动，切换到已运行的实例");
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    let links: Vec<String> = argv.into_iter()
+        .skip(1)
+        .filter(|arg| arg.contains("://"))
+        .filter(|arg| !arg.starts_with(&format!("{}://", deeplink::DEEP_LINK_SCHEME)))
+        .collect();
+    if !links.is_empty() {
+        log_info!("收到来自命令行的分享链接");
+        deeplink::handle_urls(app, links);
+    }
 }
 
 /// 打开配置文件目录
@@ -275,6 +624,86 @@ async fn open_config_directory() -> Result<(), String> {
     Ok(())
 }
 
+/// 处理 IPC 请求
+/// 与控制 API 一样通过命令启动、停止代理和查询状态
+///
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `request` - 已通过握手的请求
+///
+/// # Returns
+/// * `ipc::IpcResponse` - 响应
+async fn handle_ipc_request(app: tauri::AppHandle, request: ipc::IpcRequest) -> ipc::IpcResponse {
+    let state = app.state::<state::AppState>();
+    let result = match request {
+        ipc::IpcRequest::Start { server_id } => commands::start_proxy(state, server_id).await.map(|_| ipc::IpcResponse::Ok),
+        ipc::IpcRequest::Stop => commands::stop_proxy(state).await.map(|_| ipc::IpcResponse::Ok),
+        ipc::IpcRequest::Status => {
+            let core_pid = state.proxy.core_pid();
+            commands::get_proxy_status(state).await.map(|status| ipc::IpcResponse::Status(ipc::IpcStatus {
+                is_running: status.is_running,
+                server_id: status.current_server,
+                core_pid,
+                uptime_secs: status.uptime,
+            }))
+        }
+        ipc::IpcRequest::Hello { .. } => return ipc::IpcResponse::error(ipc::IpcErrorCode::InvalidRequest, "重复握手"),
+    };
+    result.unwrap_or_else(|e| ipc::IpcResponse::error(ipc::IpcErrorCode::Failed, e.message))
+}
+
+/// 无窗口模式下启动后连接的服务器ID
+/// 通过命令行 `--connect` 启动且 RuRay 未运行时设置，此时不显示主窗口
+static HEADLESS_SERVER: OnceLock<String> = OnceLock::new();
+
+/// 处理命令行模式
+/// 带有 `--connect`、`--status` 等选项时与正在运行的实例通信并输出 JSON 结果
+///
+/// # Returns
+/// * `Option<i32>` - 命令已完成时返回进程退出码；返回 None 时继续启动应用
+///   （普通启动，或 RuRay 未运行时以无窗口模式启动并连接服务器）
+pub fn run_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // 数据目录需在首次读取配置前确定
+    let (data_dir_result, command) = match cli::take_data_dir_options(&args) {
+        Ok((options, rest)) => (
+            config::AppConfig::init_data_dir(options.portable, options.data_dir),
+            cli::parse_args(&rest),
+        ),
+        Err(e) => (Ok(()), Some(Err(e))),
+    };
+
+    // Release 版本为窗口程序，执行命令行命令时需在任何输出之前附加到父进程的控制台
+    #[cfg(all(windows, not(debug_assertions)))]
+    if command.is_some() {
+        unsafe {
+            use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+
+    if let Err(e) = data_dir_result {
+        eprintln!("{:#}", e);
+    }
+
+    let command = match command? {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::print_usage();
+            return Some(2);
+        }
+    };
+
+    match cli::execute(command) {
+        cli::CliOutcome::Exit(code) => Some(code),
+        cli::CliOutcome::Headless(server_id) => {
+            let _ = HEADLESS_SERVER.set(server_id);
+            None
+        }
+    }
+}
+
 /// 应用程序入口点
 /// 
 /// # Returns
@@ -282,6 +711,11 @@ async fn open_config_directory() -> Result<(), String> {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let builder = tauri::Builder::default()
+        // 单实例插件需要最先注册，重复启动时在已运行的实例中处理
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            handle_second_instance(app, argv);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
@@ -289,43 +723,138 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_process::init())
+        .manage(state::AppState::new())
         .invoke_handler(tauri::generate_handler![
             // 服务器管理
             commands::get_servers,
+            commands::query_servers,
+            commands::toggle_favorite_server,
+            commands::get_recent_servers,
             commands::add_server,
             commands::update_server,
             commands::delete_server,
+            commands::delete_servers,
+            commands::move_servers,
+            commands::export_servers,
+            commands::test_servers,
             commands::test_server_connection,
             commands::regenerate_server_config,
             commands::open_server_config_file,
+            commands::run_speed_test,
+            commands::get_speed_test_results,
+            // 订阅管理
+            commands::get_subscriptions,
+            commands::add_subscription,
+            commands::delete_subscription,
+            commands::update_subscription,
+            commands::get_subscription_info,
+            commands::set_subscription_name_template,
+            // 配置方案
+            commands::list_profiles,
+            commands::switch_profile,
+            commands::duplicate_profile,
+            commands::rename_profile,
+            commands::set_profile_servers,
+            commands::delete_profile,
             // 代理控制
             commands::start_proxy,
             commands::stop_proxy,
+            commands::force_cleanup_all,
+            commands::find_adoptable_core,
+            commands::adopt_core,
             commands::get_proxy_status,
             commands::set_proxy_mode,
+            commands::test_udp_relay,
+            commands::run_self_test,
+            commands::get_app_info,
+            commands::get_setup_audit,
+            commands::get_proxy_endpoints,
+            commands::get_proxy_snippets,
+            commands::write_proxyrc,
+            commands::set_control_api,
+            commands::get_inbound_users,
+            commands::add_inbound_user,
+            commands::remove_inbound_user,
+            commands::get_inbound_user_traffic,
+            commands::get_external_ip,
+            // 流量统计
+            commands::get_traffic_usage,
+            commands::set_traffic_quota,
+            commands::reset_traffic_usage,
+            commands::get_traffic_history,
+            commands::clear_history,
             // 系统功能
             commands::get_system_stats,
             commands::set_system_proxy,
             commands::clear_system_proxy,
+            commands::can_modify_system_proxy,
+            commands::detect_conflicting_software,
             commands::get_system_proxy_status,
+            commands::set_auto_start,
+            commands::get_auto_start_status,
+            commands::get_logs,
+            commands::set_log_level,
+            commands::set_core_log_level,
+            commands::set_access_log,
+            commands::export_logs,
+            commands::export_connection_log,
             // 配置文件管理
             commands::cleanup_unused_configs,
             // Xray Core 管理
+            commands::set_core_backend,
+            commands::get_core_version,
+            commands::check_core_update,
+            commands::download_core,
             commands::check_xray_update,
             commands::download_xray_update,
             commands::download_xray_update_with_progress,
             commands::get_xray_version,
+            commands::list_installed_cores,
+            commands::switch_core_version,
+            commands::rollback_core,
             commands::check_xray_exists,
             commands::get_xray_path,
             commands::download_geo_files,
             commands::check_geo_files_exist,
+            commands::check_geo_files_update,
+            commands::get_geo_files,
+            commands::update_geo_file,
+            commands::list_geo_categories,
+            commands::query_geo_category,
             commands::ensure_xray_files,
             commands::test_xray_config,
             // 配置管理
+            commands::preview_xray_config,
             commands::get_app_config,
+            commands::get_config_recovery,
             commands::save_app_config,
+            commands::simulate_route,
+            commands::get_pac_content,
+            commands::test_pac,
+            commands::get_smart_routing_status,
+            commands::refresh_smart_routing,
+            commands::force_proxy_domain,
+            commands::force_direct_domain,
+            commands::list_domain_overrides,
+            commands::remove_domain_override,
+            commands::set_bandwidth_limit,
+            commands::update_language_config,
+            commands::get_app_lock_status,
+            commands::unlock,
+            commands::lock_app,
+            commands::enable_app_lock,
+            commands::disable_app_lock,
+            commands::set_custom_injections,
+            commands::set_log_window_open,
             commands::import_config,
             commands::export_config,
+            commands::import_servers,
+            commands::create_backup,
+            commands::restore_backup,
+            commands::webdav_sync_status,
+            commands::webdav_upload,
+            commands::webdav_download,
+            commands::export_full_xray_config,
             // TUN 模式管理
             commands::start_tun_mode,
             commands::stop_tun_mode,
@@ -336,6 +865,8 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             commands::save_tun_config,
             commands::set_tun_system_route,
             commands::toggle_tun_mode,
+            commands::get_tun_routes,
+            commands::cleanup_orphan_routes,
         ])
         .setup(|app| {
             // 初始化应用配置
@@ -347,15 +878,144 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     // 测试日志系统
-    log_info!("RuRay 应用程序启动");
+    log_info!("RuRay 应用程序启动: {}", app_info::app_info().summary());
     log_debug!("当前运行模式: {}", if cfg!(debug_assertions) { "Debug" } else { "Release" });
 
+            // 同步开机自启动项并处理最小化启动
+            if let Ok(app_config) = config::AppConfig::load() {
+                let system_manager = system::SystemManager::new();
+                // 启用时重新注册一次，确保程序移动位置后启动项路径仍然有效
+                if app_config.auto_start || system_manager.is_auto_start_enabled() {
+                    if let Err(e) = system_manager.set_auto_start(app_config.auto_start) {
+                        log_warn!("同步开机自启动失败: {}", e);
+                    }
+                }
+
+            }
+
+            // 先恢复主窗口的尺寸和位置再显示，避免窗口以默认尺寸出现后跳动
+            if let Some(window) = app.get_webview_window("main") {
+                window_state::restore(&window);
+                if HEADLESS_SERVER.get().is_some() {
+                    log_info!("以无窗口模式启动");
+                } else if config::AppConfig::load().map(|c| c.start_minimized).unwrap_or(false) {
+                    log_info!("已最小化到托盘启动");
+                } else if let Err(e) = window.show() {
+                    log_error!("显示主窗口失败: {}", e);
+                }
+            }
+
+            let app_state = app.state::<state::AppState>();
+
+            // 启动配置文件监视
+            app_state.config.start(app.handle().clone());
+
+            // 设置桌面通知使用的应用句柄
+            notify::init(app.handle().clone());
+
+            // 配置文件损坏并已从备份恢复时通知用户
+            if let Some(recovery) = config::AppConfig::last_recovery() {
+                log_warn!(
+                    "配置文件解析失败（{}），已从备份 {} 恢复，损坏的文件另存为 {}",
+                    recovery.error, recovery.backup_path, recovery.corrupt_path
+                );
+                notify::show(&i18n::format("notify.config_restored", &[&recovery.corrupt_path]));
+            }
+
             // 设置TunManager的应用句柄
-            tun::TunManager::instance().set_app_handle(app.handle().clone());
+            app_state.tun.set_app_handle(app.handle().clone());
+
+            // 设置ProxyManager的应用句柄
+            app_state.proxy.set_app_handle(app.handle().clone());
+
+            // 启动代理连通性监控
+            app_state.monitor.start(app.handle().clone());
+
+            // 启动流量统计采样
+            app_state.traffic.start(app.handle().clone());
+
+            // 启动网络变化监控，切换网络或休眠恢复后重新应用代理设置
+            network_watch::start_network_watch(app.handle().clone());
+
+            // 启动本地控制 API（未启用时不监听）
+            let api_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = api_server::ApiServer::instance().start(api_handle).await {
+                    log_error!("启动控制 API 失败: {}", e);
+                }
+            });
+
+            // 启动本机 IPC 服务，命令行通过它控制正在运行的实例
+            let ipc_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = ipc::listen(move |request| handle_ipc_request(ipc_handle.clone(), request)).await {
+                    log_error!("启动 IPC 服务失败: {:#}", e);
+                }
+            });
+
+            // 在后台检查核心、地理数据、wintun.dll、管理员权限与 WebView，结果通过 `setup-audit` 事件通知界面
+            setup_audit::spawn(app.handle().clone());
+
+            // 启动地理位置数据自动更新
+            xray::start_geo_auto_update(app.handle().clone());
+
+            // 启动智能路由测量（未启用时不测量）
+            smart_routing::start_smart_routing(app.handle().clone());
+
+            // 启动临时域名路由到期检查
+            domain_override::start_expiry_task(app.handle().clone());
+
+            // 处理 ruray:// 深度链接（含启动时携带的链接）
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+
+                // Linux 与 Windows 开发模式下需要在运行时注册协议
+                #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+                if let Err(e) = app.deep_link().register_all() {
+                    log_warn!("注册深度链接协议失败: {}", e);
+                }
+
+                let deep_link_handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    let urls = event.urls().iter().map(|url| url.to_string()).collect();
+                    deeplink::handle_urls(&deep_link_handle, urls);
+                });
+
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    deeplink::handle_urls(app.handle(), urls.iter().map(|url| url.to_string()).collect());
+                }
+            }
+
+            // 代理状态、服务器列表或配置变化时重建托盘菜单，并定时刷新托盘中的实时速度
+            app.manage(TrayStatusItems::<tauri::Wry> { speed: Mutex::new(None) });
+            for event in TRAY_REFRESH_EVENTS {
+                let tray_handle = app.handle().clone();
+                app.listen(event, move |_event| {
+                    let tray_handle = tray_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        refresh_tray(&tray_handle).await;
+                    });
+                });
+            }
+            start_tray_speed_refresh(app.handle().clone());
+
+            // 代理、TUN 或连通性状态变化时切换托盘图标
+            for event in tray_icon::TRAY_ICON_EVENTS {
+                let tray_handle = app.handle().clone();
+                app.listen(event, move |_event| {
+                    let tray_handle = tray_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        tray_icon::refresh(&tray_handle).await;
+                    });
+                });
+            }
 
             // 创建系统托盘 - 使用异步任务
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
+                // 先清理上次异常退出遗留的进程和系统代理，再创建托盘并自动连接
+                let adopted = cleanup_orphaned_state(&app_handle).await;
+
                 if let Ok(tray_menu) = build_tray_menu(&app_handle).await {
                     let _tray = TrayIconBuilder::with_id("main-tray")  // 设置托盘ID
                         .icon(app_handle.default_window_icon().unwrap().clone())
@@ -371,16 +1031,52 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                     if let Err(e) = _tray {
                         log_error!("创建系统托盘失败: {}", e);
                     }
+                    tray_icon::refresh(&app_handle).await;
                 } else {
                     log_error!("构建托盘菜单失败");
                 }
+
+                // 无窗口模式下连接命令行指定的服务器
+                if let Some(server_id) = HEADLESS_SERVER.get() {
+                    log_info!("命令行连接服务器: {}", server_id);
+                    if let Err(e) = handle_start_server(&app_handle, server_id).await {
+                        log_error!("命令行连接服务器失败: {}", e);
+                    }
+                    return;
+                }
+
+                // 启动时自动连接上次使用的服务器（TUN 模式按保存的设置一并恢复）
+                if let Ok(app_config) = config::AppConfig::load() {
+                    if app_config.connect_on_startup && !adopted {
+                        let last_server = app_config.current_server.clone().filter(|id| {
+                            app_config.servers.iter().any(|s| &s.id == id) && app_config.is_server_enabled(id)
+                        });
+                        match last_server {
+                            Some(server_id) => {
+                                log_info!("启动时自动连接服务器: {}", server_id);
+                                if let Err(e) = handle_start_server(&app_handle, &server_id).await {
+                                    log_error!("启动时自动连接失败: {}", e);
+                                }
+                            }
+                            None => log_warn!("未找到上次使用的服务器，跳过自动连接"),
+                        }
+                    }
+                }
             });
 
             Ok(())
         })
-        .on_window_event(|_window, event| {
+        .on_window_event(|window, event| {
             match event {
+                WindowEvent::Moved(_) | WindowEvent::Resized(_) if window.label() == "main" => {
+                    window_state::track(window);
+                }
                 WindowEvent::CloseRequested { .. } => {
+                    if window.label() == "main" {
+                        window_state::track(window);
+                        window_state::save();
+                    }
+
                     // 在窗口关闭时停止所有服务
                     tauri::async_runtime::spawn(async move {
                         // 检查并停止代理服务器
@@ -412,8 +1108,25 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         });
 
     builder
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app_handle, event| {
+            if let RunEvent::Exit = event {
+                // 停止流量采样，避免退出过程中继续写入统计数据
+                tauri::async_runtime::block_on(traffic::TrafficManager::instance().stop());
+
+                // 清理透明代理的防火墙规则，避免退出后本机 TCP 流量被重定向到已关闭的端口
+                if let Err(e) = transparent::TransparentManager::instance().stop_sync() {
+                    log_error!("清理透明代理规则失败: {}", e);
+                }
+
+                // 退出前记录窗口状态并写入尚未保存的配置
+                window_state::save();
+                if let Err(e) = config_store::ConfigStore::instance().flush() {
+                    log_error!("保存配置失败: {}", e);
+                }
+            }
+        });
 
     Ok(())
 }
@@ -3,14 +3,16 @@
 // CreateAt: 2024-01-01
 
 use std::fs::{File, OpenOptions};
-use std::io::{self, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex, OnceLock};
-use chrono::Utc;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
 use crate::config::AppConfig;
+use crate::privacy;
 
 /// 日志级别
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     Debug,
     Info,
@@ -27,12 +29,319 @@ impl LogLevel {
             LogLevel::Error => "ERROR",
         }
     }
+
+    /// 从字符串解析日志级别（不区分大小写，兼容 warning）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_uppercase().as_str() {
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" | "WARNING" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// 结构化日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// 时间（RFC3339）
+    pub time: String,
+    /// 日志级别（DEBUG/INFO/WARN/ERROR），无法识别时为空
+    pub level: String,
+    /// 来源："app" 为应用日志，"core" 为代理核心输出
+    pub source: String,
+    /// 日志内容
+    pub message: String,
+}
+
+impl LogEntry {
+    /// 解析应用日志行，格式为 `[2024-12-20 15:30:00.123] [INFO] 消息`（UTC 时间）
+    pub fn from_app_line(line: &str) -> Self {
+        let parsed = line.strip_prefix('[')
+            .and_then(|rest| rest.split_once("] ["))
+            .and_then(|(time, rest)| rest.split_once("] ").map(|(level, message)| (time, level, message)));
+
+        match parsed {
+            Some((time, level, message)) => Self {
+                time: NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.3f")
+                    .map(|t| t.and_utc().to_rfc3339())
+                    .unwrap_or_else(|_| time.to_string()),
+                level: level.to_string(),
+                source: "app".to_string(),
+                message: message.to_string(),
+            },
+            None => Self {
+                time: String::new(),
+                level: String::new(),
+                source: "app".to_string(),
+                message: line.to_string(),
+            },
+        }
+    }
+
+    /// 根据核心输出行构造日志条目，级别从 `[Warning]` 等标记中识别
+    pub fn from_core_line(time: chrono::DateTime<Utc>, line: &str) -> Self {
+        // Xray 输出形如 `[Warning]`，sing-box 输出形如 ` WARN `
+        let level = [
+            ("[Debug]", " DEBUG ", LogLevel::Debug),
+            ("[Info]", " INFO ", LogLevel::Info),
+            ("[Warning]", " WARN ", LogLevel::Warn),
+            ("[Error]", " ERROR ", LogLevel::Error),
+        ]
+            .iter()
+            .find(|(xray, sing_box, _)| line.contains(xray) || line.contains(sing_box))
+            .map(|(_, _, level)| level.as_str().to_string())
+            .unwrap_or_default();
+
+        Self {
+            time: time.to_rfc3339(),
+            level,
+            source: "core".to_string(),
+            message: line.to_string(),
+        }
+    }
+
+    /// 格式化为纯文本行：`时间 [级别] [来源] 内容`
+    pub fn to_text_line(&self) -> String {
+        let level = if self.level.is_empty() { "-" } else { &self.level };
+        format!("{} [{}] [{}] {}", self.time, level, self.source, self.message)
+    }
+
+    /// 日志级别
+    pub fn log_level(&self) -> Option<LogLevel> {
+        LogLevel::parse(&self.level)
+    }
+}
+
+/// 可轮转的日志文件
+/// 文件超过大小上限或日期变化时，将当前文件重命名为 `ruray.<时间>.log` 并新建文件，
+/// 同时删除超过保留天数的旧日志
+struct LogFile {
+    file: File,
+    path: PathBuf,
+    size: u64,
+    date: NaiveDate,
+    max_size: u64,
+    retention_days: u32,
+}
+
+impl LogFile {
+    /// 打开日志文件
+    fn open(path: &Path, max_size: u64, retention_days: u32) -> io::Result<Self> {
+        // 确保日志目录存在
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let metadata = file.metadata()?;
+        let date = metadata.modified()
+            .map(|t| chrono::DateTime::<Local>::from(t).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        let log_file = Self {
+            file,
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            date,
+            max_size,
+            retention_days,
+        };
+        log_file.prune();
+        Ok(log_file)
+    }
+
+    /// 写入一行日志，必要时先轮转
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let today = Local::now().date_naive();
+        let line_len = line.len() as u64 + 1;
+        if self.size > 0 && (today != self.date || self.size + line_len > self.max_size) {
+            self.rotate()?;
+        }
+        self.date = today;
+
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        self.size += line_len;
+        Ok(())
+    }
+
+    /// 轮转日志文件
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = self.rotated_path(&Local::now().format("%Y%m%d-%H%M%S").to_string());
+        std::fs::rename(&self.path, &rotated)?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.prune();
+        Ok(())
+    }
+
+    /// 生成轮转后的文件路径，如 ruray.20241220-153000.log
+    fn rotated_path(&self, suffix: &str) -> PathBuf {
+        let stem = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("ruray");
+        let file_name = match self.path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}.{}.{}", stem, suffix, ext),
+            None => format!("{}.{}", stem, suffix),
+        };
+        self.path.with_file_name(file_name)
+    }
+
+    /// 删除超过保留天数的轮转日志
+    fn prune(&self) {
+        let keep = std::time::Duration::from_secs(self.retention_days as u64 * 24 * 60 * 60);
+        for rotated in rotated_log_files(&self.path) {
+            let expired = std::fs::metadata(&rotated)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age > keep);
+            if expired {
+                let _ = std::fs::remove_file(&rotated);
+            }
+        }
+    }
+}
+
+/// 列出日志文件对应的所有轮转日志（按文件名排序，即按时间从旧到新）
+pub fn rotated_log_files(path: &Path) -> Vec<PathBuf> {
+    let Some(dir) = path.parent() else { return Vec::new() };
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("ruray");
+    let prefix = format!("{}.", stem);
+    let extension = path.extension().and_then(|e| e.to_str());
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|candidate| candidate != path)
+        .filter(|candidate| {
+            let name = candidate.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            name.starts_with(&prefix) && extension.is_none_or(|ext| name.ends_with(&format!(".{}", ext)))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// 读取全部应用日志（包括轮转日志）并解析为日志条目
+/// 不带时间前缀的行视为上一条日志的续行
+///
+/// # 参数
+/// * `path` - 当前日志文件路径
+///
+/// # 返回值
+/// * `io::Result<Vec<LogEntry>>` - 按时间顺序排列的日志条目
+pub fn read_app_log_entries(path: &Path) -> io::Result<Vec<LogEntry>> {
+    let mut files = rotated_log_files(path);
+    files.push(path.to_path_buf());
+
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for file in files {
+        let content = match std::fs::read(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        for line in String::from_utf8_lossy(&content).lines().filter(|l| !l.is_empty()) {
+            let entry = LogEntry::from_app_line(line);
+            match entries.last_mut() {
+                Some(last) if entry.time.is_empty() => {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+                _ => entries.push(entry),
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// 从日志文件末尾读取最后若干行
+/// 从文件末尾按块向前读取，只读取满足条数所需的部分，不会把整个文件读入内存
+///
+/// # 参数
+/// * `path` - 日志文件路径
+/// * `max_lines` - 最多返回的行数
+/// * `min_level` - 最低日志级别，None 表示不过滤
+///
+/// # 返回值
+/// * `io::Result<Vec<String>>` - 按时间顺序排列的日志行
+pub fn read_log_tail(path: &Path, max_lines: usize, min_level: Option<LogLevel>) -> io::Result<Vec<String>> {
+    const CHUNK_SIZE: u64 = 8 * 1024;
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut position = file.seek(SeekFrom::End(0))?;
+    let mut lines: Vec<String> = Vec::new();
+    // 尚未遇到换行符的行首部分
+    let mut pending: Vec<u8> = Vec::new();
+
+    while position > 0 && lines.len() < max_lines {
+        let read_size = CHUNK_SIZE.min(position);
+        position -= read_size;
+        file.seek(SeekFrom::Start(position))?;
+
+        let mut chunk = vec![0u8; read_size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&pending);
+
+        let mut segments = chunk.split(|b| *b == b'\n').collect::<Vec<_>>();
+        // 第一段可能是不完整的行，留到下一轮拼接
+        pending = if position > 0 { segments.remove(0).to_vec() } else { Vec::new() };
+
+        for segment in segments.into_iter().rev() {
+            if lines.len() >= max_lines {
+                break;
+            }
+            push_line(&mut lines, segment, min_level.as_ref());
+        }
+    }
+
+    if position == 0 && !pending.is_empty() && lines.len() < max_lines {
+        push_line(&mut lines, &pending, min_level.as_ref());
+    }
+
+    lines.reverse();
+    Ok(lines)
+}
+
+/// 按级别过滤并收集一行日志
+fn push_line(lines: &mut Vec<String>, raw: &[u8], min_level: Option<&LogLevel>) {
+    let line = String::from_utf8_lossy(raw).trim_end_matches('\r').to_string();
+    if line.is_empty() {
+        return;
+    }
+    if let Some(min_level) = min_level {
+        // 日志格式：[时间] [级别] 消息
+        let level = line.split("] [").nth(1)
+            .and_then(|rest| rest.split(']').next())
+            .and_then(LogLevel::parse);
+        if level.is_none_or(|level| level < *min_level) {
+            return;
+        }
+    }
+    lines.push(line);
 }
 
 /// 日志管理器
 pub struct Logger {
-    file_writer: Option<Arc<Mutex<File>>>,
+    file_writer: Option<Arc<Mutex<LogFile>>>,
     is_debug_mode: bool,
+    min_level: RwLock<LogLevel>,
 }
 
 impl Logger {
@@ -44,51 +353,59 @@ impl Logger {
             // Release模式下，创建日志文件
             match AppConfig::load() {
                 Ok(config) => {
-                    let log_path = Path::new(&config.log_path);
-                    
-                    // 确保日志目录存在
-                    if let Some(parent) = log_path.parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-                    
-                    let file = OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(log_path)?;
-                    
-                    Some(Arc::new(Mutex::new(file)))
+                    let log_file = LogFile::open(
+                        &config.log_file_path(),
+                        config.log_max_size_mb.max(1) * 1024 * 1024,
+                        config.log_retention_days,
+                    )?;
+                    Some(Arc::new(Mutex::new(log_file)))
                 }
                 Err(_) => {
                     // 如果无法加载配置，使用默认路径
                     let default_log_path = "./log/ruray.log";
-                    let log_path = Path::new(default_log_path);
-                    
-                    if let Some(parent) = log_path.parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-                    
-                    let file = OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(log_path)?;
-                    
-                    Some(Arc::new(Mutex::new(file)))
+                    let log_file = LogFile::open(
+                        Path::new(default_log_path),
+                        crate::config::default_log_max_size_mb() * 1024 * 1024,
+                        crate::config::default_log_retention_days(),
+                    )?;
+                    Some(Arc::new(Mutex::new(log_file)))
                 }
             }
         } else {
             None
         };
         
+        let min_level = AppConfig::load()
+            .ok()
+            .map(|config| Self::level_from_config(&config.app_log_level))
+            .unwrap_or(LogLevel::Info);
+
         Ok(Logger {
             file_writer,
             is_debug_mode,
+            min_level: RwLock::new(min_level),
         })
     }
+
+    /// 将配置中的日志级别（与 Xray loglevel 相同的取值）转换为应用日志级别
+    /// `none` 时应用日志仍保留错误信息
+    pub fn level_from_config(value: &str) -> LogLevel {
+        LogLevel::parse(value).unwrap_or(LogLevel::Error)
+    }
+
+    /// 设置最低日志级别
+    pub fn set_level(&self, level: LogLevel) {
+        *self.min_level.write().unwrap() = level;
+    }
     
     /// 写入日志
     pub fn log(&self, level: LogLevel, message: &str) {
+        if level < *self.min_level.read().unwrap() {
+            return;
+        }
+
         let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S%.3f");
-        let formatted_message = format!("[{}] [{}] {}", timestamp, level.as_str(), message);
+        let formatted_message = format!("[{}] [{}] {}", timestamp, level.as_str(), privacy::redact(message));
         
         if self.is_debug_mode {
             // Debug模式下输出到控制台
@@ -100,8 +417,7 @@ impl Logger {
             // Release模式下输出到文件
             if let Some(ref file_writer) = self.file_writer {
                 if let Ok(mut file) = file_writer.lock() {
-                    let _ = writeln!(file, "{}", formatted_message);
-                    let _ = file.flush();
+                    let _ = file.write_line(&formatted_message);
                 }
             }
         }
@@ -136,6 +452,7 @@ pub fn init_logger() -> io::Result<()> {
     let logger = Logger::new().unwrap_or_else(|_| Logger {
         file_writer: None,
         is_debug_mode: true,
+        min_level: RwLock::new(LogLevel::Info),
     });
     let _ = LOGGER.set(logger);
     Ok(())
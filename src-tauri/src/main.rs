@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // 命令行模式（--connect、--status 等）执行完毕后直接退出
+    if let Some(exit_code) = ruray_lib::run_cli() {
+        std::process::exit(exit_code);
+    }
     ruray_lib::run().expect("Failed to run application");
 }
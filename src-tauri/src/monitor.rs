@@ -0,0 +1,477 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2024-12-20
+ */
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::{Duration, Instant};
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::i18n;
+use crate::notify;
+use crate::proxy::ProxyManager;
+
+/// 判定隧道失效所需的连续失败次数，避免偶发超时导致误报
+const FAILURE_THRESHOLD: u32 = 2;
+/// UDP 中继测试使用的 DNS 服务器
+const UDP_TEST_DNS_SERVER: ([u8; 4], u16) = ([1, 1, 1, 1], 53);
+/// UDP 中继测试查询的域名
+const UDP_TEST_DOMAIN: &str = "www.google.com";
+/// UDP 中继测试超时时间
+const UDP_TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 出口 IP 查询服务（依次尝试）
+const IP_ECHO_SERVICES: [&str; 2] = [
+    "https://ipinfo.io/json",
+    "http://ip-api.com/json/?fields=status,message,query,country,countryCode,as",
+];
+/// 出口 IP 查询超时时间
+const IP_LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 出口 IP 信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpInfo {
+    /// IP 地址
+    pub ip: String,
+    /// 国家/地区代码（如 US）
+    pub country: Option<String>,
+    /// 自治系统编号与名称（如 AS15169 Google LLC）
+    pub asn: Option<String>,
+}
+
+/// 出口 IP 查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalIpReport {
+    /// 直连出口
+    pub direct: Option<IpInfo>,
+    /// 直连查询失败原因
+    pub direct_error: Option<String>,
+    /// 经代理的出口
+    pub proxy: Option<IpInfo>,
+    /// 经代理查询失败原因
+    pub proxy_error: Option<String>,
+}
+
+/// 查询出口 IP 与归属信息
+///
+/// # 参数
+/// * `proxy_url` - 使用的代理地址，None 表示直连
+///
+/// # 返回值
+/// * `Result<IpInfo>` - 出口 IP 信息
+///
+/// # 异常
+/// * 当所有查询服务都失败时返回最后一个错误
+pub async fn lookup_external_ip(proxy_url: Option<&str>) -> Result<IpInfo> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(IP_LOOKUP_TIMEOUT)
+        .user_agent("RuRay/1.0.0");
+    builder = match proxy_url {
+        Some(url) => builder.proxy(reqwest::Proxy::all(url)?),
+        // 直连查询需绕过系统代理
+        None => builder.no_proxy(),
+    };
+    let client = builder.build().context("创建查询客户端失败")?;
+
+    let mut last_error = anyhow::anyhow!("没有可用的出口 IP 查询服务");
+    for service in IP_ECHO_SERVICES {
+        let result = async {
+            let value: serde_json::Value = client.get(service)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            parse_ip_echo(&value)
+        }.await;
+
+        match result {
+            Ok(info) => return Ok(info),
+            Err(e) => last_error = e.context(format!("查询 {} 失败", service)),
+        }
+    }
+    Err(last_error)
+}
+
+/// 解析 ipinfo.io 与 ip-api.com 的响应
+fn parse_ip_echo(value: &serde_json::Value) -> Result<IpInfo> {
+    let field = |key: &str| value.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(str::to_string);
+
+    if value.get("status").and_then(|v| v.as_str()) == Some("fail") {
+        return Err(anyhow::anyhow!("查询服务返回错误: {}", field("message").unwrap_or_default()));
+    }
+
+    // ipinfo.io: ip/country/org；ip-api.com: query/countryCode/as
+    let ip = field("ip").or_else(|| field("query")).context("查询结果中缺少 IP 地址")?;
+    Ok(IpInfo {
+        ip,
+        country: field("countryCode").or_else(|| field("country")),
+        asn: field("org").or_else(|| field("as")),
+    })
+}
+
+/// UDP 中继测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdpRelayStatus {
+    /// UDP 中继是否可用
+    pub available: bool,
+    /// DNS 查询往返延迟（毫秒）
+    pub latency_ms: Option<u64>,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 测试时间
+    pub tested_at: String,
+}
+
+/// 连通性监控器
+/// 代理运行期间按配置的间隔通过本地 HTTP 入站访问探测地址，
+/// 检测到隧道失效或恢复时发送桌面通知并发射 `connectivity-changed` 事件，界面与托盘图标据此刷新
+pub struct ConnectivityMonitor {
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// 最近一次判定的连通状态
+    alive: Arc<AtomicBool>,
+    /// 最近一次 UDP 中继测试结果
+    udp_status: Mutex<Option<UdpRelayStatus>>,
+    /// 最近一次错误说明
+    last_error: Mutex<Option<String>>,
+    /// 最近一次检测成功的时间
+    last_healthy_at: Mutex<Option<String>>,
+}
+
+// 全局单例实例
+static CONNECTIVITY_MONITOR: OnceLock<ConnectivityMonitor> = OnceLock::new();
+
+impl ConnectivityMonitor {
+    /// 获取全局连通性监控器实例（单例模式）
+    pub fn instance() -> &'static ConnectivityMonitor {
+        CONNECTIVITY_MONITOR.get_or_init(|| {
+            Self {
+                app_handle: Arc::new(Mutex::new(None)),
+                task: Arc::new(Mutex::new(None)),
+                alive: Arc::new(AtomicBool::new(true)),
+                udp_status: Mutex::new(None),
+                last_error: Mutex::new(None),
+                last_healthy_at: Mutex::new(None),
+            }
+        })
+    }
+
+    /// 启动后台监控任务
+    ///
+    /// # 参数
+    /// * `app_handle` - Tauri 应用句柄，用于通知、托盘和事件
+    pub fn start(&self, app_handle: AppHandle) {
+        {
+            let mut handle_guard = self.app_handle.lock().unwrap();
+            *handle_guard = Some(app_handle);
+        }
+
+        let mut task_guard = self.task.lock().unwrap();
+        if task_guard.is_some() {
+            return;
+        }
+
+        let task = tauri::async_runtime::spawn(async move {
+            let monitor = ConnectivityMonitor::instance();
+            let mut failures = 0u32;
+
+            loop {
+                // 每轮重新读取配置，使间隔和探测地址的修改即时生效
+                let config = AppConfig::load_active().ok();
+                let interval = config.as_ref()
+                    .map(|c| c.connectivity_check_interval.max(5))
+                    .unwrap_or(60);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+
+                let Some(config) = config else { continue };
+                if !config.connectivity_check_enabled || !ProxyManager::instance().is_process_running() {
+                    // 代理未运行时不做判定，并复位状态
+                    failures = 0;
+                    monitor.set_alive(true);
+                    continue;
+                }
+
+                match Self::probe(&config).await {
+                    Ok(()) => {
+                        failures = 0;
+                        *monitor.last_healthy_at.lock().unwrap() = Some(chrono::Utc::now().to_rfc3339());
+                        monitor.set_alive(true);
+                    }
+                    Err(e) => {
+                        // 直连探测也失败说明本地网络不可用，不计入隧道失效
+                        if !Self::direct_reachable(&config).await {
+                            log_warn!("连通性检测失败且直连探测不可达，本地网络可能已断开: {}", e);
+                            monitor.record_error(&format!("本地网络不可用: {:#}", e));
+                            continue;
+                        }
+                        failures += 1;
+                        monitor.record_error(&format!("连通性检测失败: {:#}", e));
+                        log_warn!("连通性检测失败 ({}/{}): {}", failures, FAILURE_THRESHOLD, e);
+                        if failures >= FAILURE_THRESHOLD {
+                            monitor.set_alive(false);
+                        }
+                    }
+                }
+            }
+        });
+
+        *task_guard = Some(task);
+    }
+
+    /// 通过本地代理访问探测地址
+    async fn probe(config: &AppConfig) -> Result<()> {
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::all(config.local_http_proxy_url())?)
+            .timeout(Duration::from_secs(config.connectivity_check_timeout.max(1)))
+            .build()
+            .context("创建探测客户端失败")?;
+
+        let response = client
+            .get(&config.connectivity_check_url)
+            .send()
+            .await
+            .context("探测请求失败")?;
+
+        let status = response.status();
+        if status.is_success() || status.is_redirection() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("探测地址返回异常状态码: {}", status))
+        }
+    }
+
+    /// 不经过代理访问直连探测地址
+    /// 未配置直连探测地址时视为可达
+    async fn direct_reachable(config: &AppConfig) -> bool {
+        if config.connectivity_check_direct_url.trim().is_empty() {
+            return true;
+        }
+        let Ok(client) = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(Duration::from_secs(config.connectivity_check_timeout.max(1)))
+            .build()
+        else {
+            return true;
+        };
+        client.get(&config.connectivity_check_direct_url).send().await.is_ok()
+    }
+
+    /// 最近一次判定的连通状态
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// 最近一次错误说明
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// 最近一次检测成功的时间（RFC 3339）
+    pub fn last_healthy_at(&self) -> Option<String> {
+        self.last_healthy_at.lock().unwrap().clone()
+    }
+
+    /// 记录错误说明，供代理状态展示
+    ///
+    /// # 参数
+    /// * `error` - 错误说明
+    pub fn record_error(&self, error: &str) {
+        *self.last_error.lock().unwrap() = Some(error.to_string());
+    }
+
+    /// 清除上一次连接的检测记录，代理启动时调用
+    pub fn reset_health(&self) {
+        *self.last_error.lock().unwrap() = None;
+        *self.last_healthy_at.lock().unwrap() = None;
+    }
+
+    /// 获取最近一次 UDP 中继测试结果
+    pub fn udp_status(&self) -> Option<UdpRelayStatus> {
+        self.udp_status.lock().unwrap().clone()
+    }
+
+    /// 测试 UDP 中继是否可用
+    /// 通过 SOCKS 入站的 UDP ASSOCIATE 发送一次 DNS 查询，结果会被保存并在代理状态中报告
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    ///
+    /// # 返回值
+    /// * `UdpRelayStatus` - 测试结果
+    pub async fn test_udp_relay(&self, config: &AppConfig) -> UdpRelayStatus {
+        let result = tokio::time::timeout(UDP_TEST_TIMEOUT, Self::probe_udp(config))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("等待 DNS 响应超时")));
+
+        let status = match result {
+            Ok(latency_ms) => {
+                log_info!("UDP 中继测试成功，延迟 {} ms", latency_ms);
+                UdpRelayStatus {
+                    available: true,
+                    latency_ms: Some(latency_ms),
+                    error: None,
+                    tested_at: chrono::Utc::now().to_rfc3339(),
+                }
+            }
+            Err(e) => {
+                log_warn!("UDP 中继测试失败: {}", e);
+                UdpRelayStatus {
+                    available: false,
+                    latency_ms: None,
+                    error: Some(e.to_string()),
+                    tested_at: chrono::Utc::now().to_rfc3339(),
+                }
+            }
+        };
+
+        *self.udp_status.lock().unwrap() = Some(status.clone());
+        status
+    }
+
+    /// 通过 SOCKS5 UDP ASSOCIATE 发送 DNS 查询
+    ///
+    /// # 返回值
+    /// * `Result<u64>` - 查询往返延迟（毫秒）
+    async fn probe_udp(config: &AppConfig) -> Result<u64> {
+        if !config.inbound_udp_enabled {
+            return Err(anyhow::anyhow!("SOCKS 入站未启用 UDP"));
+        }
+
+        let started = Instant::now();
+        // UDP 关联在该 TCP 连接关闭前有效
+        let mut control = TcpStream::connect(("127.0.0.1", config.socks_port))
+            .await
+            .context("无法连接 SOCKS 入站")?;
+
+        let credentials = config.inbound_credentials();
+        let offered_method = if credentials.is_some() { 0x02 } else { 0x00 };
+        control.write_all(&[0x05, 0x01, offered_method]).await?;
+        let mut method = [0u8; 2];
+        control.read_exact(&mut method).await.context("SOCKS 握手失败")?;
+        if method != [0x05, offered_method] {
+            return Err(anyhow::anyhow!("SOCKS 入站要求认证，无法进行 UDP 测试"));
+        }
+        if let Some(user) = credentials {
+            // RFC 1929 用户名密码认证
+            let mut auth = vec![0x01, user.username.len() as u8];
+            auth.extend_from_slice(user.username.as_bytes());
+            auth.push(user.password.len() as u8);
+            auth.extend_from_slice(user.password.as_bytes());
+            control.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            control.read_exact(&mut status).await.context("SOCKS 认证失败")?;
+            if status[1] != 0x00 {
+                return Err(anyhow::anyhow!("SOCKS 入站用户名或密码错误"));
+            }
+        }
+
+        control.write_all(&[0x05, 0x03, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await?;
+        let mut head = [0u8; 4];
+        control.read_exact(&mut head).await.context("UDP ASSOCIATE 请求失败")?;
+        if head[1] != 0x00 {
+            return Err(anyhow::anyhow!("SOCKS 入站拒绝了 UDP ASSOCIATE 请求 (错误码 {})", head[1]));
+        }
+        let relay_ip = match head[3] {
+            0x01 => {
+                let mut ip = [0u8; 4];
+                control.read_exact(&mut ip).await?;
+                IpAddr::V4(Ipv4Addr::from(ip))
+            }
+            0x04 => {
+                let mut ip = [0u8; 16];
+                control.read_exact(&mut ip).await?;
+                IpAddr::V6(Ipv6Addr::from(ip))
+            }
+            _ => return Err(anyhow::anyhow!("不支持的 UDP 中继地址类型: {}", head[3])),
+        };
+        let mut port = [0u8; 2];
+        control.read_exact(&mut port).await?;
+        let relay_ip = if relay_ip.is_unspecified() { IpAddr::V4(Ipv4Addr::LOCALHOST) } else { relay_ip };
+        let relay = SocketAddr::new(relay_ip, u16::from_be_bytes(port));
+
+        let query_id: u16 = rand::random();
+        let (dns_ip, dns_port) = UDP_TEST_DNS_SERVER;
+        let mut packet = vec![0x00, 0x00, 0x00, 0x01];
+        packet.extend_from_slice(&dns_ip);
+        packet.extend_from_slice(&dns_port.to_be_bytes());
+        packet.extend_from_slice(&Self::build_dns_query(query_id, UDP_TEST_DOMAIN));
+
+        let socket = UdpSocket::bind(if relay.is_ipv4() { "127.0.0.1:0" } else { "[::1]:0" })
+            .await
+            .context("无法创建 UDP 套接字")?;
+        socket.send_to(&packet, relay).await.context("发送 UDP 数据失败")?;
+
+        let mut buffer = [0u8; 1500];
+        let size = socket.recv(&mut buffer).await.context("接收 UDP 数据失败")?;
+        let response = &buffer[..size];
+
+        // 跳过 SOCKS5 UDP 头部：RSV(2) FRAG(1) ATYP(1) 地址 端口(2)
+        let header_len = match response.get(3) {
+            Some(0x01) => 4 + 4 + 2,
+            Some(0x04) => 4 + 16 + 2,
+            Some(0x03) => 4 + 1 + *response.get(4).unwrap_or(&0) as usize + 2,
+            _ => return Err(anyhow::anyhow!("UDP 响应格式无效")),
+        };
+        let dns = response.get(header_len..).unwrap_or_default();
+        if dns.len() < 12 || u16::from_be_bytes([dns[0], dns[1]]) != query_id || dns[2] & 0x80 == 0 {
+            return Err(anyhow::anyhow!("收到无效的 DNS 响应"));
+        }
+
+        drop(control);
+        Ok(started.elapsed().as_millis() as u64)
+    }
+
+    /// 构造 A 记录 DNS 查询报文
+    fn build_dns_query(id: u16, domain: &str) -> Vec<u8> {
+        let mut query = Vec::with_capacity(32);
+        query.extend_from_slice(&id.to_be_bytes());
+        // 标准查询，期望递归；1 个问题
+        query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        for label in domain.split('.') {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.push(0x00);
+        // QTYPE=A, QCLASS=IN
+        query.extend_from_slice(&[0x00, 0x01, 0x00, 0x01]);
+        query
+    }
+
+    /// 更新连通状态，仅在状态发生变化时通知
+    fn set_alive(&self, alive: bool) {
+        if self.alive.swap(alive, Ordering::SeqCst) == alive {
+            return;
+        }
+
+        let app_handle = match self.app_handle.lock().unwrap().clone() {
+            Some(handle) => handle,
+            None => return,
+        };
+
+        let body = if alive {
+            log_info!("代理连通性已恢复");
+            i18n::t("notify.connection_restored")
+        } else {
+            log_warn!("检测到代理隧道失效");
+            i18n::t("notify.connection_lost")
+        };
+        notify::show(body);
+
+        let _ = app_handle.emit("connectivity-changed", serde_json::json!({
+            "alive": alive
+        }));
+    }
+}
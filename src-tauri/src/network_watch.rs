@@ -0,0 +1,146 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_error, log_info, log_warn};
+
+use crate::commands;
+use crate::config::AppConfig;
+use crate::proxy::ProxyManager;
+use crate::system::SystemManager;
+use crate::tun::TunManager;
+
+/// 网络状态检查间隔
+const NETWORK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 两次检查之间的实际间隔超过该值时，判定系统曾经休眠
+const RESUME_GAP: Duration = Duration::from_secs(30);
+/// 检测到变化后等待网络稳定的时间
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+/// 网络变化原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NetworkChange {
+    /// 本机出口地址变化（切换 Wi-Fi、插拔网线等）
+    AddressChanged,
+    /// 系统从休眠中恢复
+    Resumed,
+}
+
+impl NetworkChange {
+    fn name(&self) -> &'static str {
+        match self {
+            NetworkChange::AddressChanged => "address-changed",
+            NetworkChange::Resumed => "resumed",
+        }
+    }
+}
+
+/// 启动网络变化监控任务
+/// 定期检查本机出口地址，并通过两次检查之间的实际时间差识别系统休眠恢复。
+/// 代理运行期间检测到变化时，系统代理被重置则重新设置，TUN 模式运行中则重启 TUN 设备以恢复路由，
+/// 处理后向前端发射 `network-changed` 事件
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄
+pub fn start_network_watch(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_ip = SystemManager::lan_ip();
+        let mut last_check = SystemTime::now();
+
+        loop {
+            tokio::time::sleep(NETWORK_CHECK_INTERVAL).await;
+
+            // 休眠期间计时器暂停，恢复后实际经过的时间会远大于检查间隔
+            let now = SystemTime::now();
+            let elapsed = now.duration_since(last_check).unwrap_or_default();
+            last_check = now;
+
+            let ip = SystemManager::lan_ip();
+            let change = if elapsed > NETWORK_CHECK_INTERVAL + RESUME_GAP {
+                Some(NetworkChange::Resumed)
+            } else if ip.is_some() && ip != last_ip {
+                Some(NetworkChange::AddressChanged)
+            } else {
+                None
+            };
+            // 断网期间保留上一个地址，重新联网到同一网络时不重复处理
+            if ip.is_some() {
+                last_ip = ip;
+            }
+
+            let Some(change) = change else { continue };
+            let Ok(config) = AppConfig::load() else { continue };
+            if !config.reapply_on_network_change || !ProxyManager::instance().is_process_running() {
+                continue;
+            }
+
+            log_info!("检测到网络变化 ({})，检查代理设置", change.name());
+            tokio::time::sleep(SETTLE_DELAY).await;
+            let reapplied_proxy = reapply_system_proxy().await;
+            let restarted_tun = restart_tun().await;
+
+            let _ = app_handle.emit("network-changed", serde_json::json!({
+                "reason": change.name(),
+                "ip": ip.map(|ip| ip.to_string()),
+                "system_proxy_reapplied": reapplied_proxy,
+                "tun_restarted": restarted_tun,
+            }));
+        }
+    });
+}
+
+/// 系统代理被重置时重新设置
+///
+/// # 返回值
+/// * `bool` - 是否重新设置了系统代理
+async fn reapply_system_proxy() -> bool {
+    let Ok(config) = AppConfig::load_active() else { return false };
+    // 直连模式不设置系统代理
+    if config.proxy_mode == "direct" {
+        return false;
+    }
+
+    let system_manager = SystemManager::new();
+    if system_manager.is_proxy_pointing_to(&[config.http_port, config.socks_port, config.pac_port]).await {
+        return false;
+    }
+
+    log_warn!("系统代理设置已被重置，重新设置");
+    match commands::apply_system_proxy(&config, false).await {
+        Ok(()) => true,
+        Err(e) => {
+            log_error!("重新设置系统代理失败: {}", e.message);
+            false
+        }
+    }
+}
+
+/// TUN 模式运行中时重启 TUN 设备，以重建被网络切换破坏的路由
+///
+/// # 返回值
+/// * `bool` - 是否重启了 TUN 设备
+async fn restart_tun() -> bool {
+    let tun_manager = TunManager::instance();
+    if !tun_manager.is_running().await {
+        return false;
+    }
+    let Ok(config) = AppConfig::load() else { return false };
+
+    log_info!("重启TUN模式以恢复路由");
+    if let Err(e) = tun_manager.stop().await {
+        log_error!("停止TUN模式失败: {}", e);
+        return false;
+    }
+    if let Err(e) = tun_manager.start(config.tun_config).await {
+        log_error!("重新启动TUN模式失败: {}", e);
+        return false;
+    }
+    true
+}
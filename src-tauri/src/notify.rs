@@ -0,0 +1,136 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+// 导入日志宏
+use crate::log_warn;
+
+use crate::config::AppConfig;
+use crate::i18n;
+
+/// 用于发送通知的应用句柄，启动时设置
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// 通知事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// 代理已连接
+    Connected,
+    /// 代理已断开
+    Disconnected,
+    /// 运行中切换了服务器
+    ServerSwitched,
+    /// 代理核心异常退出
+    CoreCrashed,
+    /// 订阅已更新
+    SubscriptionUpdated,
+    /// 代理核心有新版本
+    UpdateAvailable,
+}
+
+impl NotifyEvent {
+    /// 通知正文在 `i18n` 文本表中的键
+    fn text_key(&self) -> &'static str {
+        match self {
+            NotifyEvent::Connected => "notify.connected",
+            NotifyEvent::Disconnected => "notify.disconnected",
+            NotifyEvent::ServerSwitched => "notify.server_switched",
+            NotifyEvent::CoreCrashed => "notify.core_crashed",
+            NotifyEvent::SubscriptionUpdated => "notify.subscription_updated",
+            NotifyEvent::UpdateAvailable => "notify.update_available",
+        }
+    }
+}
+
+/// 桌面通知开关，每类事件可单独关闭
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// 代理已连接
+    #[serde(default = "default_true")]
+    pub connected: bool,
+    /// 代理已断开
+    #[serde(default = "default_true")]
+    pub disconnected: bool,
+    /// 切换了服务器
+    #[serde(default = "default_true")]
+    pub server_switched: bool,
+    /// 代理核心异常退出
+    #[serde(default = "default_true")]
+    pub core_crashed: bool,
+    /// 订阅已更新
+    #[serde(default = "default_true")]
+    pub subscription_updated: bool,
+    /// 代理核心有新版本
+    #[serde(default = "default_true")]
+    pub update_available: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            connected: true,
+            disconnected: true,
+            server_switched: true,
+            core_crashed: true,
+            subscription_updated: true,
+            update_available: true,
+        }
+    }
+}
+
+impl NotificationConfig {
+    /// 事件的通知是否开启
+    pub fn is_enabled(&self, event: NotifyEvent) -> bool {
+        match event {
+            NotifyEvent::Connected => self.connected,
+            NotifyEvent::Disconnected => self.disconnected,
+            NotifyEvent::ServerSwitched => self.server_switched,
+            NotifyEvent::CoreCrashed => self.core_crashed,
+            NotifyEvent::SubscriptionUpdated => self.subscription_updated,
+            NotifyEvent::UpdateAvailable => self.update_available,
+        }
+    }
+}
+
+/// 为通知开关提供默认值
+fn default_true() -> bool {
+    true
+}
+
+/// 设置发送通知使用的应用句柄
+pub fn init(app_handle: AppHandle) {
+    let _ = APP_HANDLE.set(app_handle);
+}
+
+/// 发送事件通知
+/// 该事件的通知开关关闭时忽略；正文按当前语言选择，`args` 依次替换文本中的 `{}`
+///
+/// # 参数
+/// * `event` - 通知事件
+/// * `args` - 正文参数，如服务器名称
+pub fn notify(event: NotifyEvent, args: &[&str]) {
+    let enabled = AppConfig::load()
+        .map(|config| config.notifications.is_enabled(event))
+        .unwrap_or(true);
+    if enabled {
+        show(&i18n::format(event.text_key(), args));
+    }
+}
+
+/// 发送桌面通知，不检查通知开关
+///
+/// # 参数
+/// * `body` - 通知正文
+pub fn show(body: &str) {
+    let Some(app_handle) = APP_HANDLE.get() else { return };
+    if let Err(e) = app_handle.notification().builder().title(i18n::t("notify.title")).body(body).show() {
+        log_warn!("发送桌面通知失败: {}", e);
+    }
+}
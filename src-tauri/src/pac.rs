@@ -0,0 +1,190 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// PAC 脚本
+// 由路由规则生成 PAC：出站为 direct 的规则返回 DIRECT，其余规则与未命中的地址交给本地代理，
+// 由核心按完整的路由规则处理。PAC 只能表达 `domain:`、`full:`、`keyword:`、纯字符串（子串匹配）
+// 与 IPv4 地址段，`geosite:`、`geoip:`、`regexp:` 等条目生成时跳过并在脚本开头注明。
+// 测试 URL 时在 Rust 中按同一份规则列表求值，结果与生成的脚本一致
+
+use anyhow::Result;
+use ipnet::Ipv4Net;
+use serde::Serialize;
+use serde_json::json;
+use std::net::Ipv4Addr;
+
+use crate::config::AppConfig;
+use crate::routing;
+
+/// PAC 规则的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PacMatchKind {
+    /// 域名及其子域名
+    Domain,
+    /// 完整域名
+    Full,
+    /// 包含子串
+    Keyword,
+    /// IPv4 地址段（仅匹配 IP 形式的主机名，不做 DNS 解析）
+    Cidr,
+}
+
+/// 单条 PAC 规则
+#[derive(Debug, Clone)]
+struct PacRule {
+    kind: PacMatchKind,
+    value: String,
+    direct: bool,
+    /// 原始规则条目，用于测试结果展示
+    source: String,
+}
+
+impl PacRule {
+    fn matches(&self, host: &str) -> bool {
+        match self.kind {
+            PacMatchKind::Domain => crate::geodata::domain_matches(host, &self.value),
+            PacMatchKind::Full => host == self.value,
+            PacMatchKind::Keyword => host.contains(&self.value),
+            PacMatchKind::Cidr => match (host.parse::<Ipv4Addr>(), self.value.parse::<Ipv4Net>()) {
+                (Ok(ip), Ok(net)) => net.contains(&ip),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// PAC 测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PacTestResult {
+    /// 测试的主机名
+    pub host: String,
+    /// PAC 返回值，如 `DIRECT` 或 `PROXY 127.0.0.1:10086; SOCKS5 127.0.0.1:10087`
+    pub result: String,
+    /// 是否直连
+    pub direct: bool,
+    /// 命中的规则条目，未命中任何规则时为空
+    pub matched_rule: Option<String>,
+}
+
+/// 本地代理在 PAC 中的写法
+fn proxy_directive(config: &AppConfig) -> String {
+    format!("PROXY 127.0.0.1:{}; SOCKS5 127.0.0.1:{}", config.http_port, config.socks_port)
+}
+
+/// 将路由规则转换为 PAC 规则
+///
+/// # 返回值
+/// * `Result<(Vec<PacRule>, Vec<String>)>` - PAC 规则与无法表达而跳过的条目
+fn build_rules(config: &AppConfig) -> Result<(Vec<PacRule>, Vec<String>)> {
+    let (rules, _) = routing::collect_rules(config)?;
+    let mut pac_rules = Vec::new();
+    let mut skipped = Vec::new();
+
+    for rule in &rules {
+        let direct = rule.outbound_tag == "direct";
+        for entry in rule.domain.iter().flatten() {
+            let entry_lower = entry.to_ascii_lowercase();
+            let (kind, value) = match entry_lower.split_once(':') {
+                Some(("domain", value)) => (PacMatchKind::Domain, value),
+                Some(("full", value)) => (PacMatchKind::Full, value),
+                Some(("keyword", value)) => (PacMatchKind::Keyword, value),
+                Some(_) => {
+                    skipped.push(entry.clone());
+                    continue;
+                }
+                // 与 Xray 一致，没有前缀的字符串按子串匹配
+                None => (PacMatchKind::Keyword, entry_lower.as_str()),
+            };
+            pac_rules.push(PacRule { kind, value: value.to_string(), direct, source: entry.clone() });
+        }
+        for entry in rule.ip.iter().flatten() {
+            let net = entry.parse::<Ipv4Net>()
+                .ok()
+                .or_else(|| entry.parse::<Ipv4Addr>().ok().map(Ipv4Net::from));
+            match net {
+                Some(net) => pac_rules.push(PacRule {
+                    kind: PacMatchKind::Cidr,
+                    value: net.to_string(),
+                    direct,
+                    source: entry.clone(),
+                }),
+                None => skipped.push(entry.clone()),
+            }
+        }
+    }
+    Ok((pac_rules, skipped))
+}
+
+/// 生成 PAC 脚本
+///
+/// # 参数
+/// * `config` - 应用配置
+///
+/// # 返回值
+/// * `Result<String>` - PAC 脚本内容
+pub fn generate(config: &AppConfig) -> Result<String> {
+    let (rules, skipped) = build_rules(config)?;
+    let rule_list: Vec<serde_json::Value> = rules.iter()
+        .map(|rule| match rule.kind {
+            PacMatchKind::Cidr => {
+                let net: Ipv4Net = rule.value.parse()?;
+                Ok(json!([rule.kind, net.network().to_string(), rule.direct, net.netmask().to_string()]))
+            }
+            _ => Ok(json!([rule.kind, rule.value, rule.direct])),
+        })
+        .collect::<Result<_>>()?;
+
+    let mut script = String::from("// RuRay 根据路由规则生成的 PAC 脚本\n");
+    if !skipped.is_empty() {
+        script.push_str(&format!("// 以下条目无法在 PAC 中表达，已交由代理处理: {}\n", skipped.join(", ")));
+    }
+    script.push_str(&format!(
+        r#"var proxy = {proxy};
+var rules = {rules};
+
+function FindProxyForURL(url, host) {{
+    host = host.toLowerCase();
+    var isIp = /^\d+\.\d+\.\d+\.\d+$/.test(host);
+    for (var i = 0; i < rules.length; i++) {{
+        var r = rules[i], kind = r[0], value = r[1];
+        var hit = kind === "full" ? host === value
+            : kind === "domain" ? (host === value || dnsDomainIs(host, "." + value))
+            : kind === "keyword" ? host.indexOf(value) >= 0
+            : isIp && isInNet(host, value, r[3]);
+        if (hit) {{
+            return r[2] ? "DIRECT" : proxy;
+        }}
+    }}
+    return proxy;
+}}
+"#,
+        proxy = serde_json::to_string(&proxy_directive(config))?,
+        rules = serde_json::to_string(&rule_list)?,
+    ));
+    Ok(script)
+}
+
+/// 按 PAC 规则测试 URL
+///
+/// # 参数
+/// * `config` - 应用配置
+/// * `url` - URL、域名或 IP
+///
+/// # 返回值
+/// * `Result<PacTestResult>` - PAC 返回值与命中的规则
+pub fn test(config: &AppConfig, url: &str) -> Result<PacTestResult> {
+    let host = routing::parse_target(url)?;
+    let (rules, _) = build_rules(config)?;
+    let matched = rules.iter().find(|rule| rule.matches(&host));
+    let direct = matched.is_some_and(|rule| rule.direct);
+    Ok(PacTestResult {
+        result: if direct { "DIRECT".to_string() } else { proxy_directive(config) },
+        direct,
+        matched_rule: matched.map(|rule| rule.source.clone()),
+        host,
+    })
+}
@@ -0,0 +1,117 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 隐私模式
+// 启用后应用日志、核心输出缓存、导出的日志与崩溃报告中的 UUID、密码、服务器地址等替换为掩码，
+// 便于公开分享日志。需要隐藏的内容取自当前配置（服务器地址与凭据、订阅地址、入站用户密码等），
+// 另外任何形如 UUID 的文本都会被隐藏。配置变化时由配置存储调用 `refresh` 更新
+
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+use crate::config::AppConfig;
+
+/// 替换敏感内容使用的掩码
+const MASK: &str = "******";
+/// 服务器配置中需要隐藏的字段
+const SECRET_SERVER_FIELDS: [&str; 9] = ["uuid", "id", "password", "pass", "username", "privateKey", "publicKey", "sni", "host"];
+/// 短于该长度的值不隐藏，避免误伤普通文本
+const MIN_SECRET_LEN: usize = 4;
+/// 不需要隐藏的本机地址
+const LOCAL_ADDRESSES: [&str; 4] = ["127.0.0.1", "localhost", "::1", "0.0.0.0"];
+
+/// 需要隐藏的内容，隐私模式关闭时为空
+static SECRETS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// 根据配置更新隐私模式状态与需要隐藏的内容
+///
+/// # 参数
+/// * `config` - 当前配置
+pub fn refresh(config: &AppConfig) {
+    let secrets = config.privacy_mode.then(|| collect_secrets(config));
+    *SECRETS.write().unwrap() = secrets;
+}
+
+/// 收集配置中需要隐藏的内容，按长度从长到短排列，避免较短的值先替换掉较长值的一部分
+fn collect_secrets(config: &AppConfig) -> Vec<String> {
+    let mut secrets: Vec<String> = Vec::new();
+    for server in &config.servers {
+        secrets.push(server.address.clone());
+        for field in SECRET_SERVER_FIELDS {
+            if let Some(value) = server.config.get(field).and_then(|v| v.as_str()) {
+                secrets.push(value.to_string());
+            }
+        }
+    }
+    secrets.extend(config.subscriptions.iter().map(|s| s.url.clone()));
+    secrets.extend(config.inbound_users.iter().map(|u| u.password.clone()));
+    secrets.push(config.control_api.token.clone());
+    secrets.push(config.webdav.url.clone());
+    secrets.push(config.webdav.password.clone());
+
+    secrets.retain(|s| s.len() >= MIN_SECRET_LEN && !LOCAL_ADDRESSES.contains(&s.as_str()));
+    secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    secrets.dedup();
+    secrets
+}
+
+/// 隐藏文本中的敏感内容，隐私模式关闭时原样返回
+///
+/// # 参数
+/// * `text` - 原始文本
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let guard = SECRETS.read().unwrap();
+    let Some(secrets) = guard.as_ref() else {
+        return Cow::Borrowed(text);
+    };
+
+    let mut output = mask_uuids(text);
+    for secret in secrets {
+        if output.contains(secret.as_str()) {
+            output = Cow::Owned(output.replace(secret.as_str(), MASK));
+        }
+    }
+    output
+}
+
+/// 隐藏形如 `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` 的 UUID
+fn mask_uuids(text: &str) -> Cow<'_, str> {
+    const UUID_LEN: usize = 36;
+    let bytes = text.as_bytes();
+    if bytes.len() < UUID_LEN {
+        return Cow::Borrowed(text);
+    }
+
+    let is_uuid_at = |start: usize| {
+        bytes[start..start + UUID_LEN].iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+    };
+    let is_boundary = |index: Option<&u8>| index.is_none_or(|b| !b.is_ascii_alphanumeric());
+
+    let mut output = String::new();
+    let mut copied = 0;
+    let mut index = 0;
+    while index + UUID_LEN <= bytes.len() {
+        let before = index.checked_sub(1).and_then(|i| bytes.get(i));
+        if is_boundary(before) && is_uuid_at(index) && is_boundary(bytes.get(index + UUID_LEN)) {
+            output.push_str(&text[copied..index]);
+            output.push_str(MASK);
+            index += UUID_LEN;
+            copied = index;
+        } else {
+            index += 1;
+        }
+    }
+
+    if copied == 0 {
+        Cow::Borrowed(text)
+    } else {
+        output.push_str(&text[copied..]);
+        Cow::Owned(output)
+    }
+}
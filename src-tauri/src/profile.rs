@@ -0,0 +1,133 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{AppConfig, CoreLaunchOptions, RoutingConfig};
+use crate::core_backend::CoreKind;
+
+/// 配置方案（如 工作/家庭/出行）
+/// 保存一组可整体切换的设置；当前激活方案的设置同步在 AppConfig 顶层字段中，
+/// 其余模块仍只读取 AppConfig
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// 方案ID
+    pub id: String,
+    /// 方案名称
+    pub name: String,
+    /// 代理模式
+    pub proxy_mode: String,
+    /// HTTP 入站端口
+    pub http_port: u16,
+    /// SOCKS 入站端口
+    pub socks_port: u16,
+    /// PAC 服务端口
+    pub pac_port: u16,
+    /// 路由配置
+    pub routing_config: RoutingConfig,
+    /// 是否启用TUN模式
+    #[serde(default)]
+    pub tun_enabled: bool,
+    /// 代理核心
+    #[serde(default)]
+    pub core: CoreKind,
+    /// 启动代理核心时额外的环境变量与参数
+    #[serde(default)]
+    pub core_launch: CoreLaunchOptions,
+    /// 该方案启用的服务器ID列表，None 表示全部服务器可用
+    #[serde(default)]
+    pub enabled_servers: Option<Vec<String>>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Profile {
+    /// 根据当前应用配置创建方案
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    /// * `name` - 方案名称
+    ///
+    /// # 返回值
+    /// * `Profile` - 新方案，ID 随机生成
+    pub fn capture(config: &AppConfig, name: &str) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            proxy_mode: config.proxy_mode.clone(),
+            http_port: config.http_port,
+            socks_port: config.socks_port,
+            pac_port: config.pac_port,
+            routing_config: config.routing_config.clone(),
+            tun_enabled: config.tun_enabled,
+            core: config.core,
+            core_launch: config.core_launch.clone(),
+            enabled_servers: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// 将应用配置中的当前设置写回本方案
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    pub fn update_from(&mut self, config: &AppConfig) {
+        self.proxy_mode = config.proxy_mode.clone();
+        self.http_port = config.http_port;
+        self.socks_port = config.socks_port;
+        self.pac_port = config.pac_port;
+        self.routing_config = config.routing_config.clone();
+        self.tun_enabled = config.tun_enabled;
+        self.core = config.core;
+        self.core_launch = config.core_launch.clone();
+        self.updated_at = chrono::Utc::now().to_rfc3339();
+    }
+
+    /// 将本方案的设置应用到应用配置
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    pub fn apply_to(&self, config: &mut AppConfig) {
+        config.proxy_mode = self.proxy_mode.clone();
+        config.http_port = self.http_port;
+        config.socks_port = self.socks_port;
+        config.pac_port = self.pac_port;
+        config.routing_config = self.routing_config.clone();
+        config.tun_enabled = self.tun_enabled;
+        config.core = self.core;
+        config.core_launch = self.core_launch.clone();
+        config.active_profile = Some(self.id.clone());
+    }
+
+    /// 检查服务器在本方案中是否启用
+    ///
+    /// # 参数
+    /// * `server_id` - 服务器ID
+    pub fn is_server_enabled(&self, server_id: &str) -> bool {
+        self.enabled_servers
+            .as_ref()
+            .is_none_or(|ids| ids.iter().any(|id| id == server_id))
+    }
+
+    /// 将来自其他设备的方案的核心启动参数替换为本机同ID方案的值，本机没有该方案时清空
+    /// 启动参数与环境变量会传给可能以管理员权限运行的核心进程，不能随导入、恢复或同步的配置带入
+    ///
+    /// # 参数
+    /// * `profiles` - 导入的方案
+    /// * `local` - 本机现有的方案
+    pub fn keep_local_core_launch(profiles: &mut [Profile], local: &[Profile]) {
+        for profile in profiles {
+            profile.core_launch = local
+                .iter()
+                .find(|p| p.id == profile.id)
+                .map(|p| p.core_launch.clone())
+                .unwrap_or_default();
+        }
+    }
+}
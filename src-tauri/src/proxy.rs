@@ -5,30 +5,137 @@
  */
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
 use tokio::time::Duration;
 use tokio::process::Command as TokioCommand;
 use sysinfo::System;
 
 // 导入日志宏
-use crate::{log_info, log_error};
+use crate::{log_info, log_error, log_warn};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
 use crate::commands::{ProxyStatus, ServerInfo};
-use crate::config::AppConfig;
+use crate::config::{AppConfig, FragmentConfig, InboundConfig, InboundProtocol, MuxConfig};
+use crate::config_store::ConfigStore;
+use crate::connection_log;
+use crate::core_backend;
+use crate::domain_override::DomainOverrides;
+use crate::error::{AppError, ErrorCode};
+use crate::hooks::{self, HookEvent};
+use crate::notify::{self, NotifyEvent};
+use crate::privacy;
+use crate::smart_routing::SmartRouting;
+use crate::monitor::ConnectivityMonitor;
+use crate::system::SystemManager;
+use crate::template::TemplateVars;
+use crate::traffic::{TrafficManager, TrafficUsage};
+use crate::transparent::{self, TransparentManager};
+use crate::bandwidth::BandwidthLimiter;
 use crate::tun::TunManager;
+use crate::xray_api::XrayApiClient;
+
+/// 进程守护检查间隔
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+/// 重启退避的初始等待时间
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// 重启退避的最长等待时间
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// 进程稳定运行超过该时长后重置重启计数
+const RESTART_STABLE_UPTIME: Duration = Duration::from_secs(60);
+/// 保留的核心输出行数（供日志查看与导出）
+const OUTPUT_BUFFER_LINES: usize = 2000;
+/// 测速等临时实例的配置文件名前缀
+const TEMP_INSTANCE_CONFIG_PREFIX: &str = "xray_speedtest_";
+/// 等待核心入站开始接受连接的超时时间
+const INBOUND_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 是否为代理核心进程名（xray/sing-box）
+fn is_core_process_name(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "xray" | "xray.exe" | "sing-box" | "sing-box.exe")
+}
+
+/// 可接管的核心进程
+/// 上次运行遗留、仍在运行的 Xray 进程，端口从其配置文件中恢复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdoptableCore {
+    pub pid: u32,
+    pub server_id: String,
+    pub server_name: String,
+    pub config_path: String,
+    pub http_port: Option<u16>,
+    pub socks_port: Option<u16>,
+    pub api_port: Option<u16>,
+}
+
+/// 检查指定PID的进程是否存在
+fn is_pid_alive(pid: u32) -> bool {
+    let mut system = System::new();
+    system.refresh_processes();
+    system.process(sysinfo::Pid::from_u32(pid)).is_some()
+}
+
+/// 端口冲突信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortConflict {
+    /// 端口用途（http/socks/pac/api）
+    pub name: String,
+    /// 端口号
+    pub port: u16,
+    /// 占用端口的进程ID
+    pub pid: Option<u32>,
+    /// 占用端口的进程名称
+    pub process: Option<String>,
+}
+
+/// 异常退出记录中附带的输出行数
+const CRASH_OUTPUT_LINES: usize = 50;
+
+/// Xray Core 异常退出记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// 退出时间
+    pub exited_at: String,
+    /// 退出原因（退出状态或重启失败的错误信息）
+    pub reason: String,
+    /// 退出前的最后几行输出
+    pub output: Vec<String>,
+}
 
 /// 代理管理器
+/// 启动 Xray Core 后由守护任务监视进程，异常退出时按指数退避自动重启，
+/// 超过 `xray_max_restarts` 次仍失败则停止代理并清除系统代理
 pub struct ProxyManager {
     process: Arc<Mutex<Option<Child>>>,
+    /// 接管的上次运行遗留的核心进程，没有子进程句柄，只能按 PID 监视与终止
+    adopted_pid: Mutex<Option<u32>>,
     start_time: Arc<Mutex<Option<Instant>>>,
     current_server: Arc<Mutex<Option<String>>>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    supervisor: Arc<Mutex<Option<JoinHandle<()>>>>,
+    restarting: AtomicBool,
+    restart_count: AtomicU32,
+    /// 核心崩溃且自动重启次数用尽后置位，下次启动代理时清除
+    failed: AtomicBool,
+    /// 本次运行以来的自动重启总次数，重新连接时不清零
+    total_restarts: AtomicU32,
+    /// 当前核心配置中默认路由使用的出站标签
+    active_outbound: Mutex<Option<String>>,
+    /// 当前核心进程加载的配置文件内容，热切换时与新配置比较
+    running_config: Mutex<Option<serde_json::Value>>,
+    last_crash: Arc<Mutex<Option<CrashReport>>>,
+    output_tail: Arc<Mutex<VecDeque<(chrono::DateTime<chrono::Utc>, String)>>>,
 }
 
 // 全局单例实例
@@ -40,65 +147,120 @@ impl ProxyManager {
         PROXY_MANAGER.get_or_init(|| {
             Self {
                 process: Arc::new(Mutex::new(None)),
+                adopted_pid: Mutex::new(None),
                 start_time: Arc::new(Mutex::new(None)),
                 current_server: Arc::new(Mutex::new(None)),
+                app_handle: Arc::new(Mutex::new(None)),
+                supervisor: Arc::new(Mutex::new(None)),
+                restarting: AtomicBool::new(false),
+                restart_count: AtomicU32::new(0),
+                failed: AtomicBool::new(false),
+                total_restarts: AtomicU32::new(0),
+                active_outbound: Mutex::new(None),
+                running_config: Mutex::new(None),
+                last_crash: Arc::new(Mutex::new(None)),
+                output_tail: Arc::new(Mutex::new(VecDeque::with_capacity(OUTPUT_BUFFER_LINES))),
             }
         })
     }
+
+    /// 设置应用句柄，用于在守护任务中发射代理状态事件
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        let mut app_handle_guard = self.app_handle.lock().unwrap();
+        *app_handle_guard = Some(handle);
+    }
     
+    /// 获取当前运行的服务器ID
+    ///
+    /// # 返回值
+    /// * `Option<String>` - 当前服务器ID，代理未运行时为 None
+    pub fn current_server_id(&self) -> Option<String> {
+        self.current_server.lock().unwrap().clone()
+    }
+
+    /// 获取代理运行时长
+    ///
+    /// # 返回值
+    /// * `u64` - 自本次启动（或自动重启）以来的秒数，代理未运行时为 0
+    pub fn uptime_secs(&self) -> u64 {
+        self.start_time.lock().unwrap()
+            .map(|start| start.elapsed().as_secs())
+            .unwrap_or(0)
+    }
+
+    /// 获取代理核心子进程的PID
+    ///
+    /// # 返回值
+    /// * `Option<u32>` - 进程ID，代理未运行时为 None
+    pub fn core_pid(&self) -> Option<u32> {
+        self.process.lock().unwrap()
+            .as_ref()
+            .map(|child| child.id())
+            .or(*self.adopted_pid.lock().unwrap())
+    }
+
+    /// 核心是否因崩溃且重启失败而停止
+    /// 置位后直到下次启动代理前保持，托盘据此显示错误图标
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
     /// 检查代理进程是否正在运行（同步方法）
     /// 
     /// # 返回值
     /// * `bool` - 代理进程是否运行中
     pub fn is_process_running(&self) -> bool {
         let process_guard = self.process.lock().unwrap();
-        process_guard.is_some()
+        process_guard.is_some() || self.adopted_pid.lock().unwrap().is_some()
     }
 
     /// 启动代理
-    /// 确保同时只有一个 Xray 进程运行，切换时先停止上一个进程再启动新的进程
+    /// 确保同时只有一个 Xray 进程运行，切换时先停止上一个进程再启动新的进程。
+    /// 启动顺序为 核心 → 限速转发 → 等待 SOCKS 入站就绪 → TUN，停止时按相反顺序清理
     pub async fn start(&self, server: &ServerInfo) -> Result<()> {
         // 停止现有的代理进程（确保同时只有一个进程运行）
         self.stop().await?;
         
-        // 检查是否启用了TUN模式
-        let mut config = AppConfig::load()?;
-        if config.tun_enabled {
-            // 启动TUN模式
-            let tun_manager = TunManager::instance();
-            if let Err(e) = tun_manager.start(config.tun_config.clone()).await {
-                log_error!("启动TUN模式失败: {}", e);
-                 // TUN模式启动失败时，禁用TUN模式并保存配置
-                 config.tun_enabled = false;
-                 if let Err(save_err) = config.save() {
-                     log_error!("保存配置失败: {}", save_err);
-                }
-                // 继续使用传统代理模式
-            }
+        // 检查入站端口是否被占用
+        let port_adjusted = self.resolve_port_conflicts(server).await?;
+        // 端口可能已写入服务器的覆盖设置，重新读取服务器信息
+        let server = &AppConfig::load()?.servers.into_iter()
+            .find(|s| s.id == server.id)
+            .unwrap_or_else(|| server.clone());
+        if port_adjusted && AppConfig::load()?.core == core_backend::CoreKind::Xray {
+            // 端口已改变，已有的配置文件需要重新生成
+            self.regenerate_config(server).await?;
         }
 
-        // 检查 Xray Core 是否存在
-        let xray_executable = AppConfig::xray_executable()?;
-        if !xray_executable.exists() {
-            return Err(anyhow::anyhow!("Xray Core 可执行文件不存在: {}", xray_executable.display()));
+        // 服务器可覆盖全局的代理模式、端口与 TUN 开关
+        let config = AppConfig::load()?;
+        let active_config = config.with_server_overrides(server);
+
+        // 启用透明代理（仅 Linux 且使用 Xray 核心，与 TUN 模式互斥）
+        // 需在核心启动前写入规则，以便先为核心授予给出站连接打标记的能力
+        if config.transparent.enabled && !active_config.tun_enabled {
+            if config.core != core_backend::CoreKind::Xray {
+                log_warn!("透明代理模式仅支持 Xray 核心，已跳过");
+            } else if let Err(e) = TransparentManager::instance()
+                .start(&config.transparent, &AppConfig::xray_executable()?)
+                .await
+            {
+                log_error!("启用透明代理失败: {}", e);
+            }
         }
 
-        // 生成 Xray 配置
-        let config = self.generate_xray_config(server)?;
-        
-        // 保存配置到指定目录（如果配置文件已存在则不重新创建）
-        let config_path = self.save_temp_config(&config, server, false)?;
-        
-        // 启动 Xray Core 进程
-        let child = Command::new(&xray_executable)
-            .arg("-config")
-            .arg(&config_path)
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context(format!("无法启动 Xray Core: {}", xray_executable.display()))?;
+        // 启用限速时核心入站改用内部端口，需在生成配置前分配；
+        // 之后任何一步失败都要清理已写入的透明代理规则，否则本机的 TCP 连接会被重定向到无人监听的端口
+        let spawned = BandwidthLimiter::instance()
+            .prepare(&active_config)
+            .and_then(|_| self.spawn_core(server));
+        let child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                self.stop().await?;
+                return Err(e);
+            }
+        };
 
         // 存储进程句柄
         {
@@ -118,42 +280,581 @@ impl ProxyManager {
             *current_server = Some(server.id.clone());
         }
 
-        // 等待一小段时间确保进程启动成功
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        // 等待 HTTP 入站开始接受连接，慢速机器上核心启动较慢，不能只等待固定时间
+        let http_port = BandwidthLimiter::instance()
+            .backend_ports()
+            .map_or(active_config.http_port, |(http, _)| http);
+        let started = Instant::now();
+        if let Err(e) = self.wait_for_inbound(http_port, INBOUND_READY_TIMEOUT).await {
+            self.stop().await?;
+            return Err(e.context("Xray Core 启动失败"));
+        }
+        log_info!("Xray Core 启动成功，入站就绪耗时 {}ms", started.elapsed().as_millis());
+
+        // 在用户配置的端口上启动限速转发
+        if let Err(e) = BandwidthLimiter::instance().start_relays(&active_config).await {
+            self.stop().await?;
+            return Err(e);
+        }
+
+        // 检查是否启用了TUN模式
+        // 内置的 TUN 转发器把连接转给核心的 SOCKS 入站，必须等核心开始监听后再启动 TUN
+        if active_config.tun_enabled {
+            let socks_port = BandwidthLimiter::instance()
+                .backend_ports()
+                .map_or(active_config.socks_port, |(_, socks)| socks);
+            let tun_result = match self.wait_for_inbound(socks_port, INBOUND_READY_TIMEOUT).await {
+                Ok(()) => TunManager::instance().start(active_config.tun_config.clone()).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = tun_result {
+                log_error!("启动TUN模式失败: {}", e);
+                // TUN模式启动失败时，禁用TUN模式并保存配置；开关来自服务器覆盖设置时保留全局设置
+                if server.overrides.tun_enabled.is_none() {
+                    if let Err(save_err) = ConfigStore::instance().update(|config| config.tun_enabled = false) {
+                        log_error!("保存配置失败: {}", save_err);
+                    }
+                }
+                // 继续使用传统代理模式
+            }
+        }
+
+        // 启动进程守护任务
+        self.restart_count.store(0, Ordering::SeqCst);
+        self.failed.store(false, Ordering::SeqCst);
+        *self.last_crash.lock().unwrap() = None;
+        ConnectivityMonitor::instance().reset_health();
+        self.start_supervisor(server.clone());
+        Ok(())
+    }
+
+    /// 等待核心的本地端口开始接受连接
+    ///
+    /// # 参数
+    /// * `port` - 本地端口
+    /// * `timeout` - 超时时间
+    ///
+    /// # 异常
+    /// * 超时或核心进程提前退出时返回错误
+    async fn wait_for_inbound(&self, port: u16, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                return Ok(());
+            }
+            let exit_status = match self.process.lock().unwrap().as_mut() {
+                Some(child) => match child.try_wait() {
+                    Ok(None) => None,
+                    Ok(Some(status)) => Some(status.to_string()),
+                    Err(e) => Some(format!("检查进程状态失败: {}", e)),
+                },
+                None => match *self.adopted_pid.lock().unwrap() {
+                    Some(pid) if is_pid_alive(pid) => None,
+                    _ => Some("进程已停止".to_string()),
+                },
+            };
+            if let Some(status) = exit_status {
+                return Err(anyhow::anyhow!("核心进程已退出（{}），入站端口 {} 未就绪", status, port));
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("等待入站端口 {} 就绪超时", port));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// 检查入站端口冲突
+    /// 启用 `auto_port` 时将被占用的端口改为从原端口向上查找到的第一个空闲端口并保存，
+    /// 否则发射 `port-conflict` 事件并返回包含占用进程的错误。
+    /// 服务器覆盖了 HTTP/SOCKS 端口时检查并调整服务器的覆盖设置
+    ///
+    /// # 参数
+    /// * `server` - 将要启动的服务器
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 端口是否被自动调整
+    ///
+    /// # 异常
+    /// * 当端口被占用且未启用自动调整，或找不到空闲端口时返回错误
+    async fn resolve_port_conflicts(&self, server: &ServerInfo) -> Result<bool> {
+        let config = AppConfig::load()?.with_server_overrides(server);
+        let system_manager = SystemManager::new();
+        let ports = [
+            ("http", config.http_port),
+            ("socks", config.socks_port),
+            ("pac", config.pac_port),
+            ("api", config.api_port),
+        ];
+
+        let conflicts: Vec<PortConflict> = ports.iter()
+            .filter(|(_, port)| !system_manager.is_port_available(*port))
+            .map(|(name, port)| {
+                let owner = system_manager.find_port_owner(*port);
+                PortConflict {
+                    name: name.to_string(),
+                    port: *port,
+                    pid: owner.as_ref().map(|o| o.pid),
+                    process: owner.and_then(|o| o.name),
+                }
+            })
+            .collect();
+        if conflicts.is_empty() {
+            return Ok(false);
+        }
+
+        if !config.auto_port {
+            let description = conflicts.iter()
+                .map(|c| match (&c.process, c.pid) {
+                    (Some(process), Some(pid)) => format!("{} 端口 {} 被 {} (PID {}) 占用", c.name, c.port, process, pid),
+                    (None, Some(pid)) => format!("{} 端口 {} 被 PID {} 占用", c.name, c.port, pid),
+                    _ => format!("{} 端口 {} 已被占用", c.name, c.port),
+                })
+                .collect::<Vec<_>>()
+                .join("；");
+            log_error!("端口冲突: {}", description);
+            if let Some(app_handle) = self.app_handle.lock().unwrap().as_ref() {
+                let _ = app_handle.emit("port-conflict", serde_json::json!({ "conflicts": conflicts }));
+            }
+            return Err(AppError::new(ErrorCode::PortInUse, format!("端口冲突: {}", description)).into());
+        }
+
+        // 自动选择空闲端口，避免与本次使用的其他端口重复
+        let mut used: Vec<u16> = ports.iter().map(|(_, port)| *port).collect();
+        let mut replacements = Vec::new();
+        for conflict in &conflicts {
+            let free = (conflict.port.saturating_add(1)..=u16::MAX)
+                .take(1000)
+                .find(|port| !used.contains(port) && system_manager.is_port_available(*port))
+                .with_context(|| format!("找不到可替代 {} 端口 {} 的空闲端口", conflict.name, conflict.port))?;
+            used.push(free);
+            log_warn!("{} 端口 {} 已被占用，改用 {}", conflict.name, conflict.port, free);
+            replacements.push((conflict.name.clone(), free));
+        }
+
+        Self::save_ports(server, &replacements)?;
+        Ok(true)
+    }
+
+    /// 保存调整后的端口
+    /// HTTP/SOCKS 端口由服务器覆盖设置指定时写入覆盖设置，否则写入全局设置
+    ///
+    /// # 参数
+    /// * `server` - 使用这些端口的服务器
+    /// * `ports` - 端口名称（http/socks/pac/api）与新端口
+    fn save_ports(server: &ServerInfo, ports: &[(String, u16)]) -> Result<()> {
+        ConfigStore::instance().update(|config| {
+            for (name, port) in ports {
+                match name.as_str() {
+                    "http" | "socks" => {
+                        let overrides = config.servers.iter_mut()
+                            .find(|s| s.id == server.id)
+                            .map(|s| &mut s.overrides)
+                            .filter(|o| if name == "http" { o.http_port.is_some() } else { o.socks_port.is_some() });
+                        match (name.as_str(), overrides) {
+                            ("http", Some(overrides)) => overrides.http_port = Some(*port),
+                            ("socks", Some(overrides)) => overrides.socks_port = Some(*port),
+                            ("http", None) => config.http_port = *port,
+                            _ => config.socks_port = *port,
+                        }
+                    }
+                    "pac" => config.pac_port = *port,
+                    _ => config.api_port = *port,
+                }
+            }
+        })?;
+        Ok(())
+    }
+
+    /// 生成配置并启动当前配置选择的代理核心进程
+    /// 进程输出由后台线程持续读取，避免管道写满阻塞进程，并保留最后若干行用于记录崩溃原因
+    fn spawn_core(&self, server: &ServerInfo) -> Result<Child> {
+        let backend = core_backend::backend(AppConfig::load()?.core);
+
+        // 检查核心是否存在
+        let executable = backend.executable()?;
+        if !executable.exists() {
+            return Err(AppError::new(ErrorCode::XrayMissing, format!("{} 可执行文件不存在: {}", backend.name(), executable.display())).into());
+        }
+
+        // 生成并保存核心配置
+        let config = backend.generate_config(server)?;
+        let config_path = backend.write_config(server, &config)?;
+        // 已存在的配置文件可能经用户编辑而没有被覆盖，记录核心实际加载的内容
+        let config = Self::read_config_file(&config_path).unwrap_or(config);
+        // 未匹配任何路由规则的流量走第一个出站
+        *self.active_outbound.lock().unwrap() = config["outbounds"][0]["tag"].as_str().map(str::to_string);
+        *self.running_config.lock().unwrap() = Some(config);
         
-        // 检查进程是否仍在运行
+        let mut command = Command::new(&executable);
+        core_backend::prepare_command(backend, &mut command)?;
+        command
+            .args(backend.command_args(&config_path))
+            .args(&AppConfig::load()?.core_launch.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        // CREATE_NEW_PROCESS_GROUP 使停止时可以单独向核心发送 CTRL_BREAK
+        #[cfg(target_os = "windows")]
+        command.creation_flags(0x08000000 | 0x00000200); // CREATE_NO_WINDOW | CREATE_NEW_PROCESS_GROUP
+
+        let mut child = command
+            .spawn()
+            .context(format!("无法启动 {}: {}", backend.name(), executable.display()))?;
+
+        if let Some(stdout) = child.stdout.take() {
+            self.capture_output(stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.capture_output(stderr);
+        }
+
+        Ok(child)
+    }
+
+    /// 路由规则等配置变化后重新生成配置并重启运行中的核心，使变化立即生效
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 是否重启了核心；代理未运行时不做任何事
+    pub async fn reload(&self) -> Result<bool> {
+        let Some(server_id) = self.current_server_id().filter(|_| self.is_process_running()) else {
+            return Ok(false);
+        };
+        let config = AppConfig::load()?;
+        let Some(server) = config.servers.iter().find(|s| s.id == server_id) else {
+            return Ok(false);
+        };
+        self.regenerate_config(server).await?;
+        self.start(server).await?;
+        Ok(true)
+    }
+
+    /// 运行中热切换服务器
+    /// 通过 Xray API 只替换出站，HTTP/SOCKS 入站始终保持监听，经本地代理的连接不会因端口消失而中断。
+    /// 新旧配置除出站外还有其他差异（如入站、路由、DNS）时无法热切换
+    ///
+    /// # 参数
+    /// * `server` - 切换到的服务器
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 是否已热切换；为 false 时调用方需完整重启代理
+    pub async fn hot_switch(&self, server: &ServerInfo) -> Result<bool> {
+        let config = AppConfig::load()?;
+        if config.core != core_backend::CoreKind::Xray
+            || !self.is_process_running()
+            || self.restarting.load(Ordering::SeqCst)
         {
-            let mut process = self.process.lock().unwrap();
-            if let Some(ref mut child) = process.as_mut() {
-                match child.try_wait() {
-                    Ok(Some(status)) => {
-                        // 进程已退出
-                        *process = None;
-                        return Err(anyhow::anyhow!("Xray Core 启动失败，退出状态: {}", status));
+            return Ok(false);
+        }
+        // TUN 开关或 SOCKS 端口随服务器变化时需按 停止 TUN → 切换服务器 → 重新启用 的顺序完整重启，
+        // 避免 TUN 继续指向已失效的 SOCKS 端口
+        let active_config = config.with_server_overrides(server);
+        if TunManager::instance().is_running().await != active_config.tun_enabled {
+            log_info!("新服务器的 TUN 设置不同，无法热切换");
+            return Ok(false);
+        }
+        let Some(old_config) = self.running_config.lock().unwrap().clone() else { return Ok(false) };
+        // 与完整启动一样使用服务器的配置文件：已存在的文件（可能经用户编辑）不会被覆盖，
+        // 比较的是核心重启后实际会加载的内容
+        let backend = core_backend::backend(config.core);
+        let generated = self.build_xray_config(server, &config)?;
+        let config_path = backend.write_config(server, &generated)?;
+        let Some(new_config) = Self::read_config_file(&config_path) else {
+            log_warn!("无法读取配置文件，无法热切换: {}", config_path.display());
+            return Ok(false);
+        };
+
+        let without_outbounds = |value: &serde_json::Value| {
+            let mut value = value.clone();
+            if let Some(object) = value.as_object_mut() {
+                object.remove("outbounds");
+            }
+            value
+        };
+        if without_outbounds(&old_config) != without_outbounds(&new_config) {
+            log_info!("新配置除出站外还有其他变化，无法热切换");
+            return Ok(false);
+        }
+
+        let old_outbounds = old_config["outbounds"].as_array().cloned().unwrap_or_default();
+        let new_outbounds = new_config["outbounds"].as_array().cloned().unwrap_or_default();
+        let tag_of = |outbound: &serde_json::Value| outbound["tag"].as_str().unwrap_or_default().to_string();
+
+        let client = XrayApiClient::new(config.api_port)?;
+        // 核心中当前存在的出站
+        let mut running = old_outbounds.clone();
+        // 默认出站被移除后，下一个添加的出站成为默认出站，
+        // 因此先移除旧的默认出站并立即添加新配置的第一个出站，使其成为默认出站
+        if let (Some(old_default), Some(new_default)) = (old_outbounds.first(), new_outbounds.first()) {
+            if old_default != new_default {
+                for tag in [tag_of(old_default), tag_of(new_default)] {
+                    if running.iter().any(|outbound| tag_of(outbound) == tag) {
+                        client.remove_outbound(&tag).await?;
+                        running.retain(|outbound| tag_of(outbound) != tag);
                     }
-                    Ok(None) => {
-                        // 进程仍在运行，启动成功
+                }
+                client.add_outbound(new_default).await?;
+                running.push(new_default.clone());
+            }
+        }
+        // 再按新配置顺序替换其他出站
+        for new in new_outbounds.iter().skip(1) {
+            let tag = tag_of(new);
+            match running.iter().find(|outbound| tag_of(outbound) == tag) {
+                Some(outbound) if outbound == new => continue,
+                Some(_) => client.remove_outbound(&tag).await?,
+                None => {}
+            }
+            client.add_outbound(new).await?;
+            running.retain(|outbound| tag_of(outbound) != tag);
+            running.push(new.clone());
+        }
+        // 最后移除新配置中已不存在的出站（如多余的链式代理跳板）
+        for outbound in &running {
+            if !new_outbounds.iter().any(|new| tag_of(new) == tag_of(outbound)) {
+                client.remove_outbound(&tag_of(outbound)).await?;
+            }
+        }
+
+        *self.active_outbound.lock().unwrap() = new_config["outbounds"][0]["tag"].as_str().map(str::to_string);
+        *self.running_config.lock().unwrap() = Some(new_config);
+        *self.current_server.lock().unwrap() = Some(server.id.clone());
+
+        // 守护任务按服务器重新生成配置重启核心，需改为新服务器
+        if let Some(task) = self.supervisor.lock().unwrap().take() {
+            task.abort();
+        }
+        self.start_supervisor(server.clone());
+
+        log_info!("已热切换到服务器: {}", server.name);
+        Ok(true)
+    }
+
+    /// 在后台线程中读取进程输出，连同读取时间保留最后 `OUTPUT_BUFFER_LINES` 行
+    fn capture_output<R: Read + Send + 'static>(&self, reader: R) {
+        let output_tail = self.output_tail.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(|line| line.ok()) {
+                let mut tail = output_tail.lock().unwrap();
+                if tail.len() >= OUTPUT_BUFFER_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back((chrono::Utc::now(), line));
+            }
+        });
+    }
+
+    /// 启动进程守护任务
+    /// 定期检查 Xray 进程，异常退出时记录原因并按指数退避重启
+    fn start_supervisor(&self, server: ServerInfo) {
+        let task = tauri::async_runtime::spawn(async move {
+            let manager = ProxyManager::instance();
+            let mut attempts: u32 = 0;
+
+            loop {
+                tokio::time::sleep(SUPERVISE_INTERVAL).await;
+
+                let exit_status = {
+                    let mut process = manager.process.lock().unwrap();
+                    match process.as_mut().map(|child| child.try_wait()) {
+                        Some(Ok(Some(status))) => {
+                            *process = None;
+                            status.to_string()
+                        }
+                        Some(Ok(None)) => {
+                            // 稳定运行一段时间后重置退避计数
+                            let stable = manager.start_time.lock().unwrap()
+                                .is_some_and(|start| start.elapsed() >= RESTART_STABLE_UPTIME);
+                            if stable {
+                                attempts = 0;
+                            }
+                            continue;
+                        }
+                        Some(Err(e)) => {
+                            log_warn!("检查 Xray 进程状态失败: {}", e);
+                            continue;
+                        }
+                        None => {
+                            // 接管的进程只能按 PID 检查是否仍在运行
+                            let mut adopted = manager.adopted_pid.lock().unwrap();
+                            match *adopted {
+                                Some(pid) if is_pid_alive(pid) => continue,
+                                Some(pid) => {
+                                    *adopted = None;
+                                    format!("未知（接管的进程 PID {}）", pid)
+                                }
+                                // 进程已被停止
+                                None => return,
+                            }
+                        }
                     }
-                    Err(e) => {
-                        return Err(anyhow::anyhow!("检查进程状态失败: {}", e));
+                };
+
+                // 给输出读取线程一点时间读完最后的输出
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                manager.record_crash(format!("Xray Core 异常退出，退出状态: {}", exit_status));
+                notify::notify(NotifyEvent::CoreCrashed, &[&exit_status]);
+
+                let max_restarts = AppConfig::load()
+                    .map(|config| config.xray_max_restarts)
+                    .unwrap_or_else(|_| crate::config::default_xray_max_restarts());
+
+                manager.restarting.store(true, Ordering::SeqCst);
+                manager.emit_status("restarting");
+
+                if manager.restart_with_backoff(&server, &mut attempts, max_restarts).await {
+                    manager.restarting.store(false, Ordering::SeqCst);
+                    manager.emit_status("connected");
+                    continue;
+                }
+
+                // 重启次数用尽，放弃守护并停止代理
+                log_error!("Xray Core 连续重启 {} 次失败，停止代理", max_restarts);
+                manager.restarting.store(false, Ordering::SeqCst);
+                manager.failed.store(true, Ordering::SeqCst);
+                // 先移除自身句柄，避免 stop() 中止当前任务
+                drop(manager.supervisor.lock().unwrap().take());
+                if let Err(e) = manager.stop().await {
+                    log_error!("停止代理失败: {}", e);
+                }
+                if let Err(e) = SystemManager::new().unset_proxy().await {
+                    log_error!("清除系统代理失败: {}", e);
+                }
+                manager.emit_status("disconnected");
+                hooks::fire(HookEvent::ProxyStopped, None, None);
+                notify::notify(NotifyEvent::Disconnected, &[]);
+                return;
+            }
+        });
+
+        *self.supervisor.lock().unwrap() = Some(task);
+    }
+
+    /// 按指数退避重启 Xray 进程
+    ///
+    /// # 返回值
+    /// * `bool` - 是否在允许的次数内重启成功
+    async fn restart_with_backoff(&self, server: &ServerInfo, attempts: &mut u32, max_restarts: u32) -> bool {
+        while *attempts < max_restarts {
+            let delay = RESTART_BACKOFF_BASE
+                .saturating_mul(2u32.saturating_pow(*attempts))
+                .min(RESTART_BACKOFF_MAX);
+            *attempts += 1;
+            log_warn!("{} 秒后进行第 {}/{} 次重启", delay.as_secs(), attempts, max_restarts);
+            tokio::time::sleep(delay).await;
+
+            match self.spawn_core(server) {
+                Ok(child) => {
+                    *self.process.lock().unwrap() = Some(child);
+                    *self.start_time.lock().unwrap() = Some(Instant::now());
+                    self.restart_count.fetch_add(1, Ordering::SeqCst);
+                    self.total_restarts.fetch_add(1, Ordering::SeqCst);
+                    // 与启动时一样等待 HTTP 入站就绪，启动后立即崩溃或端口冲突不算重启成功
+                    let ready = async {
+                        let http_port = match BandwidthLimiter::instance().backend_ports() {
+                            Some((http, _)) => http,
+                            None => AppConfig::load()?.with_server_overrides(server).http_port,
+                        };
+                        self.wait_for_inbound(http_port, INBOUND_READY_TIMEOUT).await
+                    };
+                    match ready.await {
+                        Ok(()) => {
+                            log_info!("Xray Core 已重启");
+                            return true;
+                        }
+                        Err(e) => {
+                            // 终止未就绪的进程，避免与下一次重启争用端口
+                            let child = self.process.lock().unwrap().take();
+                            if let Some(mut child) = child {
+                                let _ = child.kill();
+                                let _ = child.wait();
+                            }
+                            self.record_crash(format!("重启 Xray Core 失败: {}", e));
+                        }
                     }
                 }
+                Err(e) => self.record_crash(format!("重启 Xray Core 失败: {}", e)),
             }
         }
-        log_info!("Xray Core 启动成功");
-        Ok(())
+        false
+    }
+
+    /// 记录异常退出原因
+    fn record_crash(&self, reason: String) {
+        let output: Vec<String> = {
+            let tail = self.output_tail.lock().unwrap();
+            tail.iter()
+                .skip(tail.len().saturating_sub(CRASH_OUTPUT_LINES))
+                .map(|(_, line)| privacy::redact(line).into_owned())
+                .collect()
+        };
+        log_error!("{}", reason);
+        for line in output.iter().rev().take(5).rev() {
+            log_error!("  {}", line);
+        }
+        ConnectivityMonitor::instance().record_error(&reason);
+        *self.last_crash.lock().unwrap() = Some(CrashReport {
+            exited_at: chrono::Utc::now().to_rfc3339(),
+            reason,
+            output,
+        });
+    }
+
+    /// 获取缓存的核心输出
+    ///
+    /// # 返回值
+    /// * `Vec<(DateTime<Utc>, String)>` - 读取时间与输出行，按时间顺序排列
+    pub fn core_output(&self) -> Vec<(chrono::DateTime<chrono::Utc>, String)> {
+        self.output_tail.lock().unwrap()
+            .iter()
+            .map(|(time, line)| (*time, privacy::redact(line).into_owned()))
+            .collect()
+    }
+
+    /// 向界面发射事件
+    ///
+    /// # 参数
+    /// * `event` - 事件名称
+    /// * `payload` - 事件数据
+    pub(crate) fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        if let Some(app_handle) = self.app_handle.lock().unwrap().as_ref() {
+            let _ = app_handle.emit(event, payload);
+        }
+    }
+
+    /// 发射代理状态变化事件
+    /// 界面与托盘菜单据此刷新代理状态
+    pub(crate) fn emit_status(&self, status: &str) {
+        let app_handle = self.app_handle.lock().unwrap().clone();
+        if let Some(app_handle) = app_handle {
+            let _ = app_handle.emit("proxy-status-changed", json!({
+                "is_running": status != "disconnected",
+                "status": status,
+                "current_server": self.current_server_id()
+            }));
+        }
     }
 
     /// 停止代理
     /// 确保完全终止 Xray Core 进程，包括强制杀死进程
     pub async fn stop(&self) -> Result<()> {
-        // 停止TUN模式（如果正在运行）
+        // 停止进程守护任务
+        if let Some(task) = self.supervisor.lock().unwrap().take() {
+            task.abort();
+        }
+        self.restarting.store(false, Ordering::SeqCst);
+
+        // 按与启动相反的顺序清理，先停止TUN模式（如果正在运行），避免 TUN 转发到已关闭的 SOCKS 入站
         let tun_manager = TunManager::instance();
         if tun_manager.is_running().await {
             if let Err(e) = tun_manager.stop().await {
                 log_error!("停止TUN模式失败: {}", e);
             }
         }
+        if let Err(e) = TransparentManager::instance().stop().await {
+            log_error!("清理透明代理规则失败: {}", e);
+        }
+        BandwidthLimiter::instance().stop();
         // 获取进程信息并立即释放锁
         let (child_opt, pid_opt) = {
             let mut process = self.process.lock().unwrap();
@@ -161,10 +862,36 @@ impl ProxyManager {
             let pid = child.as_ref().map(|c| c.id());
             (child, pid)
         };
+        let adopted_pid = self.adopted_pid.lock().unwrap().take();
+        // 先请求核心正常退出，让其完成统计写入并关闭连接，超过等待时间后再强制终止
+        let grace = Duration::from_secs(AppConfig::load().map(|c| c.core_stop_grace_secs).unwrap_or(0));
+
+        if let Some(pid) = adopted_pid {
+            let exited = !grace.is_zero()
+                && self.request_graceful_exit(pid).await.is_ok()
+                && Self::wait_for_pid_exit(pid, grace).await;
+            if exited {
+                log_info!("核心已正常退出");
+            } else {
+                self.force_kill_process(pid).await?;
+            }
+        }
         
         if let (Some(mut child), Some(pid)) = (child_opt, pid_opt) {
-            // 首先尝试正常终止进程
-            if let Err(_) = child.kill() {
+            let exited = if grace.is_zero() {
+                false
+            } else {
+                match self.request_graceful_exit(pid).await {
+                    Ok(()) => Self::wait_for_exit(&mut child, grace).await,
+                    Err(e) => {
+                        log_warn!("无法请求核心正常退出: {}", e);
+                        false
+                    }
+                }
+            };
+            if exited {
+                log_info!("核心已正常退出");
+            } else if let Err(_) = child.kill() {
                 // 如果正常终止失败，使用系统命令强制终止
                 self.force_kill_process(pid).await?;
             } else {
@@ -186,8 +913,8 @@ impl ProxyManager {
             }
         }
 
-        // 额外确保：查找并终止所有 xray 进程
-        self.kill_all_xray_processes().await?;
+        // 额外确保：终止句柄已丢失但仍在运行的本应用核心进程（不影响其他 xray 进程）
+        self.kill_stray_cores().await;
 
         // 清除启动时间
         {
@@ -205,6 +932,89 @@ impl ProxyManager {
         Ok(())
     }
 
+    /// 请求进程正常退出
+    /// Unix 发送 SIGTERM；Windows 临时附加到核心的隐藏控制台，向核心所在的进程组发送 CTRL_BREAK，
+    /// 发送失败时由调用方直接强制终止
+    async fn request_graceful_exit(&self, pid: u32) -> Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            use windows_sys::Win32::System::Console::{
+                AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, SetConsoleCtrlHandler,
+                ATTACH_PARENT_PROCESS, CTRL_BREAK_EVENT,
+            };
+            // 核心以 CREATE_NO_WINDOW 启动，拥有自己的隐藏控制台，只有附加到该控制台才能发送控制台事件；
+            // 发送期间忽略本进程的控制台事件，避免自身随核心一起退出
+            let error = unsafe {
+                let had_console = FreeConsole() != 0;
+                let error = if AttachConsole(pid) == 0 {
+                    Some(std::io::Error::last_os_error())
+                } else {
+                    SetConsoleCtrlHandler(None, 1);
+                    let error = (GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) == 0)
+                        .then(std::io::Error::last_os_error);
+                    FreeConsole();
+                    error
+                };
+                // 命令行模式下重新附加到启动本进程的终端
+                if had_console {
+                    AttachConsole(ATTACH_PARENT_PROCESS);
+                }
+                error
+            };
+            // 控制台事件异步派发，稍等后再恢复本进程的控制台事件处理
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            unsafe { SetConsoleCtrlHandler(None, 0) };
+            if let Some(e) = error {
+                return Err(e).context("发送 CTRL_BREAK 失败");
+            }
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let output = TokioCommand::new("kill")
+                .args(&["-TERM", &pid.to_string()])
+                .output()
+                .await
+                .context("执行 kill 命令失败")?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow::anyhow!("发送 SIGTERM 失败: {}", stderr));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 等待子进程退出
+    ///
+    /// # 返回值
+    /// * `bool` - 是否在等待时间内退出
+    async fn wait_for_exit(child: &mut Child, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if !matches!(child.try_wait(), Ok(None)) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    /// 等待指定PID的进程退出，用于没有子进程句柄的接管进程
+    ///
+    /// # 返回值
+    /// * `bool` - 是否在等待时间内退出
+    async fn wait_for_pid_exit(pid: u32, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if !is_pid_alive(pid) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
     /// 强制终止指定PID的进程
     async fn force_kill_process(&self, pid: u32) -> Result<()> {
         #[cfg(target_os = "windows")]
@@ -239,31 +1049,231 @@ impl ProxyManager {
         Ok(())
     }
 
-    /// 查找并终止所有 xray 进程
-    async fn kill_all_xray_processes(&self) -> Result<()> {
-        // 使用 sysinfo 库获取系统进程信息
+    /// 查找由 RuRay 启动的核心进程
+    /// 通过命令行中的配置文件路径（位于服务器配置目录下）识别，跳过当前持有句柄的核心进程
+    ///
+    /// # 参数
+    /// * `include_temp_instances` - 是否包含测速等临时实例
+    ///
+    /// # 返回值
+    /// * `Result<Vec<(u32, String)>>` - 进程ID与进程名
+    fn find_own_cores(&self, include_temp_instances: bool) -> Result<Vec<(u32, String)>> {
+        let servers_dir = AppConfig::servers_dir()?.to_string_lossy().to_lowercase();
+        let own_pid = self.core_pid();
+
         let mut system = System::new();
         system.refresh_processes();
-        
-        // 查找所有名为 xray 或 xray.exe 的进程
-        let xray_processes: Vec<u32> = system.processes()
+
+        Ok(system.processes()
             .iter()
-            .filter_map(|(pid, process)| {
-                let process_name = process.name().to_lowercase();
-                if process_name == "xray" || process_name == "xray.exe" {
-                    Some(pid.as_u32())
-                } else {
-                    None
-                }
+            .filter(|(pid, _)| Some(pid.as_u32()) != own_pid)
+            .filter(|(_, process)| {
+                is_core_process_name(process.name())
+                    && process.cmd().iter().any(|arg| {
+                        let arg = arg.to_lowercase();
+                        arg.starts_with(&servers_dir)
+                            && (include_temp_instances || !arg.contains(TEMP_INSTANCE_CONFIG_PREFIX))
+                    })
             })
+            .map(|(pid, process)| (pid.as_u32(), process.name().to_string()))
+            .collect())
+    }
+
+    /// 终止句柄已丢失但仍在运行的代理核心进程
+    /// 只处理命令行引用服务器配置目录的进程，不影响测速等临时实例和其他用户运行的 xray
+    async fn kill_stray_cores(&self) {
+        let strays = match self.find_own_cores(false) {
+            Ok(strays) => strays,
+            Err(e) => {
+                log_error!("查找残留的核心进程失败: {}", e);
+                return;
+            }
+        };
+        for (pid, name) in strays {
+            match self.force_kill_process(pid).await {
+                Ok(()) => log_warn!("已终止残留的核心进程: {} (PID {})", name, pid),
+                Err(e) => log_error!("终止残留的核心进程 {} 失败: {}", pid, e),
+            }
+        }
+    }
+
+    /// 强制终止本机所有 xray/sing-box 进程，包括其他用户或其他程序启动的进程
+    /// 仅供用户明确要求时使用，会先停止本应用的代理
+    ///
+    /// # 返回值
+    /// * `Result<usize>` - 终止的进程数量
+    pub async fn force_cleanup_all(&self) -> Result<usize> {
+        self.stop().await?;
+
+        let mut system = System::new();
+        system.refresh_processes();
+        let processes: Vec<(u32, String)> = system.processes()
+            .iter()
+            .filter(|(_, process)| is_core_process_name(process.name()))
+            .map(|(pid, process)| (pid.as_u32(), process.name().to_string()))
             .collect();
-        
-        // 终止找到的所有 xray 进程
-        for pid in xray_processes {
-            let _ = self.force_kill_process(pid).await;
+
+        let mut killed = 0;
+        for (pid, name) in processes {
+            match self.force_kill_process(pid).await {
+                Ok(()) => {
+                    log_warn!("已强制终止核心进程: {} (PID {})", name, pid);
+                    killed += 1;
+                }
+                Err(e) => log_error!("强制终止核心进程 {} 失败: {}", pid, e),
+            }
         }
-        
-        Ok(())
+        Ok(killed)
+    }
+
+    /// 查找可接管的核心进程
+    /// 上次运行（如界面崩溃后）遗留的 Xray 进程仍在运行时，按命令行中的配置文件路径匹配服务器，
+    /// 并从该配置文件中恢复入站端口。启用限速时核心只监听内部端口，转发已随上次运行结束，不能接管
+    ///
+    /// # 返回值
+    /// * `Result<Option<AdoptableCore>>` - 可接管的进程，代理已运行或没有匹配的进程时为 None
+    pub fn find_adoptable_core(&self) -> Result<Option<AdoptableCore>> {
+        if self.is_process_running() {
+            return Ok(None);
+        }
+        let config = AppConfig::load()?;
+        if config.core != core_backend::CoreKind::Xray || config.bandwidth_limit.is_active() {
+            return Ok(None);
+        }
+
+        let mut system = System::new();
+        system.refresh_processes();
+
+        for (pid, process) in system.processes() {
+            if !matches!(process.name().to_lowercase().as_str(), "xray" | "xray.exe") {
+                continue;
+            }
+            for arg in process.cmd() {
+                let server = config.servers.iter().find(|server| {
+                    self.get_server_config_path(&server.id, &server.name).to_string_lossy().to_lowercase() == arg.to_lowercase()
+                });
+                let Some(server) = server else { continue };
+                let Some(core_config) = std::fs::read_to_string(arg)
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                else {
+                    continue;
+                };
+
+                // 主 HTTP/SOCKS 入站按协议识别，API 入站按标签识别
+                let inbounds = core_config["inbounds"].as_array().cloned().unwrap_or_default();
+                let port_of = |matches: &dyn Fn(&serde_json::Value) -> bool| {
+                    inbounds.iter()
+                        .find(|inbound| matches(inbound))
+                        .and_then(|inbound| inbound["port"].as_u64())
+                        .and_then(|port| u16::try_from(port).ok())
+                };
+                return Ok(Some(AdoptableCore {
+                    pid: pid.as_u32(),
+                    server_id: server.id.clone(),
+                    server_name: server.name.clone(),
+                    config_path: arg.clone(),
+                    http_port: port_of(&|inbound| inbound["protocol"] == "http"),
+                    socks_port: port_of(&|inbound| inbound["protocol"] == "socks" || inbound["protocol"] == "mixed"),
+                    api_port: port_of(&|inbound| inbound["tag"] == "api"),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 接管上次运行遗留的核心进程，避免重复启动
+    /// 恢复的端口与当前设置不同时写回设置，随后与正常启动一样启动 TUN 与进程守护。
+    /// 接管的进程没有输出管道，崩溃报告中不包含核心输出
+    ///
+    /// # 参数
+    /// * `pid` - 由 `find_adoptable_core` 找到的进程ID
+    ///
+    /// # 返回值
+    /// * `Result<ServerInfo>` - 接管的进程所用的服务器
+    ///
+    /// # 异常
+    /// * 进程已不可接管或入站未在监听时返回错误
+    pub async fn adopt(&self, pid: u32) -> Result<ServerInfo> {
+        let core = self.find_adoptable_core()?
+            .filter(|core| core.pid == pid)
+            .context("该核心进程已退出或无法接管")?;
+        let config = AppConfig::load()?;
+        let server = config.servers.iter()
+            .find(|s| s.id == core.server_id)
+            .cloned()
+            .context("服务器不存在")?;
+
+        // 恢复端口
+        let active_config = config.with_server_overrides(&server);
+        let recovered: Vec<(String, u16)> = [
+            ("http", core.http_port, active_config.http_port),
+            ("socks", core.socks_port, active_config.socks_port),
+            ("api", core.api_port, active_config.api_port),
+        ]
+        .into_iter()
+        .filter_map(|(name, port, current)| port.filter(|port| *port != current).map(|port| (name.to_string(), port)))
+        .collect();
+        if !recovered.is_empty() {
+            for (name, port) in &recovered {
+                log_info!("从接管的核心恢复 {} 端口: {}", name, port);
+            }
+            Self::save_ports(&server, &recovered)?;
+        }
+        let active_config = AppConfig::load()?.with_server_overrides(&server);
+
+        let running_config = Self::read_config_file(&core.config_path);
+        *self.active_outbound.lock().unwrap() = running_config.as_ref()
+            .and_then(|config| config["outbounds"][0]["tag"].as_str().map(str::to_string));
+        *self.running_config.lock().unwrap() = running_config;
+        *self.adopted_pid.lock().unwrap() = Some(pid);
+        *self.start_time.lock().unwrap() = Some(Instant::now());
+        *self.current_server.lock().unwrap() = Some(server.id.clone());
+
+        if let Err(e) = self.wait_for_inbound(active_config.http_port, Duration::from_secs(2)).await {
+            *self.adopted_pid.lock().unwrap() = None;
+            *self.start_time.lock().unwrap() = None;
+            *self.current_server.lock().unwrap() = None;
+            return Err(e.context("接管的核心入站未在监听"));
+        }
+        log_info!("已接管运行中的 Xray Core (PID {})，服务器: {}", pid, server.name);
+
+        // TUN 随上次运行一起结束，按设置重新启用
+        if active_config.tun_enabled {
+            if let Err(e) = TunManager::instance().start(active_config.tun_config.clone()).await {
+                log_error!("启动TUN模式失败: {}", e);
+            }
+        }
+
+        self.restart_count.store(0, Ordering::SeqCst);
+        self.failed.store(false, Ordering::SeqCst);
+        *self.last_crash.lock().unwrap() = None;
+        ConnectivityMonitor::instance().reset_health();
+        self.start_supervisor(server.clone());
+        Ok(server)
+    }
+
+    /// 终止上次运行遗留的核心进程
+    /// 应用异常退出后由 RuRay 启动的 xray/sing-box 进程可能仍在运行并占用端口。
+    /// 通过命令行中的配置文件路径（位于服务器配置目录下）识别由 RuRay 启动的进程，
+    /// 不影响用户自行运行的其他 xray 进程
+    ///
+    /// # 返回值
+    /// * `Result<usize>` - 终止的进程数量
+    pub async fn kill_orphaned_cores(&self) -> Result<usize> {
+        let orphans = self.find_own_cores(true)?;
+
+        let mut killed = 0;
+        for (pid, name) in orphans {
+            match self.force_kill_process(pid).await {
+                Ok(()) => {
+                    log_warn!("已终止遗留的核心进程: {} (PID {})", name, pid);
+                    killed += 1;
+                }
+                Err(e) => log_error!("终止遗留的核心进程 {} 失败: {}", pid, e),
+            }
+        }
+        Ok(killed)
     }
 
     /// 清理指定服务器的配置文件
@@ -284,6 +1294,13 @@ impl ProxyManager {
             std::fs::remove_file(&config_path)
                 .context("删除配置文件失败")?;
         }
+
+        // 删除 sing-box 配置文件
+        let sing_box_config_path = config_dir.join(format!("{}_sing-box_config.json", server_id));
+        if sing_box_config_path.exists() {
+            std::fs::remove_file(&sing_box_config_path)
+                .context("删除配置文件失败")?;
+        }
         
         Ok(())
     }
@@ -307,7 +1324,8 @@ impl ProxyManager {
             
             // 只处理 xray_config.json 文件
             if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                if filename.ends_with("_xray_config.json") && filename != "xray_test_config.json" {
+                let is_core_config = filename.ends_with("_xray_config.json") || filename.ends_with("_sing-box_config.json");
+                if is_core_config && filename != "xray_test_config.json" {
                     // 提取服务器ID（文件名格式：服务器ID_服务器名称_xray_config.json）
                     if let Some(server_id) = filename.split('_').next() {
                         // 如果服务器ID不在活跃列表中，删除配置文件
@@ -324,7 +1342,7 @@ impl ProxyManager {
 
     /// 获取代理状态
     pub async fn get_status(&self) -> Result<ProxyStatus> {
-        let config = AppConfig::load()?;
+        let config = AppConfig::load_active()?;
 
         // 获取状态信息，立即释放锁
         let (is_running, uptime, current_server_id) = {
@@ -332,7 +1350,9 @@ impl ProxyManager {
             let start_time = self.start_time.lock().unwrap();
             let current_server = self.current_server.lock().unwrap();
 
-            let is_running = process.is_some();
+            let is_running = process.is_some()
+                || self.adopted_pid.lock().unwrap().is_some()
+                || self.restarting.load(Ordering::SeqCst);
             let uptime = if let Some(start) = *start_time {
                 start.elapsed().as_secs()
             } else {
@@ -354,6 +1374,8 @@ impl ProxyManager {
         // 确定代理状态
         let status = if !is_running {
             "disconnected".to_string()
+        } else if self.restarting.load(Ordering::SeqCst) {
+            "restarting".to_string()
         } else {
             // 进程正在运行，测试连接
             if current_server_id.is_some() {
@@ -369,85 +1391,77 @@ impl ProxyManager {
             }
         };
 
-        // TODO: 实现真实的流量统计
-        let upload_speed = if is_running { rand::random::<u64>() % 1024 * 1024 } else { 0 };
-        let download_speed = if is_running { rand::random::<u64>() % 1024 * 1024 * 10 } else { 0 };
-        let total_upload = if is_running { rand::random::<u64>() % 1024 * 1024 * 1024 } else { 0 };
-        let total_download = if is_running { rand::random::<u64>() % 1024 * 1024 * 1024 * 10 } else { 0 };
+        // 速度与累计流量来自流量统计的定期采样
+        let traffic_manager = TrafficManager::instance();
+        let (speed, session) = if is_running {
+            (traffic_manager.current_speed(), traffic_manager.session_usage())
+        } else {
+            (TrafficUsage::default(), TrafficUsage::default())
+        };
 
+        let monitor = ConnectivityMonitor::instance();
         Ok(ProxyStatus {
             is_running,
             status,
             current_server: current_server_name,
             proxy_mode: config.proxy_mode,
             uptime,
-            upload_speed,
-            download_speed,
-            total_upload,
-            total_download,
+            upload_speed: speed.upload,
+            download_speed: speed.download,
+            total_upload: session.upload,
+            total_download: session.download,
+            restart_count: self.restart_count.load(Ordering::SeqCst),
+            last_crash: self.last_crash.lock().unwrap().clone(),
+            udp_relay: if is_running { monitor.udp_status() } else { None },
+            total_restarts: self.total_restarts.load(Ordering::SeqCst),
+            last_error: if is_running { monitor.last_error() } else { None },
+            last_healthy_at: if is_running { monitor.last_healthy_at() } else { None },
+            healthy: is_running && monitor.is_alive(),
+            active_outbound: if is_running { self.active_outbound.lock().unwrap().clone() } else { None },
         })
     }
 
     /// 检查进程是否健康运行
     async fn is_process_healthy(&self) -> bool {
-        // 获取PID并立即释放锁
-        let pid_opt = {
-            let process = self.process.lock().unwrap();
-            process.as_ref().map(|child| child.id())
-        };
-        
-        if let Some(pid) = pid_opt {
-            // 使用 sysinfo 库检查进程是否存在
-            let mut system = System::new();
-            system.refresh_processes();
-            
-            // 检查指定PID的进程是否存在
-            let pid = sysinfo::Pid::from_u32(pid);
-            return system.process(pid).is_some();
-        }
-        false
+        self.core_pid().is_some_and(is_pid_alive)
     }
 
-    /// 测试服务器连接
-    /// 使用真实的 Xray 环境进行连接测试
-    pub async fn test_connection(&self, server: &ServerInfo) -> Result<bool> {
-        // 检查 Xray Core 是否存在
+    /// 使用 `xray -test` 校验配置
+    ///
+    /// # 参数
+    /// * `config` - 完整的 Xray 配置
+    ///
+    /// # 异常
+    /// * 当 Xray Core 不存在或配置校验失败时返回错误，错误信息包含 Xray 的输出
+    pub async fn validate_config(&self, config: &serde_json::Value) -> Result<()> {
         let xray_executable = AppConfig::xray_executable()?;
         if !xray_executable.exists() {
-            return Err(anyhow::anyhow!("Xray Core 可执行文件不存在: {}", xray_executable.display()));
+            return Err(AppError::new(ErrorCode::XrayMissing, format!("Xray Core 可执行文件不存在: {}", xray_executable.display())).into());
         }
 
-        // 生成测试用的 Xray 配置
-        let config = self.generate_xray_config(server)?;
-        
-        // 保存测试配置到临时文件
-        let config_path = self.save_test_config(&config)?;
-        
-        // 启动 Xray 进程进行测试
-        let output = TokioCommand::new(&xray_executable)
+        let mut command = TokioCommand::new(&xray_executable);
+        core_backend::prepare_command(core_backend::backend(core_backend::CoreKind::Xray), command.as_std_mut())?;
+        let config_path = self.save_test_config(config)?;
+        let output = command
             .arg("-config")
             .arg(&config_path)
-            .arg("-test")  // 使用测试模式
+            .arg("-test")
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
             .await
-            .context(format!("无法启动 Xray Core 进行测试: {}", xray_executable.display()))?;
-
-        // 清理测试配置文件
+            .context(format!("无法启动 Xray Core 进行测试: {}", xray_executable.display()));
         let _ = std::fs::remove_file(&config_path);
+        let output = output?;
 
-        // 检查测试结果
-        if output.status.success() {
-            Ok(true)
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if output.status.success() || stdout.contains("Configuration OK") {
+            Ok(())
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("Configuration OK") || stderr.is_empty() {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+            let message = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+            Err(anyhow::anyhow!("Xray 配置校验失败: {}", message))
         }
     }
 
@@ -471,60 +1485,104 @@ impl ProxyManager {
     /// 生成 Xray 配置
     pub fn generate_xray_config(&self, server: &ServerInfo) -> Result<serde_json::Value> {
         let config = AppConfig::load()?;
-        
-        let outbound = match server.protocol.as_str() {
-            "vmess" => self.generate_vmess_outbound(server)?,
-            "vless" => self.generate_vless_outbound(server)?,
-            "trojan" => self.generate_trojan_outbound(server)?,
-            "socks5" => self.generate_socks5_outbound(server)?,
-            "http" => self.generate_http_outbound(server)?,
-            _ => return Err(anyhow::anyhow!("不支持的协议: {}", server.protocol)),
+        self.build_xray_config(server, &config)
+    }
+
+    /// 根据指定的应用配置生成 Xray 配置
+    /// 用于在保存设置前使用修改后的配置进行校验
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `config` - 应用配置
+    pub fn build_xray_config(&self, server: &ServerInfo, config: &AppConfig) -> Result<serde_json::Value> {
+        self.build_xray_config_for(server, config, false)
+    }
+
+    /// 生成可脱离本应用独立运行的 Xray 配置，用于导出
+    /// 入站使用用户配置的端口而不是限速转发的内部端口，不包含本机的访问日志路径以及透明代理的入站和出站标记
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `config` - 应用配置
+    pub fn build_standalone_xray_config(&self, server: &ServerInfo, config: &AppConfig) -> Result<serde_json::Value> {
+        self.build_xray_config_for(server, config, true)
+    }
+
+    /// 生成 Xray 配置
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `config` - 应用配置
+    /// * `standalone` - 是否生成独立运行的配置（见 `build_standalone_xray_config`）
+    fn build_xray_config_for(&self, server: &ServerInfo, config: &AppConfig, standalone: bool) -> Result<serde_json::Value> {
+        let config = &config.with_server_overrides(server);
+        // 链式代理：代理出站依次经由 `dial_through` 指定的服务器拨号，最后一跳直接连接
+        let chain = Self::resolve_dial_chain(server, config)?;
+        let mut chain_outbounds = Vec::with_capacity(chain.len());
+        for (index, hop) in chain.iter().enumerate() {
+            let mut hop_outbound = self.generate_outbound(hop, config)?;
+            hop_outbound["tag"] = json!(Self::chain_tag(index));
+            if index + 1 < chain.len() {
+                set_dialer_proxy(&mut hop_outbound, &Self::chain_tag(index + 1));
+            } else if config.fragment.enabled {
+                set_dialer_proxy(&mut hop_outbound, "fragment");
+            }
+            chain_outbounds.push(hop_outbound);
+        }
+        let outbound = chain_outbounds.remove(0);
+
+        // 允许局域网连接时 UDP 中继地址使用本机局域网地址，局域网设备才能把 UDP 数据发到中继
+        let udp_relay_ip = if config.allow_lan {
+            SystemManager::lan_ip().map(|ip| ip.to_string()).unwrap_or_else(|| "127.0.0.1".to_string())
+        } else {
+            "127.0.0.1".to_string()
         };
+        // 启用限速时 HTTP/SOCKS 入站监听内部端口，用户配置的端口由限速转发占用
+        let backend_ports = if standalone { None } else { BandwidthLimiter::instance().backend_ports() };
+        let mut inbounds = vec![json!({
+            "tag": "api",
+            "port": config.api_port,
+            "listen": "127.0.0.1",
+            "protocol": "dokodemo-door",
+            "settings": {
+                "address": "127.0.0.1"
+            }
+        })];
+        for inbound in config.effective_inbounds() {
+            let port = match backend_ports {
+                Some((http, _)) if inbound.port == config.http_port => http,
+                Some((_, socks)) if inbound.port == config.socks_port => socks,
+                _ => inbound.port,
+            };
+            inbounds.push(inbound_json(&inbound, config, port, &udp_relay_ip));
+        }
 
-        let xray_config = json!({
+        let mut xray_config = json!({
             "log": {
-                "loglevel": config.log_level
+                "loglevel": config.core_log_level
             },
-            "inbounds": [
-                {
-                    "tag": "http",
-                    "port": config.http_port,
-                    "listen": "127.0.0.1",
-                    "protocol": "http",
-                    "sniffing": {
-                        "enabled": config.inbound_sniffing_enabled,
-                        "destOverride": [
-                            "http",
-                            "tls"
-                        ],
-                        "routeOnly": false
-                    },
-                    "settings": {
-                        "auth": config.inbound_auth_method,
-                        "udp": config.inbound_udp_enabled,
-                        "allowTransparent": config.inbound_allow_transparent
-                    }
+            // 启用 API 服务，供流量统计和运行中修改入站/出站使用
+            "api": {
+                "tag": "api",
+                "services": crate::xray_api::API_SERVICES
+            },
+            "stats": {},
+            "policy": {
+                "system": {
+                    "statsInboundUplink": true,
+                    "statsInboundDownlink": true,
+                    "statsOutboundUplink": true,
+                    "statsOutboundDownlink": true
                 },
-                {
-                    "tag": "socks",
-                    "port": config.socks_port,
-                    "listen": "127.0.0.1",
-                    "protocol": "mixed",
-                    "sniffing": {
-                        "enabled": config.inbound_sniffing_enabled,
-                        "destOverride": [
-                            "http",
-                            "tls"
-                        ],
-                        "routeOnly": false
-                    },
-                    "settings": {
-                        "auth": config.inbound_auth_method,
-                        "udp": config.inbound_udp_enabled,
-                        "allowTransparent": config.inbound_allow_transparent
+                // 入站认证时 Xray 以用户名作为统计标识，按用户统计流量
+                "levels": {
+                    "0": {
+                        "statsUserUplink": true,
+                        "statsUserDownlink": true
                     }
                 }
-            ],
+            },
+            "inbounds": inbounds,
             "outbounds": [
                 outbound,
                 {
@@ -538,7 +1596,15 @@ impl ProxyManager {
             ],
             "routing": {
                 "domainStrategy": config.routing_config.domain_strategy,
-                "rules": config.routing_config.rules.iter().map(|rule| {
+                "rules": std::iter::once(json!({
+                    "type": "field",
+                    "inboundTag": ["api"],
+                    "outboundTag": "api"
+                }))
+                // 临时覆盖与智能路由生成的规则优先于用户规则
+                .chain(DomainOverrides::instance().routing_rules())
+                .chain(SmartRouting::instance().routing_rules(config))
+                .chain(config.routing_config.rules.iter().map(|rule| {
                     let mut rule_json = json!({
                         "type": rule.rule_type,
                         "outboundTag": rule.outbound_tag
@@ -553,13 +1619,251 @@ impl ProxyManager {
                     }
                     
                     rule_json
-                }).collect::<Vec<_>>()
+                })).collect::<Vec<_>>()
             }
         });
 
+        // 访问日志记录每个连接的目标与出站，用于导出连接记录
+        if config.access_log_enabled && !standalone {
+            let access_log = connection_log::access_log_path(config);
+            // Xray 不会创建访问日志所在的目录
+            if let Some(dir) = access_log.parent() {
+                std::fs::create_dir_all(dir).context("无法创建访问日志目录")?;
+            }
+            xray_config["log"]["access"] = json!(access_log.to_string_lossy());
+        }
+
+        // 透明代理：接收防火墙重定向过来的 TCP 连接，并按原始目标地址转发
+        let transparent = cfg!(target_os = "linux") && config.transparent.enabled && !config.tun_enabled && !standalone;
+        if transparent {
+            if let Some(inbounds) = xray_config["inbounds"].as_array_mut() {
+                inbounds.push(json!({
+                    "tag": transparent::TRANSPARENT_INBOUND_TAG,
+                    "port": config.transparent.port,
+                    "listen": "127.0.0.1",
+                    "protocol": "dokodemo-door",
+                    "sniffing": {
+                        "enabled": true,
+                        "destOverride": ["http", "tls"]
+                    },
+                    "settings": {
+                        "network": "tcp",
+                        "followRedirect": true
+                    }
+                }));
+            }
+        }
+
+        if let Some(outbounds) = xray_config["outbounds"].as_array_mut() {
+            outbounds.extend(chain_outbounds);
+            if config.fragment.enabled {
+                outbounds.push(fragment_outbound(&config.fragment));
+            }
+            // 透明代理时为核心发出的连接打标记，防火墙规则据此放行，避免流量被重定向回核心
+            if transparent {
+                for outbound in outbounds.iter_mut().filter(|o| o["protocol"] != "blackhole") {
+                    outbound["streamSettings"]["sockopt"]["mark"] = json!(transparent::OUTBOUND_MARK);
+                }
+            }
+        }
+        // 替换路由规则与自定义入站/出站中的模板变量
+        let vars = TemplateVars::new(config, Some(server));
+        vars.apply(&mut xray_config["routing"]["rules"], "路由规则")?;
+        let mut custom_inbounds = json!(config.custom_inbounds);
+        let mut custom_outbounds = json!(config.custom_outbounds);
+        vars.apply(&mut custom_inbounds, "自定义入站")?;
+        vars.apply(&mut custom_outbounds, "自定义出站")?;
+        apply_custom_injections(
+            &mut xray_config,
+            custom_inbounds.as_array().map(Vec::as_slice).unwrap_or_default(),
+            custom_outbounds.as_array().map(Vec::as_slice).unwrap_or_default(),
+        );
         Ok(xray_config)
     }
 
+    /// 生成单个服务器的出站配置（包括 Mux 设置与自定义覆盖）
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `config` - 应用配置
+    fn generate_outbound(&self, server: &ServerInfo, config: &AppConfig) -> Result<serde_json::Value> {
+        let mut outbound = match server.protocol.as_str() {
+            "vmess" => self.generate_vmess_outbound(server)?,
+            "vless" => self.generate_vless_outbound(server)?,
+            "trojan" => self.generate_trojan_outbound(server)?,
+            "socks5" => self.generate_socks5_outbound(server)?,
+            "http" => self.generate_http_outbound(server)?,
+            _ => return Err(anyhow::anyhow!("不支持的协议: {}", server.protocol)),
+        };
+        apply_mux(&mut outbound, server, &config.mux);
+        self.apply_outbound_overrides(server, config, &mut outbound)?;
+        Ok(outbound)
+    }
+
+    /// 解析链式代理
+    /// 从指定服务器开始沿 `dial_through` 字段依次查找中转服务器
+    ///
+    /// # 参数
+    /// * `server` - 出口服务器
+    /// * `config` - 应用配置
+    ///
+    /// # 返回值
+    /// * `Result<Vec<ServerInfo>>` - 从出口服务器到第一跳的服务器列表，第一个元素为出口服务器本身
+    ///
+    /// # 异常
+    /// * 当中转服务器不存在或存在循环引用时返回错误
+    fn resolve_dial_chain(server: &ServerInfo, config: &AppConfig) -> Result<Vec<ServerInfo>> {
+        let mut chain = vec![server.clone()];
+        let mut current = server;
+
+        while let Some(next_id) = current.config.get("dial_through").and_then(|v| v.as_str()).filter(|id| !id.is_empty()) {
+            if chain.iter().any(|hop| hop.id == next_id) {
+                return Err(anyhow::anyhow!("链式代理存在循环引用: {}", next_id));
+            }
+            let next = config.servers.iter()
+                .find(|s| s.id == next_id)
+                .with_context(|| format!("链式代理的中转服务器不存在: {}", next_id))?;
+            chain.push(next.clone());
+            current = next;
+        }
+
+        Ok(chain)
+    }
+
+    /// 链式代理中各出站的标签，出口服务器为 `proxy`
+    fn chain_tag(index: usize) -> String {
+        if index == 0 {
+            "proxy".to_string()
+        } else {
+            format!("chain-{}", index)
+        }
+    }
+
+    /// 将服务器配置中的自定义模板与出站片段合并到生成的出站配置
+    /// 用于设置 RuRay 未建模的选项（如 mKCP seed、自定义 sockopt）：
+    /// * `outbound_template` - 自定义 JSON 模板文件路径，先合并
+    /// * `outbound_override` - 出站配置片段（JSON 对象或 JSON 字符串），后合并
+    ///
+    /// 两者中的模板变量（见 `template` 模块）在合并前替换
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `config` - 应用配置
+    /// * `outbound` - 生成的出站配置
+    ///
+    /// # 异常
+    /// * 当模板文件无法读取、内容不是 JSON 对象或包含未知模板变量时返回错误
+    fn apply_outbound_overrides(&self, server: &ServerInfo, config: &AppConfig, outbound: &mut serde_json::Value) -> Result<()> {
+        let vars = TemplateVars::new(config, Some(server));
+        if let Some(template_path) = server.config.get("outbound_template").and_then(|v| v.as_str()).filter(|p| !p.is_empty()) {
+            let content = std::fs::read_to_string(template_path)
+                .with_context(|| format!("无法读取出站模板: {}", template_path))?;
+            let mut template: serde_json::Value = serde_json::from_str(&content)
+                .with_context(|| format!("出站模板不是有效的 JSON: {}", template_path))?;
+            if !template.is_object() {
+                return Err(anyhow::anyhow!("出站模板必须是 JSON 对象: {}", template_path));
+            }
+            vars.apply(&mut template, &format!("出站模板 {}", template_path))?;
+            merge_json(outbound, &template);
+        }
+
+        let snippet = match server.config.get("outbound_override") {
+            Some(serde_json::Value::String(text)) if !text.trim().is_empty() => {
+                Some(serde_json::from_str::<serde_json::Value>(text).context("出站配置片段不是有效的 JSON")?)
+            }
+            Some(value) if value.is_object() => Some(value.clone()),
+            _ => None,
+        };
+        if let Some(mut snippet) = snippet {
+            if !snippet.is_object() {
+                return Err(anyhow::anyhow!("出站配置片段必须是 JSON 对象"));
+            }
+            vars.apply(&mut snippet, "出站配置片段")?;
+            merge_json(outbound, &snippet);
+        }
+
+        Ok(())
+    }
+
+    /// 导出完整的 Xray 配置包
+    /// 将生成的 Xray 配置、地理位置数据文件和运行说明写入指定目录，便于在服务器上无界面运行同样的配置；
+    /// 配置不包含限速、访问日志和透明代理等只在本机运行时使用的设置
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `output_dir` - 导出目录，不存在时自动创建
+    ///
+    /// # 返回值
+    /// * `Vec<PathBuf>` - 已写入的文件列表
+    ///
+    /// # 异常
+    /// * 当生成配置失败时返回错误
+    /// * 当写入导出目录失败时返回错误
+    pub fn export_config_bundle(&self, server: &ServerInfo, output_dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(output_dir)
+            .context("无法创建导出目录")?;
+
+        let mut written = Vec::new();
+
+        // 写入完整配置，使用用户配置的端口，不包含仅在本机运行时使用的设置
+        let app_config = AppConfig::load()?;
+        let config = self.build_standalone_xray_config(server, &app_config)?;
+        let config_path = output_dir.join("config.json");
+        let config_str = serde_json::to_string_pretty(&config)
+            .context("无法序列化 Xray 配置")?;
+        std::fs::write(&config_path, config_str)
+            .context("无法写入配置文件")?;
+        written.push(config_path);
+
+        // 复制配置中路由规则引用的地理位置数据文件
+        let xray_dir = AppConfig::xray_dir()?;
+        let mut missing_geo_files = Vec::new();
+        for file_name in ["geoip.dat", "geosite.dat"] {
+            let source = xray_dir.join(file_name);
+            if source.exists() {
+                let target = output_dir.join(file_name);
+                std::fs::copy(&source, &target)
+                    .context(format!("无法复制 {}", file_name))?;
+                written.push(target);
+            } else {
+                missing_geo_files.push(file_name);
+            }
+        }
+
+        // 写入运行说明
+        let app_config = app_config.with_server_overrides(server);
+        let mut readme = format!(
+            "RuRay 导出的 Xray 配置\n\
+             服务器: {} ({}:{})\n\
+             导出时间: {}\n\n\
+             运行方式（在本目录下执行）:\n\
+             \x20 Linux/macOS: XRAY_LOCATION_ASSET=. xray run -c config.json\n\
+             \x20 Windows:     set XRAY_LOCATION_ASSET=.\n\
+             \x20              xray.exe run -c config.json\n\n\
+             本地入站: HTTP 127.0.0.1:{}，SOCKS/Mixed 127.0.0.1:{}\n\
+             如需在服务器上对外提供服务，请将 config.json 中 inbounds 的 listen 改为 0.0.0.0 并开启认证。\n",
+            server.name,
+            server.address,
+            server.port,
+            chrono::Utc::now().to_rfc3339(),
+            app_config.http_port,
+            app_config.socks_port,
+        );
+        if !missing_geo_files.is_empty() {
+            readme.push_str(&format!(
+                "\n注意: 本机缺少 {}，请自行下载后放入本目录，否则引用 geoip:/geosite: 的路由规则无法加载。\n",
+                missing_geo_files.join("、")
+            ));
+        }
+        let readme_path = output_dir.join("README.txt");
+        std::fs::write(&readme_path, readme)
+            .context("无法写入运行说明")?;
+        written.push(readme_path);
+
+        log_info!("已导出服务器 {} 的 Xray 配置包到 {}", server.name, output_dir.display());
+        Ok(written)
+    }
+
     /// 生成 VMess 出站配置
     fn generate_vmess_outbound(&self, server: &ServerInfo) -> Result<serde_json::Value> {
         let uuid = server.config.get("uuid")
@@ -738,16 +2042,6 @@ impl ProxyManager {
 
         outbound["streamSettings"] = stream_settings;
 
-        // 添加 mux 设置
-        let mux_enabled = server.config.get("mux")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
-
-        outbound["mux"] = json!({
-            "enabled": mux_enabled,
-            "concurrency": if mux_enabled { 8 } else { -1 }
-        });
-
         Ok(outbound)
     }
 
@@ -818,7 +2112,7 @@ impl ProxyManager {
     /// 
     /// # 返回值
     /// * `PathBuf` - 配置文件的完整路径
-    fn save_temp_config(&self, config: &serde_json::Value, server: &ServerInfo, force_recreate: bool) -> Result<std::path::PathBuf> {
+    pub(crate) fn save_temp_config(&self, config: &serde_json::Value, server: &ServerInfo, force_recreate: bool) -> Result<std::path::PathBuf> {
         let config_dir = AppConfig::servers_dir()?;
         
         // 生成唯一的配置文件名：服务器ID_服务器名称_xray_config.json
@@ -844,6 +2138,41 @@ impl ProxyManager {
         Ok(config_path)
     }
 
+    /// 读取已保存的核心配置文件，文件不存在或不是有效的 JSON 时返回 None
+    fn read_config_file(path: &Path) -> Option<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    /// 检查已保存的配置文件的入站是否与新生成的配置不同
+    /// 限速转发的内部端口在每次启动时重新分配，透明代理会增减入站，
+    /// 入站（标签、协议、端口）不一致的旧配置文件会让核心监听错误的端口，必须重新生成
+    ///
+    /// # 参数
+    /// * `path` - 已保存的配置文件路径
+    /// * `config` - 新生成的配置
+    ///
+    /// # 返回值
+    /// * `bool` - 入站是否不同；文件不存在或无法解析时返回 false
+    pub(crate) fn saved_inbounds_differ(&self, path: &Path, config: &serde_json::Value) -> bool {
+        let Some(saved) = Self::read_config_file(path) else {
+            return false;
+        };
+        let inbounds = |value: &serde_json::Value| -> Vec<(String, String, u64)> {
+            value["inbounds"].as_array()
+                .map(|inbounds| inbounds.iter()
+                    .map(|inbound| (
+                        inbound["tag"].as_str().unwrap_or_default().to_string(),
+                        inbound["protocol"].as_str().unwrap_or_default().to_string(),
+                        inbound["port"].as_u64().unwrap_or_default(),
+                    ))
+                    .collect())
+                .unwrap_or_default()
+        };
+        inbounds(&saved) != inbounds(config)
+    }
+
     /// 重新生成服务器配置文件
     /// 强制重新生成指定服务器的配置文件，覆盖现有文件
     /// 
@@ -888,4 +2217,176 @@ impl ProxyManager {
         let config_filename = format!("{}_{}_xray_config.json", server_id, safe_name);
         config_dir.join(config_filename)
     }
-}
\ No newline at end of file
+}
+
+/// 生成配置中内置的入站与出站标签，自定义入站/出站不能使用（链式代理另外占用 `chain-` 前缀）
+pub(crate) const RESERVED_TAGS: [&str; 7] = ["api", "http", "socks", "proxy", "direct", "block", "fragment"];
+
+/// 为出站添加 Mux 设置
+/// 仅作用于 VMess/VLESS/Trojan；服务器配置中的 `mux` 字段优先于全局设置，
+/// 使用 XTLS flow 的 VLESS 出站不支持 Mux，始终关闭
+///
+/// # 参数
+/// * `outbound` - 出站配置
+/// * `server` - 服务器信息
+/// * `mux` - 全局 Mux 配置
+fn apply_mux(outbound: &mut serde_json::Value, server: &ServerInfo, mux: &MuxConfig) {
+    if !matches!(server.protocol.as_str(), "vmess" | "vless" | "trojan") {
+        return;
+    }
+
+    let has_flow = server.config.get("flow")
+        .and_then(|v| v.as_str())
+        .is_some_and(|flow| !flow.is_empty());
+    let enabled = !has_flow && server.config.get("mux")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(mux.enabled);
+
+    outbound["mux"] = if enabled {
+        json!({
+            "enabled": true,
+            "concurrency": mux.concurrency,
+            "xudpConcurrency": mux.xudp_concurrency,
+            "xudpProxyUDP443": mux.xudp_proxy_udp443
+        })
+    } else {
+        json!({
+            "enabled": false,
+            "concurrency": -1
+        })
+    };
+}
+
+/// 让出站经由指定出站拨号（链式代理的下一跳或分片出站）
+///
+/// # 参数
+/// * `outbound` - 出站配置
+/// * `dialer_tag` - 拨号使用的出站标签
+fn set_dialer_proxy(outbound: &mut serde_json::Value, dialer_tag: &str) {
+    if outbound.get("streamSettings").is_none_or(|v| !v.is_object()) {
+        outbound["streamSettings"] = json!({});
+    }
+    outbound["streamSettings"]["sockopt"]["dialerProxy"] = json!(dialer_tag);
+}
+
+/// 生成带 TLS 分片设置的 freedom 出站
+///
+/// # 参数
+/// * `fragment` - 分片配置
+fn fragment_outbound(fragment: &FragmentConfig) -> serde_json::Value {
+    json!({
+        "tag": "fragment",
+        "protocol": "freedom",
+        "settings": {
+            "fragment": {
+                "packets": fragment.packets,
+                "length": fragment.length,
+                "interval": fragment.interval
+            }
+        },
+        "streamSettings": {
+            "sockopt": {
+                "tcpNoDelay": true
+            }
+        }
+    })
+}
+
+/// 生成 HTTP/SOCKS/Mixed/任意门入站
+///
+/// # 参数
+/// * `inbound` - 入站配置
+/// * `config` - 应用配置
+/// * `port` - 实际监听的端口（启用限速时为内部端口）
+/// * `udp_relay_ip` - 监听所有地址时 SOCKS UDP 中继使用的地址
+fn inbound_json(inbound: &InboundConfig, config: &AppConfig, port: u16, udp_relay_ip: &str) -> serde_json::Value {
+    let listen = config.listen_of(inbound);
+    let sniffing = config.sniffing_of(inbound);
+    let mut value = json!({
+        "tag": inbound.tag(),
+        "port": port,
+        "listen": listen,
+        "sniffing": {
+            "enabled": sniffing.enabled,
+            "destOverride": sniffing.dest_override,
+            "routeOnly": sniffing.route_only
+        }
+    });
+    // 入站认证时 HTTP 与 SOCKS 入站接受其中任一用户
+    let accounts: Option<Vec<serde_json::Value>> = config.inbound_auth_enabled().then(|| {
+        config.inbound_users.iter()
+            .map(|user| json!({ "user": user.username, "pass": user.password }))
+            .collect()
+    });
+    match inbound.protocol {
+        InboundProtocol::Http => {
+            value["protocol"] = json!("http");
+            value["settings"] = json!({
+                "allowTransparent": config.inbound_allow_transparent
+            });
+        }
+        InboundProtocol::Socks | InboundProtocol::Mixed => {
+            value["protocol"] = json!(if inbound.protocol == InboundProtocol::Mixed { "mixed" } else { "socks" });
+            // UDP ASSOCIATE 回复中的中继地址需与监听地址一致，否则客户端无法发送 UDP 数据
+            let relay_ip = if listen == "0.0.0.0" || listen == "::" { udp_relay_ip.to_string() } else { listen.clone() };
+            value["settings"] = json!({
+                "auth": if accounts.is_some() { "password" } else { "noauth" },
+                "udp": config.inbound_udp_enabled,
+                "ip": relay_ip,
+                "allowTransparent": config.inbound_allow_transparent
+            });
+        }
+        InboundProtocol::Dokodemo => {
+            value["protocol"] = json!("dokodemo-door");
+            value["settings"] = json!({
+                "address": inbound.target_address,
+                "port": inbound.target_port,
+                "network": inbound.network.as_deref().unwrap_or("tcp,udp")
+            });
+        }
+    }
+    if let Some(accounts) = accounts.filter(|_| inbound.protocol != InboundProtocol::Dokodemo) {
+        value["settings"]["accounts"] = json!(accounts);
+    }
+    value
+}
+
+/// 将自定义入站与出站原样追加到生成的 Xray 配置
+///
+/// # 参数
+/// * `xray_config` - 生成的 Xray 配置
+/// * `inbounds` - 自定义入站
+/// * `outbounds` - 自定义出站
+pub(crate) fn apply_custom_injections(
+    xray_config: &mut serde_json::Value,
+    inbounds: &[serde_json::Value],
+    outbounds: &[serde_json::Value],
+) {
+    if let Some(list) = xray_config["inbounds"].as_array_mut() {
+        list.extend(inbounds.iter().cloned());
+    }
+    if let Some(list) = xray_config["outbounds"].as_array_mut() {
+        list.extend(outbounds.iter().cloned());
+    }
+}
+
+/// 将 `patch` 深度合并到 `base`
+/// 对象按键递归合并，`null` 表示删除该键，其余类型（包括数组）直接替换
+///
+/// # 参数
+/// * `base` - 被合并的 JSON
+/// * `patch` - 合并内容
+pub(crate) fn merge_json(base: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base_map), serde_json::Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                if value.is_null() {
+                    base_map.remove(key);
+                } else {
+                    merge_json(base_map.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
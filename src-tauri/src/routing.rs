@@ -0,0 +1,349 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 路由规则模拟
+// 按 Xray 的规则语义依次检查当前路由规则，给出访问某个域名或 IP 时命中的规则与出站，
+// 便于排查规则而无需抓包。域名条目支持 `domain:`、`full:`、`regexp:`、`keyword:`、`dotless:`、
+// `geosite:` 与 `ext:`，IP 条目支持 IP/CIDR、`geoip:`（含 `!` 取反）与 `ext:`。
+// 同一规则中同时配置域名与 IP 时需要都满足；域名是否解析为 IP 参与匹配取决于 domainStrategy：
+// * `AsIs` - 不解析，域名只能命中域名条目
+// * `IPIfNonMatch` - 所有规则都未按域名命中时解析后再按 IP 匹配一轮
+// * `IPOnDemand` - 遇到 IP 条目时立即解析
+// 解析使用本机 DNS，与核心内置 DNS 的结果可能不同
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use crate::config::{AppConfig, RoutingRule};
+use crate::core_backend::CoreKind;
+use crate::domain_override::DomainOverrides;
+use crate::geodata::{self, GeoIpList, GeoSiteList, GEOIP_FILE, GEOSITE_FILE};
+use crate::smart_routing::SmartRouting;
+use crate::template::TemplateVars;
+
+/// 没有规则命中时使用的出站（核心配置中的第一个出站）
+const DEFAULT_OUTBOUND_TAG: &str = "proxy";
+
+/// 命中的规则
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteMatch {
+    /// 规则序号（从 0 开始，对应路由设置或生成规则中的顺序）
+    pub rule_index: usize,
+    /// 是否为临时覆盖或智能路由生成的规则（不在路由设置中）
+    pub generated: bool,
+    /// 规则内容（已替换模板变量）
+    pub rule: RoutingRule,
+    /// 命中的域名条目
+    pub matched_domain: Option<String>,
+    /// 命中的 IP 条目，以及命中它的 IP
+    pub matched_ip: Option<String>,
+}
+
+/// 路由模拟结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteSimulation {
+    /// 用户输入的目标
+    pub target: String,
+    /// 从目标中提取的域名或 IP
+    pub host: String,
+    /// 使用的域名策略
+    pub domain_strategy: String,
+    /// 参与匹配的解析结果（未解析时为空）
+    pub resolved_ips: Vec<String>,
+    /// 命中的规则，为空表示没有规则命中
+    pub matched: Option<RouteMatch>,
+    /// 最终使用的出站标签
+    pub outbound_tag: String,
+    /// 模拟过程中的提示，如数据文件缺失、正则无效等
+    pub notes: Vec<String>,
+}
+
+/// 按需加载的地理位置数据，同一次模拟中每个文件只读取一次
+#[derive(Default)]
+struct GeoCache {
+    sites: HashMap<String, Option<GeoSiteList>>,
+    ips: HashMap<String, Option<GeoIpList>>,
+    notes: Vec<String>,
+}
+
+impl GeoCache {
+    fn site_list(&mut self, file_name: &str) -> Option<&GeoSiteList> {
+        let notes = &mut self.notes;
+        self.sites.entry(file_name.to_string())
+            .or_insert_with(|| GeoSiteList::load(file_name).map_err(|e| notes.push(format!("{:#}", e))).ok())
+            .as_ref()
+    }
+
+    fn ip_list(&mut self, file_name: &str) -> Option<&GeoIpList> {
+        let notes = &mut self.notes;
+        self.ips.entry(file_name.to_string())
+            .or_insert_with(|| GeoIpList::load(file_name).map_err(|e| notes.push(format!("{:#}", e))).ok())
+            .as_ref()
+    }
+
+    /// 检查 `geosite:` / `ext:` 引用的分类，`code` 可带 `@属性`
+    fn match_site(&mut self, file_name: &str, code: &str, domain: &str) -> bool {
+        let (code, attribute) = match code.split_once('@') {
+            Some((code, attribute)) => (code, Some(attribute)),
+            None => (code, None),
+        };
+        let Some(list) = self.site_list(file_name) else { return false };
+        match list.find(code) {
+            Some(site) => site.find_match(domain, attribute).is_some(),
+            None => {
+                self.notes.push(format!("{} 中没有分类 {}", file_name, code));
+                false
+            }
+        }
+    }
+
+    /// 检查 `geoip:` / `ext:` 引用的分类，`code` 以 `!` 开头表示不在该分类中
+    fn match_ip(&mut self, file_name: &str, code: &str, ip: IpAddr) -> Option<String> {
+        let (code, negate) = match code.strip_prefix('!') {
+            Some(code) => (code, true),
+            None => (code, false),
+        };
+        let found = match self.ip_list(file_name) {
+            Some(list) => match list.find(code) {
+                Some(geoip) => geoip.find_match(ip),
+                None => {
+                    self.notes.push(format!("{} 中没有分类 {}", file_name, code));
+                    return None;
+                }
+            },
+            // 缺少数据文件时 private 仍可按内置网段判断
+            None if code.eq_ignore_ascii_case("private") => is_private(ip).then(|| "private".to_string()),
+            None => return None,
+        };
+        match (found, negate) {
+            (Some(net), false) => Some(net),
+            (None, true) => Some(format!("!{}", code)),
+            _ => None,
+        }
+    }
+
+    /// 检查单个域名条目
+    fn match_domain_entry(&mut self, entry: &str, domain: &str) -> bool {
+        match entry.split_once(':') {
+            Some(("domain", value)) => geodata::domain_matches(domain, &value.to_ascii_lowercase()),
+            Some(("full", value)) => domain == value.to_ascii_lowercase(),
+            Some(("keyword", value)) => domain.contains(&value.to_ascii_lowercase()),
+            Some(("dotless", value)) => !domain.contains('.') && domain.contains(&value.to_ascii_lowercase()),
+            Some(("regexp", value)) => match regex::Regex::new(value) {
+                Ok(re) => re.is_match(domain),
+                Err(e) => {
+                    self.notes.push(format!("无效的正则表达式 {}: {}", value, e));
+                    false
+                }
+            },
+            Some(("geosite", code)) => self.match_site(GEOSITE_FILE, code, domain),
+            Some(("ext", reference)) => match reference.split_once(':') {
+                Some((file_name, code)) => self.match_site(file_name, code, domain),
+                None => false,
+            },
+            _ => domain.contains(&entry.to_ascii_lowercase()),
+        }
+    }
+
+    /// 检查单个 IP 条目
+    fn match_ip_entry(&mut self, entry: &str, ip: IpAddr) -> Option<String> {
+        if let Some(code) = entry.strip_prefix("geoip:") {
+            return self.match_ip(GEOIP_FILE, code, ip);
+        }
+        if let Some(reference) = entry.strip_prefix("ext:") {
+            let (file_name, code) = reference.split_once(':')?;
+            return self.match_ip(file_name, code, ip);
+        }
+        let contained = match entry.parse::<ipnet::IpNet>() {
+            Ok(net) => net.contains(&ip),
+            Err(_) => entry.parse::<IpAddr>().is_ok_and(|addr| addr == ip),
+        };
+        contained.then(|| entry.to_string())
+    }
+
+    /// 检查规则是否命中
+    ///
+    /// # 返回值
+    /// * `Option<(Option<String>, Option<String>)>` - 命中时返回命中的域名条目与 IP 条目
+    fn match_rule(&mut self, rule: &RoutingRule, domain: Option<&str>, ips: &[IpAddr]) -> Option<(Option<String>, Option<String>)> {
+        let domains = rule.domain.as_ref().filter(|d| !d.is_empty());
+        let rule_ips = rule.ip.as_ref().filter(|i| !i.is_empty());
+        if domains.is_none() && rule_ips.is_none() {
+            return None;
+        }
+
+        let matched_domain = match domains {
+            Some(entries) => {
+                let domain = domain?;
+                Some(entries.iter().find(|entry| self.match_domain_entry(entry, domain))?.clone())
+            }
+            None => None,
+        };
+        let matched_ip = match rule_ips {
+            Some(entries) => Some(ips.iter().find_map(|ip| {
+                entries.iter()
+                    .find_map(|entry| self.match_ip_entry(entry, *ip))
+                    .map(|net| format!("{} ({})", net, ip))
+            })?),
+            None => None,
+        };
+        Some((matched_domain, matched_ip))
+    }
+
+    /// 按顺序查找第一条命中的规则
+    fn first_match(&mut self, rules: &[RoutingRule], domain: Option<&str>, ips: &[IpAddr]) -> Option<RouteMatch> {
+        rules.iter().enumerate().find_map(|(rule_index, rule)| {
+            self.match_rule(rule, domain, ips).map(|(matched_domain, matched_ip)| RouteMatch {
+                rule_index,
+                generated: false,
+                rule: rule.clone(),
+                matched_domain,
+                matched_ip,
+            })
+        })
+    }
+}
+
+/// 内置私有网段判断，用于缺少 geoip.dat 时的 `geoip:private`
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || v4.is_broadcast(),
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback() || v6.is_unspecified() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// 从用户输入中提取域名或 IP，支持 URL、`host:port` 与 `[IPv6]:port`
+pub(crate) fn parse_target(target: &str) -> Result<String> {
+    let target = target.trim();
+    let host = if target.contains("://") {
+        let url = url::Url::parse(target).context("无效的 URL")?;
+        url.host_str().context("URL 中没有主机名")?.to_string()
+    } else if let Ok(addr) = target.parse::<std::net::SocketAddr>() {
+        addr.ip().to_string()
+    } else if target.parse::<IpAddr>().is_ok() {
+        target.to_string()
+    } else {
+        // 去掉路径与端口
+        let host = target.split('/').next().unwrap_or_default();
+        host.rsplit_once(':').map_or(host, |(host, _)| host).to_string()
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']').trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() {
+        return Err(anyhow::anyhow!("请输入域名、IP 或 URL"));
+    }
+    Ok(host)
+}
+
+/// 使用本机 DNS 解析域名
+async fn resolve(host: &str, notes: &mut Vec<String>) -> Vec<IpAddr> {
+    match tokio::net::lookup_host((host, 0)).await {
+        Ok(addrs) => {
+            let mut ips: Vec<IpAddr> = Vec::new();
+            for addr in addrs {
+                if !ips.contains(&addr.ip()) {
+                    ips.push(addr.ip());
+                }
+            }
+            ips
+        }
+        Err(e) => {
+            notes.push(format!("无法解析 {}: {}", host, e));
+            Vec::new()
+        }
+    }
+}
+
+/// 按核心配置中的顺序收集路由规则（已替换模板变量）
+/// 临时覆盖与智能路由生成的规则排在用户规则之前
+///
+/// # 参数
+/// * `config` - 应用配置
+///
+/// # 返回值
+/// * `Result<(Vec<RoutingRule>, usize)>` - 全部规则与其中生成规则的数量
+pub(crate) fn collect_rules(config: &AppConfig) -> Result<(Vec<RoutingRule>, usize)> {
+    // 与生成核心配置时一样替换规则中的模板变量
+    let server = config.current_server.as_ref()
+        .and_then(|id| config.servers.iter().find(|s| &s.id == id));
+    let mut rules_json = serde_json::to_value(&config.routing_config.rules)?;
+    TemplateVars::new(config, server).apply(&mut rules_json, "路由规则")?;
+    let user_rules: Vec<RoutingRule> = serde_json::from_value(rules_json)?;
+    let generated_rules: Vec<RoutingRule> = DomainOverrides::instance().routing_rules()
+        .into_iter()
+        .chain(SmartRouting::instance().routing_rules(config))
+        .filter_map(|rule| serde_json::from_value(rule).ok())
+        .collect();
+    let generated_count = generated_rules.len();
+    Ok((generated_rules.into_iter().chain(user_rules).collect(), generated_count))
+}
+
+/// 模拟访问目标时的路由结果
+///
+/// # 参数
+/// * `config` - 应用配置
+/// * `target` - 域名、IP 或 URL
+///
+/// # 返回值
+/// * `Result<RouteSimulation>` - 模拟结果
+pub async fn simulate(config: &AppConfig, target: &str) -> Result<RouteSimulation> {
+    let host = parse_target(target)?;
+    let (rules, generated_count) = collect_rules(config)?;
+
+    let strategy = config.routing_config.domain_strategy.clone();
+    let mut cache = GeoCache::default();
+    let mut resolved: Vec<IpAddr> = Vec::new();
+
+    let matched = if let Ok(ip) = host.parse::<IpAddr>() {
+        cache.first_match(&rules, None, &[ip])
+    } else {
+        let has_ip_rules = rules.iter().any(|r| r.ip.as_ref().is_some_and(|i| !i.is_empty()));
+        match strategy.as_str() {
+            "IPOnDemand" if has_ip_rules => {
+                resolved = resolve(&host, &mut cache.notes).await;
+                cache.first_match(&rules, Some(&host), &resolved)
+            }
+            "IPIfNonMatch" if has_ip_rules => match cache.first_match(&rules, Some(&host), &[]) {
+                Some(matched) => Some(matched),
+                None => {
+                    resolved = resolve(&host, &mut cache.notes).await;
+                    cache.first_match(&rules, Some(&host), &resolved)
+                }
+            },
+            _ => cache.first_match(&rules, Some(&host), &[]),
+        }
+    };
+
+    let matched = matched.map(|mut matched| {
+        matched.generated = matched.rule_index < generated_count;
+        if !matched.generated {
+            matched.rule_index -= generated_count;
+        }
+        matched
+    });
+
+    let mut notes = cache.notes;
+    if config.core == CoreKind::SingBox {
+        notes.push("当前使用 sing-box 核心，除 geoip:private 外的 geosite/geoip 条目会被跳过，实际结果可能不同".to_string());
+    }
+    let mut seen = HashSet::new();
+    notes.retain(|note| seen.insert(note.clone()));
+
+    Ok(RouteSimulation {
+        target: target.to_string(),
+        host,
+        domain_strategy: strategy,
+        resolved_ips: resolved.iter().map(IpAddr::to_string).collect(),
+        outbound_tag: matched.as_ref().map_or_else(|| DEFAULT_OUTBOUND_TAG.to_string(), |m| m.rule.outbound_tag.clone()),
+        matched,
+        notes,
+    })
+}
@@ -0,0 +1,345 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 自检
+// 按顺序检查：核心存在、配置有效、端口空闲、进程能启动、本地入站可连接、经代理的出口 IP、DNS 是否泄漏。
+// 进程相关的检查使用与测速相同的临时 Xray 实例，不影响正在运行的代理；
+// 前置检查失败时后续依赖它的检查标记为跳过
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::Instant;
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::log_info;
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+use crate::core_backend::{self, CoreKind};
+use crate::monitor::lookup_external_ip;
+use crate::proxy::ProxyManager;
+use crate::speed_test::SpeedTestManager;
+use crate::system::SystemManager;
+
+/// 临时实例启动后等待入站就绪的时间
+const INBOUND_READY_TIMEOUT: Duration = Duration::from_secs(5);
+/// 判断进程是否立即退出前的等待时间
+const PROCESS_SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// DNS 泄漏检测超时时间
+const DNS_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    /// 通过
+    Passed,
+    /// 通过但有需要注意的问题
+    Warning,
+    /// 失败
+    Failed,
+    /// 前置检查失败或不适用，未执行
+    Skipped,
+}
+
+/// 单项检查
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    /// 检查标识：core_present、config_valid、ports_free、process_starts、inbound_reachable、external_ip、dns_leak
+    pub id: String,
+    /// 检查名称
+    pub name: String,
+    /// 结果
+    pub status: StepStatus,
+    /// 结果说明
+    pub message: String,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// 使用的服务器ID
+    pub server_id: String,
+    /// 使用的服务器名称
+    pub server_name: String,
+    /// 所有检查均未失败
+    pub passed: bool,
+    /// 各项检查，按执行顺序排列
+    pub steps: Vec<SelfTestStep>,
+    /// 开始时间
+    pub started_at: String,
+}
+
+impl SelfTestReport {
+    /// 记录一项检查
+    fn record(&mut self, id: &str, name: &str, started: Instant, status: StepStatus, message: impl Into<String>) {
+        self.steps.push(SelfTestStep {
+            id: id.to_string(),
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// 记录检查结果，出错时记为失败
+    fn record_result(&mut self, id: &str, name: &str, started: Instant, result: Result<(StepStatus, String)>) -> bool {
+        match result {
+            Ok((status, message)) => {
+                self.record(id, name, started, status, message);
+                status != StepStatus::Failed
+            }
+            Err(e) => {
+                self.record(id, name, started, StepStatus::Failed, format!("{:#}", e));
+                false
+            }
+        }
+    }
+
+    /// 记录跳过的检查
+    fn skip(&mut self, id: &str, name: &str, reason: &str) {
+        self.record(id, name, Instant::now(), StepStatus::Skipped, reason);
+    }
+}
+
+/// 对服务器执行自检
+///
+/// # 参数
+/// * `server` - 服务器信息
+///
+/// # 返回值
+/// * `Result<SelfTestReport>` - 自检报告，各项检查的失败记录在报告中
+pub async fn run(server: &ServerInfo) -> Result<SelfTestReport> {
+    let config = AppConfig::load()?;
+    let backend = core_backend::backend(config.core);
+    let mut report = SelfTestReport {
+        server_id: server.id.clone(),
+        server_name: server.name.clone(),
+        passed: false,
+        steps: Vec::new(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    // 1. 核心存在
+    let started = Instant::now();
+    let core_present = report.record_result("core_present", "代理核心", started, (|| {
+        let executable = backend.executable()?;
+        if !executable.exists() {
+            return Err(anyhow::anyhow!("{} 可执行文件不存在: {}", backend.name(), executable.display()));
+        }
+        let version = backend.version().unwrap_or_else(|_| "未知版本".to_string());
+        Ok((StepStatus::Passed, format!("{} {}", backend.name(), version)))
+    })());
+
+    // 2. 配置有效
+    let config_valid = if core_present {
+        let started = Instant::now();
+        let result = check_config(config.core, server).await;
+        report.record_result("config_valid", "核心配置", started, result)
+    } else {
+        report.skip("config_valid", "核心配置", "代理核心不存在");
+        false
+    };
+
+    // 3. 端口空闲（临时实例使用其他端口，端口被占用不影响后续检查）
+    let started = Instant::now();
+    let result = check_ports(&config);
+    report.record_result("ports_free", "本地端口", started, result);
+
+    // 4-7. 临时实例上的运行检查
+    let runtime_steps = [
+        ("process_starts", "进程启动"),
+        ("inbound_reachable", "本地入站"),
+        ("external_ip", "出口 IP"),
+        ("dns_leak", "DNS 泄漏"),
+    ];
+    if !config_valid {
+        for (id, name) in runtime_steps {
+            report.skip(id, name, "核心配置无效");
+        }
+    } else if config.core != CoreKind::Xray {
+        for (id, name) in runtime_steps {
+            report.skip(id, name, "运行检查仅支持 Xray Core");
+        }
+    } else {
+        run_instance_checks(server, &mut report).await;
+    }
+
+    report.passed = report.steps.iter().all(|step| step.status != StepStatus::Failed);
+    log_info!(
+        "服务器 {} 自检完成: {}",
+        server.name,
+        if report.passed { "全部通过" } else { "存在失败项" }
+    );
+    Ok(report)
+}
+
+/// 生成配置并使用核心自带的检查命令校验
+async fn check_config(core: CoreKind, server: &ServerInfo) -> Result<(StepStatus, String)> {
+    let backend = core_backend::backend(core);
+    let generated = backend.generate_config(server)?;
+    let config_path = std::env::temp_dir().join(format!("ruray_self_test_{}.json", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&config_path, serde_json::to_string_pretty(&generated)?).context("写入临时配置失败")?;
+
+    let args: Vec<std::ffi::OsString> = match core {
+        CoreKind::Xray => vec!["-test".into(), "-config".into(), config_path.clone().into()],
+        CoreKind::SingBox => vec!["check".into(), "-c".into(), config_path.clone().into()],
+    };
+    let mut command = tokio::process::Command::new(backend.executable()?);
+    core_backend::prepare_command(backend, command.as_std_mut())?;
+    command.args(&args).stdin(std::process::Stdio::null());
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = command.output().await;
+    let _ = std::fs::remove_file(&config_path);
+    let output = output.context("执行配置检查失败")?;
+
+    if output.status.success() {
+        Ok((StepStatus::Passed, "配置检查通过".to_string()))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let detail = if stderr.trim().is_empty() { stdout } else { stderr };
+        Err(anyhow::anyhow!("配置检查失败: {}", detail.trim()))
+    }
+}
+
+/// 检查 HTTP、SOCKS 与 API 端口是否空闲
+/// 代理运行中时端口由核心占用，视为通过
+fn check_ports(config: &AppConfig) -> Result<(StepStatus, String)> {
+    if ProxyManager::instance().is_process_running() {
+        return Ok((StepStatus::Passed, "代理运行中，端口由代理核心使用".to_string()));
+    }
+
+    let system = SystemManager::new();
+    let mut busy = Vec::new();
+    for (name, port) in [("HTTP", config.http_port), ("SOCKS", config.socks_port), ("API", config.api_port)] {
+        if !system.is_port_available(port) {
+            let owner = system.find_port_owner(port)
+                .map(|owner| format!("{} (PID {})", owner.name.unwrap_or_else(|| "未知进程".to_string()), owner.pid))
+                .unwrap_or_else(|| "未知进程".to_string());
+            busy.push(format!("{} 端口 {} 被 {} 占用", name, port, owner));
+        }
+    }
+
+    if busy.is_empty() {
+        Ok((StepStatus::Passed, format!("端口 {}、{}、{} 均空闲", config.http_port, config.socks_port, config.api_port)))
+    } else if config.auto_port {
+        Ok((StepStatus::Warning, format!("{}，启动时将自动改用空闲端口", busy.join("；"))))
+    } else {
+        Err(anyhow::anyhow!("{}", busy.join("；")))
+    }
+}
+
+/// 启动临时实例并依次执行进程、入站、出口 IP 与 DNS 检查
+async fn run_instance_checks(server: &ServerInfo, report: &mut SelfTestReport) {
+    let started = Instant::now();
+    let (mut child, port, config_path) = match SpeedTestManager::new().spawn_instance(server).await {
+        Ok(instance) => instance,
+        Err(e) => {
+            report.record("process_starts", "进程启动", started, StepStatus::Failed, format!("{:#}", e));
+            report.skip("inbound_reachable", "本地入站", "进程未能启动");
+            report.skip("external_ip", "出口 IP", "进程未能启动");
+            report.skip("dns_leak", "DNS 泄漏", "进程未能启动");
+            return;
+        }
+    };
+
+    tokio::time::sleep(PROCESS_SETTLE_DELAY).await;
+    let exited = child.try_wait().ok().flatten();
+    let process_ok = report.record_result("process_starts", "进程启动", started, match exited {
+        Some(status) => Err(anyhow::anyhow!("进程启动后立即退出: {}", status)),
+        None => Ok((StepStatus::Passed, format!("临时实例已启动（PID {}）", child.id().unwrap_or_default()))),
+    });
+
+    let inbound_ok = if process_ok {
+        let started = Instant::now();
+        let result = SpeedTestManager::wait_for_port(port, INBOUND_READY_TIMEOUT)
+            .await
+            .map(|_| (StepStatus::Passed, format!("127.0.0.1:{} 可连接", port)));
+        report.record_result("inbound_reachable", "本地入站", started, result)
+    } else {
+        report.skip("inbound_reachable", "本地入站", "进程未能启动");
+        false
+    };
+
+    if inbound_ok {
+        let proxy_url = format!("http://127.0.0.1:{}", port);
+
+        let started = Instant::now();
+        let (direct, proxied) = tokio::join!(lookup_external_ip(None), lookup_external_ip(Some(&proxy_url)));
+        let direct_country = direct.as_ref().ok().and_then(|info| info.country.clone());
+        let proxy_country = proxied.as_ref().ok().and_then(|info| info.country.clone());
+        let result = proxied.map(|info| {
+            let location = [info.country.as_deref(), info.asn.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            match &direct {
+                Ok(direct) if direct.ip == info.ip => {
+                    (StepStatus::Warning, format!("经代理的出口 IP {} 与直连相同，流量可能未经过代理", info.ip))
+                }
+                _ => (StepStatus::Passed, format!("出口 IP {} {}", info.ip, location).trim_end().to_string()),
+            }
+        });
+        let external_ok = report.record_result("external_ip", "出口 IP", started, result);
+
+        if external_ok {
+            let started = Instant::now();
+            let result = check_dns_leak(&proxy_url, direct_country.as_deref(), proxy_country.as_deref()).await;
+            report.record_result("dns_leak", "DNS 泄漏", started, result);
+        } else {
+            report.skip("dns_leak", "DNS 泄漏", "无法经代理访问外网");
+        }
+    } else {
+        report.skip("external_ip", "出口 IP", "本地入站不可用");
+        report.skip("dns_leak", "DNS 泄漏", "本地入站不可用");
+    }
+
+    let _ = child.kill().await;
+    let _ = std::fs::remove_file(&config_path);
+}
+
+/// 检查 DNS 是否泄漏
+/// 经代理访问 ip-api.com 的 EDNS 回显服务（每次使用随机子域名避免缓存），得到实际解析该域名的 DNS 服务器，
+/// 再查询其所在国家/地区：与本机直连出口相同而与代理出口不同时，说明域名仍由本地 DNS 解析
+async fn check_dns_leak(proxy_url: &str, direct_country: Option<&str>, proxy_country: Option<&str>) -> Result<(StepStatus, String)> {
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(proxy_url)?)
+        .timeout(DNS_CHECK_TIMEOUT)
+        .build()
+        .context("创建检测客户端失败")?;
+
+    let echo_url = format!("http://{}.edns.ip-api.com/json", uuid::Uuid::new_v4().simple());
+    let echo: serde_json::Value = client.get(&echo_url).send().await?.error_for_status()?.json().await
+        .context("查询 DNS 服务器失败")?;
+    let resolver_ip = echo.pointer("/dns/ip").and_then(|v| v.as_str()).context("回显结果中缺少 DNS 服务器地址")?;
+    let resolver_geo = echo.pointer("/dns/geo").and_then(|v| v.as_str()).unwrap_or_default();
+
+    let lookup: serde_json::Value = client
+        .get(format!("http://ip-api.com/json/{}?fields=status,countryCode", resolver_ip))
+        .send()
+        .await?
+        .json()
+        .await
+        .context("查询 DNS 服务器归属失败")?;
+    let resolver_country = lookup.get("countryCode").and_then(|v| v.as_str());
+
+    let leaking = resolver_country.is_some()
+        && resolver_country == direct_country
+        && direct_country != proxy_country;
+    if leaking {
+        Ok((StepStatus::Warning, format!("域名由本地网络的 DNS 服务器 {}（{}）解析，可能存在 DNS 泄漏", resolver_ip, resolver_geo)))
+    } else {
+        Ok((StepStatus::Passed, format!("DNS 服务器 {}（{}）", resolver_ip, resolver_geo)))
+    }
+}
@@ -0,0 +1,255 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 启动依赖检查
+// 启动时在后台检查运行所需的依赖：代理核心、地理数据文件、wintun.dll、管理员权限与 WebView，
+// 检查完成后发射一次 `setup-audit` 事件，界面据此显示首次运行向导，而不是等到第一次连接时才报错。
+// 结果会缓存，界面加载晚于事件时可通过命令读取；每项检查附带修复动作标识，由界面映射到对应操作
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Runtime};
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::core_backend::{self, CoreKind};
+use crate::self_test::StepStatus;
+use crate::tun::TunManager;
+use crate::xray::XrayManager;
+
+/// 检查完成后发射的事件
+pub const SETUP_AUDIT_EVENT: &str = "setup-audit";
+
+/// 最近一次检查结果
+static LAST_REPORT: Mutex<Option<SetupAuditReport>> = Mutex::new(None);
+
+/// 修复动作标识
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixAction {
+    /// 下载代理核心（`download_core` 命令）
+    DownloadCore,
+    /// 下载地理数据文件（`download_geo_files` 命令）
+    DownloadGeoFiles,
+    /// 以管理员身份重新运行
+    RunAsAdmin,
+    /// 重新安装以恢复损坏的文件（应用的资源文件，或存在但无法运行的代理核心）
+    Reinstall,
+    /// 安装或修复 WebView 运行时
+    InstallWebview,
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditItem {
+    /// 检查标识：core_present、geo_files、wintun、admin、webview
+    pub id: String,
+    /// 检查名称
+    pub name: String,
+    /// 结果，不适用于当前平台或核心时为跳过
+    pub status: StepStatus,
+    /// 结果说明
+    pub message: String,
+    /// 建议的修复动作，无需修复时为空
+    pub fix: Option<FixAction>,
+}
+
+/// 依赖检查报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupAuditReport {
+    /// 没有失败项，可以直接连接
+    pub ready: bool,
+    /// 各项检查结果
+    pub items: Vec<AuditItem>,
+    /// 检查时间
+    pub checked_at: String,
+}
+
+impl SetupAuditReport {
+    /// 记录一项检查
+    fn record(&mut self, id: &str, name: &str, status: StepStatus, message: impl Into<String>, fix: Option<FixAction>) {
+        self.items.push(AuditItem {
+            id: id.to_string(),
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            fix: fix.filter(|_| matches!(status, StepStatus::Failed | StepStatus::Warning)),
+        });
+    }
+}
+
+/// 执行依赖检查
+/// 管理员权限与 DLL 加载等检查会阻塞，在阻塞线程池中执行
+///
+/// # 参数
+/// * `app` - 应用句柄，用于解析内置资源路径
+///
+/// # 返回值
+/// * `SetupAuditReport` - 检查报告
+pub async fn run<R: Runtime>(app: &AppHandle<R>) -> SetupAuditReport {
+    let wintun_path = resolve_wintun_path(app);
+    let report = tauri::async_runtime::spawn_blocking(move || audit(wintun_path))
+        .await
+        .unwrap_or_else(|e| SetupAuditReport {
+            ready: false,
+            items: vec![AuditItem {
+                id: "audit".to_string(),
+                name: "依赖检查".to_string(),
+                status: StepStatus::Failed,
+                message: format!("依赖检查异常中止: {}", e),
+                fix: None,
+            }],
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        });
+
+    *LAST_REPORT.lock().unwrap() = Some(report.clone());
+    report
+}
+
+/// 在后台执行依赖检查并发射 `setup-audit` 事件，不阻塞启动
+///
+/// # 参数
+/// * `app` - 应用句柄
+pub fn spawn<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let report = run(&app).await;
+        let failed: Vec<&str> = report.items.iter()
+            .filter(|item| item.status == StepStatus::Failed)
+            .map(|item| item.id.as_str())
+            .collect();
+        if failed.is_empty() {
+            log_info!("启动依赖检查通过");
+        } else {
+            log_warn!("启动依赖检查未通过: {}", failed.join(", "));
+        }
+        let _ = app.emit(SETUP_AUDIT_EVENT, &report);
+    });
+}
+
+/// 获取最近一次检查结果
+///
+/// # 返回值
+/// * `Option<SetupAuditReport>` - 启动检查尚未完成时为 None
+pub fn last_report() -> Option<SetupAuditReport> {
+    LAST_REPORT.lock().unwrap().clone()
+}
+
+/// 依次执行各项检查
+fn audit(wintun_path: Option<std::path::PathBuf>) -> SetupAuditReport {
+    let config = AppConfig::load().ok();
+    let core = config.as_ref().map(|c| c.core).unwrap_or_default();
+    let tun_enabled = config.as_ref().is_some_and(|c| c.tun_enabled);
+    // 未启用 TUN 时管理员权限与 wintun.dll 只影响 TUN 模式，记为警告
+    let tun_severity = if tun_enabled { StepStatus::Failed } else { StepStatus::Warning };
+
+    let mut report = SetupAuditReport {
+        ready: false,
+        items: Vec::new(),
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    // 1. 代理核心
+    let backend = core_backend::backend(core);
+    match backend.executable() {
+        // 文件存在但无法查询版本时，多半已损坏或与当前系统架构不匹配
+        Ok(executable) if executable.exists() => match backend.version() {
+            Ok(version) => report.record("core_present", "代理核心", StepStatus::Passed, format!("{} {}", backend.name(), version), None),
+            Err(e) => report.record(
+                "core_present", "代理核心", StepStatus::Failed,
+                format!("{} 无法运行: {:#}", backend.name(), e),
+                Some(FixAction::Reinstall),
+            ),
+        },
+        Ok(executable) => report.record(
+            "core_present", "代理核心", StepStatus::Failed,
+            format!("{} 可执行文件不存在: {}", backend.name(), executable.display()),
+            Some(FixAction::DownloadCore),
+        ),
+        Err(e) => report.record("core_present", "代理核心", StepStatus::Failed, format!("{:#}", e), Some(FixAction::DownloadCore)),
+    }
+
+    // 2. 地理数据文件，sing-box 不使用
+    if core == CoreKind::Xray {
+        match XrayManager::new().check_geo_files_exist() {
+            Ok(true) => report.record("geo_files", "地理数据文件", StepStatus::Passed, "geoip.dat 与 geosite.dat 已就绪", None),
+            Ok(false) => report.record(
+                "geo_files", "地理数据文件", StepStatus::Failed,
+                "缺少 geoip.dat 或 geosite.dat，包含 geosite/geoip 的路由规则将无法使用",
+                Some(FixAction::DownloadGeoFiles),
+            ),
+            Err(e) => report.record("geo_files", "地理数据文件", StepStatus::Failed, format!("{:#}", e), Some(FixAction::DownloadGeoFiles)),
+        }
+    } else {
+        report.record("geo_files", "地理数据文件", StepStatus::Skipped, format!("{} 不使用地理数据文件", backend.name()), None);
+    }
+
+    // 3. wintun.dll
+    check_wintun(&mut report, wintun_path, tun_severity);
+
+    // 4. 管理员权限
+    if TunManager::is_admin() {
+        report.record("admin", "管理员权限", StepStatus::Passed, "已具有管理员权限", None);
+    } else {
+        report.record(
+            "admin", "管理员权限", tun_severity,
+            "未以管理员身份运行，TUN 模式无法启动",
+            Some(FixAction::RunAsAdmin),
+        );
+    }
+
+    // 5. WebView
+    match tauri::webview_version() {
+        Ok(version) => report.record("webview", "WebView", StepStatus::Passed, version, None),
+        Err(e) => report.record(
+            "webview", "WebView", StepStatus::Failed,
+            format!("无法获取 WebView 版本: {}", e),
+            Some(FixAction::InstallWebview),
+        ),
+    }
+
+    report.ready = report.items.iter().all(|item| item.status != StepStatus::Failed);
+    report
+}
+
+/// 解析内置 wintun.dll 的路径
+#[cfg(target_os = "windows")]
+fn resolve_wintun_path<R: Runtime>(app: &AppHandle<R>) -> Option<std::path::PathBuf> {
+    use tauri::{path::BaseDirectory, Manager};
+
+    let resource = TunManager::wintun_resource_path()?;
+    app.path().resolve(resource, BaseDirectory::Resource).ok()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn resolve_wintun_path<R: Runtime>(_app: &AppHandle<R>) -> Option<std::path::PathBuf> {
+    None
+}
+
+/// 检查 wintun.dll 能否加载，内置文件缺失时尝试系统中的 wintun.dll
+#[cfg(target_os = "windows")]
+fn check_wintun(report: &mut SetupAuditReport, wintun_path: Option<std::path::PathBuf>, severity: StepStatus) {
+    let target = wintun_path
+        .filter(|path| path.exists())
+        .map(|path| path.into_os_string())
+        .unwrap_or_else(|| "wintun.dll".into());
+
+    // 只加载不调用，加载成功即说明文件完整且与当前架构匹配
+    match unsafe { libloading::Library::new(&target) } {
+        Ok(_) => report.record("wintun", "wintun.dll", StepStatus::Passed, format!("已加载 {}", target.to_string_lossy()), None),
+        Err(e) => report.record(
+            "wintun", "wintun.dll", severity,
+            format!("无法加载 {}: {}，TUN 模式无法启动", target.to_string_lossy(), e),
+            Some(FixAction::Reinstall),
+        ),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn check_wintun(report: &mut SetupAuditReport, _wintun_path: Option<std::path::PathBuf>, _severity: StepStatus) {
+    report.record("wintun", "wintun.dll", StepStatus::Skipped, "仅 Windows 需要 wintun.dll", None);
+}
@@ -0,0 +1,312 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 智能路由（直连更快时优先直连）
+// 对设置中列出的域名定期分别测量直连与经当前服务器访问的延迟，生成选择较快路径的路由规则。
+// 代理延迟通过临时核心实例测量，不受当前路由规则影响；生成的规则放在用户路由规则之前，
+// 结果变化且代理正在运行时重新生成配置并重启核心。仅 Xray 核心支持
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+use crate::core_backend::CoreKind;
+use crate::proxy::ProxyManager;
+use crate::speed_test::SpeedTestManager;
+
+/// 调度任务的检查间隔
+const SCHEDULE_TICK: Duration = Duration::from_secs(60);
+/// 每个域名每条路径的测量次数，取最小值
+const SAMPLES: usize = 2;
+/// 临时实例入站就绪的超时时间
+const INSTANCE_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 全局智能路由实例
+static SMART_ROUTING: OnceLock<SmartRouting> = OnceLock::new();
+
+/// 智能路由设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartRoutingConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 需要测量的域名，如 `example.com`（按 `domain:` 规则匹配其子域名）
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// 重新测量的间隔（分钟）
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    /// 直连延迟不超过代理延迟加该值（毫秒）时选择直连
+    #[serde(default = "default_margin_ms")]
+    pub margin_ms: u64,
+}
+
+impl Default for SmartRoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domains: Vec::new(),
+            interval_minutes: default_interval_minutes(),
+            margin_ms: default_margin_ms(),
+        }
+    }
+}
+
+/// 默认每 30 分钟重新测量
+fn default_interval_minutes() -> u64 {
+    30
+}
+
+/// 默认直连慢 20 毫秒以内仍选择直连
+fn default_margin_ms() -> u64 {
+    20
+}
+
+/// 单个域名的测量结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SmartRouteDecision {
+    /// 域名
+    pub domain: String,
+    /// 直连延迟（毫秒），失败时为空
+    pub direct_ms: Option<u64>,
+    /// 代理延迟（毫秒），失败时为空
+    pub proxy_ms: Option<u64>,
+    /// 选择的出站：`direct` 或 `proxy`，两条路径都失败时为空，不生成规则
+    pub outbound_tag: Option<String>,
+    /// 测量时间
+    pub measured_at: String,
+}
+
+/// 智能路由
+pub struct SmartRouting {
+    decisions: Mutex<Vec<SmartRouteDecision>>,
+    last_refresh: Mutex<Option<Instant>>,
+    refreshing: AtomicBool,
+}
+
+impl SmartRouting {
+    /// 获取全局智能路由实例（单例模式）
+    pub fn instance() -> &'static SmartRouting {
+        SMART_ROUTING.get_or_init(|| Self {
+            decisions: Mutex::new(Vec::new()),
+            last_refresh: Mutex::new(None),
+            refreshing: AtomicBool::new(false),
+        })
+    }
+
+    /// 最近一次的测量结果
+    pub fn decisions(&self) -> Vec<SmartRouteDecision> {
+        self.decisions.lock().unwrap().clone()
+    }
+
+    /// 生成核心配置中的路由规则
+    /// 只包含仍在设置中的域名，按出站分为直连和代理两条规则
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    pub fn routing_rules(&self, config: &AppConfig) -> Vec<serde_json::Value> {
+        if !config.smart_routing.enabled {
+            return Vec::new();
+        }
+        let decisions = self.decisions.lock().unwrap();
+        ["direct", "proxy"].iter()
+            .filter_map(|tag| {
+                let domains: Vec<String> = decisions.iter()
+                    .filter(|d| d.outbound_tag.as_deref() == Some(*tag))
+                    .filter(|d| config.smart_routing.domains.contains(&d.domain))
+                    .map(|d| domain_rule(&d.domain))
+                    .collect();
+                (!domains.is_empty()).then(|| json!({
+                    "type": "field",
+                    "domain": domains,
+                    "outboundTag": tag
+                }))
+            })
+            .collect()
+    }
+
+    /// 立即测量所有域名，结果变化且代理正在运行时重启核心
+    ///
+    /// # 返回值
+    /// * `Result<Vec<SmartRouteDecision>>` - 测量结果
+    ///
+    /// # 异常
+    /// * 正在测量、未启用 Xray 核心或没有可用服务器时返回错误
+    pub async fn refresh(&self) -> Result<Vec<SmartRouteDecision>> {
+        if self.refreshing.swap(true, Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("正在测量中，请稍后再试"));
+        }
+        let result = self.refresh_inner().await;
+        self.refreshing.store(false, Ordering::SeqCst);
+        *self.last_refresh.lock().unwrap() = Some(Instant::now());
+        result
+    }
+
+    async fn refresh_inner(&self) -> Result<Vec<SmartRouteDecision>> {
+        let config = AppConfig::load()?;
+        if config.core != CoreKind::Xray {
+            return Err(anyhow::anyhow!("智能路由仅支持 Xray 核心"));
+        }
+        let server_id = ProxyManager::instance().current_server_id()
+            .or_else(|| config.current_server.clone())
+            .context("没有当前服务器，无法测量代理延迟")?;
+        let server = config.servers.iter()
+            .find(|s| s.id == server_id)
+            .with_context(|| format!("服务器不存在: {}", server_id))?;
+
+        let decisions = measure(&config, server).await?;
+        let changed = {
+            let mut current = self.decisions.lock().unwrap();
+            let routes = |list: &[SmartRouteDecision]| -> Vec<(String, Option<String>)> {
+                list.iter().map(|d| (d.domain.clone(), d.outbound_tag.clone())).collect()
+            };
+            let changed = routes(&current) != routes(&decisions);
+            *current = decisions.clone();
+            changed
+        };
+
+        for decision in &decisions {
+            log_info!(
+                "智能路由 {}: 直连 {}，代理 {} -> {}",
+                decision.domain,
+                format_latency(decision.direct_ms),
+                format_latency(decision.proxy_ms),
+                decision.outbound_tag.as_deref().unwrap_or("不生成规则")
+            );
+        }
+        if changed && ProxyManager::instance().is_process_running() {
+            log_info!("智能路由规则已变化，重启核心");
+            ProxyManager::instance().reload().await?;
+        }
+        Ok(decisions)
+    }
+
+    /// 距上次测量是否已超过设置的间隔
+    fn is_due(&self, config: &SmartRoutingConfig) -> bool {
+        match *self.last_refresh.lock().unwrap() {
+            Some(last) => last.elapsed() >= Duration::from_secs(config.interval_minutes.max(1) * 60),
+            None => true,
+        }
+    }
+}
+
+/// 域名转换为路由规则条目，已带前缀（如 `full:`）时原样使用
+fn domain_rule(domain: &str) -> String {
+    if domain.contains(':') {
+        domain.to_string()
+    } else {
+        format!("domain:{}", domain)
+    }
+}
+
+/// 测量使用的主机名（去掉规则前缀）
+fn host_of(domain: &str) -> &str {
+    domain.split_once(':').map_or(domain, |(_, host)| host)
+}
+
+fn format_latency(latency: Option<u64>) -> String {
+    latency.map(|ms| format!("{}ms", ms)).unwrap_or_else(|| "失败".to_string())
+}
+
+/// 测量所有域名的直连与代理延迟
+async fn measure(config: &AppConfig, server: &ServerInfo) -> Result<Vec<SmartRouteDecision>> {
+    let timeout = Duration::from_secs(config.connectivity_check_timeout.max(1));
+    let direct_client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(timeout)
+        .build()
+        .context("创建直连客户端失败")?;
+
+    let (mut child, port, config_path) = SpeedTestManager::new().spawn_instance(server).await?;
+    let result = async {
+        SpeedTestManager::wait_for_port(port, INSTANCE_READY_TIMEOUT).await?;
+        let proxy_client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::all(format!("http://127.0.0.1:{}", port))?)
+            .timeout(timeout)
+            .build()
+            .context("创建代理客户端失败")?;
+
+        let mut decisions = Vec::new();
+        for domain in &config.smart_routing.domains {
+            let url = format!("https://{}/", host_of(domain));
+            let (direct_ms, proxy_ms) = tokio::join!(
+                best_latency(&direct_client, &url),
+                best_latency(&proxy_client, &url)
+            );
+            let outbound_tag = match (direct_ms, proxy_ms) {
+                (Some(direct), Some(proxy)) if direct <= proxy + config.smart_routing.margin_ms => Some("direct"),
+                (Some(_), Some(_)) | (None, Some(_)) => Some("proxy"),
+                (Some(_), None) => Some("direct"),
+                (None, None) => None,
+            };
+            decisions.push(SmartRouteDecision {
+                domain: domain.clone(),
+                direct_ms,
+                proxy_ms,
+                outbound_tag: outbound_tag.map(str::to_string),
+                measured_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+        Ok::<_, anyhow::Error>(decisions)
+    }
+    .await;
+
+    let _ = child.kill().await;
+    let _ = std::fs::remove_file(&config_path);
+    result
+}
+
+/// 多次请求取最短耗时，全部失败时返回 None
+async fn best_latency(client: &reqwest::Client, url: &str) -> Option<u64> {
+    let mut best: Option<u64> = None;
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        if client.head(url).send().await.is_ok() {
+            let elapsed = start.elapsed().as_millis() as u64;
+            best = Some(best.map_or(elapsed, |b| b.min(elapsed)));
+        }
+    }
+    best
+}
+
+/// 启动智能路由调度任务
+/// 启用后按设置的间隔重新测量，完成后发射 `smart-routing-updated` 事件
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄
+pub fn start_smart_routing(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULE_TICK).await;
+
+            let Ok(config) = AppConfig::load() else { continue };
+            let smart_routing = SmartRouting::instance();
+            if !config.smart_routing.enabled
+                || config.smart_routing.domains.is_empty()
+                || !smart_routing.is_due(&config.smart_routing)
+            {
+                continue;
+            }
+
+            match smart_routing.refresh().await {
+                Ok(decisions) => {
+                    let _ = app_handle.emit("smart-routing-updated", json!(decisions));
+                }
+                Err(e) => log_warn!("智能路由测量失败: {:#}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,313 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2024-12-20
+ */
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::process::{Child, Command as TokioCommand};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+use crate::core_backend::{self, CoreKind};
+use crate::error::{AppError, ErrorCode};
+use crate::proxy::ProxyManager;
+
+/// 测速下载地址（返回指定字节数的数据）
+const SPEED_TEST_DOWNLOAD_URL: &str = "https://speed.cloudflare.com/__down";
+/// 测速上传地址
+const SPEED_TEST_UPLOAD_URL: &str = "https://speed.cloudflare.com/__up";
+/// 下载测试数据大小（10MB）
+const DOWNLOAD_TEST_BYTES: u64 = 10 * 1024 * 1024;
+/// 上传测试数据大小（2MB）
+const UPLOAD_TEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// 测速结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedTestResult {
+    /// 服务器ID
+    pub server_id: String,
+    /// 下载速度（字节/秒）
+    pub download_speed: u64,
+    /// 上传速度（字节/秒）
+    pub upload_speed: u64,
+    /// 实际下载的字节数
+    pub downloaded_bytes: u64,
+    /// 实际上传的字节数
+    pub uploaded_bytes: u64,
+    /// 测试总耗时（毫秒）
+    pub duration_ms: u64,
+    /// 测试时间
+    pub tested_at: String,
+}
+
+/// 测速管理器
+/// 为指定服务器启动一个独立的临时 Xray 实例，通过其本地 HTTP 入站测量真实带宽，
+/// 不影响当前正在运行的代理
+pub struct SpeedTestManager;
+
+impl SpeedTestManager {
+    /// 创建新的测速管理器实例
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 对指定服务器执行测速
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    /// * `progress_callback` - 进度回调函数，接收 (当前进度, 总进度, 状态消息)
+    ///
+    /// # 返回值
+    /// * `Result<SpeedTestResult>` - 测速结果
+    pub async fn run<F>(&self, server: &ServerInfo, mut progress_callback: F) -> Result<SpeedTestResult>
+    where
+        F: FnMut(u64, u64, String) + Send,
+    {
+        let started = Instant::now();
+        progress_callback(0, 100, "正在启动测速实例...".to_string());
+
+        let (mut child, port, config_path) = self.spawn_instance(server).await?;
+
+        let result = async {
+            Self::wait_for_port(port, Duration::from_secs(5)).await?;
+
+            let client = reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all(format!("http://127.0.0.1:{}", port))?)
+                .timeout(Duration::from_secs(60))
+                .build()
+                .context("创建测速客户端失败")?;
+
+            // 下载测试：5-80%
+            progress_callback(5, 100, "正在测试下载速度...".to_string());
+            let (downloaded_bytes, download_speed) = self
+                .measure_download(&client, |downloaded, total| {
+                    let progress = 5 + downloaded * 75 / total.max(1);
+                    progress_callback(progress.min(80), 100, format!("下载中... {:.1}MB/{:.1}MB",
+                        downloaded as f64 / 1024.0 / 1024.0,
+                        total as f64 / 1024.0 / 1024.0));
+                })
+                .await?;
+
+            // 上传测试：80-100%
+            progress_callback(80, 100, "正在测试上传速度...".to_string());
+            let (uploaded_bytes, upload_speed) = self.measure_upload(&client).await?;
+
+            Ok::<_, anyhow::Error>(SpeedTestResult {
+                server_id: server.id.clone(),
+                download_speed,
+                upload_speed,
+                downloaded_bytes,
+                uploaded_bytes,
+                duration_ms: started.elapsed().as_millis() as u64,
+                tested_at: chrono::Utc::now().to_rfc3339(),
+            })
+        }
+        .await;
+
+        // 终止测速实例并清理配置文件
+        let _ = child.kill().await;
+        let _ = std::fs::remove_file(&config_path);
+
+        match &result {
+            Ok(r) => {
+                log_info!("服务器 {} 测速完成: 下载 {} B/s, 上传 {} B/s", server.name, r.download_speed, r.upload_speed);
+                progress_callback(100, 100, "测速完成！".to_string());
+            }
+            Err(e) => log_warn!("服务器 {} 测速失败: {}", server.name, e),
+        }
+
+        result
+    }
+
+    /// 测量服务器延迟
+    /// 通过临时实例访问 `connectivity_check_url`，以完成一次 HTTP 请求的耗时作为延迟，
+    /// 超时时间为 `connectivity_check_timeout`
+    ///
+    /// # 参数
+    /// * `server` - 服务器信息
+    ///
+    /// # 返回值
+    /// * `Result<u64>` - 延迟（毫秒）
+    pub async fn measure_latency(&self, server: &ServerInfo) -> Result<u64> {
+        let config = AppConfig::load()?;
+        let (mut child, port, config_path) = self.spawn_instance(server).await?;
+
+        let result = async {
+            Self::wait_for_port(port, Duration::from_secs(5)).await?;
+
+            let client = reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all(format!("http://127.0.0.1:{}", port))?)
+                .timeout(Duration::from_secs(config.connectivity_check_timeout.max(1)))
+                .build()
+                .context("创建测试客户端失败")?;
+
+            let start = Instant::now();
+            let response = client
+                .get(&config.connectivity_check_url)
+                .send()
+                .await
+                .context("延迟测试请求失败")?;
+            let status = response.status();
+            if !(status.is_success() || status.is_redirection()) {
+                return Err(anyhow::anyhow!("探测地址返回异常状态码: {}", status));
+            }
+            Ok(start.elapsed().as_millis() as u64)
+        }
+        .await;
+
+        let _ = child.kill().await;
+        let _ = std::fs::remove_file(&config_path);
+        result
+    }
+
+    /// 启动临时 Xray 实例
+    /// 实例监听在空闲端口上，句柄释放时自动终止；配置文件名包含端口，多个实例可同时运行
+    ///
+    /// # 返回值
+    /// * `Result<(Child, u16, PathBuf)>` - (进程句柄, HTTP 入站端口, 配置文件路径)
+    pub(crate) async fn spawn_instance(&self, server: &ServerInfo) -> Result<(Child, u16, PathBuf)> {
+        // 检查 Xray Core 是否存在
+        let xray_executable = AppConfig::xray_executable()?;
+        if !xray_executable.exists() {
+            return Err(AppError::new(ErrorCode::XrayMissing, format!("Xray Core 可执行文件不存在: {}", xray_executable.display())).into());
+        }
+
+        // 为临时实例分配空闲端口并生成配置
+        let port = Self::pick_free_port()?;
+        let config = self.generate_speed_test_config(server, port)?;
+        let config_path = AppConfig::servers_dir()?.join(format!("xray_speedtest_{}.json", port));
+        let config_str = serde_json::to_string_pretty(&config)
+            .context("序列化测速配置失败")?;
+        std::fs::write(&config_path, config_str)
+            .context("写入测速配置文件失败")?;
+
+        let mut command = TokioCommand::new(&xray_executable);
+        if let Err(e) = core_backend::prepare_command(core_backend::backend(CoreKind::Xray), command.as_std_mut()) {
+            let _ = std::fs::remove_file(&config_path);
+            return Err(e);
+        }
+        command
+            .arg("-config")
+            .arg(&config_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        #[cfg(target_os = "windows")]
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+        let child = command
+            .spawn()
+            .context(format!("无法启动 Xray Core: {}", xray_executable.display()))?;
+        Ok((child, port, config_path))
+    }
+
+    /// 生成测速专用的 Xray 配置
+    /// 复用正常的配置生成逻辑，只保留一个监听在临时端口上的 HTTP 入站
+    fn generate_speed_test_config(&self, server: &ServerInfo, port: u16) -> Result<serde_json::Value> {
+        let mut config = ProxyManager::instance().generate_xray_config(server)?;
+
+        config["inbounds"] = serde_json::json!([{
+            "tag": "speedtest",
+            "port": port,
+            "listen": "127.0.0.1",
+            "protocol": "http"
+        }]);
+        // 测速流量必须全部经过代理出站，不受路由规则影响
+        config["routing"] = serde_json::json!({ "rules": [] });
+
+        Ok(config)
+    }
+
+    /// 测量下载速度
+    ///
+    /// # 返回值
+    /// * `Result<(u64, u64)>` - (下载字节数, 下载速度 字节/秒)
+    async fn measure_download<F>(&self, client: &reqwest::Client, mut progress: F) -> Result<(u64, u64)>
+    where
+        F: FnMut(u64, u64) + Send,
+    {
+        let response = client
+            .get(SPEED_TEST_DOWNLOAD_URL)
+            .query(&[("bytes", DOWNLOAD_TEST_BYTES)])
+            .send()
+            .await
+            .context("下载测试请求失败")?
+            .error_for_status()
+            .context("下载测试请求失败")?;
+
+        let total = response.content_length().unwrap_or(DOWNLOAD_TEST_BYTES);
+        let mut downloaded = 0u64;
+        let mut stream = response.bytes_stream();
+        let start = Instant::now();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("下载测试过程中出现错误")?;
+            downloaded += chunk.len() as u64;
+            progress(downloaded, total);
+        }
+
+        Ok((downloaded, Self::bytes_per_second(downloaded, start.elapsed())))
+    }
+
+    /// 测量上传速度
+    ///
+    /// # 返回值
+    /// * `Result<(u64, u64)>` - (上传字节数, 上传速度 字节/秒)
+    async fn measure_upload(&self, client: &reqwest::Client) -> Result<(u64, u64)> {
+        let payload = vec![0u8; UPLOAD_TEST_BYTES];
+        let start = Instant::now();
+
+        client
+            .post(SPEED_TEST_UPLOAD_URL)
+            .body(payload)
+            .send()
+            .await
+            .context("上传测试请求失败")?
+            .error_for_status()
+            .context("上传测试请求失败")?;
+
+        let uploaded = UPLOAD_TEST_BYTES as u64;
+        Ok((uploaded, Self::bytes_per_second(uploaded, start.elapsed())))
+    }
+
+    /// 计算每秒字节数
+    fn bytes_per_second(bytes: u64, elapsed: Duration) -> u64 {
+        let secs = elapsed.as_secs_f64();
+        if secs > 0.0 {
+            (bytes as f64 / secs) as u64
+        } else {
+            0
+        }
+    }
+
+    /// 获取一个本地空闲端口
+    fn pick_free_port() -> Result<u16> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")
+            .context("无法分配测速端口")?;
+        Ok(listener.local_addr()?.port())
+    }
+
+    /// 等待本地端口开始监听
+    pub(crate) async fn wait_for_port(port: u16, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Err(anyhow::anyhow!("测速实例启动超时，端口 {} 未就绪", port))
+    }
+}
@@ -0,0 +1,64 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use crate::api_server::ApiServer;
+use crate::app_lock::AppLock;
+use crate::bandwidth::BandwidthLimiter;
+use crate::config_store::ConfigStore;
+use crate::domain_override::DomainOverrides;
+use crate::history::HistoryStore;
+use crate::monitor::ConnectivityMonitor;
+use crate::proxy::ProxyManager;
+use crate::smart_routing::SmartRouting;
+use crate::traffic::TrafficManager;
+use crate::tun::TunManager;
+
+/// 应用全局状态
+/// 汇总各个全局单例管理器，由 Tauri 托管并注入到命令中，
+/// 命令通过 `State<AppState>` 访问管理器，而不是各自获取实例
+pub struct AppState {
+    /// 配置存储
+    pub config: &'static ConfigStore,
+    /// 代理管理器
+    pub proxy: &'static ProxyManager,
+    /// TUN 模式管理器
+    pub tun: &'static TunManager,
+    /// 流量统计管理器
+    pub traffic: &'static TrafficManager,
+    /// 连通性监控
+    pub monitor: &'static ConnectivityMonitor,
+    /// 本地控制 API 服务
+    pub api_server: &'static ApiServer,
+    /// 应用锁
+    pub app_lock: &'static AppLock,
+    /// 流量历史存储
+    pub history: &'static HistoryStore,
+    /// 限速器
+    pub bandwidth: &'static BandwidthLimiter,
+    /// 临时域名分流覆盖
+    pub domain_overrides: &'static DomainOverrides,
+    /// 智能分流
+    pub smart_routing: &'static SmartRouting,
+}
+
+impl AppState {
+    /// 创建应用状态，引用各管理器的全局实例
+    pub fn new() -> Self {
+        Self {
+            config: ConfigStore::instance(),
+            proxy: ProxyManager::instance(),
+            tun: TunManager::instance(),
+            traffic: TrafficManager::instance(),
+            monitor: ConnectivityMonitor::instance(),
+            api_server: ApiServer::instance(),
+            app_lock: AppLock::instance(),
+            history: HistoryStore::instance(),
+            bandwidth: BandwidthLimiter::instance(),
+            domain_overrides: DomainOverrides::instance(),
+            smart_routing: SmartRouting::instance(),
+        }
+    }
+}
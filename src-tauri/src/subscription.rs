@@ -0,0 +1,343 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::ServerInfo;
+use crate::share_link;
+
+/// 订阅信息结构体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    /// 订阅ID
+    pub id: String,
+    /// 订阅名称
+    pub name: String,
+    /// 订阅地址
+    pub url: String,
+    /// 最近一次更新时从 `subscription-userinfo` 响应头解析出的流量与到期信息
+    #[serde(default)]
+    pub info: Option<SubscriptionInfo>,
+    /// 最近一次成功更新的时间
+    #[serde(default)]
+    pub last_updated: Option<String>,
+    /// 节点命名模板，如 `{flag} {name} {latency}`，为空时保留订阅中的原始名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<String>,
+    pub created_at: String,
+}
+
+/// 订阅流量与到期信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionInfo {
+    /// 已用上传流量（字节）
+    pub upload: u64,
+    /// 已用下载流量（字节）
+    pub download: u64,
+    /// 总流量（字节），0 表示不限
+    pub total: u64,
+    /// 到期时间（Unix 时间戳，秒），None 表示长期有效
+    pub expire: Option<i64>,
+}
+
+impl SubscriptionInfo {
+    /// 解析 `subscription-userinfo` 响应头
+    /// 格式形如 `upload=123; download=456; total=789; expire=1700000000`
+    ///
+    /// # 参数
+    /// * `value` - 响应头的值
+    ///
+    /// # 返回值
+    /// * `Option<SubscriptionInfo>` - 解析结果，不包含任何已知字段时返回 None
+    pub fn parse_header(value: &str) -> Option<Self> {
+        let mut info = SubscriptionInfo::default();
+        let mut matched = false;
+
+        for part in value.split(';') {
+            let Some((key, val)) = part.split_once('=') else { continue };
+            let val = val.trim();
+            // 部分服务商会返回浮点数形式的字节数
+            let number = val.parse::<u64>().ok()
+                .or_else(|| val.parse::<f64>().ok().map(|v| v as u64));
+
+            match key.trim().to_lowercase().as_str() {
+                "upload" => info.upload = number.unwrap_or(0),
+                "download" => info.download = number.unwrap_or(0),
+                "total" => info.total = number.unwrap_or(0),
+                "expire" => info.expire = number.filter(|v| *v > 0).map(|v| v as i64),
+                _ => continue,
+            }
+            matched = true;
+        }
+
+        matched.then_some(info)
+    }
+}
+
+/// 订阅管理器
+pub struct SubscriptionManager {
+    client: Client,
+}
+
+impl SubscriptionManager {
+    /// 创建新的订阅管理器实例
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+
+    /// 拉取订阅内容
+    ///
+    /// # 参数
+    /// * `url` - 订阅地址
+    ///
+    /// # 返回值
+    /// * `Result<(Vec<ServerInfo>, Option<SubscriptionInfo>)>` - 解析出的服务器列表与订阅流量信息
+    ///
+    /// # 异常
+    /// * 当请求失败或订阅中没有可识别的节点时返回错误
+    pub async fn fetch(&self, url: &str) -> Result<(Vec<ServerInfo>, Option<SubscriptionInfo>)> {
+        let response = self.client
+            .get(url)
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .context("无法获取订阅内容")?
+            .error_for_status()
+            .context("订阅服务器返回错误")?;
+
+        let info = response.headers()
+            .get("subscription-userinfo")
+            .and_then(|v| v.to_str().ok())
+            .and_then(SubscriptionInfo::parse_header);
+
+        let body = response.text().await.context("无法读取订阅内容")?;
+        let servers = share_link::parse_subscription_content(&body);
+        if servers.is_empty() {
+            return Err(anyhow::anyhow!("订阅中没有可识别的节点"));
+        }
+
+        Ok((servers, info))
+    }
+}
+
+/// 服务器配置中保存订阅原始节点名称的字段，重新套用命名模板时以此为准
+pub const ORIGINAL_NAME_KEY: &str = "original_name";
+
+/// 国家/地区代码及其在节点名称中的常见写法
+/// 中文与英文全称按子串匹配，两位代码仅在作为独立单词出现时匹配；
+/// 名称互相包含的（如 印度尼西亚 / 印度）需把较长的放在前面
+const COUNTRY_HINTS: &[(&str, &[&str])] = &[
+    ("HK", &["香港", "hong kong", "hongkong"]),
+    ("TW", &["台湾", "臺灣", "台灣", "taiwan"]),
+    ("MO", &["澳门", "澳門", "macau", "macao"]),
+    ("JP", &["日本", "东京", "大阪", "japan", "tokyo", "osaka"]),
+    ("KR", &["韩国", "韓國", "首尔", "korea", "seoul"]),
+    ("SG", &["新加坡", "狮城", "singapore"]),
+    ("US", &["美国", "美國", "洛杉矶", "硅谷", "united states", "america", "los angeles", "san jose"]),
+    ("CA", &["加拿大", "canada"]),
+    ("GB", &["英国", "英國", "伦敦", "united kingdom", "britain", "london"]),
+    ("DE", &["德国", "德國", "法兰克福", "germany", "frankfurt"]),
+    ("FR", &["法国", "法國", "巴黎", "france", "paris"]),
+    ("NL", &["荷兰", "荷蘭", "阿姆斯特丹", "netherlands", "amsterdam"]),
+    ("RU", &["俄罗斯", "俄羅斯", "莫斯科", "russia", "moscow"]),
+    ("TR", &["土耳其", "turkey", "türkiye"]),
+    ("ID", &["印尼", "印度尼西亚", "indonesia", "jakarta"]),
+    ("IN", &["印度", "india", "mumbai"]),
+    ("AU", &["澳大利亚", "澳洲", "悉尼", "australia", "sydney"]),
+    ("MY", &["马来西亚", "馬來西亞", "malaysia"]),
+    ("TH", &["泰国", "泰國", "thailand", "bangkok"]),
+    ("VN", &["越南", "vietnam"]),
+    ("PH", &["菲律宾", "菲律賓", "philippines"]),
+    ("AR", &["阿根廷", "argentina"]),
+    ("BR", &["巴西", "brazil"]),
+    ("CN", &["中国", "中國", "回国", "china"]),
+];
+
+/// 两位代码中常与普通单词冲突的代码，仅在名称为大写时匹配
+const AMBIGUOUS_CODES: &[&str] = &["IN", "ID", "MY", "CA", "AR"];
+
+/// 识别节点名称中的国家/地区
+/// 名称开头已带国旗 emoji 时直接使用，否则按 [`COUNTRY_HINTS`] 匹配
+///
+/// # 参数
+/// * `name` - 节点名称
+///
+/// # 返回值
+/// * `Option<String>` - 两位国家/地区代码，无法识别时返回 None
+pub fn detect_country(name: &str) -> Option<String> {
+    if let Some(code) = leading_flag_code(name) {
+        return Some(code);
+    }
+
+    let lower = name.to_lowercase();
+    for (code, hints) in COUNTRY_HINTS {
+        if hints.iter().any(|hint| lower.contains(hint)) {
+            return Some(code.to_string());
+        }
+    }
+
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|w| w.len() == 2)
+        .collect();
+    // 代码后常跟编号，如 `HK01`，先拆出字母部分再比较
+    COUNTRY_HINTS.iter()
+        .map(|(code, _)| *code)
+        .chain(["UK"])
+        .find(|code| words.iter().any(|w| {
+            if AMBIGUOUS_CODES.contains(code) {
+                w == code
+            } else {
+                w.eq_ignore_ascii_case(code)
+            }
+        }))
+        .map(|code| if code == "UK" { "GB".to_string() } else { code.to_string() })
+}
+
+/// 将两位国家/地区代码转换为国旗 emoji
+///
+/// # 参数
+/// * `code` - 两位国家/地区代码
+///
+/// # 返回值
+/// * `Option<String>` - 国旗 emoji，代码无效时返回 None
+pub fn flag_emoji(code: &str) -> Option<String> {
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    code.to_ascii_uppercase()
+        .chars()
+        .map(|c| char::from_u32(0x1F1E6 + (c as u32 - 'A' as u32)))
+        .collect()
+}
+
+/// 读取名称开头的国旗 emoji 对应的国家/地区代码
+fn leading_flag_code(name: &str) -> Option<String> {
+    let mut chars = name.trim_start().chars();
+    let code: String = [chars.next()?, chars.next()?]
+        .iter()
+        .map(|c| regional_indicator_letter(*c))
+        .collect::<Option<String>>()?;
+    Some(code)
+}
+
+/// 区域指示符号转换为对应的大写字母
+fn regional_indicator_letter(c: char) -> Option<char> {
+    let offset = (c as u32).checked_sub(0x1F1E6)?;
+    (offset < 26).then(|| char::from(b'A' + offset as u8))
+}
+
+/// 去掉名称开头的国旗 emoji，避免模板中的 `{flag}` 重复
+fn strip_leading_flag(name: &str) -> &str {
+    let trimmed = name.trim_start();
+    if leading_flag_code(trimmed).is_none() {
+        return name;
+    }
+    let mut chars = trimmed.chars();
+    chars.next();
+    chars.next();
+    chars.as_str().trim_start()
+}
+
+/// 按命名模板生成节点名称
+/// 支持的占位符：`{flag}` 国旗、`{country}` 国家/地区代码、`{name}` 原始名称（去掉开头国旗）、
+/// `{protocol}` 协议、`{latency}` 最近一次测试延迟（如 `120ms`）、`{subscription}` 订阅名称。
+/// 无法取得的占位符替换为空，多余的空白会被合并
+///
+/// # 参数
+/// * `template` - 命名模板
+/// * `server` - 服务器信息，名称应为订阅中的原始名称
+/// * `subscription_name` - 所属订阅名称
+///
+/// # 返回值
+/// * `String` - 生成的名称，结果为空时返回原始名称
+pub fn apply_name_template(template: &str, server: &ServerInfo, subscription_name: &str) -> String {
+    let country = detect_country(&server.name);
+    let values = [
+        ("{flag}", country.as_deref().and_then(flag_emoji).unwrap_or_default()),
+        ("{country}", country.clone().unwrap_or_default()),
+        ("{name}", strip_leading_flag(&server.name).to_string()),
+        ("{protocol}", server.protocol.clone()),
+        ("{latency}", server.latency_ms.map(|ms| format!("{}ms", ms)).unwrap_or_default()),
+        ("{subscription}", subscription_name.to_string()),
+    ];
+
+    let mut name = template.to_string();
+    for (placeholder, value) in &values {
+        name = name.replace(placeholder, value);
+    }
+
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        server.name.clone()
+    } else {
+        name
+    }
+}
+
+/// 去掉订阅中协议、地址、端口与配置完全相同的重复节点，保留第一次出现的节点
+///
+/// # 参数
+/// * `servers` - 订阅解析出的服务器列表
+///
+/// # 返回值
+/// * `usize` - 去掉的重复节点数量
+pub fn dedup_servers(servers: &mut Vec<ServerInfo>) -> usize {
+    let before = servers.len();
+    let mut seen = HashSet::new();
+    servers.retain(|server| {
+        // HashMap 的遍历顺序不固定，转为 BTreeMap 后再序列化作为比较依据
+        let config: BTreeMap<_, _> = server.config.iter()
+            .filter(|(k, _)| k.as_str() != ORIGINAL_NAME_KEY)
+            .collect();
+        let key = (
+            server.protocol.clone(),
+            server.address.to_lowercase(),
+            server.port,
+            serde_json::to_string(&config).unwrap_or_default(),
+        );
+        seen.insert(key)
+    });
+    before - servers.len()
+}
+
+/// 为订阅节点套用命名模板
+/// 节点的原始名称保存在配置的 `original_name` 中，每次套用均以原始名称为准；
+/// 模板为空时恢复原始名称。生成的名称重复时依次追加序号
+///
+/// # 参数
+/// * `servers` - 同一订阅下的服务器列表
+/// * `template` - 命名模板
+/// * `subscription_name` - 订阅名称
+pub fn apply_name_template_to_servers(servers: &mut [ServerInfo], template: Option<&str>, subscription_name: &str) {
+    let template = template.map(str::trim).filter(|t| !t.is_empty());
+    let mut used: HashSet<String> = HashSet::new();
+
+    for server in servers.iter_mut() {
+        let original = server.config.get(ORIGINAL_NAME_KEY)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| server.name.clone());
+        server.config.insert(ORIGINAL_NAME_KEY.to_string(), serde_json::json!(original));
+        server.name = original;
+
+        let Some(template) = template else { continue };
+        let base = apply_name_template(template, server, subscription_name);
+        let mut name = base.clone();
+        let mut index = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{} {}", base, index);
+            index += 1;
+        }
+        server.name = name;
+    }
+}
@@ -5,10 +5,129 @@
  */
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 
-use sysinfo::{System, Networks};
+use sysinfo::{Pid, System, Networks};
 
-use crate::commands::SystemStats;
+use crate::commands::{ProcessStats, SystemStats};
+use crate::config::AppConfig;
+use crate::proxy::ProxyManager;
+use crate::traffic::TrafficManager;
+use crate::tun::TunManager;
+
+// 导入日志宏
+#[cfg(target_os = "windows")]
+use crate::log_warn;
+
+/// 开机自启动项名称
+#[cfg(target_os = "windows")]
+const AUTO_START_NAME: &str = "RuRay";
+
+/// 常见代理软件的进程名关键字（小写）与显示名称
+const PROXY_SOFTWARE: [(&str, &str); 16] = [
+    ("clash", "Clash"),
+    ("mihomo", "Clash Meta (mihomo)"),
+    ("v2rayn", "v2rayN"),
+    ("qv2ray", "Qv2ray"),
+    ("nekoray", "NekoRay"),
+    ("nekobox", "NekoBox"),
+    ("hiddify", "Hiddify"),
+    ("shadowsocks", "Shadowsocks"),
+    ("ss-local", "Shadowsocks"),
+    ("trojan", "Trojan"),
+    ("v2ray", "V2Ray"),
+    ("xray", "Xray"),
+    ("sing-box", "sing-box"),
+    ("surge", "Surge"),
+    ("proxifier", "Proxifier"),
+    ("shadowrocket", "Shadowrocket"),
+];
+/// 常见 VPN 客户端的进程名关键字（小写）与显示名称
+const VPN_SOFTWARE: [(&str, &str); 8] = [
+    ("openvpn", "OpenVPN"),
+    ("wireguard", "WireGuard"),
+    ("vpnagent", "Cisco AnyConnect"),
+    ("forticlient", "FortiClient"),
+    ("nordvpn", "NordVPN"),
+    ("expressvpn", "ExpressVPN"),
+    ("tailscale", "Tailscale"),
+    ("zerotier", "ZeroTier"),
+];
+/// 常见代理软件的默认本地端口
+const PROXY_SOFTWARE_PORTS: [(u16, &str); 8] = [
+    (7890, "Clash"),
+    (7891, "Clash"),
+    (7897, "Clash Verge"),
+    (10808, "v2rayN"),
+    (10809, "v2rayN"),
+    (2080, "NekoRay"),
+    (1080, "Shadowsocks"),
+    (1087, "ShadowsocksX-NG"),
+];
+/// VPN 网卡名称关键字（小写）
+const VPN_INTERFACE_PATTERNS: [&str; 7] = ["wg", "tun", "tap", "ppp", "ipsec", "wireguard", "openvpn"];
+
+/// 冲突的代理软件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictWarning {
+    /// 类型：process 为代理软件进程，vpn 为 VPN 客户端或网卡，port 为常见代理端口被占用，
+    /// system_proxy 为系统代理指向其他程序
+    pub kind: String,
+    /// 软件或网卡名称
+    pub name: String,
+    /// 说明
+    pub detail: String,
+}
+
+/// 端口占用进程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortOwner {
+    /// 进程ID
+    pub pid: u32,
+    /// 进程名称
+    pub name: Option<String>,
+}
+
+/// 修改系统代理失败的错误
+/// 命令返回时转换为 `AppError`，没有修改权限时错误代码为 `ADMIN_REQUIRED`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemProxyError {
+    /// 错误类型，固定为 `system_proxy`
+    pub kind: String,
+    /// 错误代码：permission_denied 表示没有修改权限（如受组策略管理的设备），failed 表示其他错误
+    pub code: String,
+    /// 错误说明
+    pub message: String,
+}
+
+impl SystemProxyError {
+    /// 根据修改系统代理时的错误生成结构化错误
+    ///
+    /// # 参数
+    /// * `error` - 修改系统代理时的错误
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let code = if SystemManager::is_permission_denied(error) { "permission_denied" } else { "failed" };
+        Self {
+            kind: "system_proxy".to_string(),
+            code: code.to_string(),
+            message: format!("{:#}", error),
+        }
+    }
+
+    /// 是否为权限不足导致的失败
+    pub fn is_permission_denied(&self) -> bool {
+        self.code == "permission_denied"
+    }
+}
+
+/// 修改系统代理的能力
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemProxyCapability {
+    /// 当前用户是否可以修改系统代理
+    pub can_modify: bool,
+    /// 不能修改的原因
+    pub reason: Option<String>,
+}
 
 /// 系统管理器
 pub struct SystemManager {
@@ -26,7 +145,35 @@ impl SystemManager {
     }
 
     /// 获取系统统计信息
+    /// 包含整机统计以及代理核心子进程、应用自身进程的资源占用。
+    /// 网络流量按 `stats_scope` 设置统计：system 为所有网卡；proxy 为 TUN 网卡（TUN 模式运行时）
+    /// 或经 Xray 统计服务查询的 HTTP/SOCKS 入站流量，不包含与代理无关的局域网流量
     pub async fn get_stats(&self) -> Result<SystemStats> {
+        let config = AppConfig::load().ok();
+        let proxy_scope = config.as_ref().map(|c| c.stats_scope == "proxy").unwrap_or(false);
+        let tun_device = config.as_ref()
+            .filter(|_| proxy_scope && TunManager::instance().is_running_sync())
+            .map(|c| c.tun_config.name.clone());
+        let api_port = config.as_ref()
+            .filter(|_| proxy_scope && tun_device.is_none() && ProxyManager::instance().is_process_running())
+            .map(|c| c.api_port);
+        let inbound_tags: Vec<String> = config.as_ref()
+            .map(|c| c.effective_inbounds().iter().map(|inbound| inbound.tag()).collect())
+            .unwrap_or_default();
+
+        let inbound_before = match api_port {
+            Some(port) => TrafficManager::query_inbound_stats(port, &inbound_tags).await.ok(),
+            None => None,
+        };
+
+        // CPU 使用率按两次刷新之间的差值计算，创建实例时已刷新过一次
+        tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+
+        let inbound_after = match api_port {
+            Some(port) => TrafficManager::query_inbound_stats(port, &inbound_tags).await.ok(),
+            None => None,
+        };
+
         let mut system = self.system.lock().unwrap();
         let mut networks = self.networks.lock().unwrap();
         
@@ -50,9 +197,20 @@ impl SystemManager {
         let mut total_received = 0;
         let mut total_transmitted = 0;
         
-        for (_interface_name, network) in networks.iter() {
-            total_received += network.received();
-            total_transmitted += network.transmitted();
+        if !proxy_scope {
+            for (_interface_name, network) in networks.iter() {
+                total_received += network.received();
+                total_transmitted += network.transmitted();
+            }
+        } else if let Some(device) = &tun_device {
+            for (_interface_name, network) in networks.iter().filter(|(name, _)| *name == device) {
+                total_received += network.received();
+                total_transmitted += network.transmitted();
+            }
+        } else if let (Some(before), Some(after)) = (inbound_before, inbound_after) {
+            // 入站的上行即客户端发往代理的数据，对应本机上传
+            total_transmitted = after.upload.saturating_sub(before.upload);
+            total_received = after.download.saturating_sub(before.download);
         }
         
         Ok(SystemStats {
@@ -62,6 +220,24 @@ impl SystemManager {
             memory_used,
             network_upload: total_transmitted,
             network_download: total_received,
+            core_process: ProxyManager::instance().core_pid().and_then(|pid| Self::process_stats(&system, pid)),
+            app_process: Self::process_stats(&system, std::process::id()),
+        })
+    }
+
+    /// 获取指定进程的资源占用
+    ///
+    /// # 参数
+    /// * `system` - 已刷新的系统信息
+    /// * `pid` - 进程ID
+    fn process_stats(system: &System, pid: u32) -> Option<ProcessStats> {
+        let process = system.process(Pid::from_u32(pid))?;
+        let cpu_count = system.cpus().len().max(1) as f32;
+        Some(ProcessStats {
+            pid,
+            name: process.name().to_string(),
+            cpu_usage: process.cpu_usage() / cpu_count,
+            memory: process.memory(),
         })
     }
 
@@ -119,6 +295,422 @@ impl SystemManager {
         }
     }
 
+    /// 判断错误是否由权限不足导致
+    /// 注册表写入被拒绝（ERROR_ACCESS_DENIED）或 networksetup 要求管理员权限时返回 true
+    ///
+    /// # 参数
+    /// * `error` - 修改系统代理时的错误
+    pub fn is_permission_denied(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| {
+            if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+                return io_error.kind() == std::io::ErrorKind::PermissionDenied;
+            }
+            let message = cause.to_string().to_lowercase();
+            message.contains("admin privileges") || message.contains("access is denied")
+        })
+    }
+
+    /// 检查当前用户能否修改系统代理
+    /// Windows 检查组策略是否禁止修改代理以及注册表项能否以写权限打开；
+    /// macOS 检查当前用户是否属于 admin 组（networksetup 修改代理需要管理员权限）；
+    /// Linux 通过环境变量设置代理，始终可以修改
+    ///
+    /// # 返回值
+    /// * `SystemProxyCapability` - 能否修改及原因
+    pub fn can_modify_system_proxy(&self) -> SystemProxyCapability {
+        #[cfg(target_os = "windows")]
+        {
+            use winreg::enums::*;
+            use winreg::RegKey;
+
+            // 组策略“阻止更改代理设置”
+            let policy_locked = [HKEY_LOCAL_MACHINE, HKEY_CURRENT_USER].iter().any(|root| {
+                RegKey::predef(*root)
+                    .open_subkey("Software\\Policies\\Microsoft\\Internet Explorer\\Control Panel")
+                    .and_then(|key| key.get_value::<u32, _>("Proxy"))
+                    .map(|value| value == 1)
+                    .unwrap_or(false)
+            });
+            if policy_locked {
+                return SystemProxyCapability {
+                    can_modify: false,
+                    reason: Some("组策略禁止修改代理设置".to_string()),
+                };
+            }
+
+            let writable = RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey_with_flags("Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", KEY_WRITE);
+            match writable {
+                Ok(_) => SystemProxyCapability { can_modify: true, reason: None },
+                Err(e) => SystemProxyCapability {
+                    can_modify: false,
+                    reason: Some(format!("无法写入代理注册表项: {}", e)),
+                },
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let is_admin = std::process::Command::new("id")
+                .arg("-Gn")
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).split_whitespace().any(|g| g == "admin"))
+                .unwrap_or(false);
+            if is_admin {
+                SystemProxyCapability { can_modify: true, reason: None }
+            } else {
+                SystemProxyCapability {
+                    can_modify: false,
+                    reason: Some("当前用户不是管理员，无法通过 networksetup 修改代理".to_string()),
+                }
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            SystemProxyCapability { can_modify: true, reason: None }
+        }
+    }
+
+    /// 获取本机的局域网 IPv4 地址
+    /// 通过 UDP 套接字“连接”公网地址得到默认路由使用的本地地址，不会实际发送数据
+    ///
+    /// # 返回值
+    /// * `Option<Ipv4Addr>` - 局域网地址，没有可用网络时为 None
+    pub fn lan_ip() -> Option<std::net::Ipv4Addr> {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+        socket.connect("8.8.8.8:80").ok()?;
+        match socket.local_addr().ok()?.ip() {
+            std::net::IpAddr::V4(ip) if !ip.is_loopback() && !ip.is_unspecified() => Some(ip),
+            _ => None,
+        }
+    }
+
+    /// 检查系统代理是否指向本机的指定端口
+    /// 用于启动时识别应用异常退出后残留的系统代理设置
+    ///
+    /// # 参数
+    /// * `ports` - 应用使用的入站端口（HTTP、SOCKS、PAC）
+    ///
+    /// # 返回值
+    /// * `bool` - 系统代理已启用且地址为本机上述端口之一
+    pub async fn is_proxy_pointing_to(&self, ports: &[u16]) -> bool {
+        let Ok(status) = self.get_proxy_status().await else {
+            return false;
+        };
+        if !status["enabled"].as_bool().unwrap_or(false) {
+            return false;
+        }
+
+        let targets: Vec<&str> = ["proxy_server", "http_proxy", "https_proxy", "auto_config_url"]
+            .iter()
+            .filter_map(|key| status[*key].as_str())
+            .collect();
+        ports.iter().any(|port| {
+            let local = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+            targets.iter().any(|target| local.iter().any(|addr| target.contains(addr.as_str())))
+        })
+    }
+
+    /// 设置开机自启动
+    /// Windows 写入当前用户的 Run 注册表项，macOS 写入 LaunchAgent，Linux 写入 XDG autostart 桌面文件
+    ///
+    /// # 参数
+    /// * `enable` - 是否启用开机自启动
+    ///
+    /// # 返回值
+    /// * `Result<()>` - 设置结果
+    ///
+    /// # 异常
+    /// * 当无法获取程序路径或写入系统启动项失败时返回错误
+    pub fn set_auto_start(&self, enable: bool) -> Result<()> {
+        let exe_path = std::env::current_exe().context("无法获取程序路径")?;
+
+        #[cfg(target_os = "windows")]
+        {
+            use winreg::enums::*;
+            use winreg::RegKey;
+
+            let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+            let (run_key, _) = hkcu
+                .create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run")
+                .context("无法打开启动项注册表")?;
+
+            if enable {
+                let command = format!("\"{}\"", exe_path.display());
+                run_key
+                    .set_value(AUTO_START_NAME, &command)
+                    .context("无法写入启动项")?;
+            } else if run_key.get_value::<String, _>(AUTO_START_NAME).is_ok() {
+                run_key
+                    .delete_value(AUTO_START_NAME)
+                    .context("无法删除启动项")?;
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let agent_path = Self::auto_start_entry_path()?;
+            if enable {
+                let plist = format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>com.lander.ruray</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+                    exe_path.display()
+                );
+                if let Some(parent) = agent_path.parent() {
+                    std::fs::create_dir_all(parent).context("无法创建 LaunchAgents 目录")?;
+                }
+                std::fs::write(&agent_path, plist).context("无法写入 LaunchAgent")?;
+            } else if agent_path.exists() {
+                std::fs::remove_file(&agent_path).context("无法删除 LaunchAgent")?;
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let desktop_path = Self::auto_start_entry_path()?;
+            if enable {
+                let desktop_entry = format!(
+                    "[Desktop Entry]\n\
+                     Type=Application\n\
+                     Name=RuRay\n\
+                     Comment=RuRay - Xray Core Desktop Client\n\
+                     Exec=\"{}\"\n\
+                     Terminal=false\n\
+                     X-GNOME-Autostart-enabled=true\n",
+                    exe_path.display()
+                );
+                if let Some(parent) = desktop_path.parent() {
+                    std::fs::create_dir_all(parent).context("无法创建 autostart 目录")?;
+                }
+                std::fs::write(&desktop_path, desktop_entry).context("无法写入自启动文件")?;
+            } else if desktop_path.exists() {
+                std::fs::remove_file(&desktop_path).context("无法删除自启动文件")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 检查开机自启动是否已注册
+    ///
+    /// # 返回值
+    /// * `bool` - 系统中是否存在本程序的启动项
+    pub fn is_auto_start_enabled(&self) -> bool {
+        #[cfg(target_os = "windows")]
+        {
+            use winreg::enums::*;
+            use winreg::RegKey;
+
+            RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run")
+                .and_then(|key| key.get_value::<String, _>(AUTO_START_NAME))
+                .is_ok()
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            Self::auto_start_entry_path()
+                .map(|path| path.exists())
+                .unwrap_or(false)
+        }
+    }
+
+    /// 检查本地 TCP 端口是否可用
+    ///
+    /// # 参数
+    /// * `port` - 端口号
+    pub fn is_port_available(&self, port: u16) -> bool {
+        std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+    }
+
+    /// 查找监听指定 TCP 端口的进程
+    /// Windows 使用 netstat，macOS/Linux 使用 lsof（Linux 上不可用时回退到 ss）
+    ///
+    /// # 参数
+    /// * `port` - 端口号
+    ///
+    /// # 返回值
+    /// * `Option<PortOwner>` - 占用端口的进程，无法确定时为 None
+    pub fn find_port_owner(&self, port: u16) -> Option<PortOwner> {
+        let pid = Self::find_listening_pid(port)?;
+        let name = {
+            let mut system = self.system.lock().unwrap();
+            system.refresh_processes();
+            system.process(Pid::from_u32(pid)).map(|process| process.name().to_string())
+        };
+        Some(PortOwner { pid, name })
+    }
+
+    /// 检测可能与本应用冲突的代理软件
+    /// 按进程名识别其他代理软件与 VPN 客户端，检查常见代理端口的占用和 VPN 网卡，
+    /// 并检查系统代理是否指向其他程序。本应用启动的核心进程与自身的 TUN 网卡不计入
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    ///
+    /// # 返回值
+    /// * `Vec<ConflictWarning>` - 冲突列表，没有冲突时为空
+    pub async fn detect_conflicts(&self, config: &AppConfig) -> Vec<ConflictWarning> {
+        let mut warnings = Vec::new();
+        let own_pid = Pid::from_u32(std::process::id());
+        let core_pid = ProxyManager::instance().core_pid().map(Pid::from_u32);
+
+        {
+            let mut system = self.system.lock().unwrap();
+            system.refresh_processes();
+            let mut seen = std::collections::HashSet::new();
+            for (pid, process) in system.processes() {
+                if *pid == own_pid || Some(*pid) == core_pid || process.parent() == Some(own_pid) {
+                    continue;
+                }
+                let process_name = process.name().to_lowercase();
+                let matched = PROXY_SOFTWARE.iter().map(|(key, name)| ("process", *key, *name))
+                    .chain(VPN_SOFTWARE.iter().map(|(key, name)| ("vpn", *key, *name)))
+                    .find(|(_, key, _)| process_name.contains(key));
+                if let Some((kind, _, name)) = matched {
+                    if seen.insert(name) {
+                        warnings.push(ConflictWarning {
+                            kind: kind.to_string(),
+                            name: name.to_string(),
+                            detail: format!("正在运行: {} (PID {})", process.name(), pid.as_u32()),
+                        });
+                    }
+                }
+            }
+        }
+
+        let own_ports = [config.http_port, config.socks_port, config.pac_port, config.api_port];
+        for (port, name) in PROXY_SOFTWARE_PORTS {
+            if own_ports.contains(&port) || self.is_port_available(port) {
+                continue;
+            }
+            let owner = self.find_port_owner(port);
+            if owner.as_ref().is_some_and(|o| o.pid == std::process::id() || Some(Pid::from_u32(o.pid)) == core_pid) {
+                continue;
+            }
+            let process = owner.and_then(|o| o.name).unwrap_or_else(|| "未知进程".to_string());
+            warnings.push(ConflictWarning {
+                kind: "port".to_string(),
+                name: name.to_string(),
+                detail: format!("端口 {}（{} 默认端口）被 {} 占用", port, name, process),
+            });
+        }
+
+        {
+            let mut networks = self.networks.lock().unwrap();
+            networks.refresh_list();
+            let own_tun = config.tun_config.name.to_lowercase();
+            for (interface, _) in networks.iter() {
+                let lower = interface.to_lowercase();
+                // macOS 的 utun 网卡多为系统服务使用，不视为 VPN
+                if lower == own_tun || lower.starts_with("utun") {
+                    continue;
+                }
+                if VPN_INTERFACE_PATTERNS.iter().any(|pattern| lower.starts_with(pattern) || lower.contains(&format!("{} ", pattern))) {
+                    warnings.push(ConflictWarning {
+                        kind: "vpn".to_string(),
+                        name: interface.clone(),
+                        detail: format!("检测到 VPN 网卡 {}，可能接管系统路由", interface),
+                    });
+                }
+            }
+        }
+
+        if let Ok(status) = self.get_proxy_status().await {
+            if status["enabled"].as_bool().unwrap_or(false) && !self.is_proxy_pointing_to(&own_ports).await {
+                let target = ["proxy_server", "http_proxy", "auto_config_url"]
+                    .iter()
+                    .find_map(|key| status[*key].as_str().filter(|s| !s.is_empty()))
+                    .unwrap_or("未知地址")
+                    .to_string();
+                warnings.push(ConflictWarning {
+                    kind: "system_proxy".to_string(),
+                    name: target.clone(),
+                    detail: format!("系统代理当前指向 {}，不是本应用的端口，可能已被其他软件修改", target),
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// 查找监听指定端口的进程ID
+    #[cfg(target_os = "windows")]
+    fn find_listening_pid(port: u16) -> Option<u32> {
+        use std::os::windows::process::CommandExt;
+
+        let output = std::process::Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output()
+            .ok()?;
+        let suffix = format!(":{}", port);
+
+        // 格式：TCP    127.0.0.1:10086    0.0.0.0:0    LISTENING    1234
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .find(|cols| cols.len() >= 5 && cols[1].ends_with(&suffix) && cols[3] == "LISTENING")
+            .and_then(|cols| cols[4].parse().ok())
+    }
+
+    /// 查找监听指定端口的进程ID
+    #[cfg(not(target_os = "windows"))]
+    fn find_listening_pid(port: u16) -> Option<u32> {
+        let lsof = std::process::Command::new("lsof")
+            .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-t"])
+            .output();
+        if let Ok(output) = lsof {
+            if let Some(pid) = String::from_utf8_lossy(&output.stdout).lines().find_map(|l| l.trim().parse().ok()) {
+                return Some(pid);
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            // 格式：LISTEN 0 4096 127.0.0.1:10086 0.0.0.0:* users:(("xray",pid=1234,fd=3))
+            let output = std::process::Command::new("ss")
+                .args(["-ltnpH", &format!("sport = :{}", port)])
+                .output()
+                .ok()?;
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let start = stdout.find("pid=")? + 4;
+            let digits: String = stdout[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+            return digits.parse().ok();
+        }
+
+        #[allow(unreachable_code)]
+        None
+    }
+
+    /// 获取自启动文件路径（macOS LaunchAgent / Linux autostart 桌面文件）
+    #[cfg(target_os = "macos")]
+    fn auto_start_entry_path() -> Result<std::path::PathBuf> {
+        let home = dirs::home_dir().context("无法获取用户目录")?;
+        Ok(home.join("Library").join("LaunchAgents").join("com.lander.ruray.plist"))
+    }
+
+    /// 获取自启动文件路径（macOS LaunchAgent / Linux autostart 桌面文件）
+    #[cfg(target_os = "linux")]
+    fn auto_start_entry_path() -> Result<std::path::PathBuf> {
+        let config_dir = dirs::config_dir().context("无法获取配置目录")?;
+        Ok(config_dir.join("autostart").join("ruray.desktop"))
+    }
+
     #[cfg(target_os = "windows")]
     async fn set_windows_proxy(&self, proxy_url: &str) -> Result<()> {
         use winreg::enums::*;
@@ -170,8 +762,8 @@ impl SystemManager {
             .set_value("AutoConfigURL", &"")
             .context("无法设置 AutoConfigURL")?;
 
-        // 刷新系统设置
-        // self.refresh_windows_proxy_settings().await?;
+        // 刷新系统设置，让浏览器立即使用新代理
+        self.refresh_windows_proxy_settings().await?;
 
         Ok(())
     }
@@ -197,44 +789,83 @@ impl SystemManager {
         Ok(())
     }
 
+    /// 通知系统代理设置已变更
+    /// 通知失败时将 ProxyEnable 临时取反再恢复，并在每次写入后重新通知，强制 WinINET 重新读取注册表
     #[cfg(target_os = "windows")]
     async fn refresh_windows_proxy_settings(&self) -> Result<()> {
-        // 方法1: 使用 Windows API 通知系统设置变更
-        #[cfg(target_os = "windows")]
-        {
-            use std::ptr;
-            
-            // 使用 InternetSetOption 刷新代理设置
-            // 这是参考 Privoxy 和其他代理软件的标准做法
-            unsafe {
-                // 定义 Windows API 常量
-                const INTERNET_OPTION_SETTINGS_CHANGED: u32 = 39;
-                const INTERNET_OPTION_REFRESH: u32 = 37;
-                
-                // 加载 wininet.dll
-                let wininet = libloading::Library::new("wininet.dll")
-                    .context("无法加载 wininet.dll")?;
-                
-                // 获取 InternetSetOption 函数
-                let internet_set_option: libloading::Symbol<unsafe extern "system" fn(
-                    hinternet: *mut std::ffi::c_void,
-                    dwoption: u32,
-                    lpbuffer: *const std::ffi::c_void,
-                    dwbufferlength: u32,
-                ) -> i32> = wininet.get(b"InternetSetOptionA")
-                    .context("无法获取 InternetSetOptionA 函数")?;
-                
-                // 通知系统设置已更改
-                internet_set_option(ptr::null_mut(), INTERNET_OPTION_SETTINGS_CHANGED, ptr::null(), 0);
-                
-                // 刷新设置
-                internet_set_option(ptr::null_mut(), INTERNET_OPTION_REFRESH, ptr::null(), 0);
-            }
+        use winreg::enums::*;
+        use winreg::RegKey;
+
+        if Self::notify_wininet_settings_changed()? {
+            return Ok(());
         }
+        log_warn!("通知代理设置变更失败，切换 ProxyEnable 强制刷新");
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let internet_settings = hkcu
+            .open_subkey_with_flags("Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", KEY_READ | KEY_WRITE)
+            .context("无法打开注册表项")?;
+        let proxy_enable: u32 = internet_settings.get_value("ProxyEnable").unwrap_or(0);
+
+        internet_settings
+            .set_value("ProxyEnable", &(1 - proxy_enable.min(1)))
+            .context("无法设置 ProxyEnable")?;
+        let _ = Self::notify_wininet_settings_changed();
+        internet_settings
+            .set_value("ProxyEnable", &proxy_enable)
+            .context("无法设置 ProxyEnable")?;
 
+        if !Self::notify_wininet_settings_changed()? {
+            anyhow::bail!("刷新系统代理设置失败，新设置可能需要重启浏览器后生效");
+        }
         Ok(())
     }
 
+    /// 调用 InternetSetOption 通知 WinINET 代理设置已变更并刷新
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 通知是否全部成功
+    ///
+    /// # 异常
+    /// * 无法加载 wininet.dll 时返回错误
+    #[cfg(target_os = "windows")]
+    fn notify_wininet_settings_changed() -> Result<bool> {
+        use std::ptr;
+
+        // 使用 InternetSetOption 刷新代理设置
+        // 这是参考 Privoxy 和其他代理软件的标准做法
+        unsafe {
+            // 定义 Windows API 常量
+            const INTERNET_OPTION_SETTINGS_CHANGED: u32 = 39;
+            const INTERNET_OPTION_REFRESH: u32 = 37;
+            const INTERNET_OPTION_PROXY_SETTINGS_CHANGED: u32 = 95;
+
+            // 加载 wininet.dll
+            let wininet = libloading::Library::new("wininet.dll")
+                .context("无法加载 wininet.dll")?;
+
+            // 获取 InternetSetOption 函数
+            let internet_set_option: libloading::Symbol<unsafe extern "system" fn(
+                hinternet: *mut std::ffi::c_void,
+                dwoption: u32,
+                lpbuffer: *const std::ffi::c_void,
+                dwbufferlength: u32,
+            ) -> i32> = wininet.get(b"InternetSetOptionA")
+                .context("无法获取 InternetSetOptionA 函数")?;
+
+            // 通知系统设置已更改
+            let changed = internet_set_option(ptr::null_mut(), INTERNET_OPTION_SETTINGS_CHANGED, ptr::null(), 0) != 0;
+
+            // 通知已打开的连接重新读取代理设置（较旧的系统不支持，忽略结果）
+            internet_set_option(ptr::null_mut(), INTERNET_OPTION_PROXY_SETTINGS_CHANGED, ptr::null(), 0);
+
+            // 刷新设置
+            let refreshed = internet_set_option(ptr::null_mut(), INTERNET_OPTION_REFRESH, ptr::null(), 0) != 0;
+
+            Ok(changed && refreshed)
+        }
+    }
+
     #[cfg(target_os = "windows")]
     async fn get_windows_proxy_status(&self) -> Result<serde_json::Value> {
         use winreg::enums::*;
@@ -315,16 +946,10 @@ impl SystemManager {
             let service = line.trim();
             
             // 设置 HTTP 代理
-            Command::new("networksetup")
-                .args(&["-setwebproxy", service, host, &port.to_string()])
-                .output()
-                .context("无法设置 HTTP 代理")?;
+            Self::run_networksetup(&["-setwebproxy", service, host, &port.to_string()]).context("无法设置 HTTP 代理")?;
             
             // 设置 HTTPS 代理
-            Command::new("networksetup")
-                .args(&["-setsecurewebproxy", service, host, &port.to_string()])
-                .output()
-                .context("无法设置 HTTPS 代理")?;
+            Self::run_networksetup(&["-setsecurewebproxy", service, host, &port.to_string()]).context("无法设置 HTTPS 代理")?;
         }
 
         Ok(())
@@ -348,21 +973,38 @@ impl SystemManager {
             let service = line.trim();
             
             // 禁用 HTTP 代理
-            Command::new("networksetup")
-                .args(&["-setwebproxystate", service, "off"])
-                .output()
-                .context("无法禁用 HTTP 代理")?;
+            Self::run_networksetup(&["-setwebproxystate", service, "off"]).context("无法禁用 HTTP 代理")?;
             
             // 禁用 HTTPS 代理
-            Command::new("networksetup")
-                .args(&["-setsecurewebproxystate", service, "off"])
-                .output()
-                .context("无法禁用 HTTPS 代理")?;
+            Self::run_networksetup(&["-setsecurewebproxystate", service, "off"]).context("无法禁用 HTTPS 代理")?;
         }
 
         Ok(())
     }
 
+    /// 执行 networksetup 修改命令
+    /// networksetup 出错时退出码可能仍为 0，需要同时检查输出中的错误信息
+    #[cfg(target_os = "macos")]
+    fn run_networksetup(args: &[&str]) -> Result<()> {
+        let output = std::process::Command::new("networksetup")
+            .args(args)
+            .output()
+            .context("无法执行 networksetup")?;
+
+        let message = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        if output.status.success() && !message.contains("** Error") {
+            return Ok(());
+        }
+        if message.contains("admin privileges") {
+            return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, message).into());
+        }
+        Err(anyhow::anyhow!("networksetup 执行失败: {}", message))
+    }
+
     #[cfg(target_os = "linux")]
     async fn set_linux_proxy(&self, proxy_url: &str) -> Result<()> {
         // 在 Linux 上设置环境变量
@@ -0,0 +1,199 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 配置模板变量
+// 自定义入站/出站、出站模板与出站配置片段、路由规则中的字符串可以包含 `${名称}` 占位符，生成核心配置时替换：
+// * `${HTTP_PORT}` / `${SOCKS_PORT}` / `${API_PORT}` - 本地入站与 API 端口
+// * `${LAN_IP}` - 本机局域网 IPv4 地址
+// * `${SERVER_IP}` / `${SERVER_PORT}` / `${SERVER_NAME}` / `${SERVER_ID}` - 当前服务器
+// * `${env:NAME}` - 环境变量 NAME
+// 字符串只包含一个占位符且替换结果为数字时替换为 JSON 数字，如 `"port": "${HTTP_PORT}"`；
+// `$${` 表示字面量 `${`。未知变量或未设置的环境变量会返回错误
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+use crate::system::SystemManager;
+
+/// 与服务器无关的变量
+const GLOBAL_VARIABLES: [&str; 4] = ["HTTP_PORT", "SOCKS_PORT", "API_PORT", "LAN_IP"];
+/// 与服务器相关的变量，仅在生成某个服务器的配置时可用
+const SERVER_VARIABLES: [&str; 4] = ["SERVER_IP", "SERVER_PORT", "SERVER_NAME", "SERVER_ID"];
+/// 环境变量前缀
+const ENV_PREFIX: &str = "env:";
+
+/// 模板变量表
+pub struct TemplateVars {
+    values: BTreeMap<&'static str, String>,
+}
+
+impl TemplateVars {
+    /// 根据应用配置和当前服务器构建变量表
+    ///
+    /// # 参数
+    /// * `config` - 应用配置
+    /// * `server` - 当前服务器，为空时服务器相关变量不可用
+    pub fn new(config: &AppConfig, server: Option<&ServerInfo>) -> Self {
+        let mut values = BTreeMap::new();
+        values.insert("HTTP_PORT", config.http_port.to_string());
+        values.insert("SOCKS_PORT", config.socks_port.to_string());
+        values.insert("API_PORT", config.api_port.to_string());
+        values.insert("LAN_IP", SystemManager::lan_ip().map(|ip| ip.to_string()).unwrap_or_else(|| "127.0.0.1".to_string()));
+        if let Some(server) = server {
+            values.insert("SERVER_IP", server.address.clone());
+            values.insert("SERVER_PORT", server.port.to_string());
+            values.insert("SERVER_NAME", server.name.clone());
+            values.insert("SERVER_ID", server.id.clone());
+        }
+        Self { values }
+    }
+
+    /// 替换 JSON 中所有字符串里的占位符
+    ///
+    /// # 参数
+    /// * `value` - 待替换的 JSON
+    /// * `context` - 出错时用于说明位置，如 "自定义出站"
+    ///
+    /// # 异常
+    /// * 存在未知变量、未设置的环境变量或占位符未闭合时返回错误
+    pub fn apply(&self, value: &mut serde_json::Value, context: &str) -> Result<()> {
+        match value {
+            serde_json::Value::String(text) if text.contains('$') => {
+                *value = self.resolve_string(text).map_err(|e| anyhow::anyhow!("{}: {}", context, e))?;
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.apply(item, context)?;
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.apply(item, context)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 替换单个字符串中的占位符
+    fn resolve_string(&self, text: &str) -> Result<serde_json::Value> {
+        let segments = parse(text)?;
+        // 整个字符串就是一个占位符且结果为数字时输出数字，便于用于端口等数值字段
+        let whole = matches!(segments.as_slice(), [Segment::Variable(_)]);
+
+        let mut output = String::with_capacity(text.len());
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => output.push_str(&text),
+                Segment::Variable(name) => output.push_str(&self.lookup(&name)?),
+            }
+        }
+
+        if whole {
+            if let Ok(number) = output.parse::<u64>() {
+                return Ok(serde_json::json!(number));
+            }
+        }
+        Ok(serde_json::Value::String(output))
+    }
+
+    /// 查找变量值
+    fn lookup(&self, name: &str) -> Result<String> {
+        if let Some(env_name) = name.strip_prefix(ENV_PREFIX) {
+            return std::env::var(env_name).map_err(|_| anyhow::anyhow!("环境变量未设置: ${{{}}}", name));
+        }
+        if let Some(value) = self.values.get(name) {
+            return Ok(value.clone());
+        }
+        if SERVER_VARIABLES.contains(&name) {
+            return Err(anyhow::anyhow!("模板变量 ${{{}}} 只能在生成服务器配置时使用", name));
+        }
+        Err(unknown_variable(name))
+    }
+}
+
+/// 检查 JSON 中的占位符是否都是已知变量，保存配置时调用
+/// 只检查变量名，不检查环境变量是否已设置（生成配置时才需要）
+///
+/// # 参数
+/// * `value` - 待检查的 JSON
+/// * `context` - 出错时用于说明位置
+pub fn validate(value: &serde_json::Value, context: &str) -> Result<()> {
+    match value {
+        serde_json::Value::String(text) if text.contains('$') => {
+            for segment in parse(text).map_err(|e| anyhow::anyhow!("{}: {}", context, e))? {
+                if let Segment::Variable(name) = segment {
+                    let known = name.starts_with(ENV_PREFIX)
+                        || GLOBAL_VARIABLES.contains(&name.as_str())
+                        || SERVER_VARIABLES.contains(&name.as_str());
+                    if !known {
+                        return Err(anyhow::anyhow!("{}: {}", context, unknown_variable(&name)));
+                    }
+                }
+            }
+            Ok(())
+        }
+        serde_json::Value::Array(items) => items.iter().try_for_each(|item| validate(item, context)),
+        serde_json::Value::Object(map) => map.values().try_for_each(|item| validate(item, context)),
+        _ => Ok(()),
+    }
+}
+
+/// 未知变量错误，列出可用变量
+fn unknown_variable(name: &str) -> anyhow::Error {
+    let available: Vec<String> = GLOBAL_VARIABLES.iter()
+        .chain(SERVER_VARIABLES.iter())
+        .map(|name| format!("${{{}}}", name))
+        .chain(std::iter::once("${env:NAME}".to_string()))
+        .collect();
+    anyhow::anyhow!("未知的模板变量 ${{{}}}，可用变量: {}", name, available.join(", "))
+}
+
+/// 字符串片段
+enum Segment {
+    /// 普通文本
+    Text(String),
+    /// 占位符中的变量名
+    Variable(String),
+}
+
+/// 将字符串拆分为普通文本与占位符
+fn parse(text: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+
+    while let Some(index) = rest.find('$') {
+        literal.push_str(&rest[..index]);
+        let after = &rest[index..];
+        if let Some(escaped) = after.strip_prefix("$${") {
+            literal.push_str("${");
+            rest = escaped;
+        } else if let Some(body) = after.strip_prefix("${") {
+            let end = body.find('}').ok_or_else(|| anyhow::anyhow!("模板占位符未闭合: {}", text))?;
+            let name = body[..end].trim();
+            if name.is_empty() {
+                return Err(anyhow::anyhow!("模板占位符缺少变量名: {}", text));
+            }
+            if !literal.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Variable(name.to_string()));
+            rest = &body[end + 1..];
+        } else {
+            literal.push('$');
+            rest = &after[1..];
+        }
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+    Ok(segments)
+}
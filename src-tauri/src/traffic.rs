@@ -0,0 +1,420 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2024-12-20
+ */
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::{log_debug, log_error, log_warn};
+
+use crate::config::AppConfig;
+use crate::history::HistoryStore;
+use crate::proxy::ProxyManager;
+use crate::xray_api::XrayApiClient;
+
+/// 流量采样间隔
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// 配额提醒阈值（百分比）
+const QUOTA_WARN_LEVELS: [u8; 2] = [80, 100];
+
+/// 流量用量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrafficUsage {
+    /// 上传字节数
+    pub upload: u64,
+    /// 下载字节数
+    pub download: u64,
+}
+
+impl TrafficUsage {
+    /// 上传与下载合计
+    pub fn total(&self) -> u64 {
+        self.upload + self.download
+    }
+
+    fn add(&mut self, other: &TrafficUsage) {
+        self.upload += other.upload;
+        self.download += other.download;
+    }
+}
+
+/// 持久化的流量统计数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TrafficStore {
+    /// 月份（YYYY-MM） -> 服务器ID -> 用量
+    #[serde(default)]
+    monthly: HashMap<String, HashMap<String, TrafficUsage>>,
+    /// 已发出的配额提醒（键为 "月份:目标ID"，值为最高提醒阈值）
+    #[serde(default)]
+    warned: HashMap<String, u8>,
+}
+
+/// 流量统计报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficReport {
+    /// 统计月份（YYYY-MM）
+    pub month: String,
+    /// 各服务器用量
+    pub servers: HashMap<String, TrafficUsage>,
+    /// 各订阅用量（按服务器配置中的 `subscription_id` 聚合）
+    pub subscriptions: HashMap<String, TrafficUsage>,
+    /// 已配置的配额（字节）
+    pub quotas: HashMap<String, u64>,
+}
+
+/// 流量统计管理器
+/// 代理运行期间定期通过 Xray 统计服务读取代理出站的流量增量，
+/// 按月累计到当前服务器名下并持久化到 traffic.json，超出配额阈值时发射 `traffic-quota-warning` 事件；
+/// 每次采样同时写入统计历史，供仪表盘绘制速度与运行时长图表
+pub struct TrafficManager {
+    store: Arc<Mutex<TrafficStore>>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// 通知采样任务退出
+    shutdown: Mutex<Option<tokio::sync::oneshot::Sender<()>>>,
+    /// 最近一次采样的速度（字节/秒）
+    speed: Mutex<TrafficUsage>,
+    /// 本次代理运行期间的累计流量
+    session: Mutex<TrafficUsage>,
+}
+
+// 全局单例实例
+static TRAFFIC_MANAGER: OnceLock<TrafficManager> = OnceLock::new();
+
+impl TrafficManager {
+    /// 获取全局流量统计管理器实例（单例模式）
+    pub fn instance() -> &'static TrafficManager {
+        TRAFFIC_MANAGER.get_or_init(|| {
+            let store = Self::load_store().unwrap_or_else(|e| {
+                log_warn!("加载流量统计数据失败: {}", e);
+                TrafficStore::default()
+            });
+            Self {
+                store: Arc::new(Mutex::new(store)),
+                task: Arc::new(Mutex::new(None)),
+                shutdown: Mutex::new(None),
+                speed: Mutex::new(TrafficUsage::default()),
+                session: Mutex::new(TrafficUsage::default()),
+            }
+        })
+    }
+
+    /// 启动后台流量采样任务
+    /// 使用 tokio 定时器按固定间隔采样，可通过 `stop` 停止
+    ///
+    /// # 参数
+    /// * `app_handle` - Tauri 应用句柄，用于发射配额提醒事件
+    pub fn start(&self, app_handle: AppHandle) {
+        let mut task_guard = self.task.lock().unwrap();
+        if task_guard.is_some() {
+            return;
+        }
+
+        let (shutdown_tx, mut shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let task = tauri::async_runtime::spawn(async move {
+            let manager = TrafficManager::instance();
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            // 采样耗时超过间隔时顺延，而不是连续补采
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // 第一次 tick 立即返回，跳过以保证首个样本覆盖完整的采样间隔
+            interval.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    _ = interval.tick() => manager.sample(&app_handle).await,
+                }
+            }
+            manager.reset_session();
+            log_debug!("流量采样任务已停止");
+        });
+
+        *self.shutdown.lock().unwrap() = Some(shutdown_tx);
+        *task_guard = Some(task);
+    }
+
+    /// 停止后台流量采样任务
+    /// 通知采样任务退出并等待其结束，正在进行的采样完成后才返回
+    pub async fn stop(&self) {
+        if let Some(shutdown) = self.shutdown.lock().unwrap().take() {
+            let _ = shutdown.send(());
+        }
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+
+    /// 执行一次流量采样
+    /// 代理未运行时清零本次运行的统计
+    async fn sample(&self, app_handle: &AppHandle) {
+        let proxy_manager = ProxyManager::instance();
+        let server_id = proxy_manager.current_server_id()
+            .filter(|_| proxy_manager.is_process_running());
+        let Some(server_id) = server_id else {
+            self.reset_session();
+            return;
+        };
+
+        let config = match AppConfig::load() {
+            Ok(config) => config,
+            Err(_) => return,
+        };
+
+        match Self::query_stats(config.api_port, true).await {
+            Ok(delta) => {
+                self.record_speed(&delta);
+                HistoryStore::instance().record(
+                    &server_id,
+                    &delta,
+                    SAMPLE_INTERVAL.as_secs(),
+                    proxy_manager.uptime_secs(),
+                );
+                if delta.total() > 0 {
+                    self.record(&server_id, &delta);
+                    self.check_quotas(app_handle, &config);
+                }
+            }
+            Err(e) => log_debug!("查询流量统计失败: {}", e),
+        }
+    }
+
+    /// 通过 Xray 统计服务查询代理出站的流量
+    ///
+    /// # 参数
+    /// * `api_port` - Xray API 监听端口
+    /// * `reset` - 查询后是否清零计数器
+    ///
+    /// # 返回值
+    /// * `Result<TrafficUsage>` - 流量用量
+    pub async fn query_stats(api_port: u16, reset: bool) -> Result<TrafficUsage> {
+        Self::statsquery(api_port, "outbound>>>proxy>>>traffic", reset).await
+    }
+
+    /// 通过 Xray 统计服务查询经本地代理入站的流量
+    /// 只统计经过代理入站端口的流量，不包含 API 入站；计数器不清零，供调用方按两次查询的差值计算
+    ///
+    /// # 参数
+    /// * `api_port` - Xray API 监听端口
+    /// * `tags` - 入站标签
+    ///
+    /// # 返回值
+    /// * `Result<TrafficUsage>` - 自核心启动以来的累计流量
+    pub async fn query_inbound_stats(api_port: u16, tags: &[String]) -> Result<TrafficUsage> {
+        let mut total = TrafficUsage::default();
+        for tag in tags {
+            let usage = Self::statsquery(api_port, &format!("inbound>>>{}>>>traffic", tag), false).await?;
+            total.upload += usage.upload;
+            total.download += usage.download;
+        }
+        Ok(total)
+    }
+
+    /// 通过 Xray 统计服务查询各入站用户的流量
+    /// 仅在入站启用用户名密码认证时有数据，计数器不清零
+    ///
+    /// # 参数
+    /// * `api_port` - Xray API 监听端口
+    ///
+    /// # 返回值
+    /// * `Result<HashMap<String, TrafficUsage>>` - 用户名到自核心启动以来累计流量的映射
+    pub async fn query_user_stats(api_port: u16) -> Result<HashMap<String, TrafficUsage>> {
+        let mut users: HashMap<String, TrafficUsage> = HashMap::new();
+        for (name, bytes) in Self::statsquery_counters(api_port, "user>>>", false).await? {
+            // 计数器名称格式: user>>>{用户名}>>>traffic>>>uplink
+            let Some(rest) = name.strip_prefix("user>>>") else { continue };
+            let Some((username, direction)) = rest.rsplit_once(">>>traffic>>>") else { continue };
+            let usage = users.entry(username.to_string()).or_default();
+            match direction {
+                "uplink" => usage.upload += bytes,
+                "downlink" => usage.download += bytes,
+                _ => {}
+            }
+        }
+        Ok(users)
+    }
+
+    /// 通过 StatsService 查询匹配的计数器并汇总上下行流量
+    async fn statsquery(api_port: u16, pattern: &str, reset: bool) -> Result<TrafficUsage> {
+        let mut usage = TrafficUsage::default();
+        for (name, bytes) in Self::statsquery_counters(api_port, pattern, reset).await? {
+            if name.ends_with(">>>uplink") {
+                usage.upload += bytes;
+            } else if name.ends_with(">>>downlink") {
+                usage.download += bytes;
+            }
+        }
+        Ok(usage)
+    }
+
+    /// 通过 StatsService 查询匹配的计数器名称和值
+    async fn statsquery_counters(api_port: u16, pattern: &str, reset: bool) -> Result<Vec<(String, u64)>> {
+        let stats = XrayApiClient::new(api_port)?
+            .query_stats(pattern, reset)
+            .await
+            .context("查询统计信息失败")?;
+        Ok(stats.into_iter().map(|stat| (stat.name, stat.value.max(0) as u64)).collect())
+    }
+
+    /// 获取最近一次采样的速度
+    ///
+    /// # 返回值
+    /// * `TrafficUsage` - 上传与下载速度（字节/秒），代理未运行时为 0
+    pub fn current_speed(&self) -> TrafficUsage {
+        self.speed.lock().unwrap().clone()
+    }
+
+    /// 获取本次代理运行期间的累计流量
+    pub fn session_usage(&self) -> TrafficUsage {
+        self.session.lock().unwrap().clone()
+    }
+
+    /// 根据采样增量更新速度与本次运行的累计流量
+    fn record_speed(&self, delta: &TrafficUsage) {
+        let secs = SAMPLE_INTERVAL.as_secs().max(1);
+        *self.speed.lock().unwrap() = TrafficUsage {
+            upload: delta.upload / secs,
+            download: delta.download / secs,
+        };
+        self.session.lock().unwrap().add(delta);
+    }
+
+    /// 代理停止后清零速度与本次运行的累计流量
+    fn reset_session(&self) {
+        *self.speed.lock().unwrap() = TrafficUsage::default();
+        *self.session.lock().unwrap() = TrafficUsage::default();
+    }
+
+    /// 累计服务器流量并持久化
+    fn record(&self, server_id: &str, delta: &TrafficUsage) {
+        let mut store = self.store.lock().unwrap();
+        store.monthly
+            .entry(Self::current_month())
+            .or_default()
+            .entry(server_id.to_string())
+            .or_default()
+            .add(delta);
+
+        if let Err(e) = Self::save_store(&store) {
+            log_error!("保存流量统计数据失败: {}", e);
+        }
+    }
+
+    /// 生成指定月份的流量统计报告
+    ///
+    /// # 参数
+    /// * `month` - 月份（YYYY-MM），为空时使用当前月份
+    /// * `config` - 应用配置，用于按订阅聚合
+    pub fn report(&self, month: Option<String>, config: &AppConfig) -> TrafficReport {
+        let month = month.unwrap_or_else(Self::current_month);
+        let servers = {
+            let store = self.store.lock().unwrap();
+            store.monthly.get(&month).cloned().unwrap_or_default()
+        };
+
+        let mut subscriptions: HashMap<String, TrafficUsage> = HashMap::new();
+        for server in &config.servers {
+            let Some(subscription_id) = server.config.get("subscription_id").and_then(|v| v.as_str()) else { continue };
+            if let Some(usage) = servers.get(&server.id) {
+                subscriptions.entry(subscription_id.to_string()).or_default().add(usage);
+            }
+        }
+
+        TrafficReport {
+            month,
+            servers,
+            subscriptions,
+            quotas: config.traffic_quotas.clone(),
+        }
+    }
+
+    /// 清空流量统计
+    ///
+    /// # 参数
+    /// * `server_id` - 指定服务器ID时只清空该服务器当月数据，否则清空全部数据
+    pub fn reset(&self, server_id: Option<&str>) -> Result<()> {
+        let mut store = self.store.lock().unwrap();
+        match server_id {
+            Some(id) => {
+                if let Some(servers) = store.monthly.get_mut(&Self::current_month()) {
+                    servers.remove(id);
+                }
+            }
+            None => *store = TrafficStore::default(),
+        }
+        Self::save_store(&store)
+    }
+
+    /// 检查配额并在跨过提醒阈值时发射事件
+    fn check_quotas(&self, app_handle: &AppHandle, config: &AppConfig) {
+        if config.traffic_quotas.is_empty() {
+            return;
+        }
+
+        let report = self.report(None, config);
+        let mut store = self.store.lock().unwrap();
+        let mut changed = false;
+
+        for (target_id, limit) in &config.traffic_quotas {
+            if *limit == 0 {
+                continue;
+            }
+            let used = report.servers.get(target_id)
+                .or_else(|| report.subscriptions.get(target_id))
+                .map(|u| u.total())
+                .unwrap_or(0);
+            let percent = (used.saturating_mul(100) / limit).min(u8::MAX as u64) as u8;
+
+            let Some(level) = QUOTA_WARN_LEVELS.iter().rev().find(|l| percent >= **l).copied() else { continue };
+            let key = format!("{}:{}", report.month, target_id);
+            if store.warned.get(&key).copied().unwrap_or(0) >= level {
+                continue;
+            }
+            store.warned.insert(key, level);
+            changed = true;
+
+            log_warn!("流量配额提醒: {} 已使用 {}% ({} / {} 字节)", target_id, percent, used, limit);
+            let _ = app_handle.emit("traffic-quota-warning", serde_json::json!({
+                "target_id": target_id,
+                "used": used,
+                "limit": limit,
+                "percent": percent
+            }));
+        }
+
+        if changed {
+            if let Err(e) = Self::save_store(&store) {
+                log_error!("保存流量统计数据失败: {}", e);
+            }
+        }
+    }
+
+    /// 当前月份（YYYY-MM，本地时间）
+    fn current_month() -> String {
+        chrono::Local::now().format("%Y-%m").to_string()
+    }
+
+    /// 从磁盘加载流量统计数据
+    fn load_store() -> Result<TrafficStore> {
+        let path = AppConfig::traffic_path()?;
+        if !path.exists() {
+            return Ok(TrafficStore::default());
+        }
+        let content = std::fs::read_to_string(&path).context("无法读取流量统计文件")?;
+        serde_json::from_str(&content).context("无法解析流量统计文件")
+    }
+
+    /// 将流量统计数据写入磁盘
+    fn save_store(store: &TrafficStore) -> Result<()> {
+        let path = AppConfig::traffic_path()?;
+        let content = serde_json::to_string_pretty(store).context("无法序列化流量统计数据")?;
+        std::fs::write(&path, content).context("无法写入流量统计文件")
+    }
+}
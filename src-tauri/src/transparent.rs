@@ -0,0 +1,367 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+
+/// 透明代理入站标签
+pub const TRANSPARENT_INBOUND_TAG: &str = "transparent";
+/// 核心出站连接使用的防火墙标记，带该标记的流量不再被重定向，避免回环
+pub const OUTBOUND_MARK: u32 = 255;
+/// iptables 自定义链名称 / nftables 表名称
+const RULE_NAME: &str = "RURAY";
+const NFT_TABLE: &str = "ruray";
+/// 规则生效期间保存所用防火墙后端的文件（位于数据目录下），应用异常退出后据此清理残留规则
+const STATE_FILE_NAME: &str = "transparent.state";
+
+/// 不经过透明代理的保留地址段
+const BYPASS_CIDRS: [&str; 8] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+];
+
+/// 透明代理配置（仅 Linux）
+/// 通过 dokodemo-door 入站配合 iptables/nftables 重定向本机发出的 TCP 流量，作为 TUN 模式之外的另一种全局代理方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransparentConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// dokodemo-door 入站端口
+    #[serde(default = "default_transparent_port")]
+    pub port: u16,
+    /// 防火墙后端：`auto`、`iptables` 或 `nftables`，`auto` 时优先使用 nftables
+    #[serde(default = "default_firewall_backend")]
+    pub backend: String,
+    /// 额外不经过代理的目标地址段（CIDR）
+    #[serde(default)]
+    pub bypass: Vec<String>,
+}
+
+impl Default for TransparentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_transparent_port(),
+            backend: default_firewall_backend(),
+            bypass: Vec::new(),
+        }
+    }
+}
+
+/// 为 port 字段提供默认值
+fn default_transparent_port() -> u16 {
+    12345
+}
+
+/// 为 backend 字段提供默认值
+fn default_firewall_backend() -> String {
+    "auto".to_string()
+}
+
+/// 实际使用的防火墙后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FirewallBackend {
+    Iptables,
+    Nftables,
+}
+
+impl FirewallBackend {
+    /// 保存在状态文件中的名称
+    fn name(self) -> &'static str {
+        match self {
+            FirewallBackend::Iptables => "iptables",
+            FirewallBackend::Nftables => "nftables",
+        }
+    }
+
+    /// 根据状态文件中的名称解析后端
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "iptables" => Some(FirewallBackend::Iptables),
+            "nftables" => Some(FirewallBackend::Nftables),
+            _ => None,
+        }
+    }
+}
+
+/// 透明代理管理器
+/// 负责写入和清理重定向规则。规则与授予核心的 `CAP_NET_ADMIN` 能力（用于给出站连接打标记）
+/// 需要 root 权限，非 root 运行时通过 pkexec 一次性提权执行
+pub struct TransparentManager {
+    /// 规则是否已生效
+    active: AtomicBool,
+    /// 写入规则时使用的后端，清理时使用同一后端
+    backend: Mutex<Option<FirewallBackend>>,
+}
+
+// 全局单例实例
+static TRANSPARENT_MANAGER: OnceLock<TransparentManager> = OnceLock::new();
+
+impl TransparentManager {
+    /// 获取全局透明代理管理器实例（单例模式）
+    pub fn instance() -> &'static TransparentManager {
+        TRANSPARENT_MANAGER.get_or_init(|| {
+            Self {
+                active: AtomicBool::new(false),
+                backend: Mutex::new(None),
+            }
+        })
+    }
+
+    /// 透明代理规则是否已生效
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// 写入重定向规则
+    /// 需在核心进程启动前调用，以便先为核心授予打标记所需的能力
+    ///
+    /// # 参数
+    /// * `config` - 透明代理配置
+    /// * `core_executable` - 核心可执行文件路径
+    ///
+    /// # 异常
+    /// * 非 Linux 系统、找不到防火墙工具或提权失败时返回错误
+    pub async fn start(&self, config: &TransparentConfig, core_executable: &Path) -> Result<()> {
+        if !cfg!(target_os = "linux") {
+            return Err(anyhow::anyhow!("透明代理模式仅支持 Linux"));
+        }
+
+        let backend = Self::resolve_backend(&config.backend)?;
+        // 写入语句任一失败即中止，清理语句自身忽略错误
+        let mut script = String::from("set -e\n");
+        if !Self::has_net_admin(core_executable) {
+            script.push_str(&format!("setcap cap_net_admin,cap_net_bind_service+ep {}\n", shell_quote(&core_executable.to_string_lossy())));
+        }
+        // 先清理可能残留的旧规则，保证重复执行结果一致
+        script.push_str(&Self::cleanup_script(backend));
+        script.push_str(&Self::setup_script(backend, config));
+
+        tokio::task::spawn_blocking(move || run_privileged(&script))
+            .await
+            .context("执行透明代理规则任务失败")??;
+
+        *self.backend.lock().unwrap() = Some(backend);
+        self.active.store(true, Ordering::SeqCst);
+        if let Err(e) = state_path().and_then(|path| std::fs::write(path, backend.name()).context("无法保存透明代理状态")) {
+            log_warn!("保存透明代理状态失败: {}", e);
+        }
+        log_info!("透明代理已启用 ({:?})，重定向端口: {}", backend, config.port);
+        Ok(())
+    }
+
+    /// 清理重定向规则
+    pub async fn stop(&self) -> Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        tokio::task::spawn_blocking(|| TransparentManager::instance().stop_sync())
+            .await
+            .context("执行透明代理规则任务失败")?
+    }
+
+    /// 清理重定向规则（同步方法），供应用退出时调用
+    /// 清理失败（如取消了提权认证）时规则仍然生效，保持已启用状态以便再次清理
+    pub fn stop_sync(&self) -> Result<()> {
+        if !self.active.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let Some(backend) = self.backend.lock().unwrap().take() else { return Ok(()) };
+
+        if let Err(e) = run_privileged(&Self::cleanup_script(backend)) {
+            *self.backend.lock().unwrap() = Some(backend);
+            self.active.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+        if let Ok(path) = state_path() {
+            let _ = std::fs::remove_file(path);
+        }
+        log_info!("透明代理规则已清理");
+        Ok(())
+    }
+
+    /// 处理上次运行遗留的重定向规则
+    /// 应用异常退出后 `RURAY` 链或 `ruray` 表仍在，本机所有 TCP 连接会被重定向到无人监听的端口。
+    /// 接管了上次的核心进程时恢复为已启用状态，停止代理时一并清理；否则立即清理
+    ///
+    /// # 参数
+    /// * `adopted` - 是否接管了上次运行的核心进程
+    ///
+    /// # 返回值
+    /// * `Result<bool>` - 是否清理了遗留的规则
+    ///
+    /// # 异常
+    /// * 执行清理脚本失败（如取消了提权认证）时返回错误，状态文件保留以便下次启动时重试
+    pub async fn cleanup_orphaned_rules(&self, adopted: bool) -> Result<bool> {
+        if self.is_active() {
+            return Ok(false);
+        }
+        let path = state_path()?;
+        let Ok(name) = std::fs::read_to_string(&path) else { return Ok(false) };
+        let Some(backend) = FirewallBackend::from_name(name.trim()) else {
+            let _ = std::fs::remove_file(&path);
+            return Ok(false);
+        };
+
+        if adopted {
+            *self.backend.lock().unwrap() = Some(backend);
+            self.active.store(true, Ordering::SeqCst);
+            return Ok(false);
+        }
+        let script = Self::cleanup_script(backend);
+        tokio::task::spawn_blocking(move || run_privileged(&script))
+            .await
+            .context("执行透明代理规则任务失败")??;
+        let _ = std::fs::remove_file(&path);
+        Ok(true)
+    }
+
+    /// 确定使用的防火墙后端
+    fn resolve_backend(preference: &str) -> Result<FirewallBackend> {
+        match preference {
+            "iptables" => Ok(FirewallBackend::Iptables),
+            "nftables" => Ok(FirewallBackend::Nftables),
+            _ if command_exists("nft") => Ok(FirewallBackend::Nftables),
+            _ if command_exists("iptables") => Ok(FirewallBackend::Iptables),
+            _ => Err(anyhow::anyhow!("未找到 nft 或 iptables，无法启用透明代理")),
+        }
+    }
+
+    /// 检查核心是否已具有 CAP_NET_ADMIN 能力
+    fn has_net_admin(core_executable: &Path) -> bool {
+        Command::new("getcap")
+            .arg(core_executable)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains("cap_net_admin"))
+            .unwrap_or(false)
+    }
+
+    /// 生成写入规则的脚本：本机发出的 TCP 流量中，除核心自身出站和保留地址外全部重定向到透明代理端口
+    fn setup_script(backend: FirewallBackend, config: &TransparentConfig) -> String {
+        let bypass: Vec<String> = BYPASS_CIDRS.iter()
+            .map(|cidr| cidr.to_string())
+            .chain(config.bypass.iter().map(|cidr| cidr.trim().to_string()).filter(|cidr| is_valid_cidr(cidr)))
+            .collect();
+
+        match backend {
+            FirewallBackend::Iptables => {
+                let mut script = format!("iptables -t nat -N {}\n", RULE_NAME);
+                script.push_str(&format!("iptables -t nat -A {} -m mark --mark {} -j RETURN\n", RULE_NAME, OUTBOUND_MARK));
+                for cidr in &bypass {
+                    script.push_str(&format!("iptables -t nat -A {} -d {} -j RETURN\n", RULE_NAME, cidr));
+                }
+                script.push_str(&format!("iptables -t nat -A {} -p tcp -j REDIRECT --to-ports {}\n", RULE_NAME, config.port));
+                script.push_str(&format!("iptables -t nat -A OUTPUT -p tcp -j {}\n", RULE_NAME));
+                script
+            }
+            FirewallBackend::Nftables => format!(
+                "nft -f - <<'EOF'\n\
+                 table ip {table} {{\n\
+                 \tchain output {{\n\
+                 \t\ttype nat hook output priority -100; policy accept;\n\
+                 \t\tmeta mark {mark} return\n\
+                 \t\tip daddr {{ {bypass} }} return\n\
+                 \t\tmeta l4proto tcp redirect to :{port}\n\
+                 \t}}\n\
+                 }}\n\
+                 EOF\n",
+                table = NFT_TABLE,
+                mark = OUTBOUND_MARK,
+                bypass = bypass.join(", "),
+                port = config.port,
+            ),
+        }
+    }
+
+    /// 生成清理规则的脚本，规则不存在时忽略错误
+    fn cleanup_script(backend: FirewallBackend) -> String {
+        match backend {
+            FirewallBackend::Iptables => format!(
+                "iptables -t nat -D OUTPUT -p tcp -j {name} 2>/dev/null || true\n\
+                 iptables -t nat -F {name} 2>/dev/null || true\n\
+                 iptables -t nat -X {name} 2>/dev/null || true\n",
+                name = RULE_NAME
+            ),
+            FirewallBackend::Nftables => format!("nft delete table ip {} 2>/dev/null || true\n", NFT_TABLE),
+        }
+    }
+}
+
+/// 以 root 权限执行脚本
+/// 已是 root 时直接执行，否则通过 pkexec 弹出系统认证对话框
+fn run_privileged(script: &str) -> Result<()> {
+    let is_root = Command::new("id")
+        .arg("-u")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+        .unwrap_or(false);
+
+    let mut command = if is_root {
+        Command::new("sh")
+    } else {
+        if !command_exists("pkexec") {
+            return Err(anyhow::anyhow!("需要 root 权限，且未找到 pkexec，请以 root 身份运行"));
+        }
+        let mut command = Command::new("pkexec");
+        command.arg("sh");
+        command
+    };
+
+    let output = command
+        .arg("-c")
+        .arg(script)
+        .output()
+        .context("无法执行透明代理规则脚本")?;
+
+    match output.status.code() {
+        Some(0) => Ok(()),
+        // pkexec: 126 为用户取消认证，127 为未获授权
+        Some(126) | Some(127) if !is_root => Err(anyhow::anyhow!("未获得 root 权限，透明代理规则未生效")),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log_warn!("透明代理规则脚本执行失败: {}", stderr.trim());
+            Err(anyhow::anyhow!("写入防火墙规则失败: {}", stderr.trim()))
+        }
+    }
+}
+
+/// 获取透明代理状态文件路径
+fn state_path() -> Result<PathBuf> {
+    Ok(AppConfig::data_dir()?.join(STATE_FILE_NAME))
+}
+
+/// 检查命令是否存在于 PATH 中
+fn command_exists(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+        .unwrap_or(false)
+}
+
+/// 校验 IPv4 CIDR，避免将任意文本写入防火墙脚本
+fn is_valid_cidr(cidr: &str) -> bool {
+    cidr.parse::<ipnet::Ipv4Net>().is_ok() || cidr.parse::<std::net::Ipv4Addr>().is_ok()
+}
+
+/// 为 shell 参数加单引号
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -0,0 +1,185 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// 托盘图标状态
+// 根据代理状态切换托盘图标：未连接为灰色，已连接为彩色，TUN 运行时在右下角加绿色角标，
+// 核心崩溃、重启中或连通性检测失败时为红色。图标由默认窗口图标生成，
+// 数据目录的 `tray_icons` 下放置 `disconnected.png`、`connected.png`、`tun.png`、`error.png` 时使用自定义图标。
+// 由代理状态、TUN 状态与连通性事件驱动刷新，状态未变化时不重复设置图标
+
+use std::sync::Mutex;
+use tauri::{image::Image, AppHandle, Runtime};
+
+// 导入日志宏
+use crate::log_warn;
+
+use crate::config::AppConfig;
+use crate::i18n;
+use crate::monitor::ConnectivityMonitor;
+use crate::proxy::ProxyManager;
+use crate::tun::TunManager;
+
+/// 自定义托盘图标所在目录（相对数据目录）
+const TRAY_ICON_DIR: &str = "tray_icons";
+
+/// 触发托盘图标刷新的事件，`config-changed` 用于在切换界面语言后更新提示文字
+pub const TRAY_ICON_EVENTS: [&str; 4] = ["proxy-status-changed", "tun-status-changed", "connectivity-changed", "config-changed"];
+
+/// TUN 角标颜色（RGB）
+const BADGE_COLOR: [u8; 3] = [0x22, 0xC5, 0x5E];
+
+/// 当前显示的图标状态，状态未变化时跳过重新设置图标
+static CURRENT_STATE: Mutex<Option<TrayIconState>> = Mutex::new(None);
+
+/// 托盘图标状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayIconState {
+    /// 代理未运行
+    Disconnected,
+    /// 代理已连接
+    Connected,
+    /// 代理已连接且 TUN 模式运行中
+    Tun,
+    /// 核心崩溃、重启中或连通性检测失败
+    Error,
+}
+
+impl TrayIconState {
+    /// 自定义图标文件名（不含扩展名）
+    fn asset_name(&self) -> &'static str {
+        match self {
+            TrayIconState::Disconnected => "disconnected",
+            TrayIconState::Connected => "connected",
+            TrayIconState::Tun => "tun",
+            TrayIconState::Error => "error",
+        }
+    }
+
+    /// 托盘提示文字在 `i18n` 文本表中的键
+    fn tooltip_key(&self) -> &'static str {
+        match self {
+            TrayIconState::Disconnected => "tray.tooltip_disconnected",
+            TrayIconState::Connected => "tray.tooltip_connected",
+            TrayIconState::Tun => "tray.tooltip_tun",
+            TrayIconState::Error => "tray.tooltip_error",
+        }
+    }
+}
+
+/// 根据代理、TUN 与连通性状态确定托盘图标状态
+///
+/// # 返回值
+/// * `TrayIconState` - 当前应显示的图标状态
+pub async fn current_state() -> TrayIconState {
+    let proxy = ProxyManager::instance();
+    if proxy.has_failed() {
+        return TrayIconState::Error;
+    }
+
+    let status = proxy.get_status().await.ok();
+    let is_running = status.as_ref().map_or_else(|| proxy.is_process_running(), |s| s.is_running);
+    if !is_running {
+        return TrayIconState::Disconnected;
+    }
+
+    let restarting = status.as_ref().is_some_and(|s| s.status == "restarting");
+    if restarting || !ConnectivityMonitor::instance().is_alive() {
+        TrayIconState::Error
+    } else if TunManager::instance().is_running().await {
+        TrayIconState::Tun
+    } else {
+        TrayIconState::Connected
+    }
+}
+
+/// 按当前状态刷新托盘图标与提示文字
+/// 托盘尚未创建时不做任何事，创建后再次调用即可
+///
+/// # 参数
+/// * `app` - 应用句柄
+pub async fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let state = current_state().await;
+    let Some(tray) = app.tray_by_id("main-tray") else { return };
+
+    let mut current = CURRENT_STATE.lock().unwrap();
+    if *current != Some(state) {
+        let Some(icon) = icon_for(app, state) else { return };
+        if let Err(e) = tray.set_icon(Some(icon)) {
+            log_warn!("更新托盘图标失败: {}", e);
+            return;
+        }
+        *current = Some(state);
+    }
+    let _ = tray.set_tooltip(Some(i18n::t(state.tooltip_key())));
+}
+
+/// 获取指定状态的图标，优先使用数据目录中的自定义图标
+fn icon_for<R: Runtime>(app: &AppHandle<R>, state: TrayIconState) -> Option<Image<'static>> {
+    if let Some(icon) = load_custom_icon(state) {
+        return Some(icon);
+    }
+
+    let base = app.default_window_icon()?;
+    let icon = match state {
+        TrayIconState::Connected => Image::new_owned(base.rgba().to_vec(), base.width(), base.height()),
+        TrayIconState::Disconnected => map_pixels(base, |gray, alpha| [gray, gray, gray, alpha]),
+        TrayIconState::Error => map_pixels(base, |gray, alpha| [128 + gray / 2, gray / 4, gray / 4, alpha]),
+        TrayIconState::Tun => with_badge(base),
+    };
+    Some(icon)
+}
+
+/// 读取自定义图标，文件不存在或无法解析时返回 None
+fn load_custom_icon(state: TrayIconState) -> Option<Image<'static>> {
+    let path = AppConfig::data_dir().ok()?
+        .join(TRAY_ICON_DIR)
+        .join(format!("{}.png", state.asset_name()));
+    if !path.exists() {
+        return None;
+    }
+
+    match Image::from_path(&path) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            log_warn!("读取自定义托盘图标 {} 失败: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 按像素的灰度值与透明度生成新图标
+fn map_pixels(icon: &Image<'_>, map: impl Fn(u8, u8) -> [u8; 4]) -> Image<'static> {
+    let rgba = icon.rgba()
+        .chunks_exact(4)
+        .flat_map(|px| {
+            let gray = (px[0] as u32 * 30 + px[1] as u32 * 59 + px[2] as u32 * 11) / 100;
+            map(gray as u8, px[3])
+        })
+        .collect();
+    Image::new_owned(rgba, icon.width(), icon.height())
+}
+
+/// 在图标右下角绘制带白边的圆形角标
+fn with_badge(icon: &Image<'_>) -> Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let radius = width.min(height) as f32 * 0.22;
+    let border = (radius * 0.25).max(1.0);
+    let center_x = width as f32 - radius - border;
+    let center_y = height as f32 - radius - border;
+
+    let mut rgba = icon.rgba().to_vec();
+    for (index, px) in rgba.chunks_exact_mut(4).enumerate() {
+        let x = (index as u32 % width) as f32 + 0.5;
+        let y = (index as u32 / width) as f32 + 0.5;
+        let distance = ((x - center_x).powi(2) + (y - center_y).powi(2)).sqrt();
+        if distance <= radius {
+            px.copy_from_slice(&[BADGE_COLOR[0], BADGE_COLOR[1], BADGE_COLOR[2], 0xFF]);
+        } else if distance <= radius + border {
+            px.copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        }
+    }
+    Image::new_owned(rgba, width, height)
+}
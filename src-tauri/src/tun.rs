@@ -7,7 +7,7 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr};
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use tun::{Configuration, Layer};
 use tokio::net::{TcpStream, UdpSocket};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -16,11 +16,16 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::task::JoinHandle;
 use std::collections::HashMap;
 use tokio::sync::Mutex as AsyncMutex;
-use tauri::{AppHandle, Manager, path::BaseDirectory};
+use tauri::{AppHandle, Emitter, Manager, path::BaseDirectory};
 
 // 导入日志宏
 use crate::{log_debug, log_info, log_warn, log_error};
 
+use crate::error::{AppError, ErrorCode};
+use crate::hooks::{self, HookEvent};
+use crate::tun_bypass::{self, BypassRanges};
+use crate::validation;
+
 #[cfg(target_os = "windows")]
 use std::os::windows::ffi::{OsStrExt};
 
@@ -44,6 +49,11 @@ fn default_fake_ip_end() -> IpAddr {
     IpAddr::V4(Ipv4Addr::new(198, 18, 255, 254))
 }
 
+/// 默认直连的 geoip 分类
+fn default_bypass_countries() -> Vec<String> {
+    vec!["cn".to_string()]
+}
+
 /// TUN设备配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TunConfig {
@@ -69,15 +79,19 @@ pub struct TunConfig {
     /// 自定义DNS服务器地址
     #[serde(default = "default_dns_server")]
     pub dns_server: String,
-    /// FakeIP模式：为域名分配虚假IP地址，实现DNS劫持和流量重定向
+    /// FakeIP模式：作为虚拟DNS在本地应答发往53端口的A查询，从地址池为域名分配虚假IP，
+    /// 连接时再还原为域名交给代理解析，无需同时开启DNS劫持
     #[serde(default)]
     pub fake_ip: bool,
-    /// FakeIP地址池范围起始地址
+    /// FakeIP地址池范围起始地址（仅支持IPv4）
     #[serde(default = "default_fake_ip_start")]
     pub fake_ip_start: IpAddr,
-    /// FakeIP地址池范围结束地址
+    /// FakeIP地址池范围结束地址（仅支持IPv4，不小于起始地址）
     #[serde(default = "default_fake_ip_end")]
     pub fake_ip_end: IpAddr,
+    /// 按目的地国家/地区直连：geoip.dat 中的分类代码（如 `cn`），命中的目标地址不经代理
+    #[serde(default = "default_bypass_countries")]
+    pub bypass_countries: Vec<String>,
 }
 
 impl Default for TunConfig {
@@ -95,6 +109,7 @@ impl Default for TunConfig {
             fake_ip: false,      // 默认不启用FakeIP模式
             fake_ip_start: default_fake_ip_start(),  // FakeIP起始地址
             fake_ip_end: default_fake_ip_end(),      // FakeIP结束地址
+            bypass_countries: default_bypass_countries(),  // 默认中国大陆直连
         }
     }
 }
@@ -129,8 +144,24 @@ struct FakeIpMapping {
     created_at: std::time::Instant,
 }
 
+/// DNS查询类型：A
+const DNS_TYPE_A: u16 = 1;
+/// DNS查询类型：AAAA
+const DNS_TYPE_AAAA: u16 = 28;
+
+/// DNS查询中的问题部分
+struct DnsQuestion {
+    /// 查询的域名
+    domain: String,
+    /// 查询类型
+    qtype: u16,
+    /// 问题部分结束的偏移
+    end: usize,
+}
+
 /// FakeIP管理器
-#[derive(Debug)]
+/// 映射表均为共享引用，克隆后与原管理器共用同一份映射
+#[derive(Debug, Clone)]
 struct FakeIpManager {
     /// 域名到FakeIP的映射
     domain_to_fake: Arc<AsyncMutex<HashMap<String, Ipv4Addr>>>,
@@ -159,36 +190,58 @@ impl FakeIpManager {
         }
     }
 
+    /// 按TUN配置创建FakeIP管理器
+    ///
+    /// # 参数
+    /// * `config` - TUN设备配置，使用其中的地址池范围
+    ///
+    /// # 异常
+    /// * 地址池不是IPv4或起始地址大于结束地址时返回错误
+    fn from_config(config: &TunConfig) -> Result<Self> {
+        let (IpAddr::V4(start_ip), IpAddr::V4(end_ip)) = (config.fake_ip_start, config.fake_ip_end) else {
+            return Err(anyhow::anyhow!(
+                "FakeIP地址池仅支持IPv4: {} - {}", config.fake_ip_start, config.fake_ip_end
+            ));
+        };
+        if u32::from(start_ip) > u32::from(end_ip) {
+            return Err(anyhow::anyhow!("FakeIP地址池起始地址 {} 大于结束地址 {}", start_ip, end_ip));
+        }
+        Ok(Self::new(start_ip, end_ip))
+    }
+
     /// 为域名分配FakeIP地址
-    async fn allocate_fake_ip(&self, domain: &str) -> Result<Ipv4Addr> {
+    /// 地址池用尽后从起始地址开始复用，复用前移除该地址原有的映射
+    async fn allocate_fake_ip(&self, domain: &str) -> Ipv4Addr {
         let mut domain_to_fake = self.domain_to_fake.lock().await;
         
         // 检查是否已经分配过
         if let Some(&fake_ip) = domain_to_fake.get(domain) {
-            return Ok(fake_ip);
+            return fake_ip;
         }
         
         // 分配新的FakeIP
-        let mut next_ip = self.next_ip.lock().await;
-        let current_ip = *next_ip;
-        
-        // 检查是否超出范围
-        if current_ip > u32::from(self.end_ip) {
-            return Err(anyhow::anyhow!("FakeIP地址池已满"));
-        }
-        
-        let fake_ip = Ipv4Addr::from(current_ip);
-        *next_ip = current_ip + 1;
+        let fake_ip = {
+            let mut next_ip = self.next_ip.lock().await;
+            let current_ip = *next_ip;
+            *next_ip = if current_ip >= u32::from(self.end_ip) {
+                u32::from(self.start_ip)
+            } else {
+                current_ip + 1
+            };
+            Ipv4Addr::from(current_ip)
+        };
         
         // 更新映射
-        domain_to_fake.insert(domain.to_string(), fake_ip);
-        drop(domain_to_fake);
-        
         let mut fake_to_domain = self.fake_to_domain.lock().await;
-        fake_to_domain.insert(fake_ip, domain.to_string());
+        if let Some(old_domain) = fake_to_domain.insert(fake_ip, domain.to_string()) {
+            domain_to_fake.remove(&old_domain);
+            self.fake_to_real.lock().await.remove(&fake_ip);
+            log_debug!("FakeIP地址池已用尽，{} 改为分配给 {}（原域名 {}）", fake_ip, domain, old_domain);
+        }
+        domain_to_fake.insert(domain.to_string(), fake_ip);
         
         log_debug!("为域名 {} 分配FakeIP: {}", domain, fake_ip);
-        Ok(fake_ip)
+        fake_ip
     }
     
     /// 根据FakeIP获取域名
@@ -236,6 +289,23 @@ pub struct TunStatus {
     pub error: Option<String>,
 }
 
+/// 指向 TUN 虚拟网卡的系统路由
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunRoute {
+    /// 目标网络
+    pub destination: String,
+    /// 子网掩码
+    pub netmask: String,
+    /// 网关，`On-link` 表示直连
+    pub gateway: String,
+    /// 出接口地址
+    pub interface: String,
+    /// 跃点数
+    pub metric: u32,
+    /// TUN 未运行时残留的路由，可通过 `cleanup_orphan_routes` 删除
+    pub stale: bool,
+}
+
 /// TUN设备管理器
 pub struct TunManager {
     config: Arc<Mutex<TunConfig>>,
@@ -250,6 +320,8 @@ pub struct TunManager {
     original_routes: Arc<Mutex<Vec<String>>>,
     /// FakeIP管理器
     fake_ip_manager: Arc<Mutex<Option<FakeIpManager>>>,
+    /// 按国家/地区直连的网段，启动时从 geoip.dat 或缓存加载
+    bypass: RwLock<Option<BypassRanges>>,
 }
 
 // 全局单例实例
@@ -276,6 +348,7 @@ impl TunManager {
                 connections: Arc::new(AsyncMutex::new(HashMap::new())),
                 original_routes: Arc::new(Mutex::new(Vec::new())),
                 fake_ip_manager: Arc::new(Mutex::new(None)),
+                bypass: RwLock::new(None),
             }
         })
     }
@@ -302,16 +375,9 @@ impl TunManager {
             .context("应用句柄未设置，请先调用 set_app_handle")?;
         
         // 根据系统架构确定要使用的wintun.dll路径
-        let arch = std::env::consts::ARCH;
-        let wintun_resource_path = match arch {
-            "x86_64" => "wintun/bin/amd64/wintun.dll",
-            "x86" => "wintun/bin/x86/wintun.dll",
-            "aarch64" => "wintun/bin/arm64/wintun.dll",
-            "arm" => "wintun/bin/arm/wintun.dll",
-            _ => {
-                log_warn!("警告: 不支持的架构 {}, 使用默认路径", arch);
-                return Ok(());
-            }
+        let Some(wintun_resource_path) = Self::wintun_resource_path() else {
+            log_warn!("警告: 不支持的架构 {}, 使用默认路径", std::env::consts::ARCH);
+            return Ok(());
         };
         
         // 使用Tauri的路径解析API获取资源文件路径
@@ -347,6 +413,21 @@ impl TunManager {
         Ok(())
     }
     
+    /// 当前架构对应的内置 wintun.dll 资源路径
+    ///
+    /// # 返回值
+    /// * `Option<&'static str>` - 相对资源目录的路径，不支持的架构返回 None
+    #[cfg(target_os = "windows")]
+    pub(crate) fn wintun_resource_path() -> Option<&'static str> {
+        match std::env::consts::ARCH {
+            "x86_64" => Some("wintun/bin/amd64/wintun.dll"),
+            "x86" => Some("wintun/bin/x86/wintun.dll"),
+            "aarch64" => Some("wintun/bin/arm64/wintun.dll"),
+            "arm" => Some("wintun/bin/arm/wintun.dll"),
+            _ => None,
+        }
+    }
+
     #[cfg(not(target_os = "windows"))]
     fn init_wintun_path() -> Result<()> {
         // 非Windows平台不需要WinTun
@@ -361,9 +442,12 @@ impl TunManager {
     /// # 返回值
     /// * `Result<()>` - 启动结果
     pub async fn start(&self, config: TunConfig) -> Result<()> {
+        // 校验配置，字段错误在创建设备之前返回
+        validation::validate_tun_config(&config)?;
+
         // 检查管理员权限
         if !Self::is_admin() {
-            return Err(anyhow::anyhow!("启动TUN模式需要管理员权限，请以管理员身份运行程序"));
+            return Err(AppError::new(ErrorCode::AdminRequired, "启动TUN模式需要管理员权限，请以管理员身份运行程序").into());
         }
 
         // 初始化WinTun库路径
@@ -380,11 +464,23 @@ impl TunManager {
             *current_config = config.clone();
         }
 
+        // 创建FakeIP管理器，地址池无效时在创建设备之前失败
+        let fake_ip_manager = if config.fake_ip {
+            Some(FakeIpManager::from_config(&config)?)
+        } else {
+            None
+        };
+
+        // 提取按国家/地区直连的网段，解析 geoip.dat 较慢，在阻塞线程中执行
+        let countries = config.bypass_countries.clone();
+        let bypass = tokio::task::spawn_blocking(move || tun_bypass::load(&countries)).await?;
+        *self.bypass.write().unwrap() = Some(bypass);
+
         // 在单独的作用域中创建TUN设备
         {
             let mut tun_config = Configuration::default();
             tun_config
-                .name("ruray-tun")
+                .name(&config.name)
                 .address(config.address)
                 .netmask(config.netmask)
                 .destination(config.gateway)
@@ -400,7 +496,7 @@ impl TunManager {
             // 创建TUN设备
              let device = match tun::create(&tun_config) {
                  Ok(device) => {
-                     log_info!("TUN设备创建成功: ruray-tun");
+                     log_info!("TUN设备创建成功: {}", config.name);
                      device
                  }
                  Err(e) => {
@@ -418,16 +514,12 @@ impl TunManager {
         // 配置TUN设备的IP地址和网关
         self.configure_tun_ip(&config).await?;
 
-        // 初始化FakeIP管理器（如果启用）
-        if config.fake_ip {
-            let fake_ip_manager = FakeIpManager::new(
-                config.fake_ip_start.to_string().parse()?,
-                config.fake_ip_end.to_string().parse()?
-            );
-            let mut manager_guard = self.fake_ip_manager.lock().unwrap();
-            *manager_guard = Some(fake_ip_manager);
+        // 安装FakeIP管理器，未启用时清除上次运行留下的映射
+        if fake_ip_manager.is_some() {
             log_info!("FakeIP管理器已初始化，地址池: {} - {}", config.fake_ip_start, config.fake_ip_end);
+            Self::check_xray_fakedns();
         }
+        *self.fake_ip_manager.lock().unwrap() = fake_ip_manager;
 
         // 备份原始路由表
         if let Err(e) = self.backup_routes() {
@@ -448,7 +540,7 @@ impl TunManager {
         {
             let mut status = self.status.lock().unwrap();
             status.is_running = true;
-            status.device_name = "ruray-tun".to_string();
+            status.device_name = config.name.clone();
             status.ip_address = config.address.to_string();
             status.bytes_received = 0;
             status.bytes_sent = 0;
@@ -458,7 +550,9 @@ impl TunManager {
         // 更新运行状态
         self.running.store(true, Ordering::SeqCst);
         
-        log_info!("TUN模式启动成功，虚拟网卡: ruray-tun");
+        log_info!("TUN模式启动成功，虚拟网卡: {}", config.name);
+        hooks::fire(HookEvent::TunStarted, None, None);
+        self.emit_status();
         Ok(())
     }
 
@@ -500,9 +594,34 @@ impl TunManager {
         }
 
         log_info!("TUN设备已停止");
+        self.emit_status();
         Ok(())
     }
 
+    /// 检查 Xray 入站是否启用了 fakedns 嗅探并提示两者的关系
+    /// TUN 的 FakeIP 在本地应答 DNS，转发时把 FakeIP 还原为域名后以域名形式请求 SOCKS 入站，
+    /// Xray 直接拿到域名用于路由；fakedns 嗅探依赖 Xray 内置 DNS 的 fakedns 地址池，
+    /// 应用不生成该地址池，因此对 TUN 流量不起作用，也不会与 TUN 的地址池冲突
+    fn check_xray_fakedns() {
+        use crate::config::AppConfig;
+
+        let Ok(config) = AppConfig::load_active() else { return };
+        let fakedns = config.effective_inbounds().iter()
+            .any(|inbound| config.sniffing_of(inbound).dest_override.iter().any(|p| p == "fakedns"));
+        if fakedns {
+            log_info!("TUN FakeIP 已在本地还原域名，入站嗅探中的 fakedns 对 TUN 流量不起作用，可只保留 http/tls/quic");
+        }
+    }
+
+    /// 发射 TUN 状态变化事件，托盘据此切换图标
+    fn emit_status(&self) {
+        if let Some(app_handle) = self.app_handle.lock().unwrap().as_ref() {
+            let _ = app_handle.emit("tun-status-changed", serde_json::json!({
+                "is_running": self.running.load(Ordering::SeqCst)
+            }));
+        }
+    }
+
     /// 停止TUN设备（同步版本，用于应用关闭时调用）
     /// 
     /// # 返回值
@@ -580,6 +699,8 @@ impl TunManager {
     /// # 返回值
     /// * `Result<()>` - 更新结果
     pub async fn update_config(&self, config: TunConfig) -> Result<()> {
+        validation::validate_tun_config(&config)?;
+
         let was_running = self.is_running().await;
         
         if was_running {
@@ -609,9 +730,9 @@ impl TunManager {
         let running = self.running.clone();
         let status = self.status.clone();
         let connections = self.connections.clone();
+        let mtu = self.config.lock().unwrap().mtu as usize;
 
         let handle = tokio::spawn(async move {
-            let mut _buffer = [0u8; 1500]; // MTU大小的缓冲区
             
             while running.load(Ordering::SeqCst) {
                 // 检查设备是否可用
@@ -627,7 +748,7 @@ impl TunManager {
                     let connections_clone = connections.clone();
                     
                     match tokio::task::spawn_blocking(move || {
-                        let mut _buffer = [0u8; 1500]; // MTU大小的缓冲区
+                        let mut _buffer = vec![0u8; mtu]; // MTU大小的缓冲区
                         
                         // 在闭包内部获取设备引用
                          let mut device_guard = device_clone.lock().unwrap();
@@ -908,8 +1029,9 @@ impl TunManager {
         let should_proxy = config.fake_ip && target_domain.is_some() || Self::should_proxy(&target_ip, dst_port);
         
         if should_proxy {
+            // 代理连接保留原目标地址，响应以FakeIP回写，连接时再还原为域名
             log_debug!("TCP流量通过代理转发: {}:{}", target_ip, dst_port);
-            Self::forward_to_proxy_with_response(src_ip, src_port, target_ip, dst_port, tcp_data, "tcp", device, connections).await?
+            Self::forward_to_proxy_with_response(src_ip, src_port, dst_ip, dst_port, tcp_data, "tcp", device, connections).await?
         } else {
             log_debug!("TCP流量直接转发: {}:{}", target_ip, dst_port);
             Self::forward_direct_with_response(src_ip, src_port, target_ip, dst_port, tcp_data, "tcp", device).await?
@@ -990,12 +1112,9 @@ impl TunManager {
             }
         }
         
-        // 检查是否为DNS查询并需要劫持
-        if target_ip == dst_ip && dst_port == 53 {
-            if config.dns_hijack {
-                log_debug!("DNS劫持: 重定向DNS查询到 {}", config.dns_server);
-                return Self::handle_dns_hijack(src_ip, src_port, &config.dns_server, udp_data, device).await;
-            }
+        // DNS查询：FakeIP模式下作为虚拟DNS在本地应答，DNS劫持时转发到指定DNS服务器
+        if target_domain.is_none() && dst_port == 53 && (config.fake_ip || config.dns_hijack) {
+            return Self::handle_dns_hijack(src_ip, src_port, dst_ip, &config, udp_data, device).await;
         }
         
         // 检查是否需要代理（FakeIP总是通过代理）
@@ -1085,9 +1204,9 @@ impl TunManager {
             return false;
         }
         
-        // 检查是否为中国大陆IP地址（可选择不代理）
-        if Self::is_china_ip(dst_ip) {
-            log_debug!("中国大陆IP地址，不代理: {}", dst_ip);
+        // 检查是否在按国家/地区直连的网段内
+        if Self::is_bypassed(dst_ip) {
+            log_debug!("直连国家/地区网段，不代理: {}", dst_ip);
             return false;
         }
         
@@ -1126,25 +1245,11 @@ impl TunManager {
         }
     }
     
-    /// 检查是否为中国大陆IP地址
-    /// 这里只包含一些主要的中国IP段，实际应用中可以使用更完整的IP数据库
-    fn is_china_ip(ip: &Ipv4Addr) -> bool {
-        let octets = ip.octets();
-        
-        // 一些主要的中国IP段（简化版本）
-        match octets[0] {
-            // 中国电信
-            58 | 59 | 60 | 61 | 112 | 113 | 114 | 115 | 116 | 117 | 118 | 119 => true,
-            // 中国联通
-            123 | 124 | 125 | 126 => true,
-            // 中国移动
-            111 => true,
-            // 教育网
-            202 if octets[1] >= 112 && octets[1] <= 120 => true,
-            // 其他常见中国IP段
-            121 | 122 => true,
-            _ => false,
-        }
+    /// 检查是否在按国家/地区直连的网段内（配置的 `bypass_countries`）
+    fn is_bypassed(ip: &Ipv4Addr) -> bool {
+        TunManager::instance().bypass.read().unwrap()
+            .as_ref()
+            .is_some_and(|bypass| bypass.contains(ip))
     }
     
     /// 检查是否为系统服务端口
@@ -1293,8 +1398,8 @@ impl TunManager {
           Ok(())
       }
 
-      /// 解析DNS查询中的域名
-      fn parse_dns_query(dns_data: &[u8]) -> Option<String> {
+      /// 解析DNS查询中的第一个问题
+      fn parse_dns_query(dns_data: &[u8]) -> Option<DnsQuestion> {
           if dns_data.len() < 12 {
               return None;
           }
@@ -1321,26 +1426,33 @@ impl TunManager {
               pos += len;
           }
           
-          if domain.is_empty() {
-              None
-          } else {
-              Some(domain)
+          // 跳过名称结尾的0字节，读取 QTYPE(2) + QCLASS(2)
+          let end = pos + 5;
+          if domain.is_empty() || end > dns_data.len() {
+              return None;
           }
+          let qtype = u16::from_be_bytes([dns_data[pos + 1], dns_data[pos + 2]]);
+          Some(DnsQuestion { domain, qtype, end })
       }
       
       /// 创建DNS响应包
-      fn create_dns_response(query_data: &[u8], fake_ip: Ipv4Addr) -> Vec<u8> {
-          let mut response = query_data.to_vec();
-          
-          if response.len() < 12 {
-              return response;
-          }
+      /// 只保留查询的头部与问题部分，`fake_ip` 为空时返回不含记录的空应答
+      fn create_dns_response(query_data: &[u8], question: &DnsQuestion, fake_ip: Option<Ipv4Addr>) -> Vec<u8> {
+          let mut response = query_data[..question.end].to_vec();
           
           // 设置响应标志
           response[2] = 0x81; // QR=1, Opcode=0, AA=0, TC=0, RD=1
           response[3] = 0x80; // RA=1, Z=0, RCODE=0
           
-          // 设置Answer RRs计数为1
+          // 问题数为1，清空Authority与Additional计数（丢弃查询中的EDNS记录）
+          response[4..6].copy_from_slice(&1u16.to_be_bytes());
+          response[8..12].fill(0);
+          
+          // 设置Answer RRs计数
+          let Some(fake_ip) = fake_ip else {
+              response[6..8].fill(0);
+              return response;
+          };
           response[6] = 0x00;
           response[7] = 0x01;
           
@@ -1357,11 +1469,11 @@ impl TunManager {
           response.push(0x00);
           response.push(0x01);
           
-          // TTL: 300秒
+          // TTL: 1秒，避免客户端在地址复用后仍缓存旧映射
+          response.push(0x00);
           response.push(0x00);
           response.push(0x00);
           response.push(0x01);
-          response.push(0x2c);
           
           // Data length: 4字节
           response.push(0x00);
@@ -1375,64 +1487,39 @@ impl TunManager {
       }
 
       /// 处理DNS劫持和FakeIP分配
-      /// 将DNS查询重定向到指定的DNS服务器，并支持FakeIP模式
+      /// FakeIP模式下作为虚拟DNS在本地应答A查询，其他查询转发到上游DNS服务器：
+      /// 启用DNS劫持时为配置的DNS服务器，否则为查询原本发往的服务器
       async fn handle_dns_hijack(
           src_ip: Ipv4Addr,
           src_port: u16,
-          dns_server: &str,
+          dst_ip: Ipv4Addr,
+          config: &TunConfig,
           dns_data: &[u8],
           device: Arc<Mutex<Option<tun::platform::Device>>>
       ) -> Result<()> {
-          log_debug!("处理DNS劫持: {}:{} -> {}", src_ip, src_port, dns_server);
-          
-          // 检查是否启用FakeIP模式
-          let manager = TunManager::instance();
-          let config = manager.get_config().await;
+          let dns_server = if config.dns_hijack { config.dns_server.clone() } else { dst_ip.to_string() };
+          log_debug!("处理DNS查询: {}:{} -> {}", src_ip, src_port, dns_server);
           
           if config.fake_ip {
-              // FakeIP模式：解析域名并分配虚假IP
-              if let Some(domain) = Self::parse_dns_query(dns_data) {
-                  log_debug!("解析到域名: {}", domain);
-                  
-                  // 获取FakeIP管理器的克隆引用
-                  let fake_manager_opt = {
-                      let guard = manager.fake_ip_manager.lock().unwrap();
-                      guard.as_ref().map(|fm| {
-                          (fm.domain_to_fake.clone(), fm.fake_to_domain.clone(), fm.start_ip, fm.end_ip, fm.next_ip.clone())
-                      })
+              let fake_manager = TunManager::instance().fake_ip_manager.lock().unwrap().clone();
+              if let (Some(fake_manager), Some(question)) = (fake_manager, Self::parse_dns_query(dns_data)) {
+                  let response = match question.qtype {
+                      DNS_TYPE_A => {
+                          let fake_ip = fake_manager.allocate_fake_ip(&question.domain).await;
+                          log_info!("为域名 {} 分配FakeIP: {}", question.domain, fake_ip);
+                          Some(Self::create_dns_response(dns_data, &question, Some(fake_ip)))
+                      }
+                      // 地址池只有IPv4，AAAA查询返回空应答，客户端随后改用A记录
+                      DNS_TYPE_AAAA => Some(Self::create_dns_response(dns_data, &question, None)),
+                      // MX、TXT等查询需要真实结果，转发到上游
+                      _ => None,
                   };
                   
-                  if let Some((domain_to_fake, fake_to_domain, start_ip, end_ip, next_ip)) = fake_manager_opt {
-                      // 检查是否已有FakeIP
-                      let fake_ip = if let Some(existing_ip) = domain_to_fake.lock().await.get(&domain).cloned() {
-                          existing_ip
-                      } else {
-                          // 分配新的FakeIP
-                          let mut next_guard = next_ip.lock().await;
-                          let new_fake_ip = Ipv4Addr::from(*next_guard);
-                          *next_guard = if new_fake_ip == end_ip { 
-                              u32::from(start_ip) 
-                          } else { 
-                              *next_guard + 1
-                          };
-                          drop(next_guard);
-                          
-                          // 更新映射表
-                          domain_to_fake.lock().await.insert(domain.clone(), new_fake_ip);
-                          fake_to_domain.lock().await.insert(new_fake_ip, domain.clone());
-                          
-                          new_fake_ip
-                      };
-                      
-                      log_info!("为域名 {} 分配FakeIP: {}", domain, fake_ip);
-                      
-                      // 创建DNS响应包
-                      let response = Self::create_dns_response(dns_data, fake_ip);
-                      
-                      // 将DNS响应写回TUN设备
+                  if let Some(response) = response {
+                      // 以查询原本发往的地址回写响应
                       return Self::write_response_packet(
                           device,
-                          "8.8.8.8".parse().unwrap(), // 伪装成来自DNS服务器的响应
+                          dst_ip,
                           53,
                           src_ip,
                           src_port,
@@ -1443,7 +1530,7 @@ impl TunManager {
               }
           }
           
-          // 传统DNS劫持模式：转发到真实DNS服务器
+          // 转发到上游DNS服务器
           let dns_ip: Ipv4Addr = dns_server.parse()
               .with_context(|| format!("无效的DNS服务器地址: {}", dns_server))?;
           
@@ -1462,10 +1549,10 @@ impl TunManager {
           
           response_buf.truncate(response_len);
           
-          // 将DNS响应写回TUN设备
+          // 将DNS响应写回TUN设备，源地址为查询原本发往的地址，否则客户端会丢弃响应
           Self::write_response_packet(
               device,
-              dns_ip,
+              dst_ip,
               53,
               src_ip,
               src_port,
@@ -1473,7 +1560,7 @@ impl TunManager {
               17 // UDP协议号
           ).await?;
           
-          log_debug!("DNS劫持完成: 响应长度 {}", response_len);
+          log_debug!("DNS查询已由 {} 应答: 响应长度 {}", dns_server, response_len);
           Ok(())
       }
      
@@ -1619,8 +1706,17 @@ impl TunManager {
                       return Err(e);
                   }
                   
-                  // SOCKS5连接请求
-                  if let Err(e) = Self::socks5_connect(&mut stream, dst_ip, dst_port).await {
+                  // SOCKS5连接请求，FakeIP还原为域名后交给代理解析
+                  let fake_manager = TunManager::instance().fake_ip_manager.lock().unwrap().clone();
+                  let domain = match fake_manager {
+                      Some(fake_manager) if fake_manager.is_fake_ip(&dst_ip) => fake_manager.get_domain_by_fake_ip(&dst_ip).await,
+                      _ => None,
+                  };
+                  let connect = match &domain {
+                      Some(domain) => Self::socks5_connect_domain(&mut stream, domain, dst_port).await,
+                      None => Self::socks5_connect(&mut stream, dst_ip, dst_port).await,
+                  };
+                  if let Err(e) = connect {
                       log_error!("SOCKS5连接请求失败: {}", e);
                       return Err(e);
                   }
@@ -1789,6 +1885,29 @@ impl TunManager {
         // 发送连接请求
         stream.write_all(&request).await?;
         
+        Self::socks5_read_reply(stream).await
+    }
+
+    /// SOCKS5域名连接请求
+    /// 用于FakeIP还原出的域名，由代理端解析真实地址
+    async fn socks5_connect_domain(stream: &mut TcpStream, domain: &str, dst_port: u16) -> Result<()> {
+        // 构建连接请求: VER(1) + CMD(1) + RSV(1) + ATYP(1) + LEN(1) + DST.ADDR(LEN) + DST.PORT(2)
+        let host = domain.as_bytes();
+        if host.is_empty() || host.len() > 255 {
+            return Err(anyhow::anyhow!("域名长度无效: {}", domain));
+        }
+        let mut request = Vec::with_capacity(7 + host.len());
+        request.extend_from_slice(&[0x05, 0x01, 0x00, 0x03, host.len() as u8]);
+        request.extend_from_slice(host);
+        request.extend_from_slice(&dst_port.to_be_bytes());
+        
+        stream.write_all(&request).await?;
+        
+        Self::socks5_read_reply(stream).await
+    }
+
+    /// 读取SOCKS5连接请求的响应
+    async fn socks5_read_reply(stream: &mut TcpStream) -> Result<()> {
         // 读取服务器响应: VER(1) + REP(1) + RSV(1) + ATYP(1) + BND.ADDR(4) + BND.PORT(2)
         let mut response = [0u8; 10];
         stream.read_exact(&mut response).await?;
@@ -1826,7 +1945,7 @@ impl TunManager {
         // 检查SOCKS5端口是否真正在监听
         let proxy_port = {
             use crate::config::AppConfig;
-            match AppConfig::load() {
+            match AppConfig::load_active() {
                 Ok(config) => config.socks_port,
                 Err(_) => 1080 // 默认端口
             }
@@ -1854,7 +1973,7 @@ impl TunManager {
         use crate::config::AppConfig;
         
         // 尝试加载配置获取SOCKS5端口
-        match AppConfig::load() {
+        match AppConfig::load_active() {
             Ok(config) => {
                 // 获取SOCKS5端口
                 let port = config.socks_port;
@@ -2159,7 +2278,7 @@ impl TunManager {
     /// 
     /// # 返回值
     /// * `bool` - 是否具有管理员权限
-    fn is_admin() -> bool {
+    pub(crate) fn is_admin() -> bool {
         #[cfg(target_os = "windows")]
         {
             use std::process::Command;
@@ -2337,6 +2456,97 @@ impl TunManager {
         Ok(())
     }
     
+    /// 列出指向 TUN 虚拟网卡的路由
+    /// 网关或出接口为 TUN 地址的路由均视为 RuRay 添加的路由，TUN 未运行时它们都是崩溃后残留的路由，
+    /// 其中默认路由与分割路由会使系统无法联网
+    ///
+    /// # 返回值
+    /// * `Result<Vec<TunRoute>>` - 路由列表，非 Windows 平台不支持 TUN 模式，始终为空
+    pub async fn get_tun_routes(&self) -> Result<Vec<TunRoute>> {
+        #[cfg(target_os = "windows")]
+        {
+            use std::process::Command;
+
+            let config = self.get_config().await;
+            let tun_addresses = [config.address.to_string(), config.gateway.to_string()];
+            let stale = !self.is_running().await;
+
+            let output = Command::new("route")
+                .args(["print", "-4"])
+                .output()
+                .context("执行route print命令失败")?;
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("route print命令执行失败"));
+            }
+
+            let routes = parse_route_table(&String::from_utf8_lossy(&output.stdout))
+                .into_iter()
+                .filter(|route| tun_addresses.contains(&route.gateway) || tun_addresses.contains(&route.interface))
+                .map(|route| TunRoute { stale, ..route })
+                .collect();
+            Ok(routes)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    /// 删除崩溃后残留的 TUN 路由
+    /// TUN 运行中时不删除任何路由；删除时指定网关，不会误删系统原有的默认路由
+    ///
+    /// # 返回值
+    /// * `Result<usize>` - 删除的路由数量
+    ///
+    /// # 异常
+    /// * 存在残留路由但没有管理员权限时返回错误
+    pub async fn cleanup_orphan_routes(&self) -> Result<usize> {
+        let stale: Vec<TunRoute> = self.get_tun_routes().await?
+            .into_iter()
+            .filter(|route| route.stale)
+            .collect();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+        if !Self::is_admin() {
+            return Err(anyhow::anyhow!("检测到 {} 条残留的 TUN 路由，删除需要管理员权限", stale.len()));
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            use std::process::Command;
+
+            let mut removed = 0;
+            for route in &stale {
+                let mut command = Command::new("route");
+                command.args(["delete", &route.destination, "mask", &route.netmask]);
+                // 直连路由的网关列为 On-link（中文系统为“在链路上”），删除时不能指定
+                if route.gateway.parse::<IpAddr>().is_ok() {
+                    command.arg(&route.gateway);
+                }
+                let output = command.output();
+                match output {
+                    Ok(output) if output.status.success() => {
+                        log_info!("已删除残留路由: {}/{} -> {}", route.destination, route.netmask, route.gateway);
+                        removed += 1;
+                    }
+                    Ok(output) => log_warn!(
+                        "删除残留路由 {}/{} 失败: {}",
+                        route.destination, route.netmask, String::from_utf8_lossy(&output.stderr).trim()
+                    ),
+                    Err(e) => log_warn!("执行删除残留路由命令失败: {}/{} - {}", route.destination, route.netmask, e),
+                }
+            }
+            Ok(removed)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            Ok(0)
+        }
+    }
+
     /// 设置系统路由规则
     /// 
     /// 参数
@@ -2771,4 +2981,28 @@ impl TunManager {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 解析 `route print -4` 输出中的活动路由
+/// 只读取 `Active Routes:`（活动路由）之后到分隔线为止的五列数据行，永久路由部分不包含接口与跃点数，不解析
+#[cfg(target_os = "windows")]
+fn parse_route_table(output: &str) -> Vec<TunRoute> {
+    output.lines()
+        .skip_while(|line| !line.trim_start().starts_with("Network Destination") && !line.contains("网络目标"))
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('='))
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [destination, netmask, gateway, interface, metric] = parts.as_slice() else { return None };
+            destination.parse::<Ipv4Addr>().ok()?;
+            Some(TunRoute {
+                destination: destination.to_string(),
+                netmask: netmask.to_string(),
+                gateway: gateway.to_string(),
+                interface: interface.to_string(),
+                metric: metric.parse().ok()?,
+                stale: false,
+            })
+        })
+        .collect()
+}
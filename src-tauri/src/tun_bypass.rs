@@ -0,0 +1,213 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// TUN 按目的地国家/地区直连
+// 从 geoip.dat 中提取指定分类（如 `cn`）的 IPv4 网段，合并为有序区间供 TUN 转发时二分查找，
+// 命中的目标地址不经代理直接连接。提取结果缓存到数据目录，geoip.dat 的大小与修改时间
+// 或分类列表变化时重新提取；geoip.dat 缺失或无法解析时，`cn` 回退到内置的粗略网段
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+// 导入日志宏
+use crate::{log_info, log_warn};
+
+use crate::config::AppConfig;
+use crate::geodata::{self, GeoIpList, GEOIP_FILE};
+
+/// 提取结果缓存文件（相对数据目录）
+const CACHE_FILE: &str = "tun_bypass_cache.json";
+
+/// 内置的中国大陆网段（按首字节粗略划分），仅在无法读取 geoip.dat 时使用
+const BUILTIN_CN_RANGES: &[(u8, u8, u8, u8)] = &[
+    // 58.0.0.0 - 61.255.255.255
+    (58, 0, 61, 255),
+    // 111.0.0.0 - 119.255.255.255
+    (111, 0, 119, 255),
+    // 121.0.0.0 - 126.255.255.255
+    (121, 0, 126, 255),
+    // 202.112.0.0 - 202.120.255.255（教育网）
+    (202, 112, 202, 120),
+];
+
+/// 直连网段的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BypassSource {
+    /// 从 geoip.dat 提取
+    Geoip,
+    /// 读取缓存
+    Cache,
+    /// geoip.dat 不可用，使用内置网段
+    Builtin,
+}
+
+/// 直连网段集合
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BypassRanges {
+    /// 分类代码（小写、去重、排序）
+    pub countries: Vec<String>,
+    /// 合并后的 IPv4 区间（含首尾），按起始地址排序且互不重叠
+    pub ranges: Vec<(u32, u32)>,
+    /// 来源
+    pub source: BypassSource,
+}
+
+/// 缓存文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct BypassCache {
+    /// 提取时 geoip.dat 的大小，不一致时缓存失效
+    geoip_size: u64,
+    /// 提取时 geoip.dat 的修改时间（Unix 时间戳，秒），不一致时缓存失效
+    geoip_modified: u64,
+    /// 提取结果
+    ranges: BypassRanges,
+}
+
+impl BypassRanges {
+    /// 目标地址是否在直连网段内
+    ///
+    /// # 参数
+    /// * `ip` - 目标地址
+    pub fn contains(&self, ip: &Ipv4Addr) -> bool {
+        let ip = u32::from(*ip);
+        let index = self.ranges.partition_point(|(start, _)| *start <= ip);
+        index > 0 && self.ranges[index - 1].1 >= ip
+    }
+}
+
+/// 加载指定分类的直连网段，优先使用缓存
+/// 解析 geoip.dat 较慢，应在阻塞线程中调用
+///
+/// # 参数
+/// * `countries` - geoip 分类代码，如 `["cn"]`
+///
+/// # 返回值
+/// * `BypassRanges` - 直连网段，分类为空时不包含任何网段
+pub fn load(countries: &[String]) -> BypassRanges {
+    let countries = normalize(countries);
+    if countries.is_empty() {
+        return BypassRanges { countries, ranges: Vec::new(), source: BypassSource::Geoip };
+    }
+
+    match load_from_geoip(&countries) {
+        Ok(ranges) => {
+            log_info!("TUN 直连分类 {} 共 {} 个网段（{:?}）", countries.join(","), ranges.ranges.len(), ranges.source);
+            ranges
+        }
+        Err(e) => {
+            log_warn!("无法从 geoip.dat 提取直连网段: {:#}", e);
+            builtin(countries)
+        }
+    }
+}
+
+/// 从缓存或 geoip.dat 读取网段
+fn load_from_geoip(countries: &[String]) -> Result<BypassRanges> {
+    let metadata = std::fs::metadata(geodata::asset_path(GEOIP_FILE)?)
+        .with_context(|| format!("{} 不存在", GEOIP_FILE))?;
+    let geoip_size = metadata.len();
+    let geoip_modified = metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let cache_path = cache_path()?;
+    if let Some(cache) = std::fs::read(&cache_path).ok()
+        .and_then(|data| serde_json::from_slice::<BypassCache>(&data).ok())
+    {
+        if cache.geoip_size == geoip_size && cache.geoip_modified == geoip_modified && cache.ranges.countries == countries {
+            return Ok(BypassRanges { source: BypassSource::Cache, ..cache.ranges });
+        }
+    }
+
+    let list = GeoIpList::load(GEOIP_FILE)?;
+    let ranges = extract(&list, countries)?;
+
+    let cache = BypassCache { geoip_size, geoip_modified, ranges };
+    match serde_json::to_vec(&cache) {
+        Ok(data) => {
+            if let Err(e) = std::fs::write(&cache_path, data) {
+                log_warn!("写入直连网段缓存失败: {}", e);
+            }
+        }
+        Err(e) => log_warn!("序列化直连网段缓存失败: {}", e),
+    }
+    Ok(cache.ranges)
+}
+
+/// 从 geoip 数据中提取分类的 IPv4 网段并合并
+///
+/// # 异常
+/// * 分类不存在或为反向匹配分类时返回错误
+fn extract(list: &GeoIpList, countries: &[String]) -> Result<BypassRanges> {
+    let mut ranges = Vec::new();
+    for code in countries {
+        let entry = list.find(code).with_context(|| format!("{} 中没有分类 {}", GEOIP_FILE, code))?;
+        if entry.reverse_match {
+            return Err(anyhow::anyhow!("分类 {} 为反向匹配，不能用于直连", code));
+        }
+        ranges.extend(entry.cidr.iter()
+            .filter_map(|cidr| match cidr.to_net()? {
+                ipnet::IpNet::V4(net) => Some((u32::from(net.network()), u32::from(net.broadcast()))),
+                ipnet::IpNet::V6(_) => None,
+            }));
+    }
+
+    Ok(BypassRanges {
+        countries: countries.to_vec(),
+        ranges: merge(ranges),
+        source: BypassSource::Geoip,
+    })
+}
+
+/// 内置网段，只支持 `cn`
+fn builtin(countries: Vec<String>) -> BypassRanges {
+    let ranges = if countries.iter().any(|c| c == "cn") {
+        log_warn!("使用内置的中国大陆网段，直连判断可能不准确");
+        BUILTIN_CN_RANGES.iter()
+            .map(|&(a1, b1, a2, b2)| (
+                u32::from(Ipv4Addr::new(a1, b1, 0, 0)),
+                u32::from(Ipv4Addr::new(a2, b2, 255, 255)),
+            ))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    BypassRanges { countries, ranges, source: BypassSource::Builtin }
+}
+
+/// 合并重叠或相邻的区间
+fn merge(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// 分类代码转为小写并去重排序
+fn normalize(countries: &[String]) -> Vec<String> {
+    let mut countries: Vec<String> = countries.iter()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect();
+    countries.sort();
+    countries.dedup();
+    countries
+}
+
+/// 缓存文件路径
+fn cache_path() -> Result<PathBuf> {
+    Ok(AppConfig::data_dir()?.join(CACHE_FILE))
+}
@@ -0,0 +1,432 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
+
+use crate::commands::ServerInfo;
+use crate::config::AppConfig;
+use crate::tun::TunConfig;
+
+/// 支持的代理协议
+pub const SUPPORTED_PROTOCOLS: [&str; 7] = ["vmess", "vless", "trojan", "socks5", "http", "hysteria2", "shadowsocks"];
+
+/// 支持导入的配置格式主版本
+pub const CONFIG_MAJOR_VERSION: u64 = 1;
+
+/// 支持的传输方式
+const SUPPORTED_NETWORKS: [&str; 9] = ["tcp", "ws", "grpc", "h2", "http", "kcp", "quic", "httpupgrade", "xhttp"];
+
+/// TUN 虚拟网卡的 MTU 范围，下限为 IPv4 要求的最小值，上限为常见的巨型帧大小
+pub const TUN_MTU_RANGE: RangeInclusive<u16> = 576..=9000;
+
+/// 字段校验错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    /// 字段名，协议相关字段为 `config.<键名>`
+    pub field: String,
+    /// 错误说明
+    pub message: String,
+}
+
+/// 服务器校验失败结果
+/// 命令返回时转换为 `VALIDATION_FAILED` 错误，`details` 为各字段的错误，界面可据此在对应字段下显示错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationErrors {
+    /// 错误类型，固定为 `validation`
+    pub kind: String,
+    /// 各字段的错误
+    pub errors: Vec<FieldError>,
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.errors.iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// 规范化服务器信息
+/// 去除名称与地址两端空白、协议统一为小写，名称为空时使用 `地址:端口`
+///
+/// # 参数
+/// * `server` - 服务器信息
+pub fn sanitize_server(server: &mut ServerInfo) {
+    server.protocol = server.protocol.trim().to_lowercase();
+    if server.protocol == "socks" {
+        server.protocol = "socks5".to_string();
+    }
+    server.address = server.address.trim().trim_start_matches('[').trim_end_matches(']').to_string();
+    server.name = server.name.trim().to_string();
+    if server.name.is_empty() {
+        server.name = format!("{}:{}", server.address, server.port);
+    }
+
+    for value in server.config.values_mut() {
+        if let serde_json::Value::String(text) = value {
+            *text = text.trim().to_string();
+        }
+    }
+}
+
+/// 校验服务器信息
+///
+/// # 参数
+/// * `server` - 服务器信息（应先经过 `sanitize_server` 规范化）
+/// * `servers` - 现有服务器列表，用于校验链式代理引用
+///
+/// # 返回值
+/// * `Result<(), ValidationErrors>` - 校验失败时返回所有字段错误
+pub fn validate_server(server: &ServerInfo, servers: &[ServerInfo]) -> Result<(), ValidationErrors> {
+    let mut errors = Vec::new();
+    let mut error = |field: &str, message: String| errors.push(FieldError {
+        field: field.to_string(),
+        message,
+    });
+
+    if !SUPPORTED_PROTOCOLS.contains(&server.protocol.as_str()) {
+        error("protocol", format!("不支持的协议: {}", server.protocol));
+    }
+    if server.address.is_empty() {
+        error("address", "服务器地址不能为空".to_string());
+    } else if !is_valid_address(&server.address) {
+        error("address", format!("服务器地址格式无效: {}", server.address));
+    }
+    if server.port == 0 {
+        error("port", "端口必须在 1-65535 之间".to_string());
+    }
+
+    let text = |key: &str| server.config.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+
+    match server.protocol.as_str() {
+        "vmess" | "vless" => match text("uuid") {
+            None => error("config.uuid", "UUID 不能为空".to_string()),
+            Some(uuid) if uuid::Uuid::parse_str(uuid).is_err() => {
+                error("config.uuid", format!("UUID 格式无效: {}", uuid))
+            }
+            _ => {}
+        },
+        "trojan" | "hysteria2" => {
+            if text("password").is_none() {
+                error("config.password", "密码不能为空".to_string());
+            }
+        }
+        "shadowsocks" => {
+            if text("method").is_none() {
+                error("config.method", "加密方式不能为空".to_string());
+            }
+            if text("password").is_none() {
+                error("config.password", "密码不能为空".to_string());
+            }
+        }
+        "socks5" | "http" => {
+            if text("username").is_some() != text("password").is_some() {
+                error("config.password", "用户名和密码需同时填写".to_string());
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(alter_id) = server.config.get("alterId") {
+        if alter_id.as_u64().is_none() {
+            error("config.alterId", "alterId 必须是非负整数".to_string());
+        }
+    }
+    if let Some(network) = text("network") {
+        if !SUPPORTED_NETWORKS.contains(&network) {
+            error("config.network", format!("不支持的传输方式: {}", network));
+        }
+    }
+    if let Some(sni) = text("sni") {
+        if !is_valid_address(sni) {
+            error("config.sni", format!("SNI 格式无效: {}", sni));
+        }
+    }
+    // 出站配置片段中的模板变量
+    if let Some(snippet) = server.config.get("outbound_override") {
+        let snippet = match snippet {
+            serde_json::Value::String(text) => serde_json::from_str(text).unwrap_or_default(),
+            value => value.clone(),
+        };
+        if let Err(e) = crate::template::validate(&snippet, "出站配置片段") {
+            error("config.outbound_override", e.to_string());
+        }
+    }
+    if let Some(dial_through) = text("dial_through") {
+        if dial_through == server.id {
+            error("config.dial_through", "中转服务器不能是自身".to_string());
+        } else if !servers.iter().any(|s| s.id == dial_through) {
+            error("config.dial_through", format!("中转服务器不存在: {}", dial_through));
+        }
+    }
+
+    if let Some(mode) = &server.overrides.proxy_mode {
+        if !matches!(mode.as_str(), "pac" | "global" | "direct") {
+            error("overrides.proxy_mode", format!("无效的代理模式: {}", mode));
+        }
+    }
+    for (field, port) in [("overrides.http_port", server.overrides.http_port), ("overrides.socks_port", server.overrides.socks_port)] {
+        if port == Some(0) {
+            error(field, "端口必须在 1-65535 之间".to_string());
+        }
+    }
+    if server.overrides.http_port.is_some() && server.overrides.http_port == server.overrides.socks_port {
+        error("overrides.socks_port", "HTTP 与 SOCKS 端口不能相同".to_string());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors {
+            kind: "validation".to_string(),
+            errors,
+        })
+    }
+}
+
+/// 校验导入的应用配置
+/// 检查版本兼容性、端口范围、重复ID与引用关系，并逐个校验服务器；
+/// 字段名使用 `servers[序号].port` 形式的路径，便于定位到导入文件中的具体位置
+///
+/// # 参数
+/// * `config` - 导入的配置（服务器应先经过 `sanitize_server` 规范化）
+///
+/// # 返回值
+/// * `Result<(), ValidationErrors>` - 校验失败时返回所有错误
+pub fn validate_app_config(config: &AppConfig) -> Result<(), ValidationErrors> {
+    let mut errors = Vec::new();
+    let mut error = |field: String, message: String| errors.push(FieldError { field, message });
+
+    match config.version.split('.').next().and_then(|major| major.trim().parse::<u64>().ok()) {
+        Some(CONFIG_MAJOR_VERSION) => {}
+        Some(_) => error(
+            "version".to_string(),
+            format!("不兼容的配置版本 {}，仅支持 {}.x", config.version, CONFIG_MAJOR_VERSION),
+        ),
+        None => error("version".to_string(), format!("无效的配置版本: {}", config.version)),
+    }
+
+    let ports = [
+        ("http_port", config.http_port),
+        ("socks_port", config.socks_port),
+        ("pac_port", config.pac_port),
+        ("api_port", config.api_port),
+    ];
+    for (index, (field, port)) in ports.iter().enumerate() {
+        if *port == 0 {
+            error(field.to_string(), "端口必须在 1-65535 之间".to_string());
+        } else if let Some((other, _)) = ports[..index].iter().find(|(_, other_port)| other_port == port) {
+            error(field.to_string(), format!("端口 {} 与 {} 相同", port, other));
+        }
+    }
+    if let Err(e) = config.validate_inbounds() {
+        error("inbounds".to_string(), e.to_string());
+    }
+    if let Err(e) = config.core_launch.validate() {
+        error("core_launch.env".to_string(), e.to_string());
+    }
+
+    let servers = config.servers.iter().map(|s| s.id.as_str());
+    let subscriptions = config.subscriptions.iter().map(|s| s.id.as_str());
+    let profiles = config.profiles.iter().map(|p| p.id.as_str());
+    for (field, ids) in [
+        ("servers", servers.collect::<Vec<_>>()),
+        ("subscriptions", subscriptions.collect()),
+        ("profiles", profiles.collect()),
+    ] {
+        for (index, id) in ids.iter().enumerate() {
+            if id.is_empty() {
+                error(format!("{}[{}].id", field, index), "ID 不能为空".to_string());
+            } else if ids[..index].contains(id) {
+                error(format!("{}[{}].id", field, index), format!("ID 重复: {}", id));
+            }
+        }
+    }
+
+    for (index, server) in config.servers.iter().enumerate() {
+        if let Err(server_errors) = validate_server(server, &config.servers) {
+            for e in server_errors.errors {
+                error(format!("servers[{}].{}", index, e.field), e.message);
+            }
+        }
+    }
+
+    if let Some(id) = &config.current_server {
+        if !config.servers.iter().any(|s| &s.id == id) {
+            error("current_server".to_string(), format!("当前服务器不存在: {}", id));
+        }
+    }
+    if let Some(id) = &config.active_profile {
+        if !config.profiles.iter().any(|p| &p.id == id) {
+            error("active_profile".to_string(), format!("当前配置方案不存在: {}", id));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors {
+            kind: "validation".to_string(),
+            errors,
+        })
+    }
+}
+
+/// 校验 TUN 设备配置
+/// 在创建虚拟网卡之前检查 MTU、网卡名称、地址与子网掩码、网关、DNS 服务器与 FakeIP 地址池，
+/// 避免错误的配置到创建设备或配置地址时才失败
+///
+/// # 参数
+/// * `config` - TUN 设备配置
+///
+/// # 返回值
+/// * `Result<(), ValidationErrors>` - 校验失败时返回所有字段错误
+pub fn validate_tun_config(config: &TunConfig) -> Result<(), ValidationErrors> {
+    let mut errors = Vec::new();
+    let mut error = |field: &str, message: String| errors.push(FieldError {
+        field: field.to_string(),
+        message,
+    });
+
+    if !TUN_MTU_RANGE.contains(&config.mtu) {
+        error("mtu", format!("MTU 必须在 {}-{} 之间", TUN_MTU_RANGE.start(), TUN_MTU_RANGE.end()));
+    }
+    if let Err(message) = check_tun_name(&config.name) {
+        error("name", message);
+    }
+
+    // 虚拟网卡只配置 IPv4
+    let ipv4 = |ip: IpAddr| match ip {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(_) => None,
+    };
+    for (field, ip) in [("address", config.address), ("netmask", config.netmask), ("gateway", config.gateway)] {
+        if ipv4(ip).is_none() {
+            error(field, format!("仅支持 IPv4 地址: {}", ip));
+        }
+    }
+
+    // 子网掩码必须是连续的 1，得到前缀长度
+    let prefix = ipv4(config.netmask).and_then(|mask| {
+        let bits = u32::from(mask);
+        (bits.leading_ones() + bits.trailing_zeros() == 32).then_some(bits.leading_ones())
+    });
+    match (ipv4(config.netmask), prefix) {
+        (Some(mask), None) => error("netmask", format!("子网掩码不连续: {}", mask)),
+        (Some(_), Some(0)) => error("netmask", "子网掩码不能为 0.0.0.0".to_string()),
+        _ => {}
+    }
+
+    // 虚拟网卡所在子网：网络地址、广播地址与前缀长度
+    let subnet = match (ipv4(config.address), prefix) {
+        (Some(address), Some(prefix)) if prefix > 0 => {
+            let mask = u32::MAX << (32 - prefix);
+            let network = u32::from(address) & mask;
+            Some((network, network | !mask, prefix))
+        }
+        _ => None,
+    };
+    if let (Some(address), Some((network, broadcast, prefix))) = (ipv4(config.address), subnet) {
+        let cidr = format!("{}/{}", Ipv4Addr::from(network), prefix);
+        if address.is_unspecified() || address.is_multicast() || address.is_broadcast() || address.is_loopback() {
+            error("address", format!("不能用作网卡地址: {}", address));
+        } else if prefix <= 30 && (u32::from(address) == network || u32::from(address) == broadcast) {
+            error("address", format!("{} 是子网 {} 的网络地址或广播地址", address, cidr));
+        }
+        if let Some(gateway) = ipv4(config.gateway) {
+            if !(network..=broadcast).contains(&u32::from(gateway)) {
+                error("gateway", format!("网关 {} 不在子网 {} 内", gateway, cidr));
+            }
+        }
+    }
+
+    if config.dns_hijack && config.dns_server.parse::<Ipv4Addr>().is_err() {
+        error("dns_server", format!("DNS 服务器必须是 IPv4 地址: {}", config.dns_server));
+    }
+
+    if config.fake_ip {
+        match (ipv4(config.fake_ip_start), ipv4(config.fake_ip_end)) {
+            (Some(start), Some(end)) if u32::from(start) > u32::from(end) => {
+                error("fake_ip_end", format!("结束地址 {} 小于起始地址 {}", end, start));
+            }
+            (Some(start), Some(end)) => {
+                if let Some((network, broadcast, prefix)) = subnet {
+                    if u32::from(start) <= broadcast && u32::from(end) >= network {
+                        error("fake_ip_start", format!(
+                            "FakeIP 地址池与虚拟网卡子网 {}/{} 重叠", Ipv4Addr::from(network), prefix
+                        ));
+                    }
+                }
+            }
+            (start, end) => {
+                if start.is_none() {
+                    error("fake_ip_start", format!("仅支持 IPv4 地址: {}", config.fake_ip_start));
+                }
+                if end.is_none() {
+                    error("fake_ip_end", format!("仅支持 IPv4 地址: {}", config.fake_ip_end));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors {
+            kind: "validation".to_string(),
+            errors,
+        })
+    }
+}
+
+/// 按平台检查虚拟网卡名称
+/// Linux 网卡名最长 15 字节；macOS 只能使用 `utun` 加编号；Windows（wintun）最长 127 个字符
+fn check_tun_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("网卡名称不能为空".to_string());
+    }
+    if cfg!(target_os = "macos") {
+        let numbered = name.strip_prefix("utun")
+            .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()));
+        return if numbered {
+            Ok(())
+        } else {
+            Err(format!("macOS 的网卡名称必须为 utun 加编号，如 utun8: {}", name))
+        };
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("网卡名称只能包含字母、数字、- 和 _: {}", name));
+    }
+    let max_len = if cfg!(target_os = "windows") { 127 } else { 15 };
+    if name.len() > max_len {
+        return Err(format!("网卡名称最长 {} 个字符: {}", max_len, name));
+    }
+    Ok(())
+}
+
+/// 检查地址是否为有效的 IP 地址或主机名
+fn is_valid_address(address: &str) -> bool {
+    if address.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    if address.len() > 253 {
+        return false;
+    }
+
+    address.trim_end_matches('.').split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
@@ -0,0 +1,335 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+// WebDAV 配置同步
+// 上传时将数据目录备份（见 `backup`）用同步密码加密后写入 WebDAV 目录下的 `ruray-sync.bin`，
+// 同时写入明文的 `ruray-sync.json`（备份说明，不含服务器信息）供其他设备判断远端版本。
+// 每次同步后记录两端配置的 updated_at 作为基准（`WebDavConfig::last_sync`），
+// 将被覆盖的一方自上次同步后有修改时视为冲突，需显式强制覆盖；尚未同步过时按两端 updated_at 的先后判断。
+// 加密格式：魔数 + 盐 + 随机数 + AES-256-GCM 密文，密钥由同步密码经 PBKDF2-HMAC-SHA256 派生
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use rand::RngCore;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Cursor;
+use tokio::time::Duration;
+
+// 导入日志宏
+use crate::log_info;
+
+use crate::backup::{self, BackupManifest};
+use crate::config::AppConfig;
+use crate::config_store::ConfigStore;
+
+/// 远端加密备份文件名
+const REMOTE_BACKUP_NAME: &str = "ruray-sync.bin";
+/// 远端备份说明文件名
+const REMOTE_MANIFEST_NAME: &str = "ruray-sync.json";
+/// 加密文件魔数
+const MAGIC: &[u8] = b"RURAYSYNC1";
+/// 盐长度
+const SALT_LEN: usize = 16;
+/// AES-GCM 随机数长度
+const NONCE_LEN: usize = 12;
+/// PBKDF2 迭代次数
+const PBKDF2_ROUNDS: u32 = 200_000;
+/// 请求超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// WebDAV 同步设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebDavConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// WebDAV 目录地址，如 `https://dav.example.com/ruray/`
+    #[serde(default)]
+    pub url: String,
+    /// 用户名
+    #[serde(default)]
+    pub username: String,
+    /// 密码
+    #[serde(default)]
+    pub password: String,
+    /// 同步密码，用于加密上传的备份，各设备需一致
+    #[serde(default)]
+    pub passphrase: String,
+    /// 上次成功同步时两端配置的修改时间，只由同步过程更新
+    #[serde(default)]
+    pub last_sync: Option<WebDavSyncBase>,
+}
+
+/// 同步基准
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebDavSyncBase {
+    /// 同步完成时本地配置的 updated_at
+    pub local_updated_at: String,
+    /// 同步完成时远端备份中配置的 updated_at
+    pub remote_updated_at: String,
+}
+
+/// 同步状态
+#[derive(Debug, Clone, Serialize)]
+pub struct WebDavSyncStatus {
+    /// 本地配置的最后修改时间
+    pub local_updated_at: String,
+    /// 远端备份的说明，远端尚无备份时为空
+    pub remote: Option<BackupManifest>,
+    /// 是否执行了上传或下载（存在冲突且未强制时为 false）
+    pub synced: bool,
+    /// 本地与远端是否冲突
+    pub conflict: bool,
+}
+
+/// WebDAV 同步客户端
+pub struct WebDavSync {
+    config: WebDavConfig,
+    client: reqwest::Client,
+}
+
+impl WebDavSync {
+    /// 使用当前配置创建同步客户端
+    ///
+    /// # 异常
+    /// * 未启用 WebDAV 同步或设置不完整时返回错误
+    pub fn from_config() -> Result<Self> {
+        let config = AppConfig::load()?.webdav;
+        if !config.enabled {
+            return Err(anyhow::anyhow!("未启用 WebDAV 同步"));
+        }
+        if config.url.trim().is_empty() {
+            return Err(anyhow::anyhow!("未设置 WebDAV 地址"));
+        }
+        if config.passphrase.is_empty() {
+            return Err(anyhow::anyhow!("未设置同步密码"));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("无法创建 HTTP 客户端")?;
+        Ok(Self { config, client })
+    }
+
+    /// 远端文件地址
+    fn file_url(&self, name: &str) -> String {
+        format!("{}/{}", self.config.url.trim_end_matches('/'), name)
+    }
+
+    /// 构建带认证的请求
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, url);
+        if self.config.username.is_empty() {
+            builder
+        } else {
+            builder.basic_auth(&self.config.username, Some(&self.config.password))
+        }
+    }
+
+    /// 下载远端文件，不存在时返回 None
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.request(Method::GET, &self.file_url(name)).send().await
+            .context("无法连接 WebDAV 服务器")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("下载 {} 失败: HTTP {}", name, response.status()));
+        }
+        Ok(Some(response.bytes().await?.to_vec()))
+    }
+
+    /// 上传文件，目录不存在时先创建目录
+    async fn put(&self, name: &str, body: Vec<u8>) -> Result<()> {
+        let url = self.file_url(name);
+        let mut response = self.request(Method::PUT, &url).body(body.clone()).send().await
+            .context("无法连接 WebDAV 服务器")?;
+        if response.status() == StatusCode::CONFLICT || response.status() == StatusCode::NOT_FOUND {
+            let mkcol = Method::from_bytes(b"MKCOL").expect("有效的 HTTP 方法");
+            self.request(mkcol, &self.config.url).send().await
+                .context("无法创建 WebDAV 目录")?;
+            response = self.request(Method::PUT, &url).body(body).send().await?;
+        }
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("上传 {} 失败: HTTP {}", name, response.status()));
+        }
+        Ok(())
+    }
+
+    /// 读取远端备份说明
+    async fn remote_manifest(&self) -> Result<Option<BackupManifest>> {
+        match self.get(REMOTE_MANIFEST_NAME).await? {
+            Some(content) => Ok(Some(serde_json::from_slice(&content).context("无法解析远端备份说明")?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 查询本地与远端的同步状态，不上传也不下载
+    pub async fn status(&self) -> Result<WebDavSyncStatus> {
+        let local_updated_at = AppConfig::load()?.updated_at;
+        let remote = self.remote_manifest().await?;
+        Ok(WebDavSyncStatus { local_updated_at, remote, synced: false, conflict: false })
+    }
+
+    /// 上传本地配置
+    /// 远端自上次同步后被其他设备修改过时视为冲突，`force` 为 true 时仍然覆盖
+    ///
+    /// # 参数
+    /// * `force` - 是否忽略冲突
+    pub async fn upload(&self, force: bool) -> Result<WebDavSyncStatus> {
+        let local_updated_at = AppConfig::load()?.updated_at;
+        let remote = self.remote_manifest().await?;
+        let conflict = remote.as_ref().is_some_and(|remote| match &self.config.last_sync {
+            Some(base) => remote.config_updated_at != base.remote_updated_at,
+            None => is_newer(&remote.config_updated_at, &local_updated_at),
+        });
+        if conflict && !force {
+            return Ok(WebDavSyncStatus { local_updated_at, remote, synced: false, conflict });
+        }
+
+        let (archive, manifest) = tokio::task::spawn_blocking(|| -> Result<(Vec<u8>, BackupManifest)> {
+            let mut buffer = Cursor::new(Vec::new());
+            let manifest = backup::write_backup(&mut buffer)?;
+            Ok((buffer.into_inner(), manifest))
+        })
+        .await??;
+
+        // 密钥派生需要数十万次迭代，不在异步运行时线程上执行
+        let passphrase = self.config.passphrase.clone();
+        let encrypted = tokio::task::spawn_blocking(move || encrypt(&archive, &passphrase)).await??;
+        self.put(REMOTE_BACKUP_NAME, encrypted).await?;
+        self.put(REMOTE_MANIFEST_NAME, serde_json::to_vec_pretty(&manifest)?).await?;
+        record_sync(&manifest.config_updated_at, &manifest.config_updated_at)?;
+
+        log_info!("已上传配置到 WebDAV（配置修改时间 {}）", manifest.config_updated_at);
+        Ok(WebDavSyncStatus { local_updated_at: manifest.config_updated_at.clone(), remote: Some(manifest), synced: true, conflict })
+    }
+
+    /// 下载远端配置并应用
+    /// 本地配置自上次同步后有修改时视为冲突，`force` 为 true 时仍然覆盖。
+    /// `merge` 为 true 时保留远端没有的本地服务器、订阅和配置方案；
+    /// 本机相关的设置（WebDAV 设置、核心路径、日志路径、窗口状态、应用锁、钩子与核心启动参数）始终保留本地值
+    ///
+    /// # 参数
+    /// * `merge` - 是否与本地配置合并
+    /// * `force` - 是否忽略冲突
+    pub async fn download(&self, merge: bool, force: bool) -> Result<WebDavSyncStatus> {
+        let local = AppConfig::load()?;
+        let remote = self.remote_manifest().await?.context("远端没有可下载的配置")?;
+        let conflict = match &self.config.last_sync {
+            Some(base) => local.updated_at != base.local_updated_at,
+            None => is_newer(&local.updated_at, &remote.config_updated_at),
+        };
+        if conflict && !force {
+            return Ok(WebDavSyncStatus { local_updated_at: local.updated_at, remote: Some(remote), synced: false, conflict });
+        }
+
+        let encrypted = self.get(REMOTE_BACKUP_NAME).await?.context("远端缺少加密备份文件")?;
+        let passphrase = self.config.passphrase.clone();
+        let (manifest, _) = tokio::task::spawn_blocking(move || {
+            let archive = decrypt(&encrypted, &passphrase)?;
+            backup::read_backup(Cursor::new(archive), |remote_config| merge_config(remote_config, local, merge))
+        })
+        .await??;
+
+        let local_updated_at = AppConfig::load()?.updated_at;
+        record_sync(&local_updated_at, &manifest.config_updated_at)?;
+        log_info!("已从 WebDAV 下载配置（{}，配置修改时间 {}）", if merge { "合并" } else { "覆盖" }, manifest.config_updated_at);
+        Ok(WebDavSyncStatus { local_updated_at, remote: Some(manifest), synced: true, conflict })
+    }
+}
+
+/// 将下载的配置与本地配置合并
+///
+/// # 参数
+/// * `remote` - 远端配置
+/// * `local` - 本地配置
+/// * `merge` - 是否保留远端没有的本地服务器、订阅和配置方案
+fn merge_config(mut remote: AppConfig, local: AppConfig, merge: bool) -> AppConfig {
+    remote.keep_local_settings(&local);
+    if merge {
+        let server_ids: HashSet<String> = remote.servers.iter().map(|s| s.id.clone()).collect();
+        remote.servers.extend(local.servers.into_iter().filter(|s| !server_ids.contains(&s.id)));
+        let subscription_ids: HashSet<String> = remote.subscriptions.iter().map(|s| s.id.clone()).collect();
+        remote.subscriptions.extend(local.subscriptions.into_iter().filter(|s| !subscription_ids.contains(&s.id)));
+        let profile_ids: HashSet<String> = remote.profiles.iter().map(|p| p.id.clone()).collect();
+        remote.profiles.extend(local.profiles.into_iter().filter(|p| !profile_ids.contains(&p.id)));
+    }
+    remote
+}
+
+/// 记录本次同步的基准
+/// 只修改 WebDAV 设置，属于本机设置，不会更新配置的 updated_at
+///
+/// # 参数
+/// * `local_updated_at` - 同步完成时本地配置的修改时间
+/// * `remote_updated_at` - 同步完成时远端配置的修改时间
+fn record_sync(local_updated_at: &str, remote_updated_at: &str) -> Result<()> {
+    ConfigStore::instance().update(|config| {
+        config.webdav.last_sync = Some(WebDavSyncBase {
+            local_updated_at: local_updated_at.to_string(),
+            remote_updated_at: remote_updated_at.to_string(),
+        });
+    })
+}
+
+/// 比较两个 RFC 3339 时间，`a` 比 `b` 新时返回 true，无法解析时按字符串比较
+fn is_newer(a: &str, b: &str) -> bool {
+    match (chrono::DateTime::parse_from_rfc3339(a), chrono::DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+/// 由同步密码派生加密密钥
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// 加密备份
+fn encrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|_| anyhow::anyhow!("加密备份失败"))?;
+
+    let mut output = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// 解密备份
+///
+/// # 异常
+/// * 文件格式不正确或同步密码错误时返回错误
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let body = data.strip_prefix(MAGIC).context("远端备份格式不正确")?;
+    if body.len() < SALT_LEN + NONCE_LEN {
+        return Err(anyhow::anyhow!("远端备份已损坏"));
+    }
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = derive_key(passphrase, salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("解密失败，请检查同步密码是否与上传设备一致"))
+}
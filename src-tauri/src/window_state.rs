@@ -0,0 +1,132 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use std::sync::Mutex;
+use tauri::{PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, Window};
+
+// 导入日志宏
+use crate::{log_debug, log_error};
+
+use crate::config::{AppConfig, WindowState};
+use crate::config_store::ConfigStore;
+
+/// 主窗口的最小尺寸，与 tauri.conf.json 中的 minWidth/minHeight 一致
+const MIN_WIDTH: u32 = 800;
+const MIN_HEIGHT: u32 = 600;
+
+/// 窗口移动、缩放时记录的最新状态，退出时一次性写入配置
+/// 避免拖动窗口期间频繁写入配置文件
+static PENDING_STATE: Mutex<Option<WindowState>> = Mutex::new(None);
+
+/// 恢复主窗口状态
+/// 保存的位置不在任何显示器范围内时（如外接显示器已断开）只恢复尺寸
+///
+/// # 参数
+/// * `window` - 主窗口
+pub fn restore<R: Runtime>(window: &WebviewWindow<R>) {
+    let Some(state) = AppConfig::load().ok().and_then(|config| config.window_state) else {
+        return;
+    };
+
+    let size = PhysicalSize::new(state.width.max(MIN_WIDTH), state.height.max(MIN_HEIGHT));
+    if let Err(e) = window.set_size(size) {
+        log_error!("恢复窗口尺寸失败: {}", e);
+    }
+
+    let position = PhysicalPosition::new(state.x, state.y);
+    if is_on_screen(window, position) {
+        if let Err(e) = window.set_position(position) {
+            log_error!("恢复窗口位置失败: {}", e);
+        }
+    } else {
+        log_debug!("保存的窗口位置不在可用显示器范围内，保持居中");
+    }
+
+    if state.maximized {
+        let _ = window.maximize();
+    }
+
+    *PENDING_STATE.lock().unwrap() = Some(state);
+}
+
+/// 记录主窗口当前状态
+/// 在窗口移动或缩放时调用，最小化时不记录
+///
+/// # 参数
+/// * `window` - 主窗口
+pub fn track<R: Runtime>(window: &Window<R>) {
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+
+    let mut pending = PENDING_STATE.lock().unwrap();
+    let mut state = pending.clone().unwrap_or_default();
+    state.maximized = window.is_maximized().unwrap_or(false);
+
+    // 最大化时保留最大化前的尺寸和位置，还原时才能回到原来的大小
+    if !state.maximized {
+        if let (Ok(size), Ok(position)) = (window.outer_size(), window.outer_position()) {
+            state.width = size.width;
+            state.height = size.height;
+            state.x = position.x;
+            state.y = position.y;
+        }
+    }
+    *pending = Some(state);
+}
+
+/// 记录高级日志窗口是否打开
+///
+/// # 参数
+/// * `open` - 日志窗口是否打开
+pub fn set_log_window_open(open: bool) {
+    let mut pending = PENDING_STATE.lock().unwrap();
+    let mut state = pending.clone()
+        .or_else(|| AppConfig::load().ok().and_then(|config| config.window_state))
+        .unwrap_or_default();
+    state.log_window_open = open;
+    *pending = Some(state);
+}
+
+/// 将记录的窗口状态写入配置
+/// 状态未变化时不写入
+pub fn save() {
+    let Some(state) = PENDING_STATE.lock().unwrap().clone() else {
+        return;
+    };
+    // 尚未记录到有效尺寸（窗口从未显示过）时不覆盖已保存的状态
+    if state.width == 0 || state.height == 0 {
+        return;
+    }
+
+    let store = ConfigStore::instance();
+    let unchanged = store.get()
+        .map(|config| config.window_state.as_ref() == Some(&state))
+        .unwrap_or(false);
+    if unchanged {
+        return;
+    }
+
+    if let Err(e) = store.update(|config| config.window_state = Some(state)) {
+        log_error!("保存窗口状态失败: {}", e);
+    }
+}
+
+/// 检查窗口左上角是否位于某个显示器范围内
+fn is_on_screen<R: Runtime>(window: &WebviewWindow<R>, position: PhysicalPosition<i32>) -> bool {
+    let Ok(monitors) = window.available_monitors() else {
+        return false;
+    };
+
+    monitors.iter().any(|monitor| {
+        let origin = monitor.position();
+        let size = monitor.size();
+        position.x >= origin.x
+            && position.y >= origin.y
+            && i64::from(position.x) < i64::from(origin.x) + i64::from(size.width)
+            && i64::from(position.y) < i64::from(origin.y) + i64::from(size.height)
+    })
+}
@@ -6,21 +6,81 @@
 
 use anyhow::{Context, Result};
 use reqwest::Client;
-use serde::Deserialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use tauri::{AppHandle, Emitter};
 use tokio::io::AsyncWriteExt;
+use tokio::time::Duration;
 use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+
+// 导入日志宏
+use crate::{log_error, log_info, log_warn};
 
 use crate::config::AppConfig;
+use crate::config_store::ConfigStore;
+use crate::geodata::{self, GeoDownloadSource, GeoFileFormat, GEOIP_FILE, GEOSITE_FILE};
+
+/// 地理位置数据文件的发布仓库
+const GEO_RELEASE_API: &str = "https://api.github.com/repos/Loyalsoldier/v2ray-rules-dat/releases/latest";
+/// 地理位置数据文件名
+const GEO_FILES: [&str; 2] = ["geoip.dat", "geosite.dat"];
+/// 自动更新地理位置数据文件的周期
+const GEO_AUTO_UPDATE_PERIOD: chrono::Duration = chrono::Duration::days(7);
+/// 自动更新任务的检查间隔
+const GEO_AUTO_UPDATE_TICK: Duration = Duration::from_secs(60 * 60);
 
 /// GitHub Release 信息
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    #[serde(default)]
+    published_at: Option<String>,
     assets: Vec<GitHubAsset>,
 }
 
+/// 地理位置数据文件更新检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoUpdateInfo {
+    /// 是否有文件需要更新
+    pub has_update: bool,
+    /// 上游最新发布版本
+    pub latest_version: String,
+    /// 上游最新发布时间
+    pub published_at: Option<String>,
+    /// 各文件状态
+    pub files: Vec<GeoFileStatus>,
+}
+
+/// 已安装的 Xray Core 版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledCore {
+    /// 版本号
+    pub version: String,
+    /// 可执行文件路径
+    pub path: String,
+    /// 是否为当前使用的版本
+    pub active: bool,
+    /// 安装时间
+    pub installed_at: Option<String>,
+    /// 最近一次使用该版本成功连接的服务器ID
+    pub servers: Vec<String>,
+}
+
+/// 单个地理位置数据文件的状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoFileStatus {
+    /// 文件名
+    pub name: String,
+    /// 本地文件是否存在
+    pub exists: bool,
+    /// 本地文件修改时间
+    pub modified_at: Option<String>,
+    /// 是否早于上游发布时间
+    pub outdated: bool,
+}
+
 /// GitHub Asset 信息
 #[derive(Debug, Deserialize)]
 struct GitHubAsset {
@@ -56,7 +116,7 @@ impl XrayManager {
     }
 
     /// 获取最新版本信息
-    async fn get_latest_version(&self) -> Result<String> {
+    pub(crate) async fn get_latest_version(&self) -> Result<String> {
         let url = "https://api.github.com/repos/XTLS/Xray-core/releases/latest";
         
         let response = self.client
@@ -75,8 +135,11 @@ impl XrayManager {
     }
 
     /// 下载 Xray Core 更新
+    /// 只解压到版本目录，不替换当前使用的可执行文件；调用方停止代理后通过 `activate_core` 切换
     pub async fn download_update(&self, version: &str) -> Result<()> {
-        let download_url = self.get_download_url(version).await?;
+        Self::ensure_managed_core()?;
+        let (download_url, digest_url) = self.get_download_url(version).await?;
+        let expected_sha256 = self.fetch_expected_sha256(&digest_url).await?;
         let xray_dir = AppConfig::xray_dir()?;
         
         // 下载文件
@@ -91,6 +154,9 @@ impl XrayManager {
             .await
             .context("无法读取下载内容")?;
 
+        // 校验压缩包，校验失败时不替换现有的可执行文件
+        Self::verify_sha256(&format!("{:x}", Sha256::digest(&bytes)), &expected_sha256)?;
+
         // 保存到临时文件
         let temp_file = xray_dir.join("xray_temp.zip");
         let mut file = tokio::fs::File::create(&temp_file)
@@ -101,8 +167,8 @@ impl XrayManager {
             .await
             .context("无法写入临时文件")?;
 
-        // 解压文件
-        self.extract_xray(&temp_file, &xray_dir).await?;
+        // 解压到版本目录
+        self.install_version(&temp_file, version).await?;
 
         // 删除临时文件
         tokio::fs::remove_file(&temp_file)
@@ -113,13 +179,16 @@ impl XrayManager {
     }
 
     /// 下载 Xray Core 更新（带进度回调）
+    /// 只解压到版本目录，不替换当前使用的可执行文件；调用方停止代理后通过 `activate_core` 切换
     pub async fn download_update_with_progress<F>(&self, version: &str, mut progress_callback: F) -> Result<()>
     where
         F: FnMut(u64, u64, String) + Send,
     {
+        Self::ensure_managed_core()?;
         progress_callback(0, 100, "正在获取下载信息...".to_string());
         
-        let download_url = self.get_download_url(version).await?;
+        let (download_url, digest_url) = self.get_download_url(version).await?;
+        let expected_sha256 = self.fetch_expected_sha256(&digest_url).await?;
         let xray_dir = AppConfig::xray_dir()?;
         
         progress_callback(10, 100, "开始下载...".to_string());
@@ -134,6 +203,7 @@ impl XrayManager {
         let total_size = response.content_length().unwrap_or(0);
         let mut downloaded = 0u64;
         let mut stream = response.bytes_stream();
+        let mut hasher = Sha256::new();
 
         // 保存到临时文件
         let temp_file = xray_dir.join("xray_temp.zip");
@@ -147,6 +217,7 @@ impl XrayManager {
             file.write_all(&chunk)
                 .await
                 .context("无法写入临时文件")?;
+            hasher.update(&chunk);
             
             downloaded += chunk.len() as u64;
             
@@ -160,10 +231,20 @@ impl XrayManager {
             }
         }
 
+        file.flush().await.context("无法写入临时文件")?;
+        drop(file);
+
+        // 校验压缩包，校验失败时删除临时文件且不替换现有的可执行文件
+        progress_callback(90, 100, "正在校验文件...".to_string());
+        if let Err(e) = Self::verify_sha256(&format!("{:x}", hasher.finalize()), &expected_sha256) {
+            let _ = tokio::fs::remove_file(&temp_file).await;
+            return Err(e);
+        }
+
         progress_callback(90, 100, "正在解压文件...".to_string());
 
-        // 解压文件
-        self.extract_xray(&temp_file, &xray_dir).await?;
+        // 解压到版本目录
+        self.install_version(&temp_file, version).await?;
 
         progress_callback(95, 100, "清理临时文件...".to_string());
 
@@ -172,13 +253,16 @@ impl XrayManager {
             .await
             .context("无法删除临时文件")?;
 
-        progress_callback(100, 100, "更新完成！".to_string());
+        progress_callback(100, 100, "下载完成".to_string());
 
         Ok(())
     }
 
     /// 获取下载链接
-    async fn get_download_url(&self, version: &str) -> Result<String> {
+    ///
+    /// # 返回值
+    /// * `Result<(String, String)>` - 压缩包下载链接与对应的 .dgst 校验文件链接
+    async fn get_download_url(&self, version: &str) -> Result<(String, String)> {
         let url = format!("https://api.github.com/repos/XTLS/Xray-core/releases/tags/{}", version);
         
         let response = self.client
@@ -196,13 +280,50 @@ impl XrayManager {
         // 根据操作系统选择合适的资源
         let asset_name = self.get_asset_name();
         
-        for asset in release.assets {
-            if asset.name.contains(&asset_name) {
-                return Ok(asset.browser_download_url);
-            }
-        }
+        let archive = release.assets.iter()
+            .find(|asset| asset.name.contains(&asset_name) && asset.name.ends_with(".zip"))
+            .context("未找到适合的下载资源")?;
+        let digest_name = format!("{}.dgst", archive.name);
+        let digest = release.assets.iter()
+            .find(|asset| asset.name == digest_name)
+            .context(format!("未找到校验文件: {}", digest_name))?;
+
+        Ok((archive.browser_download_url.clone(), digest.browser_download_url.clone()))
+    }
 
-        Err(anyhow::anyhow!("未找到适合的下载资源"))
+    /// 获取发布页 .dgst 文件中的 SHA256 值
+    /// 文件格式为每行 `算法= 摘要`，如 `SHA2-256= 3f5c...`
+    async fn fetch_expected_sha256(&self, digest_url: &str) -> Result<String> {
+        let content = self.client
+            .get(digest_url)
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .context("无法下载校验文件")?
+            .error_for_status()
+            .context("无法下载校验文件")?
+            .text()
+            .await
+            .context("无法读取校验文件")?;
+
+        content.lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(algorithm, _)| algorithm.trim().eq_ignore_ascii_case("SHA2-256"))
+            .map(|(_, digest)| digest.trim().to_lowercase())
+            .filter(|digest| digest.len() == 64)
+            .context("校验文件中没有 SHA256 值")
+    }
+
+    /// 比较 SHA256 值
+    fn verify_sha256(actual: &str, expected: &str) -> Result<()> {
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(anyhow::anyhow!(
+                "Xray Core 压缩包 SHA256 校验失败，文件可能已损坏或被篡改（期望 {}，实际 {}）",
+                expected,
+                actual
+            ));
+        }
+        Ok(())
     }
 
     /// 获取资源名称
@@ -249,6 +370,18 @@ impl XrayManager {
         }
     }
 
+    /// 安装下载的版本
+    /// 先保留当前使用的版本，再解压到版本目录
+    async fn install_version(&self, zip_path: &Path, version: &str) -> Result<()> {
+        if let Err(e) = self.archive_current_core() {
+            log_warn!("保存当前 Xray Core 版本失败: {}", e);
+        }
+
+        let version_dir = Self::version_dir(version)?;
+        std::fs::create_dir_all(&version_dir).context("无法创建版本目录")?;
+        self.extract_xray(zip_path, &version_dir).await
+    }
+
     /// 解压 Xray Core
     async fn extract_xray(&self, zip_path: &Path, extract_dir: &Path) -> Result<()> {
         let file = std::fs::File::open(zip_path)
@@ -297,7 +430,14 @@ impl XrayManager {
             return Err(anyhow::anyhow!("Xray Core 未安装"));
         }
 
-        let output = Command::new(&xray_executable)
+        Self::executable_version(&xray_executable)
+    }
+
+    /// 获取指定 Xray 可执行文件的版本
+    /// `xray version` 输出形如 `Xray 1.8.24 (Xray, Penetrates Everything.) ...`，
+    /// 返回带 `v` 前缀的版本号以便与发布标签比较
+    pub(crate) fn executable_version(executable: &Path) -> Result<String> {
+        let output = Command::new(executable)
             .arg("version")
             .output()
             .context("无法执行 Xray Core")?;
@@ -310,12 +450,10 @@ impl XrayManager {
         
         // 解析版本信息
         for line in version_output.lines() {
-            if line.contains("Xray") && line.contains("(") {
-                if let Some(start) = line.find("(") {
-                    if let Some(end) = line.find(")") {
-                        let version = &line[start + 1..end];
-                        return Ok(version.to_string());
-                    }
+            let mut parts = line.split_whitespace();
+            if parts.next() == Some("Xray") {
+                if let Some(version) = parts.next() {
+                    return Ok(format!("v{}", version.trim_start_matches('v')));
                 }
             }
         }
@@ -323,7 +461,134 @@ impl XrayManager {
         Err(anyhow::anyhow!("无法解析版本信息"))
     }
 
-    /// 检查并下载必需的数据文件（geoip.dat 和 geosite.dat）
+    /// 获取已安装版本的存放目录
+    fn versions_dir() -> Result<PathBuf> {
+        let versions_dir = AppConfig::xray_dir()?.join("versions");
+        std::fs::create_dir_all(&versions_dir).context("无法创建版本目录")?;
+        Ok(versions_dir)
+    }
+
+    /// 获取指定版本的存放目录
+    /// 版本号只能是单个目录名，包含路径分隔符、`..` 或为绝对路径时返回错误，避免越出版本目录
+    fn version_dir(version: &str) -> Result<PathBuf> {
+        let mut components = Path::new(version).components();
+        let single_name = matches!(
+            (components.next(), components.next()),
+            (Some(std::path::Component::Normal(_)), None)
+        );
+        if !single_name || version.contains(['/', '\\']) {
+            return Err(anyhow::anyhow!("无效的 Xray Core 版本号: {}", version));
+        }
+        Ok(Self::versions_dir()?.join(version))
+    }
+
+    /// Xray 可执行文件名
+    fn executable_name() -> &'static str {
+        if cfg!(target_os = "windows") { "xray.exe" } else { "xray" }
+    }
+
+    /// 列出已安装的 Xray Core 版本
+    ///
+    /// # 返回值
+    /// * `Result<Vec<InstalledCore>>` - 按版本号倒序排列的已安装版本
+    pub fn list_installed_cores(&self) -> Result<Vec<InstalledCore>> {
+        let config = AppConfig::load()?;
+        let mut cores = Vec::new();
+
+        for entry in std::fs::read_dir(Self::versions_dir()?).context("无法读取版本目录")? {
+            let entry = entry.context("读取目录项失败")?;
+            let path = entry.path().join(Self::executable_name());
+            if !path.is_file() {
+                continue;
+            }
+
+            let version = entry.file_name().to_string_lossy().to_string();
+            let installed_at = entry.metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+            let mut servers: Vec<String> = config.core_version_usage.iter()
+                .filter(|(_, used)| **used == version)
+                .map(|(server_id, _)| server_id.clone())
+                .collect();
+            servers.sort();
+
+            cores.push(InstalledCore {
+                active: config.core_version.as_deref() == Some(version.as_str()),
+                path: path.to_string_lossy().to_string(),
+                version,
+                installed_at,
+                servers,
+            });
+        }
+
+        cores.sort_by(|a, b| compare_versions(&b.version, &a.version));
+        Ok(cores)
+    }
+
+    /// 将当前使用的可执行文件保存到版本目录（尚未保存时）
+    /// 用于保留更新前手动安装或旧版本 RuRay 下载的 Xray Core
+    fn archive_current_core(&self) -> Result<()> {
+        let executable = AppConfig::xray_dir()?.join(Self::executable_name());
+        if !executable.exists() {
+            return Ok(());
+        }
+
+        let version = Self::executable_version(&executable)?;
+        let version_dir = Self::version_dir(&version)?;
+        let archived = version_dir.join(Self::executable_name());
+        if !archived.exists() {
+            std::fs::create_dir_all(&version_dir).context("无法创建版本目录")?;
+            std::fs::copy(&executable, &archived).context("无法保存当前版本")?;
+        }
+
+        ConfigStore::instance().update(|config| {
+            if config.core_version.is_none() {
+                config.core_version = Some(version);
+            }
+        })?;
+        Ok(())
+    }
+
+    /// 检查是否使用应用管理的 Xray Core
+    /// 配置了自定义路径时无法下载或切换版本，下载前检查，避免下载完成后才失败
+    fn ensure_managed_core() -> Result<()> {
+        if AppConfig::load()?.xray_path.is_some() {
+            return Err(anyhow::anyhow!("已配置自定义 Xray Core 路径，无法切换版本"));
+        }
+        Ok(())
+    }
+
+    /// 切换到已安装的 Xray Core 版本
+    /// 将版本目录中的可执行文件复制为当前使用的可执行文件，并记录上一个版本以便回滚。
+    /// 调用方需确保 Xray 进程已停止
+    ///
+    /// # 参数
+    /// * `version` - 版本号（如 v1.8.24）
+    ///
+    /// # 异常
+    /// * 当配置了自定义 Xray 路径或版本未安装时返回错误
+    pub fn activate_core(&self, version: &str) -> Result<()> {
+        Self::ensure_managed_core()?;
+
+        let source = Self::version_dir(version)?.join(Self::executable_name());
+        if !source.is_file() {
+            return Err(anyhow::anyhow!("Xray Core {} 未安装", version));
+        }
+
+        let target = AppConfig::xray_dir()?.join(Self::executable_name());
+        std::fs::copy(&source, &target).context("无法替换 Xray Core 可执行文件")?;
+
+        ConfigStore::instance().update(|config| {
+            if config.core_version.as_deref() != Some(version) {
+                config.previous_core_version = config.core_version.take();
+                config.core_version = Some(version.to_string());
+            }
+        })?;
+        Ok(())
+    }
+
+    /// 检查并下载必需的数据文件（geoip.dat 和 geosite.dat），下载地址取自设置中的下载来源
     /// 
     /// # 参数
     /// * `progress_callback` - 进度回调函数，接收 (当前进度, 总进度, 状态消息)
@@ -335,6 +600,7 @@ impl XrayManager {
         F: FnMut(u64, u64, String) + Send,
     {
         let xray_dir = AppConfig::xray_dir()?;
+        let sources = AppConfig::load()?.geo_sources;
         
         // 确保目录存在
         tokio::fs::create_dir_all(&xray_dir)
@@ -346,8 +612,8 @@ impl XrayManager {
         // 下载 geoip.dat
         progress_callback(10, 100, "下载 geoip.dat...".to_string());
         self.download_geo_file(
-            "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geoip.dat",
-            &xray_dir.join("geoip.dat"),
+            &sources.source(GEOIP_FILE).context("缺少 geoip.dat 下载来源")?,
+            &xray_dir.join(GEOIP_FILE),
             |progress| {
                 let adjusted_progress = 10 + (progress * 40 / 100); // 10-50%
                 progress_callback(adjusted_progress, 100, format!("下载 geoip.dat... {}%", progress));
@@ -357,8 +623,8 @@ impl XrayManager {
         // 下载 geosite.dat
         progress_callback(50, 100, "下载 geosite.dat...".to_string());
         self.download_geo_file(
-            "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geosite.dat",
-            &xray_dir.join("geosite.dat"),
+            &sources.source(GEOSITE_FILE).context("缺少 geosite.dat 下载来源")?,
+            &xray_dir.join(GEOSITE_FILE),
             |progress| {
                 let adjusted_progress = 50 + (progress * 40 / 100); // 50-90%
                 progress_callback(adjusted_progress, 100, format!("下载 geosite.dat... {}%", progress));
@@ -369,39 +635,72 @@ impl XrayManager {
         Ok(())
     }
 
+    /// 更新单个地理位置数据文件（geoip.dat、geosite.dat 或设置中添加的规则文件）
+    ///
+    /// # 参数
+    /// * `name` - 文件名
+    /// * `progress_callback` - 进度回调函数，接收 (当前进度, 总进度, 状态消息)
+    ///
+    /// # 异常
+    /// * 文件未配置、下载失败或校验失败时返回错误，此时保留原有文件
+    pub async fn update_geo_file<F>(&self, name: &str, mut progress_callback: F) -> Result<()>
+    where
+        F: FnMut(u64, u64, String) + Send,
+    {
+        let source = AppConfig::load()?.geo_sources
+            .source(name)
+            .with_context(|| format!("未配置地理位置数据文件: {}", name))?;
+        let output_path = AppConfig::xray_dir()?.join(&source.name);
+
+        progress_callback(0, 100, format!("下载 {}...", name));
+        self.download_geo_file(&source, &output_path, |progress| {
+            progress_callback(progress * 90 / 100, 100, format!("下载 {}... {}%", name, progress));
+        }).await?;
+        progress_callback(100, 100, format!("{} 已更新", name));
+        log_info!("地理位置数据文件已更新: {}（{}）", name, source.url);
+        Ok(())
+    }
+
     /// 下载单个地理位置数据文件
+    /// 先下载到临时文件，校验 SHA256（设置中指定的值或下载地址旁的 `.sha256sum` 文件）并确认文件可以解析后
+    /// 再替换原文件，校验失败时保留原有文件；纯文本规则文件转换为 .dat 格式后保存
     /// 
     /// # 参数
-    /// * `url` - 下载链接
+    /// * `source` - 下载信息
     /// * `output_path` - 输出文件路径
     /// * `progress_callback` - 进度回调函数
     /// 
     /// # 返回值
     /// * `Result<()>` - 下载结果
-    async fn download_geo_file<F>(&self, url: &str, output_path: &Path, mut progress_callback: F) -> Result<()>
+    async fn download_geo_file<F>(&self, source: &GeoDownloadSource, output_path: &Path, mut progress_callback: F) -> Result<()>
     where
         F: FnMut(u64) + Send,
     {
         let response = self.client
-            .get(url)
+            .get(&source.url)
             .header("User-Agent", "RuRay/1.0.0")
             .send()
             .await
-            .context("无法下载地理位置数据文件")?;
+            .context("无法下载地理位置数据文件")?
+            .error_for_status()
+            .with_context(|| format!("无法下载 {}", source.name))?;
 
         let total_size = response.content_length().unwrap_or(0);
         let mut downloaded = 0u64;
         let mut stream = response.bytes_stream();
+        let mut hasher = Sha256::new();
 
-        let mut file = tokio::fs::File::create(output_path)
+        let temp_path = output_path.with_extension("download");
+        let mut file = tokio::fs::File::create(&temp_path)
             .await
-            .context("无法创建输出文件")?;
+            .context("无法创建临时文件")?;
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.context("下载过程中出现错误")?;
             file.write_all(&chunk)
                 .await
                 .context("无法写入文件")?;
+            hasher.update(&chunk);
             
             downloaded += chunk.len() as u64;
             
@@ -410,8 +709,118 @@ impl XrayManager {
                 progress_callback(progress);
             }
         }
+        file.flush().await.context("无法写入文件")?;
+        drop(file);
+
+        let expected = match &source.sha256 {
+            Some(sha256) => Some(sha256.clone()),
+            None => self.fetch_sha256sum(&source.url).await,
+        };
+        let result = self.install_geo_file(source, &temp_path, output_path, &format!("{:x}", hasher.finalize()), expected.as_deref()).await;
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        result
+    }
 
-        Ok(())
+    /// 校验下载的临时文件并替换原文件
+    async fn install_geo_file(&self, source: &GeoDownloadSource, temp_path: &Path, output_path: &Path, actual_sha256: &str, expected_sha256: Option<&str>) -> Result<()> {
+        match expected_sha256 {
+            Some(expected) if !actual_sha256.eq_ignore_ascii_case(expected) => {
+                return Err(anyhow::anyhow!(
+                    "{} SHA256 校验失败，文件可能已损坏或被篡改（期望 {}，实际 {}）",
+                    source.name,
+                    expected,
+                    actual_sha256
+                ));
+            }
+            Some(_) => {}
+            None => log_warn!("{} 没有可用的 SHA256 校验值，仅检查文件格式", source.name),
+        }
+
+        let data = tokio::fs::read(temp_path).await.context("无法读取下载的文件")?;
+        let data = match source.format {
+            GeoFileFormat::Dat => {
+                geodata::verify_dat(source.kind, &data).with_context(|| format!("{} 校验失败", source.name))?;
+                data
+            }
+            GeoFileFormat::Text => {
+                let text = String::from_utf8_lossy(&data);
+                geodata::compile_text_rules(source.kind, &source.category, &text)
+                    .with_context(|| format!("{} 转换失败", source.name))?
+            }
+        };
+
+        tokio::fs::write(temp_path, &data).await.context("无法写入文件")?;
+        tokio::fs::rename(temp_path, output_path)
+            .await
+            .with_context(|| format!("无法替换 {}", output_path.display()))
+    }
+
+    /// 获取下载地址旁的 `.sha256sum` 文件中的 SHA256 值（格式为 `摘要  文件名`），不存在时返回 None
+    async fn fetch_sha256sum(&self, url: &str) -> Option<String> {
+        let response = self.client
+            .get(format!("{}.sha256sum", url))
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+        let content = response.text().await.ok()?;
+        content.split_whitespace()
+            .next()
+            .map(str::to_lowercase)
+            .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    /// 检查地理位置数据文件是否有更新
+    /// 以本地文件的修改时间与上游最新发布时间比较，本地文件缺失或早于发布时间即视为需要更新
+    ///
+    /// # 返回值
+    /// * `Result<GeoUpdateInfo>` - 检查结果
+    pub async fn check_geo_files_update(&self) -> Result<GeoUpdateInfo> {
+        let release: GitHubRelease = self.client
+            .get(GEO_RELEASE_API)
+            .header("User-Agent", "RuRay/1.0.0")
+            .send()
+            .await
+            .context("无法获取地理位置数据版本信息")?
+            .json()
+            .await
+            .context("无法解析地理位置数据版本信息")?;
+
+        let published_at = release.published_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&chrono::Utc));
+
+        let xray_dir = AppConfig::xray_dir()?;
+        let files: Vec<GeoFileStatus> = GEO_FILES
+            .iter()
+            .map(|name| {
+                let modified = std::fs::metadata(xray_dir.join(name))
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(chrono::DateTime::<chrono::Utc>::from);
+                let outdated = match (modified, published_at) {
+                    (Some(modified), Some(published)) => modified < published,
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                };
+                GeoFileStatus {
+                    name: name.to_string(),
+                    exists: modified.is_some(),
+                    modified_at: modified.map(|t| t.to_rfc3339()),
+                    outdated,
+                }
+            })
+            .collect();
+
+        Ok(GeoUpdateInfo {
+            has_update: files.iter().any(|f| f.outdated),
+            latest_version: release.tag_name,
+            published_at: release.published_at,
+            files,
+        })
     }
 
     /// 检查地理位置数据文件是否存在
@@ -451,6 +860,8 @@ impl XrayManager {
                 let adjusted_progress = 10 + (progress * 40 / 100); // 10-50%
                 progress_callback(adjusted_progress, total, message);
             }).await?;
+            // 可执行文件不存在，核心不会在运行，可以直接切换
+            self.activate_core(&latest_version)?;
         } else {
             progress_callback(50, 100, "Xray Core 已存在".to_string());
         }
@@ -468,4 +879,81 @@ impl XrayManager {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 比较版本号（如 v1.8.24 与 v1.8.3），无法解析的部分按字符串比较
+pub(crate) fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse().ok())
+            .collect()
+    };
+    parse(a).cmp(&parse(b)).then_with(|| a.cmp(b))
+}
+
+/// 启动地理位置数据文件自动更新任务
+/// 启用 `geo_auto_update` 后，每周检查一次上游发布，有更新时下载并发射与手动下载相同的
+/// `geo-download-progress` 进度事件，完成后发射 `geo-files-updated` 事件。
+/// 正在运行的 Xray 进程需重启后才会加载新文件
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄
+pub fn start_geo_auto_update(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // 启动后稍作等待，避免与启动时的其他网络请求争用
+        let mut delay = Duration::from_secs(60);
+        loop {
+            tokio::time::sleep(delay).await;
+            delay = GEO_AUTO_UPDATE_TICK;
+
+            let Ok(config) = AppConfig::load() else { continue };
+            if !config.geo_auto_update {
+                continue;
+            }
+            let last_checked = config.geo_last_checked
+                .as_deref()
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok());
+            if last_checked.is_some_and(|t| chrono::Utc::now() - t.with_timezone(&chrono::Utc) < GEO_AUTO_UPDATE_PERIOD) {
+                continue;
+            }
+
+            let xray_manager = XrayManager::new();
+            let info = match xray_manager.check_geo_files_update().await {
+                Ok(info) => info,
+                Err(e) => {
+                    log_warn!("检查地理位置数据更新失败: {}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = ConfigStore::instance().update(|config| config.geo_last_checked = Some(chrono::Utc::now().to_rfc3339())) {
+                log_error!("保存配置失败: {}", e);
+            }
+
+            if !info.has_update {
+                continue;
+            }
+
+            log_info!("发现地理位置数据更新: {}，开始下载", info.latest_version);
+            let progress_handle = app_handle.clone();
+            let result = xray_manager.download_geo_files(|progress, total, message| {
+                let _ = progress_handle.emit("geo-download-progress", serde_json::json!({
+                    "progress": progress,
+                    "total": total,
+                    "message": message
+                }));
+            }).await;
+
+            match result {
+                Ok(()) => {
+                    log_info!("地理位置数据已更新到 {}", info.latest_version);
+                    let _ = app_handle.emit("geo-files-updated", serde_json::json!({
+                        "version": info.latest_version
+                    }));
+                }
+                Err(e) => log_error!("自动更新地理位置数据失败: {}", e),
+            }
+        }
+    });
+}
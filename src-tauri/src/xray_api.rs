@@ -0,0 +1,164 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::process::Command as TokioCommand;
+use tokio::time::Duration;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+
+use crate::config::AppConfig;
+
+/// 连接 API 入站的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// 单次调用的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 核心配置中需要启用的 API 服务
+pub const API_SERVICES: [&str; 2] = ["StatsService", "HandlerService"];
+
+// 以下消息与 Xray-core 的 proto 定义保持一致（字段编号不可修改）：
+// * app/stats/command/command.proto
+// * app/proxyman/command/command.proto
+
+/// 统计计数器
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Stat {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(int64, tag = "2")]
+    pub value: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryStatsRequest {
+    #[prost(string, tag = "1")]
+    pattern: String,
+    #[prost(bool, tag = "2")]
+    reset: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryStatsResponse {
+    #[prost(message, repeated, tag = "1")]
+    stat: Vec<Stat>,
+}
+
+/// 只有标签字段的请求（RemoveOutbound）
+#[derive(Clone, PartialEq, prost::Message)]
+struct TagRequest {
+    #[prost(string, tag = "1")]
+    tag: String,
+}
+
+/// 空消息
+#[derive(Clone, PartialEq, prost::Message)]
+struct Empty {}
+
+/// Xray gRPC API 客户端
+/// 通过核心配置中的 `api` 入站（`api_port`）调用 StatsService 和 HandlerService，
+/// 运行中查询流量、增删出站都无需重启核心进程
+#[derive(Clone)]
+pub struct XrayApiClient {
+    channel: Channel,
+    api_port: u16,
+}
+
+impl XrayApiClient {
+    /// 创建客户端
+    /// 连接在第一次调用时建立
+    ///
+    /// # 参数
+    /// * `api_port` - API 入站端口
+    pub fn new(api_port: u16) -> Result<Self> {
+        let channel = Endpoint::from_shared(format!("http://127.0.0.1:{}", api_port))
+            .context("无效的 API 地址")?
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .connect_lazy();
+        Ok(Self { channel, api_port })
+    }
+
+    /// 发起一次 unary 调用
+    async fn call<Req, Resp>(&self, path: &'static str, request: Req) -> Result<Resp>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready().await.context("无法连接 Xray API")?;
+        let response = grpc
+            .unary(tonic::Request::new(request), PathAndQuery::from_static(path), tonic::codec::ProstCodec::default())
+            .await
+            .with_context(|| format!("调用 Xray API 失败: {}", path))?;
+        Ok(response.into_inner())
+    }
+
+    /// 按名称前缀查询计数器
+    ///
+    /// # 参数
+    /// * `pattern` - 名称匹配字符串，为空时返回全部计数器
+    /// * `reset` - 查询后是否清零
+    ///
+    /// # 返回值
+    /// * `Result<Vec<Stat>>` - 匹配的计数器
+    pub async fn query_stats(&self, pattern: &str, reset: bool) -> Result<Vec<Stat>> {
+        let response: QueryStatsResponse = self
+            .call("/xray.app.stats.command.StatsService/QueryStats", QueryStatsRequest { pattern: pattern.to_string(), reset })
+            .await?;
+        Ok(response.stat)
+    }
+
+    /// 移除出站
+    ///
+    /// # 参数
+    /// * `tag` - 出站标签
+    pub async fn remove_outbound(&self, tag: &str) -> Result<()> {
+        let _: Empty = self
+            .call("/xray.app.proxyman.command.HandlerService/RemoveOutbound", TagRequest { tag: tag.to_string() })
+            .await?;
+        Ok(())
+    }
+
+    /// 添加出站
+    /// 出站设置需由 JSON 转换为核心内部的 protobuf 消息，转换逻辑只在核心中实现，
+    /// 因此通过 `xray api ado` 提交，效果与直接调用 HandlerService/AddOutbound 相同
+    ///
+    /// # 参数
+    /// * `outbound` - Xray 出站 JSON 对象（需包含 tag）
+    pub async fn add_outbound(&self, outbound: &serde_json::Value) -> Result<()> {
+        self.run_cli("ado", serde_json::json!({ "outbounds": [outbound] })).await
+    }
+
+    /// 通过 `xray api` 子命令提交 JSON 配置
+    async fn run_cli(&self, subcommand: &str, config: serde_json::Value) -> Result<()> {
+        let xray_executable = AppConfig::xray_executable()?;
+        let config_path = AppConfig::servers_dir()?.join(format!("xray_api_{}.json", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&config_path, serde_json::to_string(&config)?)
+            .context("写入 API 配置文件失败")?;
+
+        let mut command = TokioCommand::new(&xray_executable);
+        command
+            .arg("api")
+            .arg(subcommand)
+            .arg(format!("--server=127.0.0.1:{}", self.api_port))
+            .arg(&config_path)
+            .stdin(Stdio::null());
+
+        #[cfg(target_os = "windows")]
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+        let output = command.output().await;
+        let _ = std::fs::remove_file(&config_path);
+        let output = output.with_context(|| format!("执行 xray api {} 失败", subcommand))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("xray api {} 失败: {}", subcommand, stderr.trim()));
+        }
+        Ok(())
+    }
+}
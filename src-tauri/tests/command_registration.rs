@@ -0,0 +1,75 @@
+/*
+ * Project: RuRay
+ * Author: Lander
+ * CreateAt: 2026-10-16
+ */
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// 收集源码中所有 `#[tauri::command]` 函数，格式为 `模块::函数名`
+fn declared_commands(src_dir: &Path) -> BTreeSet<String> {
+    let mut commands = BTreeSet::new();
+    for entry in fs::read_dir(src_dir).expect("无法读取 src 目录") {
+        let path = entry.expect("无法读取目录项").path();
+        if path.extension().and_then(|e| e.to_str()) != Some("rs") {
+            continue;
+        }
+        let module = path.file_stem().unwrap().to_string_lossy().to_string();
+        let source = fs::read_to_string(&path).expect("无法读取源文件");
+
+        let mut lines = source.lines();
+        while let Some(line) = lines.next() {
+            if line.trim() != "#[tauri::command]" {
+                continue;
+            }
+            // 属性之后的第一个函数声明即为命令
+            let name = lines
+                .by_ref()
+                .find_map(|line| {
+                    let (_, rest) = line.split_once("fn ")?;
+                    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))?;
+                    Some(rest[..end].to_string())
+                })
+                .expect("#[tauri::command] 之后没有函数声明");
+            commands.insert(format!("{}::{}", module, name));
+        }
+    }
+    commands
+}
+
+/// 收集 `tauri::generate_handler!` 中注册的命令
+fn registered_commands(lib_source: &str) -> BTreeSet<String> {
+    let start = lib_source.find("generate_handler![").expect("lib.rs 中没有 generate_handler!");
+    let body = &lib_source[start + "generate_handler![".len()..];
+    let body = &body[..body.find(']').expect("generate_handler! 未闭合")];
+
+    body.lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn every_tauri_command_is_registered() {
+    let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let lib_source = fs::read_to_string(src_dir.join("lib.rs")).expect("无法读取 lib.rs");
+
+    let declared = declared_commands(&src_dir);
+    let registered = registered_commands(&lib_source);
+    assert!(!declared.is_empty(), "没有找到任何 #[tauri::command]");
+
+    // lib.rs 中定义的命令注册时不带模块前缀
+    let missing: Vec<&String> = declared
+        .iter()
+        .filter(|command| {
+            let bare = command.strip_prefix("lib::").unwrap_or(command.as_str());
+            !registered.contains(*command) && !registered.contains(bare)
+        })
+        .collect();
+    assert!(missing.is_empty(), "以下命令未在 generate_handler! 中注册: {:?}", missing);
+}
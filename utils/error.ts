@@ -0,0 +1,43 @@
+/**
+ * 后端命令返回的结构化错误
+ */
+export interface AppError {
+  code: string
+  /** 错误代码的本地化说明 */
+  summary?: string
+  message: string
+  details?: unknown
+}
+
+/** 后端返回 `APP_LOCKED` 时在 window 上派发的事件，解锁界面据此显示 */
+export const APP_LOCKED_EVENT = 'app-locked'
+
+/** 解锁成功后在 window 上派发的事件，各组件据此重新加载数据 */
+export const APP_UNLOCKED_EVENT = 'app-unlocked'
+
+/**
+ * 判断是否为后端命令返回的结构化错误
+ */
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error
+}
+
+/**
+ * 获取错误说明，兼容结构化错误、Error 对象和字符串
+ * 结构化错误显示为“本地化说明: 具体原因”；应用已锁定时同时派发 `APP_LOCKED_EVENT`
+ */
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) {
+    if (error.code === 'APP_LOCKED' && typeof window !== 'undefined') {
+      window.dispatchEvent(new CustomEvent(APP_LOCKED_EVENT))
+    }
+    if (!error.summary || error.message.startsWith(error.summary)) {
+      return error.message
+    }
+    return `${error.summary}: ${error.message}`
+  }
+  if (error instanceof Error) {
+    return error.message
+  }
+  return String(error)
+}